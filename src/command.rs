@@ -1,23 +1,20 @@
-use crate::table::Table;
+use prettytable::{row, Cell, Row, Table as PTable};
+use sql::parser::{number_literal, parse_sql};
+use sql::table::Table;
+use sql::{Database, Error, QueryResult, Value};
 
 use std::fs::File;
 use std::io::BufWriter;
 
-use crate::database::Database;
-use crate::parser::create::CreateQuery;
-use crate::parser::insert::InsertQuery;
-use crate::parser::select::SelectQuery;
-
-use sqlparser::ast::Statement;
-use sqlparser::dialect::MySqlDialect;
-use sqlparser::parser::Parser;
-
 pub enum MetaCommand {
     Exit,
     ListTables,
     PrintData,
     Persist(String),
     Restore(String),
+    SetParam(usize, String),
+    ListParams,
+    ClearParams,
     Unknown(String),
 }
 
@@ -29,6 +26,21 @@ impl MetaCommand {
         command.trim().to_string()
     }
 
+    /// `.param set N VALUE`, `.param list` and `.param clear` manage the values
+    /// bound to `?` / `$n` placeholders in the queries typed at the prompt.
+    fn param_command(command: &str) -> MetaCommand {
+        let mut parts = command.splitn(4, char::is_whitespace).skip(1);
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("set"), Some(n), Some(value)) => match n.trim_start_matches('$').parse() {
+                Ok(n) if n > 0 => MetaCommand::SetParam(n, value.trim().to_string()),
+                _ => MetaCommand::Unknown(command.to_string()),
+            },
+            (Some("list"), None, None) => MetaCommand::ListParams,
+            (Some("clear"), None, None) => MetaCommand::ClearParams,
+            _ => MetaCommand::Unknown(command.to_string()),
+        }
+    }
+
     pub fn new(command: String) -> MetaCommand {
         match command.as_ref() {
             ".exit" => MetaCommand::Exit,
@@ -41,6 +53,8 @@ impl MetaCommand {
                 } else if command.starts_with(".restore") {
                     let trimmed_command = Self::trim_command(&command, ".restore");
                     MetaCommand::Restore(trimmed_command)
+                } else if command.starts_with(".param") {
+                    Self::param_command(&command)
                 } else {
                     MetaCommand::Unknown(command)
                 }
@@ -70,6 +84,17 @@ impl DbCommand {
             _ => DbCommand::Unknown(command),
         }
     }
+
+    pub fn into_query(self) -> String {
+        match self {
+            DbCommand::Insert(query)
+            | DbCommand::Delete(query)
+            | DbCommand::Update(query)
+            | DbCommand::CreateTable(query)
+            | DbCommand::Select(query)
+            | DbCommand::Unknown(query) => query,
+        }
+    }
 }
 
 pub enum CommandType {
@@ -84,7 +109,7 @@ pub fn get_command_type(cmd: &String) -> CommandType {
     }
 }
 
-pub fn handle_meta_command(cmd: MetaCommand, db: &mut Database) {
+pub fn handle_meta_command(cmd: MetaCommand, db: &mut Database, params: &mut Vec<Value>) {
     match cmd {
         MetaCommand::Exit => std::process::exit(0),
         MetaCommand::ListTables => {
@@ -92,129 +117,132 @@ pub fn handle_meta_command(cmd: MetaCommand, db: &mut Database) {
                 println!("No tables found");
             }
             for table in &db.tables {
-                table.print_table();
+                print_table(table);
             }
         }
         MetaCommand::PrintData => {
             for table in &db.tables {
-                table.print_table_data();
+                print_table_data(table);
             }
         }
         MetaCommand::Persist(file_path) => {
-            let mut buffered_writer = BufWriter::new(
-                File::create(file_path).expect("Could not create or write to file to persist to"),
-            );
-            bincode::serialize_into(&mut buffered_writer, &db)
-                .expect("Error while trying to serialize to binary data");
+            if let Err(err) = persist(db, &file_path) {
+                eprintln!("{err}");
+            }
         }
-        MetaCommand::Restore(file_path) => {
-            let mut file =
-                File::open(file_path).expect("Could not open or find file to restore from");
-            let decoded_db: Database = bincode::deserialize_from(&mut file).unwrap();
-            *db = decoded_db;
+        MetaCommand::Restore(file_path) => match restore(&file_path) {
+            Ok(decoded_db) => *db = decoded_db,
+            Err(err) => eprintln!("{err}"),
+        },
+        MetaCommand::SetParam(n, value) => {
+            if params.len() < n {
+                params.resize(n, Value::Null);
+            }
+            params[n - 1] = parse_param(&value);
         }
+        MetaCommand::ListParams => {
+            for (i, value) in params.iter().enumerate() {
+                println!("${} = {}", i + 1, value);
+            }
+        }
+        MetaCommand::ClearParams => params.clear(),
         MetaCommand::Unknown(cmd) => eprintln!("Unrecognized meta command {cmd}"),
     }
 }
 
-pub fn process_command(query: String, db: &mut Database) {
-    let dialect = MySqlDialect {};
-    let statements = &Parser::parse_sql(&dialect, &query).unwrap();
+fn parse_param(value: &str) -> Value {
+    if let Some(s) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return Value::Str(s.replace("''", "'"));
+    }
+    match value.to_lowercase().as_ref() {
+        "null" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => number_literal(value).unwrap_or_else(|_| Value::Str(value.to_string())),
+    }
+}
 
-    for statement in statements {
-        println!("{:?}", statement);
-        match statement {
-            Statement::CreateTable { .. } => {
-                let create_query = CreateQuery::new(statement).unwrap();
-                db.tables.push(Table::new(create_query));
-            }
-            Statement::Insert { .. } => {
-                let insert_query = InsertQuery::new(statement);
-                match insert_query {
-                    Ok(InsertQuery {
-                        table_name,
-                        columns,
-                        values,
-                        ..
-                    }) => {
-                        println!("cols = {:?}\n vals = {:?}", columns, values);
-                        match db.table_exists(table_name.to_string()) {
-                            true => {
-                                let db_table = db.get_table_mut(table_name.to_string());
-                                match columns
-                                    .iter()
-                                    .all(|c| db_table.column_exists(c.to_string()))
-                                {
-                                    true => {
-                                        for value in &values {
-                                            match db_table
-                                                .does_violate_unique_constraint(&columns, value)
-                                            {
-                                                Err(err) => eprintln!(
-                                                    "Unique key constaint violation: {err}"
-                                                ),
-                                                Ok(()) => {
-                                                    db_table.insert_row(&columns, &values);
-                                                }
-                                            }
-                                        }
-                                    }
-                                    false => {
-                                        eprintln!(
-                                            "Cannot insert, some of the columns do not exist"
-                                        );
-                                    }
-                                }
-                            }
-                            false => eprintln!("Table doesn't exist"),
-                        }
-                    }
-                    Err(err) => eprintln!("Error while trying to parse insert statement: {err}"),
-                }
-            }
-            Statement::Query(_) => {
-                let select_query = SelectQuery::new(statement);
-                match select_query {
-                    Ok(mut sq) => match db.table_exists(sq.from.to_string()) {
-                        true => {
-                            let db_table = db.get_table(sq.from.to_string());
-
-                            let cloned_projection = sq.projection.clone();
-
-                            for p in &cloned_projection {
-                                if p == "*" {
-                                    let new_projections = db_table
-                                        .columns
-                                        .iter()
-                                        .map(|c| c.name.to_string())
-                                        .collect::<Vec<String>>();
-                                    sq.insert_projections(new_projections);
-                                }
-                            }
-
-                            for col in &sq.projection {
-                                if !db_table.column_exists((&col).to_string()) {
-                                    println!(
-                                        "Cannot execute query, cannot find column {} in table {}",
-                                        col, db_table.name
-                                    );
-                                    return;
-                                }
-                            }
-
-                            println!("sq = {:?}", &sq);
-                            db_table.execute_select_query(&sq);
-                        }
-                        false => {
-                            eprintln!("Cannot execute query the table {} doesn't exists", sq.from)
-                        }
-                    },
-                    Err(error) => eprintln!("{error}"),
-                }
-            }
-            _ => {
-                println!("Not a insert, create table or select query");
-            }
+fn persist(db: &Database, file_path: &str) -> Result<(), Error> {
+    let mut buffered_writer = BufWriter::new(File::create(file_path)?);
+    bincode::serialize_into(&mut buffered_writer, db)?;
+    Ok(())
+}
+
+fn restore(file_path: &str) -> Result<Database, Error> {
+    let mut file = File::open(file_path)?;
+    Ok(bincode::deserialize_from(&mut file)?)
+}
+
+fn print_table(table: &Table) {
+    let mut p_table = PTable::new();
+    p_table.add_row(row!["Column Name", "Data Type"]);
+
+    for col in &table.columns {
+        p_table.add_row(row![col.name, col.datatype]);
+    }
+
+    p_table.printstd();
+}
+
+fn print_table_data(table: &Table) {
+    let cnames = table
+        .columns
+        .iter()
+        .map(|col| col.name.to_string())
+        .collect::<Vec<String>>();
+
+    let num_rows = table.rows.values().map(|c| c.count()).max().unwrap_or(0);
+    let mut rows: Vec<Vec<Value>> = vec![vec![]; num_rows];
+
+    for col_name in &cnames {
+        let values = table
+            .rows
+            .get(col_name)
+            .map(|c| c.get_values())
+            .unwrap_or_default();
+        for (i, row) in rows.iter_mut().enumerate() {
+            row.push(values.get(i).cloned().unwrap_or(Value::Null));
+        }
+    }
+
+    pretty_print(&rows, &cnames);
+}
+
+fn pretty_print(data: &[Vec<Value>], header: &[String]) {
+    let mut p_table = PTable::new();
+
+    p_table.add_row(Row::new(
+        header.iter().map(|h| Cell::new(h)).collect::<Vec<Cell>>(),
+    ));
+
+    for row in data {
+        p_table.add_row(Row::new(
+            row.iter()
+                .map(|c| Cell::new(&c.to_string()))
+                .collect::<Vec<Cell>>(),
+        ));
+    }
+    p_table.printstd();
+}
+
+pub fn process_command(query: String, db: &mut Database, params: &[Value]) {
+    let statements = match parse_sql(&query) {
+        Ok(statements) => statements,
+        Err(err) => return eprintln!("{err}"),
+    };
+
+    for statement in &statements {
+        let result = db.prepare_statement(statement).and_then(|prepared| {
+            let bound = prepared.bind(&params[..prepared.param_count().min(params.len())])?;
+            db.execute_bound(&bound)
+        });
+        match result {
+            Ok(QueryResult::Rows(rs)) => pretty_print(&rs.rows, &rs.column_names()),
+            Ok(QueryResult::Inserted(count)) => println!("Inserted {count} row(s)"),
+            Ok(QueryResult::Created) => println!("Table created"),
+            Ok(QueryResult::CreatedType) => println!("Type created"),
+            Ok(QueryResult::AlteredType) => println!("Type altered"),
+            Err(err) => eprintln!("{err}"),
         }
     }
 }
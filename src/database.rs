@@ -1,35 +1,2604 @@
-use crate::table::Table;
+use crate::error::{Error, Result};
+use crate::expr::ScalarExpr;
+use crate::parser::create::CreateQuery;
+use crate::parser::insert::InsertQuery;
+use crate::parser::query::Query;
+use crate::parser::select::{unqualified, Operator, SelectItem, SelectQuery};
+use crate::parser::types::{AlterTypeQuery, CreateTypeQuery, LabelPosition};
+use crate::parser::{parse_sql, Operand, Statement};
+use crate::prepared::{infer_param_types, BoundStatement, Plan, PreparedStatement};
+use crate::result::{QueryResult, ResultColumn, ResultSet};
+use crate::table::{ColumnHeader, DataType, EnumType, Table};
+use crate::value::Value;
 use serde::{Deserialize, Serialize};
+use sqlparser::ast;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+mod query;
+mod with;
+
+const STATEMENT_CACHE_SIZE: usize = 256;
+const DEFAULT_RECURSION_LIMIT: usize = 1000;
+
+fn default_recursion_limit() -> usize {
+    DEFAULT_RECURSION_LIMIT
+}
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct Database {
     pub tables: Vec<Table>,
+    /// The ENUM types made with CREATE TYPE.
+    pub types: Vec<Arc<EnumType>>,
+    #[serde(skip)]
+    statements: HashMap<String, PreparedStatement>,
+    /// The results of the WITH clause of the query being planned or run.
+    /// They hide stored tables of the same name.
+    #[serde(skip)]
+    ctes: Vec<Table>,
+    #[serde(skip, default = "default_recursion_limit")]
+    recursion_limit: usize,
 }
 
 impl Database {
     pub fn new() -> Database {
-        Database { tables: vec![] }
+        Database {
+            tables: vec![],
+            types: vec![],
+            statements: HashMap::new(),
+            ctes: vec![],
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+        }
+    }
+
+    /// Sets how many times the recursive part of a `WITH RECURSIVE` query
+    /// may run and still find new rows before the query is aborted.
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.recursion_limit = limit;
     }
 
     pub fn table_exists(&self, tname: String) -> bool {
         self.tables.iter().any(|t| t.name == tname)
     }
 
-    pub fn get_table(&self, tname: String) -> &Table {
-        for t in &self.tables {
+    pub fn get_table(&self, tname: String) -> Result<&Table> {
+        for t in self.ctes.iter().rev().chain(&self.tables) {
             if t.name == tname {
-                return t;
+                return Ok(t);
             }
         }
-        panic!("Cannot find table, {}", tname);
+        Err(Error::Binding(format!("Cannot find table {tname}")))
     }
 
-    pub fn get_table_mut(&mut self, tname: String) -> &mut Table {
+    pub fn get_table_mut(&mut self, tname: String) -> Result<&mut Table> {
         for t in &mut self.tables {
             if t.name == tname {
-                return t;
+                return Ok(t);
+            }
+        }
+        Err(Error::Binding(format!("Cannot find table {tname}")))
+    }
+
+    /// Runs every statement in `sql` in order and returns the result of the
+    /// last one.
+    pub fn execute(&mut self, sql: &str) -> Result<QueryResult> {
+        let mut result = None;
+        for statement in &parse_sql(sql)? {
+            result = Some(self.execute_statement(statement)?);
+        }
+        result.ok_or_else(|| Error::Parse("No statement to execute".to_string()))
+    }
+
+    pub fn execute_statement(&mut self, statement: &Statement) -> Result<QueryResult> {
+        let prepared = self.prepare_statement(statement)?;
+        self.execute_plan(&prepared.plan)
+    }
+
+    /// Parses and plans a single statement that may contain `?` or `$n`
+    /// placeholders. Plans are cached by their SQL text, so preparing the
+    /// same query again skips parsing and planning.
+    ///
+    /// ```
+    /// use sql::{Database, QueryResult, Value};
+    ///
+    /// let mut db = Database::new();
+    /// db.execute("CREATE TABLE users (id int PRIMARY KEY, name string)").unwrap();
+    ///
+    /// let insert = db.prepare("INSERT INTO users (id, name) VALUES (?, ?)").unwrap();
+    /// let bound = insert.bind(&[Value::Int(1), Value::Str("tahmid".to_string())]).unwrap();
+    /// assert_eq!(db.execute_bound(&bound), Ok(QueryResult::Inserted(1)));
+    /// ```
+    pub fn prepare(&mut self, sql: &str) -> Result<PreparedStatement> {
+        if let Some(prepared) = self.statements.get(sql) {
+            return Ok(prepared.clone());
+        }
+
+        let statements = parse_sql(sql)?;
+        let prepared = match statements.as_slice() {
+            [statement] => self.prepare_statement(statement)?,
+            _ => {
+                return Err(Error::Parse(
+                    "Exactly one statement can be prepared at a time".to_string(),
+                ))
+            }
+        };
+        if self.statements.len() >= STATEMENT_CACHE_SIZE {
+            self.statements.clear();
+        }
+        self.statements.insert(sql.to_string(), prepared.clone());
+        Ok(prepared)
+    }
+
+    pub fn prepare_statement(&mut self, statement: &Statement) -> Result<PreparedStatement> {
+        let (plan, columns) = match statement {
+            Statement::Sql(statement) => self.plan_statement(statement)?,
+            Statement::CreateType(ctq) => (Plan::CreateType(ctq.clone()), None),
+            Statement::AlterType(atq) => (Plan::AlterType(atq.clone()), None),
+        };
+
+        let param_types = infer_param_types(&plan, |table, col| {
+            self.get_table(table.to_string())
+                .and_then(|t| t.get_column(col.to_string()))
+                .ok()
+                .map(|c| c.datatype.clone())
+        });
+
+        Ok(PreparedStatement {
+            plan,
+            param_types,
+            columns,
+        })
+    }
+
+    fn plan_statement(
+        &mut self,
+        statement: &ast::Statement,
+    ) -> Result<(Plan, Option<Vec<ResultColumn>>)> {
+        let planned = match statement {
+            ast::Statement::CreateTable { .. } => {
+                let mut cq = CreateQuery::new(statement)?;
+                for column in &mut cq.columns {
+                    if let Some(datatype) = self.named_type(&column.datatype) {
+                        column.datatype = with::type_string(&datatype);
+                    }
+                    // An unknown type, or a DEFAULT its column can't hold,
+                    // fails here rather than on insert.
+                    let header = ColumnHeader::new(
+                        column.name.to_string(),
+                        column.datatype.to_string(),
+                        column.is_pk,
+                        column.is_nullable,
+                        column.default.clone(),
+                    );
+                    if header.datatype == DataType::Invalid {
+                        return Err(Error::Type(format!(
+                            "Column {} has unknown type {}",
+                            column.name, column.datatype
+                        )));
+                    }
+                    header.default_value()?;
+                }
+                (Plan::CreateTable(cq), None)
+            }
+            ast::Statement::Insert { .. } => {
+                let mut iq = InsertQuery::new(statement, &|name: &str| self.named_type(name))?;
+                let db_table = self.get_table(iq.table_name.to_string())?;
+                if iq.columns.is_empty() {
+                    iq.columns = db_table
+                        .columns
+                        .iter()
+                        .map(|c| c.name.to_string())
+                        .collect();
+                }
+                let mut columns = vec![];
+                for c in &iq.columns {
+                    match db_table.get_column(c.to_string()) {
+                        Ok(column) => columns.push(column),
+                        Err(_) => {
+                            return Err(Error::Binding(format!(
+                                "Cannot insert, column {c} does not exist"
+                            )))
+                        }
+                    }
+                }
+                for row in &mut iq.values {
+                    for (operand, column) in row.iter_mut().zip(&columns) {
+                        if let Operand::Literal(val) = operand {
+                            *val = column.storable(val.clone())?;
+                        }
+                    }
+                }
+                (Plan::Insert(iq), None)
+            }
+            ast::Statement::Query(_) => {
+                let query = self.plan_query(Query::new(statement)?)?;
+                let columns = self.query_columns(&query)?;
+                (Plan::Select(query), Some(columns))
+            }
+            _ => {
+                return Err(Error::Parse(
+                    "Not a insert, create table or select query".to_string(),
+                ))
+            }
+        };
+        Ok(planned)
+    }
+
+    pub fn execute_bound(&mut self, bound: &BoundStatement) -> Result<QueryResult> {
+        self.execute_plan(&bound.plan)
+    }
+
+    fn execute_plan(&mut self, plan: &Plan) -> Result<QueryResult> {
+        match plan {
+            Plan::CreateTable(create_query) => {
+                if self.table_exists(create_query.table_name.to_string()) {
+                    return Err(Error::Binding(format!(
+                        "Table {} already exists",
+                        create_query.table_name
+                    )));
+                }
+                self.tables.push(Table::new(create_query.clone()));
+                Ok(QueryResult::Created)
+            }
+            Plan::Insert(InsertQuery {
+                table_name,
+                columns,
+                values,
+            }) => {
+                let values = values
+                    .iter()
+                    .map(|row| row.iter().map(|v| v.as_literal().cloned()).collect())
+                    .collect::<Result<Vec<Vec<Value>>>>()?;
+
+                // Every row is checked before any is inserted, so a failing
+                // row leaves the table as it was.
+                let db_table = self.get_table_mut(table_name.to_string())?;
+                db_table.check_unique_rows(columns, &values)?;
+                db_table.insert_row(columns, &values)?;
+                Ok(QueryResult::Inserted(values.len()))
+            }
+            Plan::Select(query) => Ok(QueryResult::Rows(ResultSet {
+                columns: self.query_columns(query)?,
+                rows: self.query_rows(query)?,
+            })),
+            Plan::CreateType(ctq) => self.create_type(ctq),
+            Plan::AlterType(atq) => self.alter_type(atq),
+        }
+    }
+
+    /// The type made with CREATE TYPE that a column type written as `name`
+    /// refers to, or `None` if it names a built-in type or none at all.
+    fn named_type(&self, name: &str) -> Option<DataType> {
+        if let Some(item) = name.strip_suffix("[]") {
+            return self
+                .named_type(item)
+                .map(|item| DataType::Array(Box::new(item)));
+        }
+        if DataType::new(name.to_string()) != DataType::Invalid {
+            return None;
+        }
+        self.types
+            .iter()
+            .find(|t| t.name == name)
+            .map(|t| DataType::Enum(t.clone()))
+    }
+
+    fn create_type(&mut self, ctq: &CreateTypeQuery) -> Result<QueryResult> {
+        if DataType::new(ctq.name.to_string()) != DataType::Invalid
+            || self.types.iter().any(|t| t.name == ctq.name)
+        {
+            return Err(Error::Binding(format!("Type {} already exists", ctq.name)));
+        }
+        for (i, label) in ctq.labels.iter().enumerate() {
+            if ctq.labels[..i].contains(label) {
+                return Err(Error::Binding(format!(
+                    "Label {label} is repeated in type {}",
+                    ctq.name
+                )));
+            }
+        }
+        self.types.push(Arc::new(EnumType {
+            name: ctq.name.to_string(),
+            labels: ctq.labels.clone(),
+        }));
+        Ok(QueryResult::CreatedType)
+    }
+
+    /// Adds a label to an ENUM type, and to the columns of that type so
+    /// their values sort by the new order.
+    fn alter_type(&mut self, atq: &AlterTypeQuery) -> Result<QueryResult> {
+        let Some(index) = self.types.iter().position(|t| t.name == atq.name) else {
+            return Err(Error::Binding(format!("Cannot find type {}", atq.name)));
+        };
+        let current = &self.types[index];
+        if current.code(&atq.label).is_some() {
+            if atq.if_not_exists {
+                return Ok(QueryResult::AlteredType);
+            }
+            return Err(Error::Binding(format!(
+                "Label {} already exists in type {}",
+                atq.label, atq.name
+            )));
+        }
+        let position = match &atq.position {
+            LabelPosition::Last => current.labels.len(),
+            LabelPosition::Before(label) | LabelPosition::After(label) => {
+                let code = current.code(label).ok_or_else(|| {
+                    Error::Binding(format!("Cannot find label {label} in type {}", atq.name))
+                })?;
+                code as usize + matches!(atq.position, LabelPosition::After(_)) as usize
+            }
+        };
+        let mut labels = current.labels.clone();
+        labels.insert(position, atq.label.to_string());
+        let updated = Arc::new(EnumType {
+            name: atq.name.to_string(),
+            labels,
+        });
+        for table in &mut self.tables {
+            table.update_enum(&updated)?;
+        }
+        self.types[index] = updated;
+        // Cached plans hold the old labels.
+        self.statements.clear();
+        Ok(QueryResult::AlteredType)
+    }
+
+    fn plan_select_query(&self, mut sq: SelectQuery) -> Result<SelectQuery> {
+        let joined = self.join_table(&sq)?;
+        let db_table = match &joined {
+            Some(table) => table,
+            None => self.get_table(sq.from.to_string())?,
+        };
+
+        let mut projection = vec![];
+        for item in &sq.projection {
+            if item.is_wildcard() {
+                projection.extend(db_table.columns.iter().map(|c| SelectItem {
+                    expr: ScalarExpr::Column(c.name.to_string()),
+                    name: unqualified(&c.name).to_string(),
+                }));
+            } else {
+                projection.push(item.clone());
+            }
+        }
+        sq.insert_projections(projection);
+
+        for expr in sq.exprs_mut() {
+            expr.resolve_types(&|name: &str| self.named_type(name))?;
+        }
+        let column_type = |col: &str| db_table.column_type(col);
+        for item in &mut sq.projection {
+            item.expr.resolve(&column_type)?;
+        }
+        for expr in &mut sq.where_expressions {
+            let column = db_table.get_column(expr.left.to_string())?;
+            if let Operator::Match(pattern) = &expr.op {
+                if column.datatype != DataType::Str {
+                    return Err(Error::Type(format!(
+                        "Operator {} cannot be applied to column {} of type {}",
+                        pattern.symbol(),
+                        column.name,
+                        column.datatype
+                    )));
+                }
             }
+            for operand in &mut expr.right {
+                match (&expr.op, operand) {
+                    (Operator::Match(pattern), Operand::Literal(Value::Str(p))) => {
+                        pattern.check(p)?
+                    }
+                    // A number the column's type can't hold exactly, like 1.5
+                    // for an int column, is compared as it is.
+                    (_, Operand::Literal(val)) => {
+                        *val = match val.inexact_for(&column.datatype) {
+                            Some(exact) => exact,
+                            None => column.coerce(val.clone())?,
+                        }
+                    }
+                    (_, Operand::Param(_)) => {}
+                }
+            }
+        }
+        if let Some(filter) = &mut sq.filter {
+            resolve_condition(filter, "WHERE", &column_type)?;
+            if filter.contains_aggregate() || filter.contains_window() {
+                return Err(Error::Parse(
+                    "Aggregates and window functions are not allowed in WHERE".to_string(),
+                ));
+            }
+        }
+        for expr in &mut sq.group_by {
+            *expr = output_reference(expr, &sq.projection, false, &column_type)?;
+            if expr.contains_aggregate() || expr.contains_window() {
+                return Err(Error::Parse(
+                    "Aggregates and window functions are not allowed in GROUP BY".to_string(),
+                ));
+            }
+            expr.resolve(&column_type)?;
+        }
+        if let Some(having) = &mut sq.having {
+            resolve_condition(having, "HAVING", &column_type)?;
+        }
+        for o in &mut sq.order_by {
+            o.expr = output_reference(&o.expr, &sq.projection, true, &column_type)?;
+            o.expr.resolve(&column_type)?;
+            if sq.distinct && !sq.projection.iter().any(|item| item.expr == o.expr) {
+                return Err(Error::Binding(
+                    "For SELECT DISTINCT, ORDER BY expressions must appear in the select list"
+                        .to_string(),
+                ));
+            }
+        }
+
+        if sq.is_aggregate() {
+            if sq.exprs().any(|e| e.contains_window()) {
+                return Err(Error::Parse(
+                    "Window functions cannot be combined with aggregates or GROUP BY".to_string(),
+                ));
+            }
+            for expr in sq
+                .projection
+                .iter()
+                .map(|i| &i.expr)
+                .chain(sq.having.iter())
+                .chain(sq.order_by.iter().map(|o| &o.expr))
+            {
+                expr.check_grouped(&sq.group_by)?;
+            }
+        }
+
+        Ok(sq)
+    }
+
+    fn result_columns(&self, sq: &SelectQuery) -> Result<Vec<ResultColumn>> {
+        let joined = self.join_table(sq)?;
+        let db_table = match &joined {
+            Some(table) => table,
+            None => self.get_table(sq.from.to_string())?,
+        };
+        let column_type = |col: &str| db_table.column_type(col);
+        sq.projection
+            .iter()
+            .map(|item| {
+                Ok(ResultColumn {
+                    name: item.name.to_string(),
+                    datatype: item.expr.datatype(&column_type)?.unwrap_or(DataType::Str),
+                    table: match item.expr {
+                        ScalarExpr::Column(_) if joined.is_none() => {
+                            Some(db_table.name.to_string())
+                        }
+                        _ => None,
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+fn resolve_condition<F>(expr: &mut ScalarExpr, clause: &str, column_type: &F) -> Result<()>
+where
+    F: Fn(&str) -> Result<DataType>,
+{
+    match expr.resolve(column_type)? {
+        None | Some(DataType::Bool) => Ok(()),
+        Some(datatype) => Err(Error::Type(format!(
+            "{clause} must be a Boolean expression, not {datatype}"
+        ))),
+    }
+}
+
+/// ORDER BY and GROUP BY can name a SELECT list entry by its position or
+/// alias. ORDER BY prefers the alias when it is also a column name, GROUP BY
+/// prefers the column.
+fn output_reference<F>(
+    expr: &ScalarExpr,
+    projection: &[SelectItem],
+    prefer_alias: bool,
+    column_type: &F,
+) -> Result<ScalarExpr>
+where
+    F: Fn(&str) -> Result<DataType>,
+{
+    match expr {
+        ScalarExpr::Value(Operand::Literal(Value::Int(n))) => usize::try_from(*n)
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| projection.get(i))
+            .map(|item| item.expr.clone())
+            .ok_or_else(|| Error::Binding(format!("Position {n} is not in the select list"))),
+        ScalarExpr::Column(name) if prefer_alias || column_type(name).is_err() => Ok(projection
+            .iter()
+            .find(|item| item.name == *name)
+            .map_or_else(|| expr.clone(), |item| item.expr.clone())),
+        _ => Ok(expr.clone()),
+    }
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rows `sql` returns.
+    fn rows(db: &mut Database, sql: &str) -> Vec<Vec<Value>> {
+        match db.execute(sql).unwrap() {
+            QueryResult::Rows(rs) => rs.rows,
+            _ => panic!("expected rows"),
         }
-        panic!("Cannot find table, {}", tname);
+    }
+
+    /// The rows `sql` returns, with every value as text.
+    fn text(db: &mut Database, sql: &str) -> Vec<Vec<String>> {
+        rows(db, sql)
+            .iter()
+            .map(|r| r.iter().map(|v| v.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn tests_executing_returns_typed_results() {
+        let mut db = Database::new();
+        assert_eq!(
+            db.execute("CREATE TABLE users (id INT PRIMARY KEY, name TEXT, score FLOAT);"),
+            Ok(QueryResult::Created)
+        );
+        assert_eq!(
+            db.execute("INSERT INTO users (id, name, score) VALUES (1, 'a', 1.5), (2, 'b', 2.5);"),
+            Ok(QueryResult::Inserted(2))
+        );
+
+        let QueryResult::Rows(rs) = db
+            .execute("SELECT id, score FROM users WHERE id > 1;")
+            .unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(rs.column_names(), vec!["id", "score"]);
+        assert_eq!(rs.columns[1].datatype, DataType::Float);
+        assert_eq!(rs.rows, vec![vec![Value::Int(2), Value::Float(2.5)]]);
+
+        assert!(db.execute("SELECT missing FROM users;").is_err());
+    }
+
+    #[test]
+    fn tests_values_are_typed_and_nullable() {
+        let mut db = Database::new();
+        db.execute(
+            "CREATE TABLE t (id INT PRIMARY KEY, name TEXT NOT NULL, score FLOAT, ok BOOL);",
+        )
+        .unwrap();
+        db.execute("INSERT INTO t VALUES (1, 'a', 10, true);")
+            .unwrap();
+        db.execute("INSERT INTO t (id, name) VALUES (2, 'b');")
+            .unwrap();
+        assert!(matches!(
+            db.execute("INSERT INTO t (id, score) VALUES (3, 1.5);"),
+            Err(Error::Constraint(_))
+        ));
+        assert!(matches!(
+            db.execute("INSERT INTO t (id, name) VALUES (NULL, 'c');"),
+            Err(Error::Constraint(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT id FROM t WHERE score > 'high';"),
+            Err(Error::Type(_))
+        ));
+
+        let QueryResult::Rows(rs) = db.execute("SELECT * FROM t;").unwrap() else {
+            panic!("expected rows");
+        };
+        assert_eq!(
+            rs.rows,
+            vec![
+                vec![
+                    Value::Int(1),
+                    Value::Str("a".to_string()),
+                    Value::Float(10.0),
+                    Value::Bool(true)
+                ],
+                vec![
+                    Value::Int(2),
+                    Value::Str("b".to_string()),
+                    Value::Null,
+                    Value::Null
+                ],
+            ]
+        );
+
+        let QueryResult::Rows(rs) = db.execute("SELECT id FROM t WHERE score >= 9.5;").unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(rs.rows, vec![vec![Value::Int(1)]]);
+        let QueryResult::Rows(rs) = db.execute("SELECT id FROM t WHERE id != 1;").unwrap() else {
+            panic!("expected rows");
+        };
+        assert_eq!(rs.rows, vec![vec![Value::Int(2)]]);
+    }
+
+    #[test]
+    fn tests_evaluating_select_expressions() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE items (id INT PRIMARY KEY, name TEXT, price FLOAT, qty INT);")
+            .unwrap();
+        db.execute("INSERT INTO items VALUES (1, 'pen', 1.5, 4), (2, 'ink', 10, 0);")
+            .unwrap();
+
+        let QueryResult::Rows(rs) = db
+            .execute(
+                "SELECT price * qty AS total, -qty, id % 2, name || '!', qty > 1 AND NOT id = 2, 7 / 2 \
+                 FROM items WHERE id = 1;",
+            )
+            .unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(
+            rs.column_names(),
+            vec![
+                "total",
+                "-qty",
+                "id % 2",
+                "name || '!'",
+                "qty > 1 AND NOT id = 2",
+                "7 / 2"
+            ]
+        );
+        assert_eq!(rs.columns[0].datatype, DataType::Float);
+        assert_eq!(rs.columns[4].datatype, DataType::Bool);
+        assert_eq!(
+            rs.rows,
+            vec![vec![
+                Value::Float(6.0),
+                Value::Int(-4),
+                Value::Int(1),
+                Value::Str("pen!".to_string()),
+                Value::Bool(true),
+                Value::Int(3),
+            ]]
+        );
+
+        assert_eq!(
+            db.execute("SELECT id / qty FROM items;"),
+            Err(Error::DivisionByZero)
+        );
+        assert!(matches!(
+            db.execute("SELECT qty * 2147483647 FROM items WHERE id = 1;"),
+            Err(Error::OutOfRange(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT name * 2 FROM items;"),
+            Err(Error::Type(_))
+        ));
+    }
+
+    #[test]
+    fn tests_functions_in_where_order_by_and_group_by() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id INT PRIMARY KEY, name TEXT, team TEXT);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO users VALUES (1, 'Ann', 'red'), (2, ' bob ', 'Blue'), (3, 'cy', 'RED'), (4, 'Dee', NULL);",
+        )
+        .unwrap();
+
+        let s = |v: &str| Value::Str(v.to_string());
+
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT UPPER(TRIM(name)), LENGTH(name), LPAD(name, 5, '*') FROM users \
+                 WHERE LOWER(name) = 'ann' OR id = 2;"
+            ),
+            vec![
+                vec![s("ANN"), Value::Int(3), s("**Ann")],
+                vec![s("BOB"), Value::Int(5), s(" bob ")],
+            ]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT id FROM users WHERE id > 1 AND SUBSTRING(name FROM 1 FOR 1) <> ' ' \
+                 ORDER BY REVERSE(name) DESC;"
+            ),
+            vec![vec![Value::Int(3)], vec![Value::Int(4)]]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT LOWER(team) AS t, COUNT(*), MAX(id) FROM users GROUP BY t ORDER BY 2 DESC, t;"
+            ),
+            vec![
+                vec![s("red"), Value::Int(2), Value::Int(3)],
+                vec![s("blue"), Value::Int(1), Value::Int(2)],
+                vec![Value::Null, Value::Int(1), Value::Int(4)],
+            ]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT MD5('abc'), CONCAT(id, '-', name) FROM users WHERE id = 1;"
+            ),
+            vec![vec![s("900150983cd24fb0d6963f7d28e17f72"), s("1-Ann")]]
+        );
+
+        assert!(matches!(
+            db.execute("SELECT NOPE(name) FROM users;"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT UPPER(id) FROM users;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT LPAD(name) FROM users;"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT name, COUNT(*) FROM users GROUP BY team;"),
+            Err(Error::Binding(_))
+        ));
+    }
+
+    #[test]
+    fn tests_math_functions_promote_and_check() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE nums (id INT PRIMARY KEY, i INT, f FLOAT);")
+            .unwrap();
+        db.execute("INSERT INTO nums VALUES (1, -7, 2.5), (2, 2147483647, -0.5);")
+            .unwrap();
+
+        let result = match db
+            .execute(
+                "SELECT ABS(i), ROUND(f), ROUND(i, -1), CEIL(f), FLOOR(f), MOD(i, 4), \
+                 POWER(2, 10), SQRT(16), LOG(100), SIGN(f), GREATEST(i, f, 3), LEAST(1, 2) \
+                 FROM nums WHERE id = 1;",
+            )
+            .unwrap()
+        {
+            QueryResult::Rows(rs) => rs,
+            _ => panic!("expected rows"),
+        };
+        let types: Vec<DataType> = result.columns.iter().map(|c| c.datatype.clone()).collect();
+        use DataType::{Double, Float, Int};
+        assert_eq!(
+            types,
+            vec![Int, Float, Int, Float, Float, Int, Double, Double, Double, Int, Float, Int]
+        );
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::Int(7),
+                Value::Float(3.0),
+                Value::Int(-10),
+                Value::Float(3.0),
+                Value::Float(2.0),
+                Value::Int(-3),
+                Value::Double(1024.0),
+                Value::Double(4.0),
+                Value::Double(2.0),
+                Value::Int(1),
+                Value::Float(3.0),
+                Value::Int(1),
+            ]]
+        );
+
+        for sql in [
+            "SELECT ROUND(i, -1) FROM nums WHERE id = 2;",
+            "SELECT ABS(-i - 1) FROM nums WHERE id = 2;",
+            "SELECT SQRT(f) FROM nums WHERE id = 2;",
+            "SELECT EXP(1000) FROM nums;",
+        ] {
+            assert!(
+                matches!(db.execute(sql), Err(Error::OutOfRange(_))),
+                "{sql}"
+            );
+        }
+        assert!(matches!(
+            db.execute("SELECT MOD(i, 0) FROM nums;"),
+            Err(Error::DivisionByZero)
+        ));
+        assert!(matches!(
+            db.execute("SELECT POWER(0, -1) FROM nums;"),
+            Err(Error::DivisionByZero)
+        ));
+        assert!(matches!(
+            db.execute("SELECT ABS('x') FROM nums;"),
+            Err(Error::Type(_))
+        ));
+    }
+
+    #[test]
+    fn tests_pattern_matching() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE words (w TEXT PRIMARY KEY, n INT);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO words VALUES ('apple', 1), ('Apricot', 2), ('banana', 3), ('ap_x', 4), ('cherry', 5);",
+        )
+        .unwrap();
+
+        let ids = |db: &mut Database, filter: &str| {
+            let sql = format!("SELECT n FROM words WHERE {filter} ORDER BY n;");
+            match db.execute(&sql).unwrap() {
+                QueryResult::Rows(rs) => rs
+                    .rows
+                    .into_iter()
+                    .map(|r| match r[0] {
+                        Value::Int(n) => n,
+                        _ => panic!("expected an Int"),
+                    })
+                    .collect::<Vec<i32>>(),
+                _ => panic!("expected rows"),
+            }
+        };
+        assert_eq!(ids(&mut db, "w LIKE 'ap%'"), vec![1, 4]);
+        assert_eq!(ids(&mut db, "w LIKE 'ap!_%' ESCAPE '!'"), vec![4]);
+        assert_eq!(ids(&mut db, "w NOT LIKE '%a%'"), vec![2, 5]);
+        assert_eq!(ids(&mut db, "w ILIKE 'AP%' AND n > 1"), vec![2, 4]);
+        assert_eq!(ids(&mut db, "w LIKE '_a%' OR n = 5"), vec![3, 5]);
+        assert_eq!(ids(&mut db, "w REGEXP '^[ab]'"), vec![1, 3, 4]);
+        assert_eq!(ids(&mut db, "w NOT RLIKE 'an|rr'"), vec![1, 2, 4]);
+        assert_eq!(ids(&mut db, "w ~* '^apr'"), vec![2]);
+
+        assert_eq!(
+            db.execute("SELECT w LIKE '%y' AS m FROM words WHERE n = 5;")
+                .unwrap(),
+            db.execute("SELECT TRUE AS m FROM words WHERE n = 5;")
+                .unwrap()
+        );
+        assert!(matches!(
+            db.execute("SELECT * FROM words WHERE n LIKE '1%';"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT * FROM words WHERE w REGEXP '(';"),
+            Err(Error::Type(_))
+        ));
+    }
+
+    #[test]
+    fn tests_between_and_in_lists() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE people (id INT PRIMARY KEY, age INT, status TEXT);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO people VALUES (1, 17, 'a'), (2, 18, 'b'), (3, 30, 'c'), (4, 31, NULL), (5, 25, 'a');",
+        )
+        .unwrap();
+
+        let ids = |db: &mut Database, filter: &str| {
+            let sql = format!("SELECT id FROM people WHERE {filter} ORDER BY id;");
+            match db.execute(&sql).unwrap() {
+                QueryResult::Rows(rs) => rs
+                    .rows
+                    .into_iter()
+                    .map(|r| match r[0] {
+                        Value::Int(n) => n,
+                        _ => panic!("expected an Int"),
+                    })
+                    .collect::<Vec<i32>>(),
+                _ => panic!("expected rows"),
+            }
+        };
+        assert_eq!(ids(&mut db, "age BETWEEN 18 AND 30"), vec![2, 3, 5]);
+        assert_eq!(ids(&mut db, "age NOT BETWEEN 18 AND 30"), vec![1, 4]);
+        assert_eq!(ids(&mut db, "id BETWEEN 2 AND 4 AND age > 18"), vec![3, 4]);
+        assert_eq!(ids(&mut db, "id BETWEEN 4 AND 2"), Vec::<i32>::new());
+        assert_eq!(ids(&mut db, "id IN (5, 1, 5, 9)"), vec![1, 5]);
+        assert_eq!(ids(&mut db, "status IN ('a', 'c')"), vec![1, 3, 5]);
+        assert_eq!(ids(&mut db, "status NOT IN ('a', 'c')"), vec![2]);
+        assert_eq!(ids(&mut db, "status NOT IN ('a', NULL)"), Vec::<i32>::new());
+        assert_eq!(ids(&mut db, "age + 1 IN (18, 32) OR id = 3"), vec![1, 3, 4]);
+        assert_eq!(ids(&mut db, "age - 1 NOT BETWEEN 17 AND 29"), vec![1, 4]);
+
+        let stmt = db
+            .prepare("SELECT id FROM people WHERE id IN ($1, $2);")
+            .unwrap();
+        assert_eq!(
+            stmt.param_types,
+            vec![Some(DataType::Int), Some(DataType::Int)]
+        );
+        assert!(matches!(
+            db.execute("SELECT id FROM people WHERE age IN (1, 'x');"),
+            Err(Error::Type(_))
+        ));
+    }
+
+    #[test]
+    fn tests_conditional_expressions() {
+        let mut db = Database::new();
+        db.execute(
+            "CREATE TABLE orders (id INT PRIMARY KEY, total FLOAT, status TEXT, note TEXT);",
+        )
+        .unwrap();
+        db.execute(
+            "INSERT INTO orders VALUES (1, 5.5, 'new', NULL), (2, 120, 'paid', 'rush'), (3, 40, 'void', NULL);",
+        )
+        .unwrap();
+
+        let result = match db
+            .execute(
+                "SELECT CASE WHEN total >= 100 THEN 'big' WHEN total >= 10 THEN 'medium' ELSE 'small' END AS size, \
+                 CASE status WHEN 'void' THEN 0 WHEN 'new' THEN total END AS amount, \
+                 COALESCE(note, status, 'none'), NULLIF(status, 'void'), IF(id > 1, id, '0'), IFNULL(note, '-') \
+                 FROM orders ORDER BY CASE WHEN status = 'paid' THEN 0 ELSE 1 END, id;",
+            )
+            .unwrap()
+        {
+            QueryResult::Rows(rs) => rs,
+            _ => panic!("expected rows"),
+        };
+        let types: Vec<DataType> = result.columns.iter().map(|c| c.datatype.clone()).collect();
+        use DataType::{Float, Int, Str};
+        assert_eq!(types, vec![Str, Float, Str, Str, Int, Str]);
+        let s = |v: &str| Value::Str(v.to_string());
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![
+                    s("big"),
+                    Value::Null,
+                    s("rush"),
+                    s("paid"),
+                    Value::Int(2),
+                    s("rush")
+                ],
+                vec![
+                    s("small"),
+                    Value::Float(5.5),
+                    s("new"),
+                    s("new"),
+                    Value::Int(0),
+                    s("-")
+                ],
+                vec![
+                    s("medium"),
+                    Value::Float(0.0),
+                    s("void"),
+                    Value::Null,
+                    Value::Int(3),
+                    s("-")
+                ],
+            ]
+        );
+
+        assert!(matches!(
+            db.execute("SELECT CASE WHEN id = 1 THEN 1 ELSE 'one' END FROM orders;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT CASE WHEN id THEN 1 END FROM orders;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT NULLIF(id) FROM orders;"),
+            Err(Error::Binding(_))
+        ));
+    }
+
+    #[test]
+    fn tests_distinct_rows_and_aggregates() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE sales (id INT PRIMARY KEY, region TEXT, amount INT, rate FLOAT);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO sales VALUES (1, 'north', 10, 0.5), (2, 'south', 10, 0.5), (3, 'north', 20, -0.0), \
+             (4, 'north', 10, 0.0), (5, NULL, NULL, NULL), (6, NULL, 20, 1.5);",
+        )
+        .unwrap();
+
+        let s = |v: &str| Value::Str(v.to_string());
+
+        assert_eq!(
+            rows(&mut db, "SELECT DISTINCT region FROM sales;"),
+            vec![vec![s("north")], vec![s("south")], vec![Value::Null]]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT DISTINCT region, amount FROM sales ORDER BY amount DESC, region LIMIT 3;"
+            ),
+            vec![
+                vec![Value::Null, Value::Null],
+                vec![s("north"), Value::Int(20)],
+                vec![Value::Null, Value::Int(20)],
+            ]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT COUNT(DISTINCT amount), SUM(DISTINCT amount), COUNT(amount), COUNT(DISTINCT rate) FROM sales;"
+            ),
+            vec![vec![
+                Value::Int(2),
+                Value::BigInt(30),
+                Value::Int(5),
+                Value::Int(3)
+            ]]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT region, COUNT(DISTINCT amount) FROM sales GROUP BY region ORDER BY region;"
+            ),
+            vec![
+                vec![s("north"), Value::Int(2)],
+                vec![s("south"), Value::Int(1)],
+                vec![Value::Null, Value::Int(1)],
+            ]
+        );
+        assert!(matches!(
+            db.execute("SELECT DISTINCT region FROM sales ORDER BY amount;"),
+            Err(Error::Binding(_))
+        ));
+
+        // Only the final SUM is range-checked, so AVG of large BIGINTs works.
+        db.execute("CREATE TABLE big (n BIGINT);").unwrap();
+        db.execute("INSERT INTO big VALUES (9223372036854775807), (9223372036854775805);")
+            .unwrap();
+        assert_eq!(
+            rows(&mut db, "SELECT AVG(n) FROM big;"),
+            vec![vec![Value::Double(9223372036854775806.0)]]
+        );
+        assert!(matches!(
+            db.execute("SELECT SUM(n) FROM big;"),
+            Err(Error::OutOfRange(_))
+        ));
+        db.execute("INSERT INTO big VALUES (-9223372036854775807);")
+            .unwrap();
+        assert_eq!(
+            rows(&mut db, "SELECT SUM(n) FROM big;"),
+            vec![vec![Value::BigInt(9223372036854775805)]]
+        );
+    }
+
+    #[test]
+    fn tests_set_operations() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE a (id INT PRIMARY KEY, name TEXT, score FLOAT);")
+            .unwrap();
+        db.execute("CREATE TABLE b (id INT PRIMARY KEY, name TEXT, rank INT);")
+            .unwrap();
+        db.execute("INSERT INTO a VALUES (1, 'x', 1.5), (2, 'y', 2.0), (3, 'y', 3.0);")
+            .unwrap();
+        db.execute("INSERT INTO b VALUES (1, 'y', 2), (2, 'z', 4), (3, 'y', 9);")
+            .unwrap();
+
+        let s = |v: &str| vec![Value::Str(v.to_string())];
+
+        assert_eq!(
+            rows(&mut db, "SELECT name FROM a UNION SELECT name FROM b;"),
+            vec![s("x"), s("y"), s("z")]
+        );
+        assert_eq!(
+            rows(&mut db, "SELECT name FROM a UNION ALL SELECT name FROM b;").len(),
+            6
+        );
+        assert_eq!(
+            rows(&mut db, "SELECT name FROM a INTERSECT SELECT name FROM b;"),
+            vec![s("y")]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT name FROM a INTERSECT ALL SELECT name FROM b;"
+            ),
+            vec![s("y"), s("y")]
+        );
+        assert_eq!(
+            rows(&mut db, "SELECT name FROM b EXCEPT SELECT name FROM a;"),
+            vec![s("z")]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT name FROM a UNION ALL SELECT name FROM a EXCEPT ALL SELECT name FROM b;"
+            ),
+            vec![s("x"), s("x"), s("y"), s("y")]
+        );
+
+        let QueryResult::Rows(rs) = db
+            .execute(
+                "SELECT name, score AS n FROM a UNION SELECT name, rank FROM b \
+                 ORDER BY n DESC, 1 LIMIT 2 OFFSET 1;",
+            )
+            .unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(rs.column_names(), vec!["name", "n"]);
+        assert_eq!(rs.columns[1].datatype, DataType::Float);
+        assert_eq!(
+            rs.rows,
+            vec![
+                vec![Value::Str("z".to_string()), Value::Float(4.0)],
+                vec![Value::Str("y".to_string()), Value::Float(3.0)],
+            ]
+        );
+
+        assert!(matches!(
+            db.execute("SELECT id, name FROM a UNION SELECT id FROM b;"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT name FROM a UNION SELECT id FROM b;"),
+            Err(Error::Type(_))
+        ));
+    }
+
+    #[test]
+    fn tests_common_table_expressions() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE staff (id INT PRIMARY KEY, name TEXT, boss INT);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO staff VALUES (1, 'ceo', NULL), (2, 'cto', 1), (3, 'dev', 2), \
+             (4, 'ops', 2), (5, 'cfo', 1), (6, 'intern', 3);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rows(
+                &mut db,
+                "WITH managers AS (SELECT boss FROM staff WHERE boss IS NOT NULL), \
+                 top (who) AS (SELECT boss FROM managers GROUP BY boss HAVING COUNT(*) > 1) \
+                 SELECT who FROM top ORDER BY who;"
+            ),
+            vec![vec![Value::Int(1)], vec![Value::Int(2)]]
+        );
+
+        let ints = |values: &[i32]| {
+            values
+                .iter()
+                .map(|v| vec![Value::Int(*v)])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            rows(
+                &mut db,
+                "WITH RECURSIVE up (n) AS (SELECT id FROM staff WHERE id = 4 \
+                 UNION ALL SELECT n + 1 FROM up WHERE n < 7) SELECT n FROM up;"
+            ),
+            ints(&[4, 5, 6, 7])
+        );
+        // UNION discards rows already found, so the cycle ends.
+        assert_eq!(
+            rows(
+                &mut db,
+                "WITH RECURSIVE cycle (n) AS (SELECT boss FROM staff WHERE id = 2 \
+                 UNION SELECT (n + 1) % 3 FROM cycle) SELECT n FROM cycle ORDER BY n;"
+            ),
+            ints(&[0, 1, 2])
+        );
+        // The recursive term joins the rows found so far to a base table.
+        assert_eq!(
+            text(
+                &mut db,
+                "WITH RECURSIVE tree (id, name, depth) AS (SELECT id, name, 0 FROM staff \
+                 WHERE boss IS NULL UNION ALL SELECT s.id, s.name, t.depth + 1 \
+                 FROM staff s JOIN tree t ON s.boss = t.id) \
+                 SELECT name, depth FROM tree ORDER BY depth, name;"
+            ),
+            [
+                ["ceo", "0"],
+                ["cfo", "1"],
+                ["cto", "1"],
+                ["dev", "2"],
+                ["ops", "2"],
+                ["intern", "3"]
+            ]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "WITH RECURSIVE chain AS (SELECT id, boss FROM staff WHERE id = 6 \
+                 UNION ALL SELECT s.id, s.boss FROM chain c INNER JOIN staff s ON s.id = c.boss) \
+                 SELECT id FROM chain;"
+            ),
+            ints(&[6, 3, 2, 1])
+        );
+        assert!(matches!(
+            db.execute("SELECT id FROM staff a JOIN staff b ON a.id = b.boss;"),
+            Err(Error::Binding(_))
+        ));
+
+        db.set_recursion_limit(10);
+        assert!(matches!(
+            db.execute(
+                "WITH RECURSIVE forever (n) AS (SELECT id FROM staff WHERE id = 1 \
+                 UNION ALL SELECT n FROM forever) SELECT n FROM forever;"
+            ),
+            Err(Error::OutOfRange(_))
+        ));
+        assert!(matches!(
+            db.execute(
+                "WITH RECURSIVE half (n) AS (SELECT id FROM staff WHERE id = 1 \
+                 UNION ALL SELECT n / 2.0 FROM half WHERE n > 1) SELECT n FROM half;"
+            ),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("WITH t (a, b) AS (SELECT id FROM staff) SELECT a FROM t;"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute(
+                "WITH c AS (SELECT id FROM staff), c AS (SELECT boss FROM staff) SELECT * FROM c;"
+            ),
+            Err(Error::Parse(_))
+        ));
+        // The CTEs are gone once the statement has run.
+        assert!(matches!(
+            db.execute("SELECT who FROM top;"),
+            Err(Error::Binding(_))
+        ));
+    }
+
+    #[test]
+    fn tests_window_functions() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE pay (id INT PRIMARY KEY, dept TEXT, salary INT);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO pay VALUES (1, 'eng', 100), (2, 'eng', 120), (3, 'ops', 90), \
+             (4, 'eng', 120), (5, 'ops', 80), (6, 'eng', 150);",
+        )
+        .unwrap();
+
+        let ints = |values: &[&[i32]]| {
+            values
+                .iter()
+                .map(|r| r.iter().map(|v| Value::Int(*v)).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT id, ROW_NUMBER() OVER (PARTITION BY dept ORDER BY salary DESC, id), \
+                 RANK() OVER (PARTITION BY dept ORDER BY salary DESC), \
+                 DENSE_RANK() OVER (PARTITION BY dept ORDER BY salary DESC) \
+                 FROM pay ORDER BY id;"
+            ),
+            ints(&[
+                &[1, 4, 4, 3],
+                &[2, 2, 2, 2],
+                &[3, 1, 1, 1],
+                &[4, 3, 2, 2],
+                &[5, 2, 2, 2],
+                &[6, 1, 1, 1],
+            ])
+        );
+        // The default frame with ORDER BY includes peers, ROWS frames don't.
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT id, SUM(salary) OVER (ORDER BY salary), \
+                 SUM(salary) OVER (ORDER BY salary, id ROWS BETWEEN 1 PRECEDING AND CURRENT ROW), \
+                 COUNT(*) OVER (ORDER BY salary RANGE BETWEEN 10 PRECEDING AND 10 FOLLOWING) \
+                 FROM pay WHERE dept = 'eng' ORDER BY id;"
+            ),
+            [
+                [1_i64, 100, 100, 1],
+                [2, 340, 220, 2],
+                [4, 340, 240, 2],
+                [6, 490, 270, 1]
+            ]
+            .iter()
+            .map(|&[id, total, running, peers]| vec![
+                Value::Int(id as i32),
+                Value::BigInt(total),
+                Value::BigInt(running),
+                Value::Int(peers as i32),
+            ])
+            .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT LAG(salary) OVER (ORDER BY id), LEAD(salary, 2, 0) OVER (ORDER BY id), \
+                 FIRST_VALUE(id) OVER (PARTITION BY dept ORDER BY salary), \
+                 LAST_VALUE(id) OVER (PARTITION BY dept ORDER BY salary \
+                 ROWS BETWEEN UNBOUNDED PRECEDING AND UNBOUNDED FOLLOWING) \
+                 FROM pay ORDER BY id LIMIT 2;"
+            ),
+            vec![
+                vec![Value::Null, Value::Int(90), Value::Int(1), Value::Int(6)],
+                vec![
+                    Value::Int(100),
+                    Value::Int(120),
+                    Value::Int(1),
+                    Value::Int(6)
+                ],
+            ]
+        );
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT id FROM pay ORDER BY ROW_NUMBER() OVER (ORDER BY salary DESC, id) LIMIT 3;"
+            ),
+            ints(&[&[6], &[2], &[4]])
+        );
+
+        assert!(matches!(
+            db.execute("SELECT id FROM pay WHERE RANK() OVER (ORDER BY id) > 1;"),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT dept, RANK() OVER (ORDER BY dept) FROM pay GROUP BY dept;"),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT SUM(dept) OVER () FROM pay;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT RANK(id) OVER () FROM pay;"),
+            Err(Error::Binding(_))
+        ));
+        for frame in [
+            "ROWS BETWEEN CURRENT ROW AND 1 PRECEDING",
+            "ROWS BETWEEN 1 FOLLOWING AND CURRENT ROW",
+            "RANGE BETWEEN 2 FOLLOWING AND 1 PRECEDING",
+        ] {
+            assert!(matches!(
+                db.execute(&format!(
+                    "SELECT SUM(salary) OVER (ORDER BY id {frame}) FROM pay;"
+                )),
+                Err(Error::Parse(_))
+            ));
+        }
+        // Offsets in the wrong order give an empty frame rather than an error.
+        assert_eq!(
+            rows(
+                &mut db,
+                "SELECT SUM(salary) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND 2 PRECEDING) \
+                 FROM pay WHERE id = 1;"
+            ),
+            vec![vec![Value::Null]]
+        );
+    }
+
+    #[test]
+    fn tests_dates_and_times() {
+        let mut db = Database::new();
+        db.execute(
+            "CREATE TABLE events (id INT PRIMARY KEY, held_on DATE, starts TIMESTAMP, \
+             starts_tz TIMESTAMP WITH TIME ZONE, duration INTERVAL);",
+        )
+        .unwrap();
+        db.execute(
+            "INSERT INTO events VALUES \
+             (1, '2024-01-31', '2024-01-31 10:30:00', '2024-01-31 10:30:00+02:00', '1 day 02:00:00'), \
+             (2, DATE '2024-03-01', TIMESTAMP '2024-02-29 23:00:00', \
+             '2024-03-01 00:00:00 America/New_York', INTERVAL 90 MINUTE);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT held_on, starts, starts_tz, duration FROM events;"
+            ),
+            vec![
+                vec![
+                    "2024-01-31",
+                    "2024-01-31 10:30:00",
+                    "2024-01-31 08:30:00+00",
+                    "1 day 02:00:00"
+                ],
+                vec![
+                    "2024-03-01",
+                    "2024-02-29 23:00:00",
+                    "2024-03-01 05:00:00+00",
+                    "01:30:00"
+                ],
+            ]
+        );
+        // Adding a month keeps the day where it can, and clamps it otherwise.
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT starts + INTERVAL '1 month', held_on + 1, held_on - DATE '2024-01-01', \
+                 starts - TIMESTAMP '2024-01-01 00:00:00', DATE_ADD(held_on, INTERVAL 1 MONTH), \
+                 starts_tz + duration, -duration * 2 FROM events WHERE id = 1;"
+            ),
+            vec![vec![
+                "2024-02-29 10:30:00",
+                "2024-02-01",
+                "30",
+                "30 days 10:30:00",
+                "2024-02-29 00:00:00",
+                "2024-02-01 10:30:00+00",
+                "-2 days -04:00:00"
+            ]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT EXTRACT(YEAR FROM held_on), DATE_PART('dow', held_on), \
+                 EXTRACT(MINUTE FROM duration), DATE_TRUNC('month', starts), \
+                 DATEDIFF(held_on, starts), starts_tz AT TIME ZONE 'Asia/Kolkata', \
+                 starts AT TIME ZONE '-01:00' FROM events WHERE id = 2;"
+            ),
+            vec![vec![
+                "2024",
+                "5",
+                "30",
+                "2024-02-01 00:00:00",
+                "1",
+                "2024-03-01 10:30:00",
+                "2024-03-01 00:00:00+00"
+            ]]
+        );
+        // Text is read as a timestamp where only a timestamp would do.
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT DATEDIFF(held_on, '2023-01-01'), DATE_ADD('2024-01-31', INTERVAL 1 MONTH), \
+                 '2024-03-01 10:00' - INTERVAL '1 day' FROM events WHERE id = 2;"
+            ),
+            vec![vec!["425", "2024-02-29 00:00:00", "2024-02-29 10:00:00"]]
+        );
+        assert!(matches!(
+            db.execute("SELECT DATEDIFF(held_on, 'soon') FROM events;"),
+            Err(Error::Type(_))
+        ));
+
+        // Dates compare with text, with each other and with timestamps.
+        for (filter, ids) in [
+            ("held_on > '2024-02-01'", vec!["2"]),
+            ("starts BETWEEN '2024-02-01' AND '2024-03-01'", vec!["2"]),
+            ("starts_tz < starts", vec!["1"]),
+            ("duration > INTERVAL '1 day'", vec!["1"]),
+            ("held_on = DATE_TRUNC('day', starts)", vec!["1"]),
+        ] {
+            let sql = format!("SELECT id FROM events WHERE {filter} ORDER BY id;");
+            let expected: Vec<Vec<&str>> = ids.into_iter().map(|id| vec![id]).collect();
+            assert_eq!(text(&mut db, &sql), expected, "{filter}");
+        }
+
+        let QueryResult::Rows(rs) = db
+            .execute("SELECT NOW(), CURRENT_DATE, starts - starts_tz FROM events;")
+            .unwrap()
+        else {
+            panic!("expected rows");
+        };
+        let types: Vec<DataType> = rs.columns.into_iter().map(|c| c.datatype).collect();
+        assert_eq!(
+            types,
+            vec![DataType::TimestampTz, DataType::Date, DataType::Interval]
+        );
+
+        assert!(matches!(
+            db.execute("INSERT INTO events (id, held_on) VALUES (3, '2024-02-30');"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT held_on + starts FROM events;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT starts + INTERVAL '1 fortnight' FROM events;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT starts AT TIME ZONE 'Mars/Olympus' FROM events;"),
+            Err(Error::Type(_))
+        ));
+
+        // Intervals are keyed by their length, with a month as 30 days.
+        db.execute("CREATE TABLE plans (length INTERVAL PRIMARY KEY, name TEXT);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO plans VALUES (INTERVAL '1 month', 'monthly'), \
+             (INTERVAL '7 days', 'weekly'), (INTERVAL '36 hours', 'trial');",
+        )
+        .unwrap();
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT name FROM plans WHERE length > INTERVAL '1 day' ORDER BY length;"
+            ),
+            [["trial"], ["weekly"], ["monthly"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT name FROM plans WHERE length = INTERVAL '1 day 12 hours';"
+            ),
+            [["trial"]]
+        );
+        assert!(matches!(
+            db.execute("INSERT INTO plans VALUES (INTERVAL '30 days', 'again');"),
+            Err(Error::Constraint(_))
+        ));
+    }
+
+    #[test]
+    fn tests_integer_and_float_widths() {
+        let mut db = Database::new();
+        db.execute(
+            "CREATE TABLE widths (id INT PRIMARY KEY, s SMALLINT, b BIGINT, \
+             u INT UNSIGNED, f FLOAT, d DOUBLE);",
+        )
+        .unwrap();
+        db.execute(
+            "INSERT INTO widths VALUES (1, 32767, 9000000000, 4294967295, 0.1, 0.1), \
+             (2, -5, -9223372036854775808, 0, 0.2, 0.2);",
+        )
+        .unwrap();
+
+        let result = match db
+            .execute("SELECT s, b, u, f, d, b + 1, u - s, d + 0.2, f + 1 FROM widths WHERE id = 1;")
+            .unwrap()
+        {
+            QueryResult::Rows(rs) => rs,
+            _ => panic!("expected rows"),
+        };
+        let types: Vec<DataType> = result.columns.iter().map(|c| c.datatype.clone()).collect();
+        use DataType::{BigInt, Double, Float, SmallInt, UnsignedInt};
+        assert_eq!(
+            types,
+            vec![
+                SmallInt,
+                BigInt,
+                UnsignedInt,
+                Float,
+                Double,
+                BigInt,
+                UnsignedInt,
+                Double,
+                Float
+            ]
+        );
+        assert_eq!(
+            result.rows,
+            vec![vec![
+                Value::SmallInt(32767),
+                Value::BigInt(9_000_000_000),
+                Value::UnsignedInt(4_294_967_295),
+                Value::Float(0.1),
+                Value::Double(0.1),
+                Value::BigInt(9_000_000_001),
+                Value::UnsignedInt(4_294_934_528),
+                Value::Double(0.1 + 0.2),
+                Value::Float(1.1),
+            ]]
+        );
+
+        let QueryResult::Rows(rs) = db
+            .execute("SELECT id FROM widths WHERE b = 9000000000;")
+            .unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(rs.rows, vec![vec![Value::Int(1)]]);
+
+        for sql in [
+            "INSERT INTO widths (id, s) VALUES (3, 32768);",
+            "INSERT INTO widths (id, u) VALUES (3, -1);",
+            "INSERT INTO widths (id, b) VALUES (3, 9223372036854775808);",
+            "SELECT s * s FROM widths WHERE id = 1;",
+            "SELECT 2147483647 + id FROM widths;",
+            "SELECT u - 1 FROM widths WHERE id = 2;",
+            "SELECT -b FROM widths WHERE id = 2;",
+            "INSERT INTO widths (id, f) VALUES (3, 1000000000000000000000000000000000000000);",
+        ] {
+            assert!(
+                matches!(db.execute(sql), Err(Error::OutOfRange(_))),
+                "{sql}"
+            );
+        }
+        assert!(matches!(
+            db.execute("SELECT b / 0 FROM widths;"),
+            Err(Error::DivisionByZero)
+        ));
+
+        // Floats of either width can be keys.
+        db.execute("CREATE TABLE points (x DOUBLE PRIMARY KEY, y FLOAT);")
+            .unwrap();
+        db.execute("INSERT INTO points VALUES (2.5, 1), (-1.5, 2), (0, 3), (12345.75, 4);")
+            .unwrap();
+        assert_eq!(
+            text(&mut db, "SELECT y FROM points WHERE x = 2.5;"),
+            [["1"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT y FROM points WHERE x BETWEEN -2 AND 1 ORDER BY x;"
+            ),
+            [["2"], ["3"]]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT y FROM points WHERE x > 0 ORDER BY x;"),
+            [["1"], ["4"]]
+        );
+        for sql in [
+            "INSERT INTO points VALUES (2.5, 5);",
+            "INSERT INTO points VALUES (-0.0, 5);",
+        ] {
+            assert!(
+                matches!(db.execute(sql), Err(Error::Constraint(_))),
+                "{sql}"
+            );
+        }
+        db.execute("CREATE TABLE ratios (r FLOAT PRIMARY KEY);")
+            .unwrap();
+        db.execute("INSERT INTO ratios VALUES (0.1), (-0.25);")
+            .unwrap();
+        assert_eq!(
+            text(&mut db, "SELECT r FROM ratios WHERE r < 0.1;"),
+            [["-0.25"]]
+        );
+        assert!(matches!(
+            db.execute("INSERT INTO ratios VALUES (0.1);"),
+            Err(Error::Constraint(_))
+        ));
+    }
+
+    #[test]
+    fn tests_exact_decimals() {
+        let mut db = Database::new();
+        db.execute(
+            "CREATE TABLE ledger (price DECIMAL(10,2) PRIMARY KEY, rate NUMERIC(6,4), qty INT);",
+        )
+        .unwrap();
+        db.execute(
+            "INSERT INTO ledger VALUES (19.995, 0.0825, 3), ('0.10', 0.1, 10), (0.2, 1, 7), \
+             (-2.5, 0.33333, 1);",
+        )
+        .unwrap();
+
+        // Values are rounded half up to the column's scale.
+        assert_eq!(
+            text(&mut db, "SELECT price, rate FROM ledger;"),
+            vec![
+                vec!["20.00", "0.0825"],
+                vec!["0.10", "0.1000"],
+                vec!["0.20", "1.0000"],
+                vec!["-2.50", "0.3333"],
+            ]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT price * qty, price + rate, price / 3, price * rate, price % 1 \
+                 FROM ledger WHERE qty = 3;"
+            ),
+            vec![vec!["60.00", "20.0825", "6.666667", "1.650000", "0.00"]]
+        );
+        // Sums are exact where floats would drift.
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT SUM(price), AVG(price), SUM(rate) FROM ledger;"
+            ),
+            vec![vec!["17.80", "4.450000", "1.5158"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT 0.1 + 0.2 = 0.3, 1.0 / 3 FROM ledger WHERE qty = 1;"
+            ),
+            vec![vec!["true", "0.33333"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT ROUND(price), TRUNCATE(price, 0), CEIL(price), FLOOR(price), \
+                 ROUND(rate, 2), ABS(price) FROM ledger WHERE qty = 1;"
+            ),
+            vec![vec!["-3", "-2", "-2", "-3", "0.33", "2.50"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT ROUND(2.5), ROUND(1.2345, 2), ROUND(1.2, 3), ROUND(125.5, -1), \
+                 TRUNC(1.2345, 3) FROM ledger WHERE qty = 1;"
+            ),
+            vec![vec!["3", "1.23", "1.2", "130", "1.234"]]
+        );
+        // Rounding keeps the digits asked for, and can carry into one more
+        // digit than the argument has.
+        db.execute("CREATE TABLE limits (d DECIMAL(10,2), wide DECIMAL(38,2));")
+            .unwrap();
+        db.execute(
+            "INSERT INTO limits VALUES (99999999.99, 999999999999999999999999999999999999.99), \
+             (-99999999.99, 0);",
+        )
+        .unwrap();
+        let QueryResult::Rows(rs) = db
+            .execute("SELECT ROUND(d, 1), CEIL(d), FLOOR(d), ROUND(d), TRUNC(d) FROM limits;")
+            .unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(
+            rs.columns
+                .iter()
+                .map(|c| c.datatype.clone())
+                .collect::<Vec<_>>(),
+            [
+                DataType::Decimal(10, 1),
+                DataType::Decimal(9, 0),
+                DataType::Decimal(9, 0),
+                DataType::Decimal(9, 0),
+                DataType::Decimal(8, 0),
+            ]
+        );
+        // Digits only known row by row keep the type's scale.
+        let QueryResult::Rows(rs) = db.execute("SELECT ROUND(price, qty) FROM ledger;").unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(rs.columns[0].datatype, DataType::Decimal(11, 2));
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT ROUND(d, 1), CEIL(d), FLOOR(d), ROUND(d), TRUNC(d) FROM limits;"
+            ),
+            [
+                [
+                    "100000000.0",
+                    "100000000",
+                    "99999999",
+                    "100000000",
+                    "99999999"
+                ],
+                [
+                    "-100000000.0",
+                    "-99999999",
+                    "-100000000",
+                    "-100000000",
+                    "-99999999"
+                ]
+            ]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT CEIL(wide) FROM limits WHERE d > 0;"),
+            [["1000000000000000000000000000000000000"]]
+        );
+        assert!(matches!(
+            db.execute("SELECT ROUND(99999999999999999999999999999999999999, -1) FROM limits;"),
+            Err(Error::OutOfRange(_))
+        ));
+
+        let QueryResult::Rows(rs) = db
+            .execute("SELECT price * qty, AVG(rate) FROM ledger GROUP BY price * qty;")
+            .unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(
+            rs.columns
+                .iter()
+                .map(|c| c.datatype.clone())
+                .collect::<Vec<_>>(),
+            vec![DataType::Decimal(38, 2), DataType::Decimal(38, 8)]
+        );
+
+        // The primary key index finds decimals however they are written.
+        assert_eq!(
+            text(&mut db, "SELECT qty FROM ledger WHERE price = 0.1;"),
+            vec![vec!["10"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT qty FROM ledger WHERE price > 0 ORDER BY price;"
+            ),
+            vec![vec!["10"], vec!["7"], vec!["3"]]
+        );
+        assert!(matches!(
+            db.execute("INSERT INTO ledger VALUES (0.1, 0, 0);"),
+            Err(Error::Constraint(_))
+        ));
+        assert!(matches!(
+            db.execute("INSERT INTO ledger VALUES (123456789.99, 0, 0);"),
+            Err(Error::OutOfRange(_))
+        ));
+        assert!(matches!(
+            db.execute("INSERT INTO ledger VALUES ('abc', 0, 0);"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT price / 0 FROM ledger;"),
+            Err(Error::DivisionByZero)
+        ));
+
+        let restored: Database = bincode::deserialize(&bincode::serialize(&db).unwrap()).unwrap();
+        assert_eq!(restored, db);
+    }
+
+    #[test]
+    fn tests_binary_values() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE blobs (hash BINARY(4) PRIMARY KEY, thumb BLOB, name TEXT);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO blobs VALUES (X'00ff10', x'89504e47', 'a'), (0x0a, NULL, 'b'), \
+             (X'0b0c', 'hi', 'c');",
+        )
+        .unwrap();
+
+        // BINARY(4) pads values with zero bytes. Bytes compare one by one,
+        // so 0x00ff10 sorts before 0x0a.
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT hash, thumb, name FROM blobs ORDER BY hash;"
+            ),
+            vec![
+                vec!["\\x00ff1000", "\\x89504e47", "a"],
+                vec!["\\x0a000000", "NULL", "b"],
+                vec!["\\x0b0c0000", "\\x6869", "c"],
+            ]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT name FROM blobs WHERE hash = X'0A000000';"),
+            vec![vec!["b"]]
+        );
+        assert!(text(&mut db, "SELECT name FROM blobs WHERE hash = X'0A';").is_empty());
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT name FROM blobs WHERE hash > X'00ff' ORDER BY hash;"
+            ),
+            vec![vec!["a"], vec!["b"], vec!["c"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT LENGTH(hash), HEX(hash), LENGTH(name), HEX(name), UNHEX('cafe'), \
+                 UNHEX('xyz') FROM blobs WHERE name = 'a';"
+            ),
+            vec![vec!["4", "00FF1000", "1", "61", "\\xcafe", "NULL"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT HEX(255), HEX(0), HEX(-1), HEX(LENGTH(hash)) FROM blobs WHERE name = 'a';"
+            ),
+            vec![vec!["FF", "0", "FFFFFFFFFFFFFFFF", "4"]]
+        );
+        assert!(matches!(
+            db.execute("SELECT HEX(1.5) FROM blobs;"),
+            Err(Error::Type(_))
+        ));
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT MD5(X'6869') = MD5('hi') FROM blobs WHERE name = 'a';"
+            ),
+            vec![vec!["true"]]
+        );
+        assert!(matches!(
+            db.execute("INSERT INTO blobs VALUES (X'0a', NULL, 'd');"),
+            Err(Error::Constraint(_))
+        ));
+        let QueryResult::Rows(rs) = db
+            .execute("SELECT thumb || X'21', thumb || thumb FROM blobs WHERE name = 'c';")
+            .unwrap()
+        else {
+            panic!("expected rows");
+        };
+        assert_eq!(
+            rs.columns
+                .iter()
+                .map(|c| c.datatype.clone())
+                .collect::<Vec<_>>(),
+            [DataType::Binary, DataType::Binary]
+        );
+        assert_eq!(
+            rs.rows,
+            [[
+                Value::Binary(b"hi!".to_vec()),
+                Value::Binary(b"hihi".to_vec())
+            ]]
+        );
+        // VARBINARY(n) keeps values as they are, up to n bytes.
+        db.execute("CREATE TABLE tags (id INT PRIMARY KEY, tag VARBINARY(3));")
+            .unwrap();
+        db.execute("INSERT INTO tags VALUES (1, X'0102');").unwrap();
+        assert_eq!(
+            text(&mut db, "SELECT tag FROM tags;"),
+            vec![vec!["\\x0102"]]
+        );
+        for sql in [
+            "INSERT INTO blobs VALUES (X'0102030405', NULL, 'e');",
+            "INSERT INTO tags VALUES (2, X'01020304');",
+        ] {
+            assert!(
+                matches!(db.execute(sql), Err(Error::OutOfRange(_))),
+                "{sql}"
+            );
+        }
+        for sql in [
+            "SELECT * FROM blobs WHERE hash = X'0g';",
+            "SELECT * FROM blobs WHERE hash = X'ABC';",
+        ] {
+            assert!(matches!(db.execute(sql), Err(Error::Parse(_))), "{sql}");
+        }
+        assert!(matches!(
+            db.execute("SELECT UPPER(hash) FROM blobs;"),
+            Err(Error::Type(_))
+        ));
+
+        let restored: Database = bincode::deserialize(&bincode::serialize(&db).unwrap()).unwrap();
+        assert_eq!(restored, db);
+    }
+
+    #[test]
+    fn tests_json_documents() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE events (id INT PRIMARY KEY, doc JSON);")
+            .unwrap();
+        db.execute(
+            r#"INSERT INTO events VALUES
+               (1, '{"user": {"name": "ann", "age": 31}, "tags": ["a", "b"], "city": "Oslo"}'),
+               (2, '{"city":"Rome","tags":[],"user":{"age":25,"name":"bob"}}'),
+               (3, '{"user": {"name": "cy", "age": 40.5}, "tags": ["c"], "city": "Oslo"}'),
+               (4, NULL);"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            db.execute("INSERT INTO events VALUES (5, '{\"user\": ');"),
+            Err(Error::Type(_))
+        ));
+
+        // Documents are stored parsed, so they print the same however they were written.
+        assert_eq!(
+            text(&mut db, "SELECT doc FROM events WHERE id = 2;"),
+            vec![vec![
+                r#"{"city": "Rome", "tags": [], "user": {"age": 25, "name": "bob"}}"#
+            ]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT doc->'$.user.name', doc->>'$.user.name', doc->'city', doc->'tags'->-1, \
+                 doc->'$.nope' FROM events WHERE id = 1;"
+            ),
+            vec![vec![r#""ann""#, "ann", r#""Oslo""#, r#""b""#, "NULL"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id FROM events WHERE doc->'$.user.age' > 30 \
+                 ORDER BY doc->'$.user.age' DESC;"
+            ),
+            vec![vec!["3"], vec!["1"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id FROM events WHERE doc->'$.user.name' = 'bob';"
+            ),
+            vec![vec!["2"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT doc->>'city', COUNT(*), JSON_ARRAYAGG(id) FROM events \
+                 WHERE doc IS NOT NULL GROUP BY doc->>'city' ORDER BY doc->>'city';"
+            ),
+            vec![vec!["Oslo", "2", "[1, 3]"], vec!["Rome", "1", "[2]"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT JSON_ARRAY_LENGTH(doc->'tags'), JSON_ARRAY_LENGTH(doc, '$.tags'), \
+                 JSON_ARRAY_LENGTH(doc), JSON_EXTRACT(doc, '$.city', '$.tags[0]') \
+                 FROM events WHERE id = 1;"
+            ),
+            vec![vec!["2", "2", "NULL", r#"["Oslo", "a"]"#]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT JSON_SET(doc, '$.city', 'Bergen', '$.tags[9]', NULL, '$.no.such', 1), \
+                 JSON_OBJECT('id', id, 'name', doc->>'$.user.name', 'x', NULL) \
+                 FROM events WHERE id = 2;"
+            ),
+            vec![vec![
+                r#"{"city": "Bergen", "tags": [null], "user": {"age": 25, "name": "bob"}}"#,
+                r#"{"id": 2, "name": "bob", "x": null}"#
+            ]]
+        );
+        // Paths are checked even where there is no document to look in.
+        db.execute("INSERT INTO events VALUES (9, NULL);").unwrap();
+        for sql in [
+            "SELECT doc->'$[' FROM events;",
+            "SELECT JSON_EXTRACT(doc, '$[') FROM events WHERE id = 9;",
+            "SELECT JSON_EXTRACT(doc, 'city') FROM events;",
+            "SELECT JSON_EXTRACT(doc, '$.city', '$.tags[x]') FROM events;",
+            "SELECT JSON_ARRAY_LENGTH(doc, 'tags') FROM events;",
+            "SELECT JSON_SET(doc, '$..city', 1) FROM events;",
+        ] {
+            assert!(matches!(db.execute(sql), Err(Error::Type(_))), "{sql}");
+        }
+
+        let restored: Database = bincode::deserialize(&bincode::serialize(&db).unwrap()).unwrap();
+        assert_eq!(restored, db);
+    }
+
+    #[test]
+    fn tests_uuid_keys() {
+        let mut db = Database::new();
+        db.execute(
+            "CREATE TABLE sessions (id UUID PRIMARY KEY DEFAULT gen_random_uuid(), \
+             owner UUID, name TEXT DEFAULT 'guest');",
+        )
+        .unwrap();
+        db.execute(
+            "INSERT INTO sessions (id, owner, name) VALUES \
+             ('A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11', '{0123456789abcdef0123456789abcdef}', 'ann');",
+        )
+        .unwrap();
+        db.execute(
+            "INSERT INTO sessions (owner) VALUES ('ffffffff-ffff-4fff-bfff-ffffffffffff'), (NULL);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT owner, name FROM sessions \
+                 WHERE id = 'a0eebc999c0b4ef8bb6d6bb9bd380a11';"
+            ),
+            vec![vec!["01234567-89ab-cdef-0123-456789abcdef", "ann"]]
+        );
+        // Each defaulted row gets its own version 4 UUID.
+        let generated = text(
+            &mut db,
+            "SELECT id, owner FROM sessions WHERE name = 'guest' ORDER BY owner;",
+        );
+        assert_eq!(generated.len(), 2);
+        assert_ne!(generated[0][0], generated[1][0]);
+        for row in &generated {
+            assert_eq!(row[0].len(), 36);
+            assert_eq!(&row[0][14..15], "4");
+        }
+        assert_eq!(generated[1][1], "NULL");
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT UUID() = GEN_RANDOM_UUID(), UUID() = id FROM sessions WHERE name = 'ann';"
+            ),
+            vec![vec!["false", "false"]]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT COUNT(*) FROM sessions WHERE id < owner;"),
+            vec![vec!["1"]]
+        );
+
+        assert!(matches!(
+            db.execute("INSERT INTO sessions (id) VALUES ('a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a1');"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute(
+                "INSERT INTO sessions (id, name) VALUES \
+                 ('a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11', 'bob');"
+            ),
+            Err(Error::Constraint(_))
+        ));
+        assert!(matches!(
+            db.execute("CREATE TABLE t (a INT, b INT DEFAULT a + 1);"),
+            Err(Error::Binding(_))
+        ));
+        for (sql, type_error) in [
+            ("CREATE TABLE t (id INT DEFAULT 'abc');", true),
+            ("CREATE TABLE t (id UUID DEFAULT NOW());", true),
+            ("CREATE TABLE t (n SMALLINT DEFAULT 70000);", false),
+        ] {
+            match db.execute(sql) {
+                Err(Error::Type(_)) => assert!(type_error, "{sql}"),
+                Err(Error::OutOfRange(_)) => assert!(!type_error, "{sql}"),
+                result => panic!("{sql}: {result:?}"),
+            }
+        }
+        assert!(!db.table_exists("t".to_string()));
+        db.execute("CREATE TABLE t (id INT DEFAULT '7', d DECIMAL(5,2) DEFAULT 1.005);")
+            .unwrap();
+        db.execute("INSERT INTO t (id) VALUES (1);").unwrap();
+        assert_eq!(text(&mut db, "SELECT id, d FROM t;"), [["1", "1.01"]]);
+
+        let restored: Database = bincode::deserialize(&bincode::serialize(&db).unwrap()).unwrap();
+        assert_eq!(restored, db);
+    }
+
+    #[test]
+    fn tests_array_columns() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE posts (id INT PRIMARY KEY, scores INT[], tags TEXT[]);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO posts (id, scores, tags) VALUES \
+             (1, ARRAY[3, 1, 2], '{rust,\"sql db\"}'), \
+             (2, '{10,NULL}', ARRAY['go']), \
+             (3, NULL, '{}');",
+        )
+        .unwrap();
+
+        assert_eq!(
+            text(&mut db, "SELECT scores, tags FROM posts ORDER BY id;"),
+            vec![
+                vec!["{3,1,2}", "{rust,\"sql db\"}"],
+                vec!["{10,NULL}", "{go}"],
+                vec!["NULL", "{}"],
+            ]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT scores[1], tags[2], ARRAY_LENGTH(tags, 1) FROM posts ORDER BY id;"
+            ),
+            vec![
+                vec!["3", "sql db", "2"],
+                vec!["10", "NULL", "1"],
+                vec!["NULL", "NULL", "NULL"],
+            ]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT id FROM posts WHERE 'go' = ANY(tags);"),
+            vec![vec!["2"]]
+        );
+        // A NULL item leaves ALL unknown unless another item fails it.
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id, 2 < ALL(scores), 5 < ALL(scores) FROM posts ORDER BY id;"
+            ),
+            vec![
+                vec!["1", "false", "false"],
+                vec!["2", "NULL", "NULL"],
+                vec!["3", "NULL", "NULL"],
+            ]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id FROM posts WHERE id = ANY('{1,3}') ORDER BY id;"
+            ),
+            vec![vec!["1"], vec!["3"]]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT ARRAY_AGG(id) FROM posts WHERE id > 1;"),
+            vec![vec!["{2,3}"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT ARRAY[1, 2.5], ARRAY[1, '2'] FROM posts WHERE id = 1;"
+            ),
+            vec![vec!["{1.0,2.5}", "{1,2}"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT x, y FROM UNNEST(ARRAY[1, 2, 3], ARRAY['a']) AS t(x, y) \
+                 WHERE x > 1 ORDER BY x DESC;"
+            ),
+            vec![vec!["3", "NULL"], vec!["2", "NULL"]]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT SUM(n) FROM UNNEST(ARRAY[4, 5]) AS n;"),
+            vec![vec!["9"]]
+        );
+        // UNNEST after a table, or in the select list, expands each row's
+        // arrays; a row with no items is left out.
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id, tag FROM posts, UNNEST(tags) AS tag ORDER BY id, tag;"
+            ),
+            vec![vec!["1", "rust"], vec!["1", "sql db"], vec!["2", "go"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT p.id, s.n FROM posts AS p CROSS JOIN UNNEST(p.scores) AS s(n) \
+                 WHERE s.n > 1 ORDER BY s.n;"
+            ),
+            vec![vec!["1", "2"], vec!["1", "3"], vec!["2", "10"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id, UNNEST(scores), UNNEST(tags) AS t FROM posts WHERE id < 3 ORDER BY id;"
+            ),
+            vec![
+                vec!["1", "3", "rust"],
+                vec!["1", "1", "sql db"],
+                vec!["1", "2", "NULL"],
+                vec!["2", "10", "go"],
+                vec!["2", "NULL", "NULL"],
+            ]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT SUM(x) FROM posts, UNNEST(scores) AS x;"),
+            vec![vec!["16"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT ARRAY[1, NULL] || ARRAY[9], scores || 4, 0 || scores, tags || NULL \
+                 FROM posts WHERE id = 1;"
+            ),
+            vec![vec![
+                "{1,NULL,9}",
+                "{3,1,2,4}",
+                "{0,3,1,2}",
+                "{rust,\"sql db\"}"
+            ]]
+        );
+        db.execute(
+            "INSERT INTO posts (id, scores) VALUES (4, ARRAY[]::int[]), (5, CAST('{7}' AS int[]));",
+        )
+        .unwrap();
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT scores || 1 FROM posts WHERE id > 3 ORDER BY id;"
+            ),
+            vec![vec!["{1}"], vec!["{7,1}"]]
+        );
+
+        assert!(matches!(
+            db.execute("INSERT INTO posts (id, scores) VALUES (6, '{1,x}');"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT scores || tags FROM posts;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT id FROM posts WHERE id = ANY(tags);"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT * FROM UNNEST(1) AS t;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT id FROM posts, UNNEST(id) AS x;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT UNNEST(a.tags) FROM posts AS a JOIN posts AS b ON a.id = b.id;"),
+            Err(Error::Parse(_))
+        ));
+
+        let restored: Database = bincode::deserialize(&bincode::serialize(&db).unwrap()).unwrap();
+        assert_eq!(restored, db);
+    }
+
+    #[test]
+    fn tests_enum_types() {
+        let mut db = Database::new();
+        assert_eq!(
+            db.execute("CREATE TYPE status AS ENUM ('new', 'paid', 'shipped');"),
+            Ok(QueryResult::CreatedType)
+        );
+        db.execute("CREATE TABLE orders (id INT PRIMARY KEY, state status, history status[]);")
+            .unwrap();
+        db.execute(
+            "INSERT INTO orders (id, state, history) VALUES \
+             (1, 'shipped', '{new,paid,shipped}'), (2, 'new', '{new}'), (3, 'paid', NULL), \
+             (4, NULL, NULL);",
+        )
+        .unwrap();
+        db.execute("CREATE TABLE stages (stage status PRIMARY KEY, note TEXT);")
+            .unwrap();
+        db.execute("INSERT INTO stages VALUES ('paid', 'settled'), ('new', 'open');")
+            .unwrap();
+
+        // Labels sort in the order they were declared, not alphabetically.
+        assert_eq!(
+            text(&mut db, "SELECT id, state FROM orders ORDER BY state, id;"),
+            vec![
+                vec!["2", "new"],
+                vec!["3", "paid"],
+                vec!["1", "shipped"],
+                vec!["4", "NULL"],
+            ]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id FROM orders WHERE state > 'new' ORDER BY id;"
+            ),
+            vec![vec!["1"], vec!["3"]]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT history[2] FROM orders WHERE id = 1;"),
+            vec![vec!["paid"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "WITH s AS (SELECT state FROM orders WHERE id < 3) \
+                 SELECT state FROM s ORDER BY state;"
+            ),
+            vec![vec!["new"], vec!["shipped"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT state FROM orders WHERE id = 3 UNION SELECT state FROM orders WHERE id = 2 \
+                 ORDER BY state;"
+            ),
+            vec![vec!["new"], vec!["paid"]]
+        );
+
+        assert_eq!(
+            db.execute("ALTER TYPE status ADD VALUE 'cancelled' BEFORE 'new';"),
+            Ok(QueryResult::AlteredType)
+        );
+        db.execute("ALTER TYPE status ADD VALUE 'returned';")
+            .unwrap();
+        db.execute("ALTER TYPE status ADD VALUE IF NOT EXISTS 'paid' AFTER 'new';")
+            .unwrap();
+        db.execute("INSERT INTO orders (id, state) VALUES (5, 'cancelled'), (6, 'returned');")
+            .unwrap();
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id FROM orders WHERE state IS NOT NULL ORDER BY state;"
+            ),
+            vec![vec!["5"], vec!["2"], vec!["3"], vec!["1"], vec!["6"]]
+        );
+        // Keys follow the labels to their new positions.
+        db.execute("INSERT INTO stages VALUES ('cancelled', 'dropped');")
+            .unwrap();
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT note FROM stages WHERE stage > 'cancelled' ORDER BY stage;"
+            ),
+            vec![vec!["open"], vec!["settled"]]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT note FROM stages WHERE stage = 'paid';"),
+            vec![vec!["settled"]]
+        );
+        assert!(matches!(
+            db.execute("INSERT INTO stages VALUES ('new', 'again');"),
+            Err(Error::Constraint(_))
+        ));
+
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT CAST('paid' AS status) < 'shipped'::status, state::text, \
+                 ARRAY['new', 'paid']::status[] FROM orders WHERE id = 3;"
+            ),
+            vec![vec!["true", "paid", "{new,paid}"]]
+        );
+        db.execute("INSERT INTO orders (id, state) VALUES (7, 'paid'::status);")
+            .unwrap();
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id FROM orders WHERE state = 'paid'::status ORDER BY id;"
+            ),
+            vec![vec!["3"], vec!["7"]]
+        );
+        assert!(matches!(
+            db.execute("SELECT 'lost'::status FROM orders;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("INSERT INTO orders (id, state) VALUES (7, 'lost');"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT id FROM orders WHERE state = 'lost';"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("CREATE TYPE status AS ENUM ('a');"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("CREATE TYPE int AS ENUM ('a');"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("CREATE TYPE twice AS ENUM ('a', 'a');"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("ALTER TYPE status ADD VALUE 'paid';"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("ALTER TYPE status ADD VALUE 'held' AFTER 'lost';"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("ALTER TYPE missing ADD VALUE 'a';"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("CREATE TYPE broken AS ENUM (1);"),
+            Err(Error::Parse(_))
+        ));
+
+        let restored: Database = bincode::deserialize(&bincode::serialize(&db).unwrap()).unwrap();
+        assert_eq!(restored, db);
+    }
+
+    #[test]
+    fn tests_casts_and_coercion() {
+        let mut db = Database::new();
+        db.execute(
+            "CREATE TABLE items (id INT PRIMARY KEY, qty SMALLINT, price FLOAT, \
+             cost DECIMAL(5,2), active BOOL, code TEXT, added TIMESTAMP);",
+        )
+        .unwrap();
+        db.execute(
+            "INSERT INTO items (id, qty, price, cost, active, code, added) VALUES \
+             (1, 2, 2.5, 3.45, true, '42', '2024-01-31 10:30:00'), \
+             (2, -3, -2.5, -1.5, false, 'x', NULL);",
+        )
+        .unwrap();
+
+        // CAST rounds halves away from zero; storing a value never rounds.
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT CAST(price AS INT), cost::int, active::int, id::bool FROM items ORDER BY id;"
+            ),
+            vec![vec!["3", "3", "1", "true"], vec!["-3", "-2", "0", "true"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT added::date, added::time, CAST(code AS INT) + 1, '2024-02-01'::date - 1 \
+                 FROM items WHERE id = 1;"
+            ),
+            vec![vec!["2024-01-31", "10:30:00", "43", "2024-01-31"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT CAST(ARRAY[1.5, 2.5] AS INT[]) FROM items WHERE id = 1;"
+            ),
+            vec![vec!["{2,3}"]]
+        );
+
+        // A whole number fits an integer column, and a comparison widens
+        // rather than narrowing the number to the column's type.
+        db.execute("INSERT INTO items (id) VALUES (3.0);").unwrap();
+        assert_eq!(
+            text(&mut db, "SELECT id FROM items WHERE id < 1.5;"),
+            vec![vec!["1"]]
+        );
+        assert_eq!(
+            text(&mut db, "SELECT id FROM items WHERE id = 2.5;"),
+            Vec::<Vec<String>>::new()
+        );
+        assert_eq!(
+            text(&mut db, "SELECT id FROM items WHERE cost = 3.451;"),
+            Vec::<Vec<String>>::new()
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id FROM items WHERE cost < '3.451' ORDER BY id;"
+            ),
+            vec![vec!["1"], vec!["2"]]
+        );
+        assert_eq!(
+            text(
+                &mut db,
+                "SELECT id FROM items WHERE qty < 70000 ORDER BY id;"
+            ),
+            vec![vec!["1"], vec!["2"]]
+        );
+
+        // A decimal rounds to its scale, but an integer has no fraction to
+        // round to, so losing one is an error.
+        assert!(matches!(
+            db.execute("INSERT INTO items (id) VALUES (4.5);"),
+            Err(Error::Type(_))
+        ));
+        db.execute("INSERT INTO items (id, cost) VALUES (5, 1.005), (6, '-2.344');")
+            .unwrap();
+        assert_eq!(
+            text(&mut db, "SELECT cost FROM items WHERE id > 4 ORDER BY id;"),
+            vec![vec!["1.01"], vec!["-2.34"]]
+        );
+        assert!(matches!(
+            db.execute("INSERT INTO items (id, qty) VALUES (4, 70000);"),
+            Err(Error::OutOfRange(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT CAST(added AS INT) FROM items;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT CAST(code AS INT) FROM items;"),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT CAST(70000 AS SMALLINT) FROM items;"),
+            Err(Error::OutOfRange(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT CAST(id AS nothing) FROM items;"),
+            Err(Error::Parse(_))
+        ));
+
+        let restored: Database = bincode::deserialize(&bincode::serialize(&db).unwrap()).unwrap();
+        assert_eq!(restored, db);
+
+        let prepared = db.prepare("SELECT CAST(? AS INT) FROM items;").unwrap();
+        assert_eq!(prepared.param_types(), &[Some(DataType::Int)]);
+    }
+
+    #[test]
+    fn tests_errors_are_reported_by_kind() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id INT PRIMARY KEY, name TEXT);")
+            .unwrap();
+        db.execute("INSERT INTO users (id, name) VALUES (1, 'a');")
+            .unwrap();
+
+        assert!(matches!(
+            db.execute("SELEC * FROM users;"),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT * FROM users WHERE EXISTS (SELECT 1);"),
+            Err(Error::Parse(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT * FROM nope;"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("SELECT * FROM users WHERE missing = 1;"),
+            Err(Error::Binding(_))
+        ));
+        assert!(matches!(
+            db.execute("INSERT INTO users (id, name) VALUES ('x', 'b');"),
+            Err(Error::Type(_))
+        ));
+        for sql in [
+            "CREATE TABLE t (id INT, kind nosuchtype);",
+            "CREATE TABLE t (id INT, tags nosuchtype[]);",
+        ] {
+            assert!(matches!(db.execute(sql), Err(Error::Type(_))));
+        }
+        assert!(!db.table_exists("t".to_string()));
+        assert!(matches!(
+            db.execute("INSERT INTO users (id, name) VALUES (1, 'b');"),
+            Err(Error::Constraint(_))
+        ));
+        // A multi-row INSERT is all or nothing.
+        for sql in [
+            "INSERT INTO users (id, name) VALUES (2, 'b'), (2, 'c');",
+            "INSERT INTO users (id, name) VALUES (3, 'c'), (1, 'd');",
+            "INSERT INTO users (id, name) VALUES (4, 'd'), ('x', 'e');",
+        ] {
+            assert!(db.execute(sql).is_err());
+        }
+
+        let QueryResult::Rows(rs) = db.execute("SELECT * FROM users;").unwrap() else {
+            panic!("expected rows");
+        };
+        assert_eq!(rs.rows.len(), 1);
     }
 }
@@ -0,0 +1,432 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{Error, Result};
+use crate::expr::{promote, EmptyScope, Scope};
+use crate::parser::create::{CreateQuery, ParsedColumn};
+use crate::parser::query::{Query, SetOp, SetQuery};
+use crate::parser::select::{SelectItem, SelectQuery, UnnestTable};
+use crate::result::ResultColumn;
+use crate::table::{compare_sort_keys, DataType, RowScope, Table};
+use crate::value::{array, Key, Value};
+
+use super::with::{cte_table, type_string};
+use super::{output_reference, Database};
+
+/// Evaluates the ORDER BY of a set operation against one combined row.
+struct OutputScope<'a> {
+    columns: &'a [String],
+    row: &'a [Value],
+}
+
+impl Scope for OutputScope<'_> {
+    fn column(&self, name: &str) -> Result<Value> {
+        match self.columns.iter().position(|c| c == name) {
+            Some(i) => Ok(self.row[i].clone()),
+            None => Err(Error::Binding(format!("Cannot find column {name}"))),
+        }
+    }
+}
+
+/// The columns an expression outside any table can read: none.
+fn no_column(name: &str) -> Result<DataType> {
+    Err(Error::Binding(format!(
+        "Cannot refer to column {name} here"
+    )))
+}
+
+/// The type of the items UNNEST takes out of an array of `datatype`.
+fn unnest_type(datatype: Option<DataType>) -> Option<DataType> {
+    match datatype {
+        Some(DataType::Array(item)) => unnest_type(Some(*item)),
+        datatype => datatype,
+    }
+}
+
+/// The items of an array UNNEST reads, with nested arrays flattened. A
+/// NULL array has none.
+fn unnest_items(value: Value) -> Result<Vec<Value>> {
+    match value {
+        Value::Array(items) => {
+            let mut leaves = vec![];
+            array::flatten(&items, &mut leaves);
+            Ok(leaves.into_iter().cloned().collect())
+        }
+        Value::Null => Ok(vec![]),
+        v => Err(Error::Type(format!("{v} is not an array"))),
+    }
+}
+
+/// The items of `arrays` side by side, one row for each, with shorter
+/// arrays padded with NULLs.
+fn zip_items(arrays: &[Vec<Value>]) -> Vec<Vec<Value>> {
+    let count = arrays.iter().map(Vec::len).max().unwrap_or(0);
+    (0..count)
+        .map(|i| {
+            arrays
+                .iter()
+                .map(|items| items.get(i).cloned().unwrap_or(Value::Null))
+                .collect()
+        })
+        .collect()
+}
+
+/// `table` with each row repeated for every item of the arrays `unnest`
+/// takes from it, and those items in columns named `alias.column`. A row
+/// whose arrays are all empty or NULL is left out.
+fn unnest_rows(table: &Table, unnest: &UnnestTable) -> Result<Table> {
+    let column_type = |col: &str| table.column_type(col);
+    let mut columns: Vec<ParsedColumn> = table
+        .columns
+        .iter()
+        .map(|c| ParsedColumn {
+            name: c.name.to_string(),
+            datatype: type_string(&c.datatype),
+            is_pk: false,
+            is_nullable: true,
+            default: None,
+        })
+        .collect();
+    for (arg, name) in unnest.args.iter().zip(&unnest.columns) {
+        let datatype = match arg.datatype(&column_type)? {
+            None => DataType::Str,
+            Some(DataType::Array(item)) => unnest_type(Some(*item)).unwrap_or(DataType::Str),
+            Some(d) => return Err(Error::Type(format!("UNNEST needs an array, not {d}"))),
+        };
+        columns.push(ParsedColumn {
+            name: format!("{}.{name}", unnest.alias),
+            datatype: type_string(&datatype),
+            is_pk: false,
+            is_nullable: true,
+            default: None,
+        });
+    }
+
+    let data: Vec<Vec<Value>> = table
+        .columns
+        .iter()
+        .map(|c| table.rows[&c.name].get_values())
+        .collect();
+    let mut rows = vec![];
+    for idx in 0..table.row_count() {
+        let scope = RowScope { table, idx };
+        let arrays = unnest
+            .args
+            .iter()
+            .map(|arg| unnest_items(arg.eval(&scope)?))
+            .collect::<Result<Vec<_>>>()?;
+        let row: Vec<Value> = data.iter().map(|values| values[idx].clone()).collect();
+        for items in zip_items(&arrays) {
+            rows.push(row.iter().cloned().chain(items).collect());
+        }
+    }
+    let names: Vec<String> = columns.iter().map(|c| c.name.to_string()).collect();
+    let mut unnested = Table::new(CreateQuery {
+        table_name: table.name.to_string(),
+        columns,
+    });
+    unnested.insert_row(&names, &rows)?;
+    Ok(unnested)
+}
+
+impl Database {
+    /// The table of every pair of rows of the tables `sq` joins, with the
+    /// columns named like `alias.column`, or None if it reads one table.
+    pub(super) fn join_table(&self, sq: &SelectQuery) -> Result<Option<Table>> {
+        if sq.joined.is_empty() {
+            return Ok(None);
+        }
+        let mut columns = vec![];
+        let mut rows: Vec<Vec<Value>> = vec![vec![]];
+        for joined in &sq.joined {
+            let table = self.get_table(joined.table.to_string())?;
+            let data: Vec<Vec<Value>> = table
+                .columns
+                .iter()
+                .map(|c| table.rows[&c.name].get_values())
+                .collect();
+            let table_rows: Vec<Vec<Value>> = (0..table.row_count())
+                .map(|i| data.iter().map(|values| values[i].clone()).collect())
+                .collect();
+            rows = rows
+                .iter()
+                .flat_map(|left| {
+                    table_rows
+                        .iter()
+                        .map(move |right| left.iter().chain(right).cloned().collect())
+                })
+                .collect();
+            columns.extend(table.columns.iter().map(|c| ParsedColumn {
+                name: format!("{}.{}", joined.alias, c.name),
+                datatype: type_string(&c.datatype),
+                is_pk: false,
+                is_nullable: true,
+                default: None,
+            }));
+        }
+        let names: Vec<String> = columns.iter().map(|c| c.name.to_string()).collect();
+        let mut table = Table::new(CreateQuery {
+            table_name: sq.from.to_string(),
+            columns,
+        });
+        table.insert_row(&names, &rows)?;
+        match &sq.unnest {
+            Some(unnest) => unnest_rows(&table, unnest).map(Some),
+            None => Ok(Some(table)),
+        }
+    }
+
+    pub(super) fn plan_query(&mut self, query: Query) -> Result<Query> {
+        match query {
+            Query::With(with) => {
+                let depth = self.ctes.len();
+                let planned = self.plan_with_query(*with);
+                self.ctes.truncate(depth);
+                Ok(Query::With(Box::new(planned?)))
+            }
+            Query::Select(sq) => Ok(Query::Select(Box::new(self.plan_select_query(*sq)?))),
+            Query::SetOperation(set) => {
+                let mut set = SetQuery {
+                    left: self.plan_query(set.left)?,
+                    right: self.plan_query(set.right)?,
+                    ..*set
+                };
+                let columns = self.query_columns(&Query::SetOperation(Box::new(set.clone())))?;
+                let projection: Vec<SelectItem> = columns
+                    .iter()
+                    .map(|c| SelectItem::column(c.name.to_string()))
+                    .collect();
+                let column_type = |col: &str| {
+                    columns
+                        .iter()
+                        .find(|c| c.name == col)
+                        .map(|c| c.datatype.clone())
+                        .ok_or_else(|| Error::Binding(format!("Cannot find column {col}")))
+                };
+                for o in &mut set.order_by {
+                    o.expr = output_reference(&o.expr, &projection, true, &column_type)?;
+                    o.expr.resolve(&column_type)?;
+                    if o.expr.contains_aggregate() {
+                        return Err(Error::Parse(format!(
+                            "Aggregates are not allowed in the ORDER BY of {}",
+                            set.op
+                        )));
+                    }
+                }
+                Ok(Query::SetOperation(Box::new(set)))
+            }
+            Query::Unnest(mut args) => {
+                for arg in &mut args {
+                    match arg.resolve(&no_column)? {
+                        None | Some(DataType::Array(_)) => {}
+                        Some(d) => {
+                            return Err(Error::Type(format!("UNNEST needs an array, not {d}")))
+                        }
+                    }
+                }
+                Ok(Query::Unnest(args))
+            }
+        }
+    }
+
+    /// The types of the columns a query returns. Where the two sides of a
+    /// set operation differ, Ints and Floats combine as Floats.
+    pub(super) fn column_types(&mut self, query: &Query) -> Result<Vec<Option<DataType>>> {
+        let set = match query {
+            Query::Select(sq) => {
+                let joined = self.join_table(sq)?;
+                let db_table = match &joined {
+                    Some(table) => table,
+                    None => self.get_table(sq.from.to_string())?,
+                };
+                let column_type = |col: &str| db_table.column_type(col);
+                return sq
+                    .projection
+                    .iter()
+                    .map(|item| item.expr.datatype(&column_type))
+                    .collect();
+            }
+            Query::SetOperation(set) => set,
+            Query::With(with) => {
+                let depth = self.ctes.len();
+                for cte in &with.ctes {
+                    self.ctes.push(cte_table(cte, &[])?);
+                }
+                let types = self.column_types(&with.body);
+                self.ctes.truncate(depth);
+                return types;
+            }
+            Query::Unnest(args) => {
+                return args
+                    .iter()
+                    .map(|arg| Ok(unnest_type(arg.datatype(&no_column)?)))
+                    .collect()
+            }
+        };
+        let left = self.column_types(&set.left)?;
+        let right = self.column_types(&set.right)?;
+        if left.len() != right.len() {
+            return Err(Error::Binding(format!(
+                "Each {} query must have the same number of columns",
+                set.op
+            )));
+        }
+        left.into_iter()
+            .zip(right)
+            .map(|(l, r)| match (l, r) {
+                (Some(l), Some(r)) if l == r => Ok(Some(l)),
+                (Some(l), Some(r)) if l.is_numeric() && r.is_numeric() => Ok(promote(&l, &r)),
+                (Some(l), Some(r)) => Err(Error::Type(format!(
+                    "{} types {l} and {r} cannot be matched",
+                    set.op
+                ))),
+                (l, r) => Ok(l.or(r)),
+            })
+            .collect()
+    }
+
+    /// The columns of a query, named after the leftmost SELECT.
+    pub(super) fn query_columns(&mut self, query: &Query) -> Result<Vec<ResultColumn>> {
+        match query {
+            Query::With(with) => {
+                let depth = self.ctes.len();
+                for cte in &with.ctes {
+                    self.ctes.push(cte_table(cte, &[])?);
+                }
+                let columns = self.query_columns(&with.body);
+                self.ctes.truncate(depth);
+                columns
+            }
+            Query::Select(sq) => self.result_columns(sq),
+            Query::SetOperation(set) => {
+                let mut columns = self.query_columns(&set.left)?;
+                for (column, datatype) in columns.iter_mut().zip(self.column_types(query)?) {
+                    column.datatype = datatype.unwrap_or(DataType::Str);
+                    column.table = None;
+                }
+                Ok(columns)
+            }
+            Query::Unnest(_) => Ok(self
+                .column_types(query)?
+                .into_iter()
+                .map(|datatype| ResultColumn {
+                    name: "unnest".to_string(),
+                    datatype: datatype.unwrap_or(DataType::Str),
+                    table: None,
+                })
+                .collect()),
+        }
+    }
+
+    pub(super) fn query_rows(&mut self, query: &Query) -> Result<Vec<Vec<Value>>> {
+        match query {
+            Query::With(with) => {
+                let depth = self.ctes.len();
+                let rows = self
+                    .materialize_ctes(with)
+                    .and_then(|_| self.query_rows(&with.body));
+                self.ctes.truncate(depth);
+                rows
+            }
+            Query::Select(sq) => match self.join_table(sq)? {
+                Some(table) => table.execute_select_query(sq),
+                None => self
+                    .get_table(sq.from.to_string())?
+                    .execute_select_query(sq),
+            },
+            Query::SetOperation(set) => self.execute_set_query(query, set),
+            Query::Unnest(args) => {
+                let arrays = args
+                    .iter()
+                    .map(|arg| unnest_items(arg.eval(&EmptyScope)?))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(zip_items(&arrays))
+            }
+        }
+    }
+
+    fn execute_set_query(&mut self, query: &Query, set: &SetQuery) -> Result<Vec<Vec<Value>>> {
+        let types = self.column_types(query)?;
+        let coerce = |rows: Vec<Vec<Value>>| {
+            rows.into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .zip(&types)
+                        .map(|(v, datatype)| match datatype {
+                            Some(d) if d.is_numeric() => v.coerce(d),
+                            _ => Ok(v),
+                        })
+                        .collect::<Result<Vec<Value>>>()
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        let left = coerce(self.query_rows(&set.left)?)?;
+        let right = coerce(self.query_rows(&set.right)?)?;
+
+        let mut seen = HashSet::new();
+        let mut rows = match set.op {
+            SetOp::Union => {
+                let mut rows = left;
+                rows.extend(right);
+                if !set.all {
+                    rows.retain(|row| seen.insert(Key(row.clone())));
+                }
+                rows
+            }
+            SetOp::Intersect | SetOp::Except => {
+                let mut counts: HashMap<Key, usize> = HashMap::new();
+                for row in right {
+                    *counts.entry(Key(row)).or_default() += 1;
+                }
+                let intersect = set.op == SetOp::Intersect;
+                let mut rows = vec![];
+                for row in left {
+                    let key = Key(row.clone());
+                    let found = match counts.get_mut(&key) {
+                        // With ALL, every row on the right cancels out one on the left.
+                        Some(count) if set.all && *count > 0 => {
+                            *count -= 1;
+                            true
+                        }
+                        Some(_) if set.all => false,
+                        Some(_) => true,
+                        None => false,
+                    };
+                    if found == intersect && (set.all || seen.insert(key)) {
+                        rows.push(row);
+                    }
+                }
+                rows
+            }
+        };
+
+        if !set.order_by.is_empty() {
+            let columns: Vec<String> = self
+                .query_columns(query)?
+                .into_iter()
+                .map(|c| c.name)
+                .collect();
+            let mut keyed = rows
+                .into_iter()
+                .map(|row| {
+                    let scope = OutputScope {
+                        columns: &columns,
+                        row: &row,
+                    };
+                    let keys = set
+                        .order_by
+                        .iter()
+                        .map(|o| o.expr.eval(&scope))
+                        .collect::<Result<Vec<Value>>>()?;
+                    Ok((keys, row))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            keyed.sort_by(|(a, _), (b, _)| compare_sort_keys(a, b, &set.order_by));
+            rows = keyed.into_iter().map(|(_, row)| row).collect();
+        }
+        Ok(rows
+            .into_iter()
+            .skip(set.offset.unwrap_or(0) as usize)
+            .take(set.limit.map_or(usize::MAX, |l| l as usize))
+            .collect())
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+
+use crate::error::{Error, Result};
+use crate::parser::create::{CreateQuery, ParsedColumn};
+use crate::parser::query::{Cte, Query, SetOp, SetQuery, WithQuery};
+use crate::table::{DataType, Table};
+use crate::value::{array, Key, Value};
+
+use super::Database;
+
+/// A table holding the rows of a planned CTE.
+pub(super) fn cte_table(cte: &Cte, rows: &[Vec<Value>]) -> Result<Table> {
+    let columns = cte
+        .columns
+        .iter()
+        .zip(&cte.types)
+        .map(|(name, datatype)| ParsedColumn {
+            name: name.to_string(),
+            datatype: type_string(datatype),
+            is_pk: false,
+            is_nullable: true,
+            default: None,
+        })
+        .collect();
+    let mut table = Table::new(CreateQuery {
+        table_name: cte.name.to_string(),
+        columns,
+    });
+    table.insert_row(&cte.columns, rows)?;
+    Ok(table)
+}
+
+/// The name `DataType::new` reads back as `datatype`.
+pub(super) fn type_string(datatype: &DataType) -> String {
+    let name = match datatype {
+        DataType::Decimal(precision, scale) => return format!("decimal({precision},{scale})"),
+        DataType::Array(item) => return format!("{}[]", type_string(item)),
+        DataType::Enum(t) => {
+            let labels: Vec<Value> = t.labels.iter().cloned().map(Value::Str).collect();
+            return format!("enum {} {}", t.name, array::format(&labels));
+        }
+        DataType::Int => "int",
+        DataType::Str => "string",
+        DataType::Float => "float",
+        DataType::Bool => "bool",
+        DataType::Date => "date",
+        DataType::Time => "time",
+        DataType::Timestamp => "timestamp",
+        DataType::TimestampTz => "timestamptz",
+        DataType::Interval => "interval",
+        DataType::SmallInt => "smallint",
+        DataType::BigInt => "bigint",
+        DataType::UnsignedSmallInt => "smallint unsigned",
+        DataType::UnsignedInt => "int unsigned",
+        DataType::UnsignedBigInt => "bigint unsigned",
+        DataType::Double => "double",
+        DataType::Binary => "binary",
+        DataType::Json => "json",
+        DataType::Uuid => "uuid",
+        DataType::Invalid => "invalid",
+    };
+    name.to_string()
+}
+
+fn refers_to(query: &Query, name: &str) -> bool {
+    query
+        .selects()
+        .iter()
+        .any(|sq| sq.from == name || sq.joined.iter().any(|j| j.table == name))
+}
+
+/// The `anchor UNION [ALL] recursive term` of a CTE in a WITH RECURSIVE
+/// clause, if its query refers to itself.
+fn recursive_union(cte: &Cte, recursive: bool) -> Result<Option<&SetQuery>> {
+    if !recursive || !refers_to(&cte.query, &cte.name) {
+        return Ok(None);
+    }
+    match &cte.query {
+        Query::SetOperation(set) if set.op == SetOp::Union && !refers_to(&set.left, &cte.name) => {
+            if !set.order_by.is_empty() || set.offset.is_some() || set.limit.is_some() {
+                return Err(Error::Parse(format!(
+                    "ORDER BY, LIMIT and OFFSET are not supported in recursive query {}",
+                    cte.name
+                )));
+            }
+            Ok(Some(set))
+        }
+        _ => Err(Error::Binding(format!(
+            "Recursive query {} must be a query that does not refer to it, UNION [ALL] one that does",
+            cte.name
+        ))),
+    }
+}
+
+impl Database {
+    /// Plans every CTE in turn, leaving an empty table for each one in
+    /// `ctes` so that the queries after it can refer to it.
+    pub(super) fn plan_with_query(&mut self, with: WithQuery) -> Result<WithQuery> {
+        let mut ctes = vec![];
+        for cte in with.ctes {
+            let cte = match recursive_union(&cte, with.recursive)?.cloned() {
+                Some(set) => {
+                    let left = self.plan_query(set.left)?;
+                    let anchor = self.plan_cte(&cte, left)?;
+                    self.ctes.push(cte_table(&anchor, &[])?);
+                    let query = Query::SetOperation(Box::new(SetQuery {
+                        left: anchor.query,
+                        right: self.plan_query(set.right)?,
+                        ..set
+                    }));
+                    let planned = self.plan_cte(&cte, query)?;
+                    self.ctes.pop();
+                    let columns = planned.columns.iter().zip(&anchor.types);
+                    for ((column, overall), anchored) in columns.zip(&planned.types) {
+                        if overall != anchored {
+                            return Err(Error::Type(format!(
+                                "Column {column} of recursive query {} is {anchored} in the anchor but {overall} overall",
+                                cte.name
+                            )));
+                        }
+                    }
+                    planned
+                }
+                None => {
+                    let query = self.plan_query(cte.query.clone())?;
+                    self.plan_cte(&cte, query)?
+                }
+            };
+            self.ctes.push(cte_table(&cte, &[])?);
+            ctes.push(cte);
+        }
+        Ok(WithQuery {
+            recursive: with.recursive,
+            ctes,
+            body: self.plan_query(with.body)?,
+        })
+    }
+
+    /// Names and types the columns of a CTE after its planned `query`.
+    fn plan_cte(&mut self, cte: &Cte, query: Query) -> Result<Cte> {
+        let columns = self.query_columns(&query)?;
+        if !cte.columns.is_empty() && cte.columns.len() != columns.len() {
+            return Err(Error::Binding(format!(
+                "WITH query {} has {} columns but {} column names",
+                cte.name,
+                columns.len(),
+                cte.columns.len()
+            )));
+        }
+        Ok(Cte {
+            name: cte.name.to_string(),
+            columns: match cte.columns.is_empty() {
+                true => columns.iter().map(|c| c.name.to_string()).collect(),
+                false => cte.columns.clone(),
+            },
+            types: columns.into_iter().map(|c| c.datatype).collect(),
+            query,
+        })
+    }
+
+    /// Runs every CTE in turn and leaves its rows in `ctes`.
+    pub(super) fn materialize_ctes(&mut self, with: &WithQuery) -> Result<()> {
+        for cte in &with.ctes {
+            let table = match recursive_union(cte, with.recursive)? {
+                Some(set) => self.recursive_rows(cte, set)?,
+                None => {
+                    let rows = self.query_rows(&cte.query)?;
+                    cte_table(cte, &rows)?
+                }
+            };
+            self.ctes.push(table);
+        }
+        Ok(())
+    }
+
+    /// Evaluates a recursive CTE semi-naively: each run of the recursive
+    /// term only sees the rows the previous run found, until none are new.
+    fn recursive_rows(&mut self, cte: &Cte, set: &SetQuery) -> Result<Table> {
+        let mut seen = HashSet::new();
+        let mut rows = self.query_rows(&set.left)?;
+        if !set.all {
+            rows.retain(|row| seen.insert(Key(row.clone())));
+        }
+        let mut found = rows.clone();
+        let mut iterations = 0;
+        loop {
+            self.ctes.push(cte_table(cte, &found)?);
+            let next = self.query_rows(&set.right);
+            self.ctes.pop();
+            found = next?;
+            if !set.all {
+                found.retain(|row| seen.insert(Key(row.clone())));
+            }
+            if found.is_empty() {
+                break;
+            }
+            iterations += 1;
+            if iterations > self.recursion_limit {
+                return Err(Error::OutOfRange(format!(
+                    "Recursive query {} did not finish within {} iterations",
+                    cte.name, self.recursion_limit
+                )));
+            }
+            rows.extend(found.iter().cloned());
+        }
+        cte_table(cte, &rows)
+    }
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+use std::io;
+
+use sqlparser::parser::ParserError;
+use sqlparser::tokenizer::TokenizerError;
+
+/// Everything that can go wrong while running a statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The SQL could not be parsed, or uses syntax the engine doesn't support.
+    Parse(String),
+    /// A name or parameter could not be resolved, e.g. an unknown table or column.
+    Binding(String),
+    /// A value doesn't fit the type it is used as.
+    Type(String),
+    /// An expression divided by zero.
+    DivisionByZero,
+    /// A computed value doesn't fit its type.
+    OutOfRange(String),
+    /// A row would break a table constraint.
+    Constraint(String),
+    /// Reading or writing a database file failed.
+    Io(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The SQLSTATE reported to clients of the wire protocols.
+    pub fn sqlstate(&self) -> &'static str {
+        match self {
+            Error::Parse(_) => "42601",
+            Error::Binding(_) => "42703",
+            Error::Type(_) => "22P02",
+            Error::DivisionByZero => "22012",
+            Error::OutOfRange(_) => "22003",
+            Error::Constraint(_) => "23505",
+            Error::Io(_) => "58030",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(msg) => write!(f, "Parse error: {msg}"),
+            Error::Binding(msg) => write!(f, "Binding error: {msg}"),
+            Error::Type(msg) => write!(f, "Type error: {msg}"),
+            Error::DivisionByZero => f.write_str("Division by zero"),
+            Error::OutOfRange(msg) => write!(f, "Out of range: {msg}"),
+            Error::Constraint(msg) => write!(f, "Constraint violation: {msg}"),
+            Error::Io(msg) => write!(f, "I/O error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ParserError> for Error {
+    fn from(e: ParserError) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<TokenizerError> for Error {
+    fn from(e: TokenizerError) -> Self {
+        Error::Parse(e.to_string())
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e.to_string())
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(e: bincode::Error) -> Self {
+        Error::Io(e.to_string())
+    }
+}
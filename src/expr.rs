@@ -0,0 +1,1347 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use sqlparser::ast::{
+    self, BinaryOperator, DateTimeField, FunctionArg, FunctionArgExpr, JsonOperator,
+    TrimWhereField, UnaryOperator,
+};
+
+use crate::error::{Error, Result};
+use crate::parser::select::{unqualified, Binary};
+use crate::parser::{create, hex_literal, number_literal, typed_literal, Operand};
+use crate::table::{DataType, EnumType};
+use crate::value::Value;
+
+pub mod aggregate;
+pub mod case;
+mod cast;
+mod datetime;
+pub mod functions;
+mod numeric;
+pub mod pattern;
+pub mod window;
+
+use aggregate::{Aggregate, AggregateFunc};
+use case::Case;
+use datetime::{is_datetime, is_temporal};
+use pattern::Pattern;
+use window::{Window, WindowFunc};
+
+pub(crate) use numeric::promote;
+
+/// Where an expression reads its columns from while it is evaluated.
+pub trait Scope {
+    fn column(&self, name: &str) -> Result<Value>;
+
+    fn aggregate(&self, aggregate: &Aggregate) -> Result<Value> {
+        Err(Error::Parse(format!(
+            "Aggregate {} is not allowed here",
+            aggregate.func
+        )))
+    }
+
+    fn window(&self, window: &Window) -> Result<Value> {
+        Err(Error::Parse(format!(
+            "Window function {} is not allowed here",
+            window.func
+        )))
+    }
+}
+
+/// A scope without a row, for expressions that cannot read columns, such as
+/// a column's DEFAULT.
+pub struct EmptyScope;
+
+impl Scope for EmptyScope {
+    fn column(&self, name: &str) -> Result<Value> {
+        Err(Error::Binding(format!(
+            "Cannot refer to column {name} here"
+        )))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOp {
+    Minus,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinaryOp {
+    Plus,
+    Minus,
+    Multiply,
+    Divide,
+    Modulo,
+    Concat,
+    Compare(Binary),
+    Match(Pattern),
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Plus
+                | BinaryOp::Minus
+                | BinaryOp::Multiply
+                | BinaryOp::Divide
+                | BinaryOp::Modulo
+        )
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Plus => "+",
+            BinaryOp::Minus => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Modulo => "%",
+            BinaryOp::Concat => "||",
+            BinaryOp::Compare(Binary::Eq) => "=",
+            BinaryOp::Compare(Binary::NotEq) => "<>",
+            BinaryOp::Compare(Binary::Lt) => "<",
+            BinaryOp::Compare(Binary::LtEq) => "<=",
+            BinaryOp::Compare(Binary::Gt) => ">",
+            BinaryOp::Compare(Binary::GtEq) => ">=",
+            BinaryOp::Match(pattern) => pattern.symbol(),
+            BinaryOp::And => "AND",
+            BinaryOp::Or => "OR",
+        }
+    }
+}
+
+impl TryFrom<&BinaryOperator> for BinaryOp {
+    type Error = Error;
+
+    fn try_from(op: &BinaryOperator) -> Result<Self> {
+        match op {
+            BinaryOperator::Plus => Ok(BinaryOp::Plus),
+            BinaryOperator::Minus => Ok(BinaryOp::Minus),
+            BinaryOperator::Multiply => Ok(BinaryOp::Multiply),
+            BinaryOperator::Divide => Ok(BinaryOp::Divide),
+            BinaryOperator::Modulo => Ok(BinaryOp::Modulo),
+            BinaryOperator::StringConcat => Ok(BinaryOp::Concat),
+            BinaryOperator::Eq => Ok(BinaryOp::Compare(Binary::Eq)),
+            BinaryOperator::NotEq => Ok(BinaryOp::Compare(Binary::NotEq)),
+            BinaryOperator::Lt => Ok(BinaryOp::Compare(Binary::Lt)),
+            BinaryOperator::LtEq => Ok(BinaryOp::Compare(Binary::LtEq)),
+            BinaryOperator::Gt => Ok(BinaryOp::Compare(Binary::Gt)),
+            BinaryOperator::GtEq => Ok(BinaryOp::Compare(Binary::GtEq)),
+            BinaryOperator::And => Ok(BinaryOp::And),
+            BinaryOperator::Or => Ok(BinaryOp::Or),
+            BinaryOperator::PGRegexMatch => Ok(BinaryOp::Match(Pattern::Regex {
+                case_insensitive: false,
+                negated: false,
+            })),
+            BinaryOperator::PGRegexIMatch => Ok(BinaryOp::Match(Pattern::Regex {
+                case_insensitive: true,
+                negated: false,
+            })),
+            BinaryOperator::PGRegexNotMatch => Ok(BinaryOp::Match(Pattern::Regex {
+                case_insensitive: false,
+                negated: true,
+            })),
+            BinaryOperator::PGRegexNotIMatch => Ok(BinaryOp::Match(Pattern::Regex {
+                case_insensitive: true,
+                negated: true,
+            })),
+            _ => Err(Error::Parse(format!("Unsupported operator {op}"))),
+        }
+    }
+}
+
+/// An expression computed for every row, e.g. a computed column in a SELECT list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScalarExpr {
+    Column(String),
+    Value(Operand),
+    Unary(UnaryOp, Box<ScalarExpr>),
+    Binary(Box<ScalarExpr>, BinaryOp, Box<ScalarExpr>),
+    IsNull(Box<ScalarExpr>, bool),
+    /// `expr [NOT] IN (list)`, negated when the flag is set.
+    InList(Box<ScalarExpr>, Vec<ScalarExpr>, bool),
+    /// A call to one of the built-in `functions`, by its upper-case name.
+    Function(String, Vec<ScalarExpr>),
+    Aggregate(Aggregate),
+    Case(Case),
+    Window(Box<Window>),
+    /// `ARRAY[a, b, ...]`.
+    Array(Vec<ScalarExpr>),
+    /// `expr op ANY(array)`, or `expr op ALL(array)` when the flag is set.
+    Quantified(Box<ScalarExpr>, Binary, Box<ScalarExpr>, bool),
+    /// `CAST(expr AS type)` or `expr::type`.
+    Cast(Box<ScalarExpr>, DataType),
+}
+
+fn function_args(f: &ast::Function) -> Result<Vec<ScalarExpr>> {
+    f.args
+        .iter()
+        .map(|arg| match arg {
+            FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => ScalarExpr::try_from(e),
+            arg => Err(Error::Parse(format!("Unsupported function argument {arg}"))),
+        })
+        .collect()
+}
+
+fn function_call(name: &str, args: Vec<ScalarExpr>) -> Result<ScalarExpr> {
+    let function = functions::lookup(name)?;
+    function.check_arity(args.len())?;
+    Ok(ScalarExpr::Function(function.name.to_string(), args))
+}
+
+impl TryFrom<&ast::Expr> for ScalarExpr {
+    type Error = Error;
+
+    fn try_from(expr: &ast::Expr) -> Result<Self> {
+        let scalar = match expr {
+            ast::Expr::Identifier(i) => ScalarExpr::Column(i.value.to_string()),
+            ast::Expr::CompoundIdentifier(idents) => match idents.as_slice() {
+                [.., table, column] => {
+                    ScalarExpr::Column(format!("{}.{}", table.value, column.value))
+                }
+                [column] => ScalarExpr::Column(column.value.to_string()),
+                [] => return Err(Error::Parse(format!("Unsupported expression {expr}"))),
+            },
+            ast::Expr::Value(v) => ScalarExpr::Value(match v {
+                ast::Value::Number(n, _) => Operand::Literal(number_literal(n)?),
+                ast::Value::SingleQuotedString(s)
+                | ast::Value::DoubleQuotedString(s)
+                | ast::Value::NationalStringLiteral(s) => {
+                    Operand::Literal(Value::Str(s.to_string()))
+                }
+                ast::Value::HexStringLiteral(hex) => Operand::Literal(hex_literal(hex)?),
+                ast::Value::Boolean(b) => Operand::Literal(Value::Bool(*b)),
+                ast::Value::Null => Operand::Literal(Value::Null),
+                ast::Value::Placeholder(p) => Operand::from_placeholder(p)?,
+                v => return Err(Error::Parse(format!("Unsupported value {v}"))),
+            }),
+            ast::Expr::Nested(e) => ScalarExpr::try_from(&**e)?,
+            ast::Expr::UnaryOp { op, expr } => {
+                let inner = ScalarExpr::try_from(&**expr)?;
+                match op {
+                    UnaryOperator::Plus => inner,
+                    UnaryOperator::Minus => ScalarExpr::Unary(UnaryOp::Minus, Box::new(inner)),
+                    UnaryOperator::Not => ScalarExpr::Unary(UnaryOp::Not, Box::new(inner)),
+                    op => return Err(Error::Parse(format!("Unsupported operator {op}"))),
+                }
+            }
+            ast::Expr::BinaryOp { left, op, right } => match &**right {
+                ast::Expr::AnyOp(array) | ast::Expr::AllOp(array) => {
+                    let BinaryOp::Compare(binary) = BinaryOp::try_from(op)? else {
+                        return Err(Error::Parse(format!(
+                            "Operator {op} cannot be used with ANY or ALL"
+                        )));
+                    };
+                    ScalarExpr::Quantified(
+                        Box::new(ScalarExpr::try_from(&**left)?),
+                        binary,
+                        Box::new(ScalarExpr::try_from(&**array)?),
+                        matches!(**right, ast::Expr::AllOp(_)),
+                    )
+                }
+                right => ScalarExpr::Binary(
+                    Box::new(ScalarExpr::try_from(&**left)?),
+                    BinaryOp::try_from(op)?,
+                    Box::new(ScalarExpr::try_from(right)?),
+                ),
+            },
+            ast::Expr::Array(array) => ScalarExpr::Array(
+                array
+                    .elem
+                    .iter()
+                    .map(ScalarExpr::try_from)
+                    .collect::<Result<_>>()?,
+            ),
+            // `tags[1]`, which the MySQL dialect reads as a map access.
+            ast::Expr::MapAccess { column, keys } => {
+                keys.iter()
+                    .try_fold(ScalarExpr::try_from(&**column)?, |array, key| {
+                        function_call("ARRAY_EXTRACT", vec![array, ScalarExpr::try_from(key)?])
+                    })?
+            }
+            ast::Expr::ArrayIndex { obj, indexes } => {
+                indexes
+                    .iter()
+                    .try_fold(ScalarExpr::try_from(&**obj)?, |array, index| {
+                        function_call("ARRAY_EXTRACT", vec![array, ScalarExpr::try_from(index)?])
+                    })?
+            }
+            ast::Expr::Cast { expr, data_type } => ScalarExpr::Cast(
+                Box::new(ScalarExpr::try_from(&**expr)?),
+                cast_type(data_type)?,
+            ),
+            ast::Expr::ArrayAgg(agg) => {
+                if agg.order_by.is_some() || agg.limit.is_some() || agg.within_group {
+                    return Err(Error::Parse(format!("Unsupported aggregate {agg}")));
+                }
+                ScalarExpr::Aggregate(Aggregate {
+                    func: AggregateFunc::ArrayAgg,
+                    arg: Some(Box::new(ScalarExpr::try_from(&*agg.expr)?)),
+                    distinct: agg.distinct,
+                })
+            }
+            ast::Expr::Like {
+                negated,
+                expr,
+                pattern,
+                escape_char,
+            } => ScalarExpr::Binary(
+                Box::new(ScalarExpr::try_from(&**expr)?),
+                BinaryOp::Match(Pattern::like(*escape_char, false, *negated)),
+                Box::new(ScalarExpr::try_from(&**pattern)?),
+            ),
+            ast::Expr::ILike {
+                negated,
+                expr,
+                pattern,
+                escape_char,
+            } => ScalarExpr::Binary(
+                Box::new(ScalarExpr::try_from(&**expr)?),
+                BinaryOp::Match(Pattern::like(*escape_char, true, *negated)),
+                Box::new(ScalarExpr::try_from(&**pattern)?),
+            ),
+            ast::Expr::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => ScalarExpr::Case(Case::from_ast(operand, conditions, results, else_result)?),
+            ast::Expr::InList {
+                expr,
+                list,
+                negated,
+            } => ScalarExpr::InList(
+                Box::new(ScalarExpr::try_from(&**expr)?),
+                list.iter()
+                    .map(ScalarExpr::try_from)
+                    .collect::<Result<Vec<_>>>()?,
+                *negated,
+            ),
+            ast::Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let expr = ScalarExpr::try_from(&**expr)?;
+                let compare = |op, bound: &ast::Expr| -> Result<ScalarExpr> {
+                    Ok(ScalarExpr::Binary(
+                        Box::new(expr.clone()),
+                        BinaryOp::Compare(op),
+                        Box::new(ScalarExpr::try_from(bound)?),
+                    ))
+                };
+                let between = ScalarExpr::Binary(
+                    Box::new(compare(Binary::GtEq, low)?),
+                    BinaryOp::And,
+                    Box::new(compare(Binary::LtEq, high)?),
+                );
+                match negated {
+                    true => ScalarExpr::Unary(UnaryOp::Not, Box::new(between)),
+                    false => between,
+                }
+            }
+            ast::Expr::IsNull(e) => {
+                ScalarExpr::IsNull(Box::new(ScalarExpr::try_from(&**e)?), false)
+            }
+            ast::Expr::IsNotNull(e) => {
+                ScalarExpr::IsNull(Box::new(ScalarExpr::try_from(&**e)?), true)
+            }
+            ast::Expr::Function(f) => {
+                let name = f.name.to_string();
+                if let Some(over) = &f.over {
+                    return Ok(ScalarExpr::Window(Box::new(Window::from_ast(f, over)?)));
+                }
+                match AggregateFunc::from_name(&name) {
+                    Some(func) => {
+                        let arg = match f.args.as_slice() {
+                            [FunctionArg::Unnamed(FunctionArgExpr::Wildcard)]
+                                if func == AggregateFunc::Count && !f.distinct =>
+                            {
+                                None
+                            }
+                            [FunctionArg::Unnamed(FunctionArgExpr::Expr(e))] => {
+                                Some(Box::new(ScalarExpr::try_from(e)?))
+                            }
+                            _ => {
+                                return Err(Error::Binding(format!(
+                                    "Aggregate {func} takes 1 argument"
+                                )))
+                            }
+                        };
+                        ScalarExpr::Aggregate(Aggregate {
+                            func,
+                            arg,
+                            distinct: f.distinct,
+                        })
+                    }
+                    None => {
+                        let args = function_args(f)?;
+                        let upper = name.to_uppercase();
+                        if let Some(case) = Case::from_function(&upper, args.clone())? {
+                            return Ok(ScalarExpr::Case(case));
+                        }
+                        // DATE_ADD(d, n) and DATE_SUB(d, n) are `d + n` and `d - n`.
+                        let op = match upper.as_ref() {
+                            "DATE_ADD" => BinaryOp::Plus,
+                            "DATE_SUB" => BinaryOp::Minus,
+                            _ => return function_call(&name, args),
+                        };
+                        match <[ScalarExpr; 2]>::try_from(args) {
+                            Ok([date, offset]) => {
+                                ScalarExpr::Binary(Box::new(date), op, Box::new(offset))
+                            }
+                            Err(args) => {
+                                return Err(Error::Binding(format!(
+                                    "Function {upper} takes 2 arguments but got {}",
+                                    args.len()
+                                )))
+                            }
+                        }
+                    }
+                }
+            }
+            ast::Expr::Substring {
+                expr,
+                substring_from,
+                substring_for,
+            } => {
+                let mut args = vec![ScalarExpr::try_from(&**expr)?];
+                args.push(match substring_from {
+                    Some(from) => ScalarExpr::try_from(&**from)?,
+                    None => ScalarExpr::Value(Operand::Literal(Value::Int(1))),
+                });
+                if let Some(count) = substring_for {
+                    args.push(ScalarExpr::try_from(&**count)?);
+                }
+                function_call("SUBSTRING", args)?
+            }
+            ast::Expr::Trim {
+                expr,
+                trim_where,
+                trim_what,
+            } => {
+                let name = match trim_where {
+                    Some(TrimWhereField::Leading) => "LTRIM",
+                    Some(TrimWhereField::Trailing) => "RTRIM",
+                    Some(TrimWhereField::Both) | None => "TRIM",
+                };
+                let mut args = vec![ScalarExpr::try_from(&**expr)?];
+                if let Some(what) = trim_what {
+                    args.push(ScalarExpr::try_from(&**what)?);
+                }
+                function_call(name, args)?
+            }
+            ast::Expr::Position { expr, r#in } => function_call(
+                "POSITION",
+                vec![
+                    ScalarExpr::try_from(&**expr)?,
+                    ScalarExpr::try_from(&**r#in)?,
+                ],
+            )?,
+            ast::Expr::Extract { field, expr } => {
+                let field = field.to_string().to_lowercase();
+                if !crate::value::datetime::is_field(&field) {
+                    return Err(Error::Parse(format!("Cannot extract {field}")));
+                }
+                function_call(
+                    "DATE_PART",
+                    vec![
+                        ScalarExpr::Value(Operand::Literal(Value::Str(field))),
+                        ScalarExpr::try_from(&**expr)?,
+                    ],
+                )?
+            }
+            ast::Expr::AtTimeZone {
+                timestamp,
+                time_zone,
+            } => function_call(
+                "TIMEZONE",
+                vec![
+                    ScalarExpr::Value(Operand::Literal(Value::Str(time_zone.to_string()))),
+                    ScalarExpr::try_from(&**timestamp)?,
+                ],
+            )?,
+            // `doc -> path` is the JSON at the path, and `doc ->> path` its text.
+            ast::Expr::JsonAccess {
+                left,
+                operator,
+                right,
+            } => {
+                // sqlparser reads all that follows `->` as its right side, so
+                // `doc -> 'a' = 1` comes out as `doc -> ('a' = 1)`. The access
+                // binds tighter than any operator, so it belongs on the
+                // leftmost operand.
+                let mut rebound = (**right).clone();
+                if left_operand(&mut rebound).is_some() {
+                    let operand = leftmost_operand(&mut rebound);
+                    *operand = ast::Expr::JsonAccess {
+                        left: left.clone(),
+                        operator: *operator,
+                        right: Box::new(operand.clone()),
+                    };
+                    return ScalarExpr::try_from(&rebound);
+                }
+                let extract = function_call(
+                    "JSON_EXTRACT_PATH",
+                    vec![
+                        ScalarExpr::try_from(&**left)?,
+                        ScalarExpr::try_from(&**right)?,
+                    ],
+                )?;
+                match operator {
+                    JsonOperator::Arrow => extract,
+                    JsonOperator::LongArrow => function_call("JSON_UNQUOTE", vec![extract])?,
+                    op => return Err(Error::Parse(format!("Unsupported operator {op}"))),
+                }
+            }
+            ast::Expr::Ceil {
+                expr,
+                field: DateTimeField::NoDateTime,
+            } => function_call("CEIL", vec![ScalarExpr::try_from(&**expr)?])?,
+            ast::Expr::Floor {
+                expr,
+                field: DateTimeField::NoDateTime,
+            } => function_call("FLOOR", vec![ScalarExpr::try_from(&**expr)?])?,
+            expr => match typed_literal(expr)? {
+                Some(value) => ScalarExpr::Value(Operand::Literal(value)),
+                None => return Err(Error::Parse(format!("Unsupported expression {expr}"))),
+            },
+        };
+        Ok(scalar)
+    }
+}
+
+/// The operand on the left of an operator expression.
+fn left_operand(expr: &mut ast::Expr) -> Option<&mut ast::Expr> {
+    match expr {
+        ast::Expr::BinaryOp { left: e, .. }
+        | ast::Expr::JsonAccess { left: e, .. }
+        | ast::Expr::IsNull(e)
+        | ast::Expr::IsNotNull(e)
+        | ast::Expr::Like { expr: e, .. }
+        | ast::Expr::ILike { expr: e, .. }
+        | ast::Expr::InList { expr: e, .. }
+        | ast::Expr::Between { expr: e, .. } => Some(e),
+        _ => None,
+    }
+}
+
+/// The type a CAST names. A name that isn't a built-in type may be one
+/// made with CREATE TYPE, so it stands as an ENUM with no labels until
+/// `ScalarExpr::resolve_types` looks it up.
+fn cast_type(data_type: &ast::DataType) -> Result<DataType> {
+    fn named(data_type: &ast::DataType) -> Option<DataType> {
+        match data_type {
+            ast::DataType::Custom(name, _) => Some(DataType::Enum(Arc::new(EnumType {
+                name: name.to_string(),
+                labels: vec![],
+            }))),
+            ast::DataType::Array(Some(item)) => named(item).map(|d| DataType::Array(Box::new(d))),
+            _ => None,
+        }
+    }
+    match DataType::new(create::type_name(data_type)) {
+        DataType::Invalid => {
+            named(data_type).ok_or_else(|| Error::Parse(format!("Unsupported type {data_type}")))
+        }
+        datatype => Ok(datatype),
+    }
+}
+
+fn resolve_named<F>(datatype: &DataType, named_type: &F) -> Result<DataType>
+where
+    F: Fn(&str) -> Option<DataType>,
+{
+    match datatype {
+        DataType::Array(item) => Ok(DataType::Array(Box::new(resolve_named(item, named_type)?))),
+        DataType::Enum(t) => {
+            named_type(&t.name).ok_or_else(|| Error::Parse(format!("Unsupported type {}", t.name)))
+        }
+        datatype => Ok(datatype.clone()),
+    }
+}
+
+fn leftmost_operand(mut expr: &mut ast::Expr) -> &mut ast::Expr {
+    while left_operand(expr).is_some() {
+        expr = left_operand(expr).unwrap();
+    }
+    expr
+}
+
+fn is_numeric(datatype: &DataType) -> bool {
+    datatype.is_numeric()
+}
+
+fn comparable(left: &Option<DataType>, right: &Option<DataType>) -> bool {
+    match (left, right) {
+        (Some(a), Some(b)) => {
+            a == b
+                || (is_numeric(a) && is_numeric(b))
+                || (is_datetime(a) && is_datetime(b))
+                || json_comparable(a, b)
+                // An ENUM compares with text by the position of its label.
+                || matches!(
+                    (a, b),
+                    (DataType::Enum(_), DataType::Str) | (DataType::Str, DataType::Enum(_))
+                )
+        }
+        _ => true,
+    }
+}
+
+/// JSON compares with anything that has a JSON form.
+fn json_comparable(a: &DataType, b: &DataType) -> bool {
+    let has_json_form = |d: &DataType| {
+        is_numeric(d) || matches!(d, DataType::Str | DataType::Bool | DataType::Json)
+    };
+    (*a == DataType::Json && has_json_form(b)) || (*b == DataType::Json && has_json_form(a))
+}
+
+fn type_name(datatype: &Option<DataType>) -> String {
+    datatype
+        .as_ref()
+        .map_or("unknown".to_string(), |d| d.to_string())
+}
+
+impl ScalarExpr {
+    fn children(&self) -> Vec<&ScalarExpr> {
+        match self {
+            ScalarExpr::Column(_) | ScalarExpr::Value(_) => vec![],
+            ScalarExpr::Unary(_, e) | ScalarExpr::IsNull(e, _) | ScalarExpr::Cast(e, _) => vec![e],
+            ScalarExpr::Binary(l, _, r) => vec![l, r],
+            ScalarExpr::InList(e, list, _) => std::iter::once(&**e).chain(list).collect(),
+            ScalarExpr::Function(_, args) => args.iter().collect(),
+            ScalarExpr::Aggregate(agg) => agg.arg.iter().map(|e| &**e).collect(),
+            ScalarExpr::Case(case) => case.children(),
+            ScalarExpr::Window(window) => window.children(),
+            ScalarExpr::Array(items) => items.iter().collect(),
+            ScalarExpr::Quantified(l, _, r, _) => vec![l, r],
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut ScalarExpr> {
+        match self {
+            ScalarExpr::Column(_) | ScalarExpr::Value(_) => vec![],
+            ScalarExpr::Unary(_, e) | ScalarExpr::IsNull(e, _) | ScalarExpr::Cast(e, _) => vec![e],
+            ScalarExpr::Binary(l, _, r) => vec![l, r],
+            ScalarExpr::InList(e, list, _) => std::iter::once(&mut **e).chain(list).collect(),
+            ScalarExpr::Function(_, args) => args.iter_mut().collect(),
+            ScalarExpr::Aggregate(agg) => agg.arg.iter_mut().map(|e| &mut **e).collect(),
+            ScalarExpr::Case(case) => case.children_mut(),
+            ScalarExpr::Window(window) => window.children_mut(),
+            ScalarExpr::Array(items) => items.iter_mut().collect(),
+            ScalarExpr::Quantified(l, _, r, _) => vec![l, r],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            ScalarExpr::Value(operand) => vec![operand],
+            e => e
+                .children_mut()
+                .into_iter()
+                .flat_map(|c| c.operands_mut())
+                .collect(),
+        }
+    }
+
+    /// Drops the table name from every `table.column` reference.
+    pub fn unqualify(&mut self) {
+        match self {
+            ScalarExpr::Column(name) => *name = unqualified(name).to_string(),
+            e => {
+                for c in e.children_mut() {
+                    c.unqualify();
+                }
+            }
+        }
+    }
+
+    /// Looks up the types made with CREATE TYPE that casts name, using
+    /// `named_type`, which gives `None` for a name no type has.
+    pub fn resolve_types<F>(&mut self, named_type: &F) -> Result<()>
+    where
+        F: Fn(&str) -> Option<DataType>,
+    {
+        if let ScalarExpr::Cast(_, datatype) = self {
+            *datatype = resolve_named(datatype, named_type)?;
+        }
+        for c in self.children_mut() {
+            c.resolve_types(named_type)?;
+        }
+        Ok(())
+    }
+
+    pub fn contains_aggregate(&self) -> bool {
+        matches!(self, ScalarExpr::Aggregate(_))
+            || self.children().iter().any(|c| c.contains_aggregate())
+    }
+
+    pub fn contains_window(&self) -> bool {
+        matches!(self, ScalarExpr::Window(_)) || self.children().iter().any(|c| c.contains_window())
+    }
+
+    /// Every distinct window function call in the expression.
+    pub fn windows<'a>(&'a self, windows: &mut Vec<&'a Window>) {
+        match self {
+            ScalarExpr::Window(window) if !windows.contains(&&**window) => windows.push(window),
+            ScalarExpr::Window(_) => {}
+            e => {
+                for c in e.children() {
+                    c.windows(windows);
+                }
+            }
+        }
+    }
+
+    /// Checks that an expression computed once per group only reads columns
+    /// through `group_by` expressions or aggregates.
+    pub fn check_grouped(&self, group_by: &[ScalarExpr]) -> Result<()> {
+        if group_by.contains(self) {
+            return Ok(());
+        }
+        match self {
+            ScalarExpr::Column(name) => Err(Error::Binding(format!(
+                "Column {name} must appear in the GROUP BY clause or be used in an aggregate function"
+            ))),
+            ScalarExpr::Aggregate(agg) => match &agg.arg {
+                Some(arg) if arg.contains_aggregate() => Err(Error::Parse(format!(
+                    "Aggregate {} cannot contain another aggregate",
+                    agg.func
+                ))),
+                _ => Ok(()),
+            },
+            e => e
+                .children()
+                .into_iter()
+                .try_for_each(|c| c.check_grouped(group_by)),
+        }
+    }
+
+    /// Type checks the expression against the table, converting text
+    /// literals that are compared with or combined with typed values, so
+    /// `price > '10'` compares numbers. Returns the type of the result, or
+    /// `None` if it is only known once parameters are bound.
+    pub fn resolve<F>(&mut self, column_type: &F) -> Result<Option<DataType>>
+    where
+        F: Fn(&str) -> Result<DataType>,
+    {
+        if let ScalarExpr::Binary(l, op, r) = self {
+            let left = l.resolve(column_type)?;
+            let right = r.resolve(column_type)?;
+            if op.is_arithmetic() || matches!(op, BinaryOp::Compare(_)) {
+                // Text is only read as a date or interval when compared, since
+                // `ts + '1 day'` could mean either, or when added to an
+                // interval, which only a point in time can be.
+                let hint = |d: Option<DataType>| match d {
+                    Some(DataType::Interval) if matches!(op, BinaryOp::Plus | BinaryOp::Minus) => {
+                        Some(DataType::Timestamp)
+                    }
+                    d => d.filter(|d| !op.is_arithmetic() || is_numeric(d)),
+                };
+                l.coerce_literal(&hint(right))?;
+                r.coerce_literal(&hint(left))?;
+            }
+        } else if let ScalarExpr::Case(case) = self {
+            case.resolve(column_type)?;
+        } else if let ScalarExpr::Function(name, args) = self {
+            // Text passed as a timestamp is read as one, as in
+            // `DATEDIFF(d, '2023-01-01')`.
+            let function = functions::lookup(name)?;
+            for (idx, arg) in args.iter_mut().enumerate() {
+                arg.resolve(column_type)?;
+                if function.arg(idx) == functions::Arg::Timestamp {
+                    arg.coerce_literal(&Some(DataType::Timestamp))?;
+                }
+            }
+        } else if let ScalarExpr::InList(e, list, _) = self {
+            let datatype = e.resolve(column_type)?;
+            for item in list {
+                item.resolve(column_type)?;
+                item.coerce_literal(&datatype)?;
+            }
+        } else if let ScalarExpr::Array(items) = self {
+            // Text items take the type of the others, so `ARRAY[1, '2']` is
+            // an array of numbers.
+            let mut datatype = None;
+            for item in items.iter_mut() {
+                match item.resolve(column_type)? {
+                    Some(DataType::Str) => {}
+                    d => datatype = datatype.or(d),
+                }
+            }
+            for item in items {
+                item.coerce_literal(&datatype)?;
+            }
+        } else if let ScalarExpr::Quantified(l, _, r, _) = self {
+            let left = l.resolve(column_type)?;
+            let right = r.resolve(column_type)?;
+            match (&mut **r, left) {
+                (ScalarExpr::Array(items), left) => {
+                    for item in items {
+                        item.coerce_literal(&left)?;
+                    }
+                }
+                // `id = ANY('{1,2}')` reads the text as an array of ids.
+                (ScalarExpr::Value(Operand::Literal(val @ Value::Str(_))), Some(left)) => {
+                    *val = val.clone().coerce(&DataType::Array(Box::new(left)))?;
+                }
+                _ => {}
+            }
+            if let Some(DataType::Array(item)) = right {
+                l.coerce_literal(&Some(*item))?;
+            }
+        } else {
+            for c in self.children_mut() {
+                c.resolve(column_type)?;
+            }
+        }
+        self.datatype(column_type)
+    }
+
+    fn coerce_literal(&mut self, datatype: &Option<DataType>) -> Result<()> {
+        if let (ScalarExpr::Value(Operand::Literal(val @ Value::Str(_))), Some(datatype)) =
+            (&mut *self, datatype)
+        {
+            if is_numeric(datatype)
+                || matches!(
+                    datatype,
+                    DataType::Bool
+                        | DataType::Binary
+                        | DataType::Uuid
+                        | DataType::Array(_)
+                        | DataType::Enum(_)
+                )
+                || is_temporal(datatype)
+            {
+                *val = val.clone().coerce(datatype)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn datatype<F>(&self, column_type: &F) -> Result<Option<DataType>>
+    where
+        F: Fn(&str) -> Result<DataType>,
+    {
+        let datatype = match self {
+            ScalarExpr::Column(name) => Some(column_type(name)?),
+            ScalarExpr::Value(Operand::Literal(v)) => v.datatype(),
+            ScalarExpr::Value(Operand::Param(_)) => None,
+            ScalarExpr::Unary(UnaryOp::Minus, e) => match e.datatype(column_type)? {
+                Some(d) if is_numeric(&d) => Some(numeric::negated_type(&d)),
+                Some(d) if d != DataType::Interval => {
+                    return Err(Error::Type(format!("Cannot negate a value of type {d}")))
+                }
+                d => d,
+            },
+            ScalarExpr::Unary(UnaryOp::Not, e) => match e.datatype(column_type)? {
+                None | Some(DataType::Bool) => Some(DataType::Bool),
+                Some(d) => return Err(Error::Type(format!("NOT cannot be applied to {d}"))),
+            },
+            ScalarExpr::IsNull(e, _) => {
+                e.datatype(column_type)?;
+                Some(DataType::Bool)
+            }
+            ScalarExpr::InList(e, list, _) => {
+                let datatype = e.datatype(column_type)?;
+                for item in list {
+                    let item = item.datatype(column_type)?;
+                    if !comparable(&datatype, &item) {
+                        return Err(Error::Type(format!(
+                            "IN cannot compare {} with {}",
+                            type_name(&datatype),
+                            type_name(&item)
+                        )));
+                    }
+                }
+                Some(DataType::Bool)
+            }
+            ScalarExpr::Function(name, args) => {
+                let function = functions::lookup(name)?;
+                for (idx, arg) in args.iter().enumerate() {
+                    if let ScalarExpr::Value(Operand::Literal(value)) = arg {
+                        function.check_literal(idx, value)?;
+                    }
+                }
+                let types = args
+                    .iter()
+                    .map(|a| a.datatype(column_type))
+                    .collect::<Result<Vec<_>>>()?;
+                let literals = args
+                    .iter()
+                    .map(|a| match a {
+                        ScalarExpr::Value(Operand::Literal(value)) => Some(value),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                function.check_args(&types, &literals)?
+            }
+            ScalarExpr::Aggregate(agg) => match &agg.arg {
+                Some(arg) => agg.func.datatype(arg.datatype(column_type)?)?,
+                None => Some(DataType::Int),
+            },
+            ScalarExpr::Case(case) => case.datatype(column_type)?,
+            ScalarExpr::Window(window) => window.datatype(column_type)?,
+            ScalarExpr::Array(items) => {
+                let mut datatype: Option<DataType> = None;
+                for item in items {
+                    datatype = match (datatype, item.datatype(column_type)?) {
+                        (None, d) | (d, None) => d,
+                        (Some(a), Some(b)) if a == b => Some(a),
+                        (Some(a), Some(b)) if is_numeric(&a) && is_numeric(&b) => {
+                            Some(promote(&a, &b).unwrap_or(DataType::Double))
+                        }
+                        (Some(a), Some(b)) => {
+                            return Err(Error::Type(format!("ARRAY cannot hold both {a} and {b}")))
+                        }
+                    };
+                }
+                datatype.map(|d| DataType::Array(Box::new(d)))
+            }
+            ScalarExpr::Cast(e, datatype) => match e.datatype(column_type)? {
+                Some(from) if !cast::castable(&from, datatype) => {
+                    return Err(Error::Type(format!("Cannot cast {from} to {datatype}")))
+                }
+                _ => Some(datatype.clone()),
+            },
+            ScalarExpr::Quantified(l, op, r, all) => {
+                let left = l.datatype(column_type)?;
+                let quantifier = if *all { "ALL" } else { "ANY" };
+                match r.datatype(column_type)? {
+                    None => {}
+                    Some(DataType::Array(item)) if comparable(&left, &Some(*item.clone())) => {}
+                    Some(DataType::Array(item)) => {
+                        return Err(Error::Type(format!(
+                            "Operator {} {quantifier} cannot compare {} with {item}",
+                            BinaryOp::Compare(op.clone()).symbol(),
+                            type_name(&left)
+                        )))
+                    }
+                    Some(d) => {
+                        return Err(Error::Type(format!("{quantifier} needs an array, not {d}")))
+                    }
+                }
+                Some(DataType::Bool)
+            }
+            ScalarExpr::Binary(l, op, r) => {
+                let left = l.datatype(column_type)?;
+                let right = r.datatype(column_type)?;
+                let mismatch = || {
+                    Error::Type(format!(
+                        "Operator {} cannot be applied to {} and {}",
+                        op.symbol(),
+                        type_name(&left),
+                        type_name(&right)
+                    ))
+                };
+                match op {
+                    BinaryOp::Concat => match (&left, &right) {
+                        (Some(DataType::Array(a)), Some(b))
+                        | (Some(b), Some(DataType::Array(a))) => {
+                            Some(concat_type(a, b).ok_or_else(mismatch)?)
+                        }
+                        (Some(array @ DataType::Array(_)), None)
+                        | (None, Some(array @ DataType::Array(_))) => Some(array.clone()),
+                        (Some(DataType::Binary), Some(DataType::Binary) | None)
+                        | (None, Some(DataType::Binary)) => Some(DataType::Binary),
+                        _ => Some(DataType::Str),
+                    },
+                    BinaryOp::Match(pattern) => match (&left, &right) {
+                        (None | Some(DataType::Str), None | Some(DataType::Str)) => {
+                            if let ScalarExpr::Value(Operand::Literal(Value::Str(p))) = &**r {
+                                pattern.check(p)?;
+                            }
+                            Some(DataType::Bool)
+                        }
+                        _ => return Err(mismatch()),
+                    },
+                    BinaryOp::And | BinaryOp::Or => match (&left, &right) {
+                        (None | Some(DataType::Bool), None | Some(DataType::Bool)) => {
+                            Some(DataType::Bool)
+                        }
+                        _ => return Err(mismatch()),
+                    },
+                    BinaryOp::Compare(_) if comparable(&left, &right) => Some(DataType::Bool),
+                    BinaryOp::Compare(_) => return Err(mismatch()),
+                    _ => match (&left, &right) {
+                        (Some(a), Some(b)) if is_numeric(a) && is_numeric(b) => {
+                            numeric::arithmetic_type(op, a, b)
+                        }
+                        (Some(a), None) | (None, Some(a)) if is_numeric(a) => Some(a.clone()),
+                        (None, None) => None,
+                        (Some(a), None) => Some(
+                            datetime::param_type(op, a, true)
+                                .and_then(|param| datetime::arithmetic_type(op, a, &param))
+                                .ok_or_else(mismatch)?,
+                        ),
+                        (None, Some(b)) => Some(
+                            datetime::param_type(op, b, false)
+                                .and_then(|param| datetime::arithmetic_type(op, &param, b))
+                                .ok_or_else(mismatch)?,
+                        ),
+                        (Some(a), Some(b)) => {
+                            Some(datetime::arithmetic_type(op, a, b).ok_or_else(mismatch)?)
+                        }
+                    },
+                }
+            }
+        };
+        Ok(datatype)
+    }
+
+    /// Records the type of every parameter that is combined with a value of
+    /// a known type, e.g. `price * ?` takes the type of `price`.
+    pub fn infer_params<F>(&self, column_type: &F, params: &mut Vec<(usize, Option<DataType>)>)
+    where
+        F: Fn(&str) -> Result<DataType>,
+    {
+        match self {
+            ScalarExpr::Value(Operand::Param(idx)) => params.push((*idx, None)),
+            ScalarExpr::Unary(UnaryOp::Not, e) => {
+                e.infer_param(Some(DataType::Bool), params);
+                e.infer_params(column_type, params);
+            }
+            ScalarExpr::Function(name, args) => {
+                if let Ok(function) = functions::lookup(name) {
+                    for (idx, arg) in args.iter().enumerate() {
+                        arg.infer_param(function.arg(idx).param_type(), params);
+                    }
+                }
+                for arg in args {
+                    arg.infer_params(column_type, params);
+                }
+            }
+            ScalarExpr::Case(case) => {
+                let (when_type, result_type) = case.param_hints(column_type);
+                if let Some(operand) = &case.operand {
+                    let when = case
+                        .branches
+                        .iter()
+                        .find_map(|(when, _)| when.datatype(column_type).ok().flatten());
+                    operand.infer_param(when, params);
+                }
+                for (when, then) in &case.branches {
+                    when.infer_param(when_type.clone(), params);
+                    then.infer_param(result_type.clone(), params);
+                }
+                if let Some(otherwise) = &case.otherwise {
+                    otherwise.infer_param(result_type, params);
+                }
+                for c in self.children() {
+                    c.infer_params(column_type, params);
+                }
+            }
+            ScalarExpr::Window(window) => {
+                if let [value, offset, rest @ ..] = window.args.as_slice() {
+                    if matches!(window.func, WindowFunc::Lag | WindowFunc::Lead) {
+                        offset.infer_param(Some(DataType::Int), params);
+                        if let Some(default) = rest.first() {
+                            default.infer_param(value.datatype(column_type).ok().flatten(), params);
+                        }
+                    }
+                }
+                for c in self.children() {
+                    c.infer_params(column_type, params);
+                }
+            }
+            ScalarExpr::Array(items) => {
+                let hint = items
+                    .iter()
+                    .find_map(|item| item.datatype(column_type).ok().flatten());
+                for item in items {
+                    item.infer_param(hint.clone(), params);
+                    item.infer_params(column_type, params);
+                }
+            }
+            // `CAST(? AS int)` binds the parameter as an int.
+            ScalarExpr::Cast(e, datatype) => {
+                e.infer_param(Some(datatype.clone()), params);
+                e.infer_params(column_type, params);
+            }
+            ScalarExpr::Quantified(l, _, r, _) => {
+                let left = l.datatype(column_type).ok().flatten();
+                let right = r.datatype(column_type).ok().flatten();
+                if let Some(DataType::Array(item)) = right {
+                    l.infer_param(Some(*item), params);
+                }
+                r.infer_param(left.map(|d| DataType::Array(Box::new(d))), params);
+                l.infer_params(column_type, params);
+                r.infer_params(column_type, params);
+            }
+            ScalarExpr::InList(e, list, _) => {
+                let datatype = e.datatype(column_type).ok().flatten();
+                let hint = datatype.clone().or_else(|| {
+                    list.iter()
+                        .find_map(|item| item.datatype(column_type).ok().flatten())
+                });
+                e.infer_param(hint.clone(), params);
+                for item in list {
+                    item.infer_param(hint.clone(), params);
+                }
+                for c in self.children() {
+                    c.infer_params(column_type, params);
+                }
+            }
+            ScalarExpr::Binary(l, op, r) => {
+                let hint = match op {
+                    BinaryOp::Concat => {
+                        let binary = Some(DataType::Binary);
+                        match (
+                            l.datatype(column_type).ok().flatten(),
+                            r.datatype(column_type).ok().flatten(),
+                        ) {
+                            // Next to an array, a parameter is taken to be
+                            // another array of the same type.
+                            (Some(array @ DataType::Array(_)), None)
+                            | (None, Some(array @ DataType::Array(_))) => {
+                                (Some(array.clone()), Some(array))
+                            }
+                            (Some(DataType::Binary), _) | (_, Some(DataType::Binary)) => {
+                                (binary.clone(), binary)
+                            }
+                            _ => (Some(DataType::Str), Some(DataType::Str)),
+                        }
+                    }
+                    BinaryOp::Match(_) => (Some(DataType::Str), Some(DataType::Str)),
+                    BinaryOp::And | BinaryOp::Or => (Some(DataType::Bool), Some(DataType::Bool)),
+                    _ => {
+                        let left = l.datatype(column_type).ok().flatten();
+                        let right = r.datatype(column_type).ok().flatten();
+                        match (left, right) {
+                            (Some(d), right) if op.is_arithmetic() && is_temporal(&d) => {
+                                (right, datetime::param_type(op, &d, true))
+                            }
+                            (left, Some(d)) if op.is_arithmetic() && is_temporal(&d) => {
+                                (datetime::param_type(op, &d, false), left)
+                            }
+                            (left, right) => (right, left),
+                        }
+                    }
+                };
+                l.infer_param(hint.0, params);
+                r.infer_param(hint.1, params);
+                l.infer_params(column_type, params);
+                r.infer_params(column_type, params);
+            }
+            e => {
+                for c in e.children() {
+                    c.infer_params(column_type, params);
+                }
+            }
+        }
+    }
+
+    fn infer_param(&self, datatype: Option<DataType>, params: &mut Vec<(usize, Option<DataType>)>) {
+        if let (ScalarExpr::Value(Operand::Param(idx)), Some(_)) = (self, &datatype) {
+            params.push((*idx, datatype));
+        }
+    }
+
+    /// Computes the expression for the row or group `scope` points at.
+    pub fn eval(&self, scope: &dyn Scope) -> Result<Value> {
+        match self {
+            ScalarExpr::Column(name) => scope.column(name),
+            ScalarExpr::Aggregate(agg) => scope.aggregate(agg),
+            ScalarExpr::Window(window) => scope.window(window),
+            ScalarExpr::Case(case) => case.eval(scope),
+            ScalarExpr::Function(name, args) => {
+                let function = functions::lookup(name)?;
+                let values = args
+                    .iter()
+                    .map(|a| a.eval(scope))
+                    .collect::<Result<Vec<_>>>()?;
+                let null = |(idx, v): (usize, &Value)| {
+                    v.is_null() && function.arg(idx) != functions::Arg::JsonValue
+                };
+                if values.iter().enumerate().any(null) {
+                    return Ok(Value::Null);
+                }
+                (function.call)(&values)
+            }
+            ScalarExpr::Value(operand) => operand.as_literal().cloned(),
+            ScalarExpr::Unary(op, e) => match (op, e.eval(scope)?) {
+                (_, Value::Null) => Ok(Value::Null),
+                (UnaryOp::Minus, Value::Interval(v)) => v
+                    .checked_neg()
+                    .map(Value::Interval)
+                    .ok_or_else(|| Error::OutOfRange(format!("-({v}) is out of range"))),
+                (UnaryOp::Minus, v) => numeric::negate(&v)
+                    .unwrap_or_else(|| Err(Error::Type(format!("Cannot apply {op:?} to {v}")))),
+                (UnaryOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+                (op, v) => Err(Error::Type(format!("Cannot apply {op:?} to {v}"))),
+            },
+            ScalarExpr::IsNull(e, negated) => Ok(Value::Bool(e.eval(scope)?.is_null() != *negated)),
+            ScalarExpr::Cast(e, datatype) => cast::cast(e.eval(scope)?, datatype),
+            ScalarExpr::InList(e, list, negated) => {
+                let value = e.eval(scope)?;
+                if value.is_null() {
+                    return Ok(Value::Null);
+                }
+                // Without a match, a NULL in the list makes the result unknown.
+                let mut unknown = false;
+                for item in list {
+                    let item = item.eval(scope)?;
+                    match value.compare(&item) {
+                        Some(Ordering::Equal) => return Ok(Value::Bool(!negated)),
+                        Some(_) => {}
+                        None if item.is_null() => unknown = true,
+                        None => {
+                            return Err(Error::Type(format!("Cannot compare {value} with {item}")))
+                        }
+                    }
+                }
+                match unknown {
+                    true => Ok(Value::Null),
+                    false => Ok(Value::Bool(*negated)),
+                }
+            }
+            ScalarExpr::Array(items) => Value::array(
+                items
+                    .iter()
+                    .map(|item| item.eval(scope))
+                    .collect::<Result<_>>()?,
+            ),
+            ScalarExpr::Quantified(l, op, r, all) => {
+                let value = l.eval(scope)?;
+                let items = match r.eval(scope)? {
+                    Value::Array(items) => items,
+                    Value::Null => return Ok(Value::Null),
+                    v => return Err(Error::Type(format!("{v} is not an array"))),
+                };
+                // Like IN, a NULL item makes the result unknown unless
+                // another item decides it.
+                let mut unknown = false;
+                for item in &items {
+                    match value.compare(item) {
+                        Some(ord) if op.matches(ord) != *all => return Ok(Value::Bool(!all)),
+                        Some(_) => {}
+                        None if value.is_null() || item.is_null() => unknown = true,
+                        None => {
+                            return Err(Error::Type(format!("Cannot compare {value} with {item}")))
+                        }
+                    }
+                }
+                match unknown {
+                    true => Ok(Value::Null),
+                    false => Ok(Value::Bool(*all)),
+                }
+            }
+            ScalarExpr::Binary(l, op, r) => {
+                let left = l.eval(scope)?;
+                let right = r.eval(scope)?;
+                match op {
+                    BinaryOp::And => logical(left, right, false),
+                    BinaryOp::Or => logical(left, right, true),
+                    BinaryOp::Concat
+                        if matches!(left, Value::Array(_)) || matches!(right, Value::Array(_)) =>
+                    {
+                        concat_arrays(left, right)
+                    }
+                    _ if left.is_null() || right.is_null() => Ok(Value::Null),
+                    BinaryOp::Concat => match (left, right) {
+                        (Value::Binary(mut left), Value::Binary(right)) => {
+                            left.extend(right);
+                            Ok(Value::Binary(left))
+                        }
+                        (left, right) => Ok(Value::Str(format!("{left}{right}"))),
+                    },
+                    BinaryOp::Match(pattern) => match (&left, &right) {
+                        (Value::Str(value), Value::Str(p)) => {
+                            Ok(Value::Bool(pattern.is_match(value, p)?))
+                        }
+                        _ => Err(Error::Type(format!(
+                            "Operator {} cannot be applied to {left} and {right}",
+                            op.symbol()
+                        ))),
+                    },
+                    BinaryOp::Compare(binary) => match left.compare(&right) {
+                        Some(ord) => Ok(Value::Bool(binary.matches(ord))),
+                        None => Err(Error::Type(format!("Cannot compare {left} with {right}"))),
+                    },
+                    op => arithmetic(op, left, right),
+                }
+            }
+        }
+    }
+}
+
+/// AND and OR with SQL's three-valued logic: `dominant` decides the result
+/// on its own (false for AND, true for OR), otherwise NULL wins.
+fn logical(left: Value, right: Value, dominant: bool) -> Result<Value> {
+    let as_bool = |v: &Value| match v {
+        Value::Bool(b) => Ok(Some(*b)),
+        Value::Null => Ok(None),
+        v => Err(Error::Type(format!("{v} is not a Boolean"))),
+    };
+    match (as_bool(&left)?, as_bool(&right)?) {
+        (Some(a), _) if a == dominant => Ok(Value::Bool(dominant)),
+        (_, Some(b)) if b == dominant => Ok(Value::Bool(dominant)),
+        (Some(_), Some(_)) => Ok(Value::Bool(!dominant)),
+        _ => Ok(Value::Null),
+    }
+}
+
+/// The type of `||` on an array of `item` and either another array or a
+/// single element of type `other`. Text takes the type of the array, as in
+/// an `ARRAY[...]` literal, and numbers promote.
+fn concat_type(item: &DataType, other: &DataType) -> Option<DataType> {
+    let other = match other {
+        DataType::Array(other) => other,
+        other => other,
+    };
+    let item = match (item, other) {
+        (a, b) if a == b => a.clone(),
+        (a, DataType::Str) | (DataType::Str, a) => a.clone(),
+        (a, b) if is_numeric(a) && is_numeric(b) => promote(a, b)?,
+        _ => return None,
+    };
+    Some(DataType::Array(Box::new(item)))
+}
+
+/// `||` with an array on at least one side: joins two arrays, or appends or
+/// prepends a single element. A NULL next to an array is an absent array,
+/// so the other side is returned as is.
+fn concat_arrays(left: Value, right: Value) -> Result<Value> {
+    match (left, right) {
+        (Value::Array(items), Value::Null) | (Value::Null, Value::Array(items)) => {
+            Ok(Value::Array(items))
+        }
+        (Value::Array(mut left), Value::Array(right)) => {
+            left.extend(right);
+            Value::array(left)
+        }
+        (Value::Array(mut items), item) => {
+            items.push(item);
+            Value::array(items)
+        }
+        // The element goes last while the type is worked out, so that the
+        // array's items decide it, then moves to the front.
+        (item, Value::Array(mut items)) => {
+            items.push(item);
+            match Value::array(items)? {
+                Value::Array(mut items) => {
+                    items.rotate_right(1);
+                    Ok(Value::Array(items))
+                }
+                value => Ok(value),
+            }
+        }
+        (left, right) => Ok(Value::Str(format!("{left}{right}"))),
+    }
+}
+
+fn arithmetic(op: &BinaryOp, left: Value, right: Value) -> Result<Value> {
+    numeric::arithmetic(op, &left, &right)
+        .or_else(|| datetime::arithmetic(op, &left, &right))
+        .unwrap_or_else(|| {
+            Err(Error::Type(format!(
+                "Operator {} cannot be applied to {left} and {right}",
+                op.symbol()
+            )))
+        })
+}
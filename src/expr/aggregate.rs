@@ -0,0 +1,202 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::numeric::promote;
+use crate::error::{Error, Result};
+use crate::table::DataType;
+use crate::value::decimal::{DIVISION_SCALE, MAX_PRECISION};
+use crate::value::{Decimal, Json, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AggregateFunc {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    /// The values as a JSON array, NULLs included.
+    JsonArrayAgg,
+    /// The values as an array, NULLs included.
+    ArrayAgg,
+}
+
+impl AggregateFunc {
+    pub fn from_name(name: &str) -> Option<AggregateFunc> {
+        match name.to_uppercase().as_ref() {
+            "COUNT" => Some(AggregateFunc::Count),
+            "SUM" => Some(AggregateFunc::Sum),
+            "AVG" => Some(AggregateFunc::Avg),
+            "MIN" => Some(AggregateFunc::Min),
+            "MAX" => Some(AggregateFunc::Max),
+            "JSON_ARRAYAGG" => Some(AggregateFunc::JsonArrayAgg),
+            "ARRAY_AGG" => Some(AggregateFunc::ArrayAgg),
+            _ => None,
+        }
+    }
+
+    /// The type of the result for an argument of type `arg`.
+    pub fn datatype(&self, arg: Option<DataType>) -> Result<Option<DataType>> {
+        match (self, arg) {
+            (AggregateFunc::Count, _) => Ok(Some(DataType::Int)),
+            (AggregateFunc::JsonArrayAgg, _) => Ok(Some(DataType::Json)),
+            (AggregateFunc::ArrayAgg, arg) => Ok(arg.map(|d| DataType::Array(Box::new(d)))),
+            (AggregateFunc::Sum, Some(d)) if d.is_numeric() => Ok(Some(sum_type(&d))),
+            (AggregateFunc::Avg, Some(DataType::Decimal(_, scale))) => Ok(Some(DataType::Decimal(
+                MAX_PRECISION,
+                (scale + DIVISION_SCALE).min(MAX_PRECISION),
+            ))),
+            (AggregateFunc::Avg, Some(d)) if d.is_numeric() => Ok(Some(DataType::Double)),
+            (AggregateFunc::Sum | AggregateFunc::Avg, Some(d)) => {
+                Err(Error::Type(format!("{self} cannot be applied to {d}")))
+            }
+            (AggregateFunc::Avg, None) => Ok(Some(DataType::Double)),
+            (_, arg) => Ok(arg),
+        }
+    }
+
+    /// Folds the values of a group into the aggregate's result. NULLs are
+    /// skipped, except by ARRAY_AGG and JSON_ARRAYAGG, and an aggregate of no values is
+    /// NULL, except for COUNT.
+    pub fn accumulate(&self, values: Vec<Value>) -> Result<Value> {
+        let values: Vec<Value> = match self {
+            AggregateFunc::JsonArrayAgg | AggregateFunc::ArrayAgg => values,
+            _ => values.into_iter().filter(|v| !v.is_null()).collect(),
+        };
+        if values.is_empty() && *self != AggregateFunc::Count {
+            return Ok(Value::Null);
+        }
+        match self {
+            AggregateFunc::Count => i32::try_from(values.len())
+                .map(Value::Int)
+                .map_err(|_| Error::OutOfRange("COUNT does not fit in an Int".to_string())),
+            AggregateFunc::Sum => sum(&values),
+            AggregateFunc::Avg => {
+                // The count always fits, and isn't zero.
+                let count = values.len();
+                match total(&values)?.1 {
+                    Total::Decimal(total) => Decimal::from_i128(count as i128)
+                        .and_then(|count| total.checked_div(&count))
+                        .map(Value::Decimal)
+                        .ok_or_else(|| Error::OutOfRange("AVG is out of range".to_string())),
+                    Total::Integer(total) => Ok(Value::Double(total as f64 / count as f64)),
+                    Total::Float(total) => Ok(Value::Double(total / count as f64)),
+                }
+            }
+            AggregateFunc::Min => Ok(extreme(values, Ordering::Less)),
+            AggregateFunc::Max => Ok(extreme(values, Ordering::Greater)),
+            AggregateFunc::JsonArrayAgg => json_array(&values),
+            AggregateFunc::ArrayAgg => Value::array(values),
+        }
+    }
+}
+
+/// The type of SUM over a numeric type: the widest integer or decimal type
+/// of the same signedness or scale, so that sums of small numbers don't
+/// overflow.
+fn sum_type(datatype: &DataType) -> DataType {
+    match datatype {
+        DataType::Decimal(_, scale) => DataType::Decimal(MAX_PRECISION, *scale),
+        DataType::SmallInt | DataType::Int | DataType::BigInt => DataType::BigInt,
+        DataType::UnsignedSmallInt | DataType::UnsignedInt | DataType::UnsignedBigInt => {
+            DataType::UnsignedBigInt
+        }
+        d => d.clone(),
+    }
+}
+
+/// A running total, kept wider than the type of SUM so that only the final
+/// result is range-checked.
+enum Total {
+    Decimal(Decimal),
+    Integer(i128),
+    Float(f64),
+}
+
+/// Adds up `values`, returning the type of their SUM and the exact total.
+fn total(values: &[Value]) -> Result<(DataType, Total)> {
+    let mut datatype = None;
+    for value in values {
+        let promoted = match (&datatype, value.datatype()) {
+            (None, Some(d)) if d.is_numeric() => Some(d),
+            (Some(a), Some(b)) => promote(a, &b),
+            _ => None,
+        };
+        datatype =
+            Some(promoted.ok_or_else(|| Error::Type(format!("SUM cannot be applied to {value}")))?);
+    }
+    let datatype = sum_type(&datatype.unwrap_or(DataType::Int));
+    let total = match &datatype {
+        // Decimals are added exactly, so a sum doesn't drift like a float's.
+        DataType::Decimal(..) => values
+            .iter()
+            .try_fold(Decimal::ZERO, |total, v| {
+                total.checked_add(&v.as_decimal()?)
+            })
+            .map(Total::Decimal),
+        d if d.is_integer() => values
+            .iter()
+            .filter_map(Value::as_i128)
+            .try_fold(0i128, i128::checked_add)
+            .map(Total::Integer),
+        _ => Some(Total::Float(values.iter().filter_map(Value::as_f64).sum())),
+    };
+    match total {
+        Some(total) => Ok((datatype, total)),
+        None => Err(Error::OutOfRange(format!(
+            "SUM is out of range for type {datatype}"
+        ))),
+    }
+}
+
+fn sum(values: &[Value]) -> Result<Value> {
+    let (datatype, total) = total(values)?;
+    let total = match total {
+        Total::Decimal(total) => Some(Value::Decimal(total)),
+        Total::Integer(total) => Value::from_i128(total, &datatype),
+        Total::Float(total) => Value::from_f64(total, &datatype),
+    };
+    total.ok_or_else(|| Error::OutOfRange(format!("SUM is out of range for type {datatype}")))
+}
+
+fn json_array(values: &[Value]) -> Result<Value> {
+    let items = values
+        .iter()
+        .map(|v| {
+            Json::from_value(v).ok_or_else(|| Error::Type(format!("{v} cannot be stored in JSON")))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Value::Json(Json::Array(items)))
+}
+
+fn extreme(values: Vec<Value>, wanted: Ordering) -> Value {
+    let mut best = Value::Null;
+    for value in values {
+        if best.is_null() || value.compare(&best) == Some(wanted) {
+            best = value;
+        }
+    }
+    best
+}
+
+impl fmt::Display for AggregateFunc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AggregateFunc::Count => f.write_str("COUNT"),
+            AggregateFunc::Sum => f.write_str("SUM"),
+            AggregateFunc::Avg => f.write_str("AVG"),
+            AggregateFunc::Min => f.write_str("MIN"),
+            AggregateFunc::Max => f.write_str("MAX"),
+            AggregateFunc::JsonArrayAgg => f.write_str("JSON_ARRAYAGG"),
+            AggregateFunc::ArrayAgg => f.write_str("ARRAY_AGG"),
+        }
+    }
+}
+
+/// An aggregate call such as `SUM(price)`. `arg` is `None` for `COUNT(*)`,
+/// and `distinct` aggregates see each distinct value only once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub func: AggregateFunc,
+    pub arg: Option<Box<super::ScalarExpr>>,
+    pub distinct: bool,
+}
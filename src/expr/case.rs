@@ -0,0 +1,246 @@
+use std::cmp::Ordering;
+
+use super::numeric::promote;
+use super::{comparable, is_numeric, type_name, ScalarExpr, Scope};
+use crate::error::{Error, Result};
+use crate::parser::Operand;
+use crate::table::DataType;
+use crate::value::Value;
+
+/// `CASE [operand] WHEN ... THEN ... [ELSE ...] END`. COALESCE, NULLIF, IF
+/// and IFNULL are planned as a CASE as well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    /// Compared with every WHEN value in a simple CASE; a searched CASE
+    /// has none and its WHEN conditions are Booleans.
+    pub operand: Option<Box<ScalarExpr>>,
+    pub branches: Vec<(ScalarExpr, ScalarExpr)>,
+    pub otherwise: Option<Box<ScalarExpr>>,
+    /// The type all results are converted to, known once resolved.
+    pub datatype: Option<DataType>,
+}
+
+fn null() -> ScalarExpr {
+    ScalarExpr::Value(Operand::Literal(Value::Null))
+}
+
+fn check_arity(name: &str, args: &[ScalarExpr], ok: bool, expected: &str) -> Result<()> {
+    match ok {
+        true => Ok(()),
+        false => Err(Error::Binding(format!(
+            "Function {name} takes {expected} arguments but got {}",
+            args.len()
+        ))),
+    }
+}
+
+impl Case {
+    fn new(
+        operand: Option<ScalarExpr>,
+        branches: Vec<(ScalarExpr, ScalarExpr)>,
+        otherwise: Option<ScalarExpr>,
+    ) -> Case {
+        Case {
+            operand: operand.map(Box::new),
+            branches,
+            otherwise: otherwise.map(Box::new),
+            datatype: None,
+        }
+    }
+
+    /// Builds the CASE for a conditional function, or returns `None` if
+    /// `name` is not one.
+    pub fn from_function(name: &str, mut args: Vec<ScalarExpr>) -> Result<Option<Case>> {
+        let case = match name {
+            "COALESCE" | "IFNULL" => {
+                match name {
+                    "IFNULL" => check_arity(name, &args, args.len() == 2, "2")?,
+                    _ => check_arity(name, &args, !args.is_empty(), "at least 1")?,
+                }
+                let last = args.pop();
+                let branches = args
+                    .into_iter()
+                    .map(|a| (ScalarExpr::IsNull(Box::new(a.clone()), true), a))
+                    .collect();
+                Case::new(None, branches, last)
+            }
+            "NULLIF" => {
+                check_arity(name, &args, args.len() == 2, "2")?;
+                let value = args.remove(0);
+                let unless = args.remove(0);
+                Case::new(Some(value.clone()), vec![(unless, null())], Some(value))
+            }
+            "IF" => {
+                check_arity(name, &args, args.len() == 3, "3")?;
+                let otherwise = args.pop();
+                let then = args.remove(1);
+                Case::new(None, vec![(args.remove(0), then)], otherwise)
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(case))
+    }
+
+    pub fn from_ast(
+        operand: &Option<Box<sqlparser::ast::Expr>>,
+        conditions: &[sqlparser::ast::Expr],
+        results: &[sqlparser::ast::Expr],
+        else_result: &Option<Box<sqlparser::ast::Expr>>,
+    ) -> Result<Case> {
+        let branches = conditions
+            .iter()
+            .zip(results)
+            .map(|(c, r)| Ok((ScalarExpr::try_from(c)?, ScalarExpr::try_from(r)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Case::new(
+            operand.as_deref().map(ScalarExpr::try_from).transpose()?,
+            branches,
+            else_result
+                .as_deref()
+                .map(ScalarExpr::try_from)
+                .transpose()?,
+        ))
+    }
+
+    pub fn children(&self) -> Vec<&ScalarExpr> {
+        let mut children: Vec<&ScalarExpr> = self.operand.iter().map(|e| &**e).collect();
+        for (when, then) in &self.branches {
+            children.push(when);
+            children.push(then);
+        }
+        children.extend(self.otherwise.iter().map(|e| &**e));
+        children
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut ScalarExpr> {
+        let mut children: Vec<&mut ScalarExpr> =
+            self.operand.iter_mut().map(|e| &mut **e).collect();
+        for (when, then) in &mut self.branches {
+            children.push(when);
+            children.push(then);
+        }
+        children.extend(self.otherwise.iter_mut().map(|e| &mut **e));
+        children
+    }
+
+    fn results(&self) -> impl Iterator<Item = &ScalarExpr> {
+        self.branches
+            .iter()
+            .map(|(_, then)| then)
+            .chain(self.otherwise.iter().map(|e| &**e))
+    }
+
+    /// The type the WHEN values are compared as.
+    fn when_type<F>(&self, column_type: &F) -> Result<Option<DataType>>
+    where
+        F: Fn(&str) -> Result<DataType>,
+    {
+        match &self.operand {
+            Some(operand) => operand.datatype(column_type),
+            None => Ok(Some(DataType::Bool)),
+        }
+    }
+
+    /// Resolves every branch, converting text literals among the WHEN
+    /// values and the results to the type the others agree on.
+    pub fn resolve<F>(&mut self, column_type: &F) -> Result<()>
+    where
+        F: Fn(&str) -> Result<DataType>,
+    {
+        for c in self.children_mut() {
+            c.resolve(column_type)?;
+        }
+        let when_type = self.when_type(column_type)?;
+        let typed_results = self
+            .results()
+            .filter(|e| !matches!(e, ScalarExpr::Value(Operand::Literal(Value::Str(_)))))
+            .map(|e| e.datatype(column_type))
+            .collect::<Result<Vec<_>>>()?;
+        let result_type = common_type(typed_results)?;
+        for (when, then) in &mut self.branches {
+            when.coerce_literal(&when_type)?;
+            then.coerce_literal(&result_type)?;
+        }
+        if let Some(otherwise) = &mut self.otherwise {
+            otherwise.coerce_literal(&result_type)?;
+        }
+        self.datatype = self.datatype(column_type)?;
+        Ok(())
+    }
+
+    pub fn datatype<F>(&self, column_type: &F) -> Result<Option<DataType>>
+    where
+        F: Fn(&str) -> Result<DataType>,
+    {
+        let when_type = self.when_type(column_type)?;
+        for (when, _) in &self.branches {
+            let datatype = when.datatype(column_type)?;
+            if !comparable(&when_type, &datatype) {
+                return Err(Error::Type(format!(
+                    "CASE cannot compare {} with {}",
+                    type_name(&when_type),
+                    type_name(&datatype)
+                )));
+            }
+        }
+        let types = self
+            .results()
+            .map(|e| e.datatype(column_type))
+            .collect::<Result<Vec<_>>>()?;
+        common_type(types)
+    }
+
+    /// The hints for parameters among the WHEN values and the results.
+    pub fn param_hints<F>(&self, column_type: &F) -> (Option<DataType>, Option<DataType>)
+    where
+        F: Fn(&str) -> Result<DataType>,
+    {
+        (
+            self.when_type(column_type).ok().flatten(),
+            self.datatype(column_type).ok().flatten(),
+        )
+    }
+
+    pub fn eval(&self, scope: &dyn Scope) -> Result<Value> {
+        let operand = self.operand.as_ref().map(|o| o.eval(scope)).transpose()?;
+        let mut result = None;
+        for (when, then) in &self.branches {
+            let when = when.eval(scope)?;
+            let matched = match &operand {
+                Some(operand) => operand.compare(&when) == Some(Ordering::Equal),
+                None => when == Value::Bool(true),
+            };
+            if matched {
+                result = Some(then);
+                break;
+            }
+        }
+        let value = match result.or(self.otherwise.as_deref()) {
+            Some(e) => e.eval(scope)?,
+            None => Value::Null,
+        };
+        match &self.datatype {
+            Some(datatype) => value.coerce(datatype),
+            None => Ok(value),
+        }
+    }
+}
+
+/// The single type a set of results can all be given: numbers mix as the
+/// type they promote to, unknown types are ignored.
+fn common_type(types: Vec<Option<DataType>>) -> Result<Option<DataType>> {
+    let mut common: Option<DataType> = None;
+    for datatype in types.into_iter().flatten() {
+        common = match common {
+            None => Some(datatype),
+            Some(c) if c == datatype => Some(c),
+            Some(c) if is_numeric(&c) && is_numeric(&datatype) => promote(&c, &datatype),
+            Some(c) => {
+                return Err(Error::Type(format!(
+                    "CASE results have incompatible types {c} and {datatype}"
+                )))
+            }
+        };
+    }
+    Ok(common)
+}
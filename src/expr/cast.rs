@@ -0,0 +1,63 @@
+use super::datetime::is_datetime;
+use super::is_numeric;
+use crate::error::{Error, Result};
+use crate::table::DataType;
+use crate::value::datetime::MICROS_PER_DAY;
+use crate::value::decimal::Rounding;
+use crate::value::Value;
+
+/// Whether `CAST` can turn a value of type `from` into one of type `to`.
+/// Any type casts to and from text, numbers cast to each other and to and
+/// from Booleans and integers, and dates and timestamps cast to each other.
+pub fn castable(from: &DataType, to: &DataType) -> bool {
+    match (from, to) {
+        (from, to) if from == to => true,
+        (DataType::Str, _) | (_, DataType::Str) => true,
+        (from, to) if is_numeric(from) && is_numeric(to) => true,
+        (DataType::Bool, to) => to.is_integer() || *to == DataType::Json,
+        (from, DataType::Bool) => from.is_integer(),
+        (from, to) if is_datetime(from) && is_datetime(to) => true,
+        (DataType::Timestamp | DataType::TimestampTz, DataType::Time) => true,
+        (DataType::Uuid, DataType::Binary) | (DataType::Binary, DataType::Uuid) => true,
+        (from, DataType::Json) => is_numeric(from) || matches!(from, DataType::Array(_)),
+        (DataType::Array(from), DataType::Array(to)) => castable(from, to),
+        (DataType::Enum(from), DataType::Enum(to)) => from.name == to.name,
+        _ => false,
+    }
+}
+
+/// Casts `value` to `datatype`. Unlike the conversion done when storing or
+/// comparing a value, numbers are rounded to fit an integer type, with
+/// halves away from zero, and any integer casts to a Boolean.
+pub fn cast(value: Value, datatype: &DataType) -> Result<Value> {
+    match (value, datatype) {
+        (Value::Null, _) => Ok(Value::Null),
+        (v @ (Value::Float(_) | Value::Double(_) | Value::Decimal(_)), d) if d.is_integer() => {
+            let rounded = match &v {
+                Value::Decimal(decimal) => decimal
+                    .rescale(0, Rounding::HalfUp)
+                    .and_then(|r| r.to_i128()),
+                v => v
+                    .as_f64()
+                    .filter(|f| f.is_finite())
+                    .map(|f| f.round() as i128),
+            };
+            rounded
+                .and_then(|n| Value::from_i128(n, d))
+                .ok_or_else(|| Error::OutOfRange(format!("{v} is out of range for type {d}")))
+        }
+        (Value::Bool(b), d) if d.is_integer() => Value::Int(b as i32).coerce(d),
+        (v, DataType::Bool) if v.as_i128().is_some() => Ok(Value::Bool(v.as_i128() != Some(0))),
+        (Value::Timestamp(t) | Value::TimestampTz(t), DataType::Time) => {
+            Ok(Value::Time(t.rem_euclid(MICROS_PER_DAY)))
+        }
+        (Value::Uuid(u), DataType::Binary) => Ok(Value::Binary(u.to_be_bytes().to_vec())),
+        (Value::Array(items), DataType::Array(item)) => Ok(Value::Array(
+            items
+                .into_iter()
+                .map(|v| cast(v, item))
+                .collect::<Result<_>>()?,
+        )),
+        (v, d) => v.coerce(d),
+    }
+}
@@ -0,0 +1,133 @@
+use super::BinaryOp;
+use crate::error::{Error, Result};
+use crate::table::DataType;
+use crate::value::datetime::{self, Interval, MICROS_PER_DAY};
+use crate::value::Value;
+
+pub fn is_temporal(datatype: &DataType) -> bool {
+    matches!(
+        datatype,
+        DataType::Date
+            | DataType::Time
+            | DataType::Timestamp
+            | DataType::TimestampTz
+            | DataType::Interval
+    )
+}
+
+/// Whether values of the type are points in time, which compare with each
+/// other whether or not they have a time of day or a time zone.
+pub fn is_datetime(datatype: &DataType) -> bool {
+    matches!(
+        datatype,
+        DataType::Date | DataType::Timestamp | DataType::TimestampTz
+    )
+}
+
+/// The type of `left op right` when either side is a date, time or interval,
+/// or `None` if the operator doesn't apply to them.
+pub fn arithmetic_type(op: &BinaryOp, left: &DataType, right: &DataType) -> Option<DataType> {
+    use DataType::*;
+    let datatype = match (op, left, right) {
+        (BinaryOp::Plus, Date, Int)
+        | (BinaryOp::Plus, Int, Date)
+        | (BinaryOp::Minus, Date, Int) => Date,
+        (BinaryOp::Minus, Date, Date) => Int,
+        (BinaryOp::Plus, Date, Time) | (BinaryOp::Plus, Time, Date) => Timestamp,
+        (BinaryOp::Plus | BinaryOp::Minus, d, Interval) | (BinaryOp::Plus, Interval, d) => {
+            match d {
+                Date | Timestamp => Timestamp,
+                TimestampTz => TimestampTz,
+                Time => Time,
+                Interval => Interval,
+                _ => return None,
+            }
+        }
+        (BinaryOp::Minus, a, b) if is_datetime(a) && is_datetime(b) => Interval,
+        (BinaryOp::Minus, Time, Time) => Interval,
+        (BinaryOp::Multiply, Interval, n) | (BinaryOp::Multiply, n, Interval) if n.is_numeric() => {
+            Interval
+        }
+        (BinaryOp::Divide, Interval, n) if n.is_numeric() => Interval,
+        _ => return None,
+    };
+    Some(datatype)
+}
+
+/// The type a parameter combined with a value of type `known` is bound as,
+/// e.g. the interval in `created_at + ?`.
+pub fn param_type(op: &BinaryOp, known: &DataType, known_on_left: bool) -> Option<DataType> {
+    use DataType::*;
+    let datatype = match (op, known) {
+        (BinaryOp::Plus | BinaryOp::Minus, Interval) => Interval,
+        (BinaryOp::Multiply | BinaryOp::Divide, Interval) => Double,
+        (BinaryOp::Minus, d) if !known_on_left => d.clone(),
+        (BinaryOp::Plus | BinaryOp::Minus, Date) => Int,
+        (BinaryOp::Plus | BinaryOp::Minus, Time | Timestamp | TimestampTz) => Interval,
+        _ => return None,
+    };
+    Some(datatype)
+}
+
+/// Adds an interval to a value, or `None` if it can't take one. The inner
+/// `None` means the result is out of range.
+fn add_interval(value: &Value, interval: &Interval) -> Option<Option<Value>> {
+    let sum = match value {
+        Value::Date(_) | Value::Timestamp(_) => {
+            datetime::add_interval(value.as_micros()?, interval).map(Value::Timestamp)
+        }
+        Value::TimestampTz(t) => datetime::add_interval(*t, interval).map(Value::TimestampTz),
+        // A time of day wraps around midnight and ignores days and months.
+        Value::Time(t) => Some(Value::Time(
+            (t + interval.micros % MICROS_PER_DAY).rem_euclid(MICROS_PER_DAY),
+        )),
+        Value::Interval(i) => i.checked_add(interval).map(Value::Interval),
+        _ => return None,
+    };
+    Some(sum)
+}
+
+/// Computes `left op right` for dates, times and intervals, or returns
+/// `None` if the operator doesn't apply to the values.
+pub fn arithmetic(op: &BinaryOp, left: &Value, right: &Value) -> Option<Result<Value>> {
+    let valid_date = |d: &i32| datetime::date_from_days(*d).is_some();
+    let result = match (op, left, right) {
+        (BinaryOp::Plus, Value::Date(d), Value::Int(n))
+        | (BinaryOp::Plus, Value::Int(n), Value::Date(d)) => {
+            d.checked_add(*n).filter(valid_date).map(Value::Date)
+        }
+        (BinaryOp::Minus, Value::Date(d), Value::Int(n)) => {
+            d.checked_sub(*n).filter(valid_date).map(Value::Date)
+        }
+        (BinaryOp::Minus, Value::Date(a), Value::Date(b)) => a.checked_sub(*b).map(Value::Int),
+        (BinaryOp::Plus, Value::Date(d), Value::Time(t))
+        | (BinaryOp::Plus, Value::Time(t), Value::Date(d)) => {
+            Some(Value::Timestamp(datetime::micros_from_days(*d) + t))
+        }
+        (BinaryOp::Plus, v, Value::Interval(i)) | (BinaryOp::Plus, Value::Interval(i), v) => {
+            add_interval(v, i)?
+        }
+        (BinaryOp::Minus, v, Value::Interval(i)) => match i.checked_neg() {
+            Some(i) => add_interval(v, &i)?,
+            None => None,
+        },
+        (BinaryOp::Minus, Value::Time(a), Value::Time(b)) => {
+            Some(Value::Interval(Interval::new(0, 0, a - b)))
+        }
+        (BinaryOp::Minus, a, b) => {
+            datetime::between(a.as_micros()?, b.as_micros()?).map(Value::Interval)
+        }
+        (BinaryOp::Multiply, Value::Interval(i), n)
+        | (BinaryOp::Multiply, n, Value::Interval(i)) => {
+            i.checked_mul(n.as_f64()?).map(Value::Interval)
+        }
+        (BinaryOp::Divide, Value::Interval(i), n) => match n.as_f64()? {
+            0.0 => return Some(Err(Error::DivisionByZero)),
+            n => i.checked_mul(1.0 / n).map(Value::Interval),
+        },
+        _ => return None,
+    };
+    Some(result.ok_or_else(|| {
+        Error::OutOfRange(format!("{left} {} {right} is out of range", op.symbol()))
+    }))
+}
@@ -0,0 +1,556 @@
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use super::numeric::promote;
+use crate::error::{Error, Result};
+use crate::table::DataType;
+use crate::value::{decimal, from_hex, to_hex, uuid, Value};
+
+mod array;
+mod datetime;
+mod json;
+mod math;
+
+/// The kind of value a function argument accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arg {
+    Str,
+    Int,
+    Numeric,
+    /// Text or a binary value.
+    Bytes,
+    /// Text, a binary value or an integer.
+    BytesOrInt,
+    /// A JSON document, or text holding one.
+    Json,
+    /// A path into a JSON document, such as `$.tags[0]`.
+    JsonPath,
+    /// A value to store in a document, where NULL is JSON null rather than
+    /// making the result NULL.
+    JsonValue,
+    /// A date or a timestamp, with or without a time zone.
+    Timestamp,
+    /// A date, time, timestamp or interval.
+    Temporal,
+    Array,
+    Any,
+}
+
+impl Arg {
+    fn accepts(&self, datatype: &DataType) -> bool {
+        match self {
+            Arg::Str => *datatype == DataType::Str,
+            Arg::Int => datatype.is_integer(),
+            Arg::Numeric => datatype.is_numeric(),
+            Arg::Bytes => matches!(datatype, DataType::Str | DataType::Binary),
+            Arg::BytesOrInt => {
+                datatype.is_integer() || matches!(datatype, DataType::Str | DataType::Binary)
+            }
+            Arg::Json => matches!(datatype, DataType::Json | DataType::Str),
+            Arg::JsonPath => *datatype == DataType::Str,
+            Arg::JsonValue => {
+                datatype.is_numeric()
+                    || matches!(datatype, DataType::Str | DataType::Bool | DataType::Json)
+            }
+            Arg::Timestamp => matches!(
+                datatype,
+                DataType::Date | DataType::Timestamp | DataType::TimestampTz
+            ),
+            Arg::Temporal => matches!(
+                datatype,
+                DataType::Date
+                    | DataType::Time
+                    | DataType::Timestamp
+                    | DataType::TimestampTz
+                    | DataType::Interval
+            ),
+            Arg::Array => matches!(datatype, DataType::Array(_)),
+            Arg::Any => true,
+        }
+    }
+
+    /// The type a parameter passed as this argument is bound as.
+    pub fn param_type(&self) -> Option<DataType> {
+        match self {
+            Arg::Str | Arg::JsonPath => Some(DataType::Str),
+            Arg::Int => Some(DataType::Int),
+            Arg::Numeric => Some(DataType::Double),
+            Arg::Timestamp => Some(DataType::Timestamp),
+            Arg::Json => Some(DataType::Json),
+            Arg::Bytes
+            | Arg::BytesOrInt
+            | Arg::JsonValue
+            | Arg::Temporal
+            | Arg::Array
+            | Arg::Any => None,
+        }
+    }
+}
+
+/// The type of a function's result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Returns {
+    Str,
+    Int,
+    Double,
+    Binary,
+    Json,
+    Uuid,
+    /// The type the numeric arguments promote to, so `ABS` of a SmallInt is
+    /// a SmallInt.
+    Promoted,
+    /// Like `Promoted`, with a decimal keeping only the digits after the
+    /// point that the second argument asks for, none if it is left out,
+    /// and room for a carry into one more digit, as when 99.9 rounds up to
+    /// 100.
+    Rounded,
+    /// Like `Rounded`, without the carry.
+    Truncated,
+    Date,
+    TimestampTz,
+    /// TimestampTz when an argument is one, Timestamp otherwise.
+    Timestamp,
+    /// Timestamp when an argument is a TimestampTz, TimestampTz otherwise.
+    SwapZone,
+    /// The type of the items of the first argument, an array.
+    Item,
+}
+
+/// A scalar function callable from SQL. Functions return NULL when any
+/// argument is NULL, so `call` only ever sees non-NULL values, except for
+/// `JsonValue` arguments.
+pub struct Function {
+    pub name: &'static str,
+    pub args: &'static [Arg],
+    /// How many of `args` must be given; the rest are optional.
+    pub required: usize,
+    /// Whether the last argument can be repeated.
+    pub variadic: bool,
+    pub returns: Returns,
+    pub call: fn(&[Value]) -> Result<Value>,
+}
+
+impl Function {
+    pub fn check_arity(&self, count: usize) -> Result<()> {
+        if count >= self.required && (self.variadic || count <= self.args.len()) {
+            return Ok(());
+        }
+        let expected = if self.variadic {
+            format!("at least {}", self.required)
+        } else if self.required == self.args.len() {
+            self.required.to_string()
+        } else {
+            format!("{} to {}", self.required, self.args.len())
+        };
+        Err(Error::Binding(format!(
+            "Function {} takes {} arguments but got {}",
+            self.name, expected, count
+        )))
+    }
+
+    /// The kind of value the argument at `idx` accepts.
+    pub fn arg(&self, idx: usize) -> Arg {
+        self.args
+            .get(idx)
+            .or(self.args.last())
+            .copied()
+            .unwrap_or(Arg::Any)
+    }
+
+    /// Checks an argument given as a literal, such as a JSON path, before
+    /// any row is read.
+    pub fn check_literal(&self, idx: usize, value: &Value) -> Result<()> {
+        match self.arg(idx) {
+            Arg::JsonPath if !value.is_null() => json::path_arg(value).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The type the known types of the `Numeric` arguments promote to.
+    fn promoted(&self, types: &[Option<DataType>]) -> Option<DataType> {
+        types
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| self.arg(*idx) == Arg::Numeric)
+            .filter_map(|(_, datatype)| datatype.clone())
+            .reduce(|a, b| promote(&a, &b).unwrap_or(DataType::Double))
+    }
+
+    /// Checks the types of the arguments, and returns the type of the
+    /// result, or `None` if it is only known once parameters are bound.
+    /// `literals` holds the arguments given as literals.
+    pub fn check_args(
+        &self,
+        types: &[Option<DataType>],
+        literals: &[Option<&Value>],
+    ) -> Result<Option<DataType>> {
+        for (idx, datatype) in types.iter().enumerate() {
+            if let Some(datatype) = datatype {
+                if !self.arg(idx).accepts(datatype) {
+                    return Err(Error::Type(format!(
+                        "Argument {} of {} cannot be {}",
+                        idx + 1,
+                        self.name,
+                        datatype
+                    )));
+                }
+            }
+        }
+        Ok(Some(match self.returns {
+            Returns::Str => DataType::Str,
+            Returns::Int => DataType::Int,
+            Returns::Double => DataType::Double,
+            Returns::Binary => DataType::Binary,
+            Returns::Json => DataType::Json,
+            Returns::Uuid => DataType::Uuid,
+            Returns::Promoted => self.promoted(types).unwrap_or(DataType::Double),
+            Returns::Rounded | Returns::Truncated => {
+                match self.promoted(types).unwrap_or(DataType::Double) {
+                    DataType::Decimal(precision, scale) => {
+                        // The digits kept are only known before the function
+                        // runs when they are left out or given as a literal.
+                        let kept = match literals.get(1) {
+                            None => 0,
+                            Some(Some(digits)) => digits
+                                .as_i128()
+                                .map_or(scale, |d| d.clamp(0, scale as i128) as u8),
+                            Some(None) => scale,
+                        };
+                        let carry = (self.returns == Returns::Rounded) as u8;
+                        DataType::Decimal(
+                            (precision - scale + carry + kept).clamp(1, decimal::MAX_PRECISION),
+                            kept,
+                        )
+                    }
+                    datatype => datatype,
+                }
+            }
+            Returns::Date => DataType::Date,
+            Returns::TimestampTz => DataType::TimestampTz,
+            Returns::Timestamp if types.contains(&Some(DataType::TimestampTz)) => {
+                DataType::TimestampTz
+            }
+            Returns::Timestamp => DataType::Timestamp,
+            Returns::SwapZone if types.contains(&Some(DataType::TimestampTz)) => {
+                DataType::Timestamp
+            }
+            Returns::SwapZone => DataType::TimestampTz,
+            Returns::Item => match types.first() {
+                Some(Some(DataType::Array(item))) => *item.clone(),
+                _ => return Ok(None),
+            },
+        }))
+    }
+}
+
+pub fn lookup(name: &str) -> Result<&'static Function> {
+    let name = name.to_uppercase();
+    FUNCTIONS
+        .iter()
+        .chain(math::FUNCTIONS)
+        .chain(datetime::FUNCTIONS)
+        .chain(json::FUNCTIONS)
+        .chain(array::FUNCTIONS)
+        .find(|f| f.name == name)
+        .ok_or_else(|| Error::Binding(format!("Unknown function {name}")))
+}
+
+static FUNCTIONS: &[Function] = &[
+    Function {
+        name: "UPPER",
+        args: &[Arg::Str],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| Ok(Value::Str(str_arg(args, 0)?.to_uppercase())),
+    },
+    Function {
+        name: "LOWER",
+        args: &[Arg::Str],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| Ok(Value::Str(str_arg(args, 0)?.to_lowercase())),
+    },
+    Function {
+        name: "LENGTH",
+        args: &[Arg::Bytes],
+        required: 1,
+        variadic: false,
+        returns: Returns::Int,
+        call: length,
+    },
+    Function {
+        name: "CHAR_LENGTH",
+        args: &[Arg::Str],
+        required: 1,
+        variadic: false,
+        returns: Returns::Int,
+        call: length,
+    },
+    Function {
+        name: "SUBSTRING",
+        args: &[Arg::Str, Arg::Int, Arg::Int],
+        required: 2,
+        variadic: false,
+        returns: Returns::Str,
+        call: substring,
+    },
+    Function {
+        name: "SUBSTR",
+        args: &[Arg::Str, Arg::Int, Arg::Int],
+        required: 2,
+        variadic: false,
+        returns: Returns::Str,
+        call: substring,
+    },
+    Function {
+        name: "TRIM",
+        args: &[Arg::Str, Arg::Str],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| {
+            let chars = trim_chars(args)?;
+            Ok(Value::Str(
+                str_arg(args, 0)?.trim_matches(&chars[..]).to_string(),
+            ))
+        },
+    },
+    Function {
+        name: "LTRIM",
+        args: &[Arg::Str, Arg::Str],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| {
+            let chars = trim_chars(args)?;
+            Ok(Value::Str(
+                str_arg(args, 0)?.trim_start_matches(&chars[..]).to_string(),
+            ))
+        },
+    },
+    Function {
+        name: "RTRIM",
+        args: &[Arg::Str, Arg::Str],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| {
+            let chars = trim_chars(args)?;
+            Ok(Value::Str(
+                str_arg(args, 0)?.trim_end_matches(&chars[..]).to_string(),
+            ))
+        },
+    },
+    Function {
+        name: "REPLACE",
+        args: &[Arg::Str, Arg::Str, Arg::Str],
+        required: 3,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| {
+            let (s, from, to) = (str_arg(args, 0)?, str_arg(args, 1)?, str_arg(args, 2)?);
+            if from.is_empty() {
+                return Ok(Value::Str(s.to_string()));
+            }
+            Ok(Value::Str(s.replace(from, to)))
+        },
+    },
+    Function {
+        name: "CONCAT",
+        args: &[Arg::Any],
+        required: 1,
+        variadic: true,
+        returns: Returns::Str,
+        call: |args| Ok(Value::Str(args.iter().map(|v| v.to_string()).collect())),
+    },
+    Function {
+        name: "LPAD",
+        args: &[Arg::Str, Arg::Int, Arg::Str],
+        required: 2,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| pad(args, true),
+    },
+    Function {
+        name: "RPAD",
+        args: &[Arg::Str, Arg::Int, Arg::Str],
+        required: 2,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| pad(args, false),
+    },
+    Function {
+        name: "POSITION",
+        args: &[Arg::Str, Arg::Str],
+        required: 2,
+        variadic: false,
+        returns: Returns::Int,
+        call: |args| {
+            let (needle, haystack) = (str_arg(args, 0)?, str_arg(args, 1)?);
+            let position = match haystack.find(needle) {
+                Some(byte_idx) => haystack[..byte_idx].chars().count() as i32 + 1,
+                None => 0,
+            };
+            Ok(Value::Int(position))
+        },
+    },
+    Function {
+        name: "REVERSE",
+        args: &[Arg::Str],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| Ok(Value::Str(str_arg(args, 0)?.chars().rev().collect())),
+    },
+    Function {
+        name: "MD5",
+        args: &[Arg::Bytes],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| Ok(Value::Str(to_hex(&Md5::digest(bytes_arg(args, 0)?)))),
+    },
+    Function {
+        name: "SHA256",
+        args: &[Arg::Bytes],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| Ok(Value::Str(to_hex(&Sha256::digest(bytes_arg(args, 0)?)))),
+    },
+    Function {
+        name: "HEX",
+        args: &[Arg::BytesOrInt],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: hex,
+    },
+    Function {
+        name: "UNHEX",
+        args: &[Arg::Str],
+        required: 1,
+        variadic: false,
+        returns: Returns::Binary,
+        call: unhex,
+    },
+    Function {
+        name: "GEN_RANDOM_UUID",
+        args: &[],
+        required: 0,
+        variadic: false,
+        returns: Returns::Uuid,
+        call: |_| Ok(Value::Uuid(uuid::random())),
+    },
+    Function {
+        name: "UUID",
+        args: &[],
+        required: 0,
+        variadic: false,
+        returns: Returns::Uuid,
+        call: |_| Ok(Value::Uuid(uuid::random())),
+    },
+];
+
+fn str_arg(args: &[Value], idx: usize) -> Result<&str> {
+    match args.get(idx) {
+        Some(Value::Str(s)) => Ok(s),
+        Some(v) => Err(Error::Type(format!("{v} is not a Str"))),
+        None => Err(Error::Binding(format!("Missing argument {}", idx + 1))),
+    }
+}
+
+/// The bytes of a binary value, or the UTF-8 bytes of a string.
+fn bytes_arg(args: &[Value], idx: usize) -> Result<&[u8]> {
+    match args.get(idx) {
+        Some(Value::Str(s)) => Ok(s.as_bytes()),
+        Some(Value::Binary(b)) => Ok(b),
+        Some(v) => Err(Error::Type(format!("{v} is not a Str or Binary"))),
+        None => Err(Error::Binding(format!("Missing argument {}", idx + 1))),
+    }
+}
+
+fn int_arg(args: &[Value], idx: usize) -> Result<i32> {
+    match args.get(idx) {
+        Some(v) => match v.as_i128() {
+            Some(n) => i32::try_from(n)
+                .map_err(|_| Error::OutOfRange(format!("{v} is out of range for type Int"))),
+            None => Err(Error::Type(format!("{v} is not an Int"))),
+        },
+        None => Err(Error::Binding(format!("Missing argument {}", idx + 1))),
+    }
+}
+
+/// The number of characters in a string, or of bytes in a binary value.
+fn length(args: &[Value]) -> Result<Value> {
+    match args.first() {
+        Some(Value::Binary(b)) => Ok(Value::Int(b.len() as i32)),
+        _ => Ok(Value::Int(str_arg(args, 0)?.chars().count() as i32)),
+    }
+}
+
+/// Like MySQL, an integer is written as the hex digits of its 64 bits,
+/// so negative numbers are in two's complement, and text or a binary
+/// value as those of its bytes.
+fn hex(args: &[Value]) -> Result<Value> {
+    let hex = match args[0].as_i128() {
+        Some(n) => format!("{:X}", n as u64),
+        None => to_hex(bytes_arg(args, 0)?).to_uppercase(),
+    };
+    Ok(Value::Str(hex))
+}
+
+/// Like MySQL, hex digits that don't make whole bytes give NULL.
+fn unhex(args: &[Value]) -> Result<Value> {
+    Ok(from_hex(str_arg(args, 0)?).map_or(Value::Null, Value::Binary))
+}
+
+/// SUBSTRING(s, start [, count]) with 1-based positions. Positions before
+/// the start of the string still count towards `count`.
+fn substring(args: &[Value]) -> Result<Value> {
+    let s = str_arg(args, 0)?;
+    let start = int_arg(args, 1)? as i64;
+    let end = match args.get(2) {
+        Some(_) => {
+            let count = int_arg(args, 2)?;
+            if count < 0 {
+                return Err(Error::Type(
+                    "Negative substring length not allowed".to_string(),
+                ));
+            }
+            start + count as i64
+        }
+        None => i64::MAX,
+    };
+    let skip = (start - 1).max(0) as usize;
+    let take = (end - start.max(1)).max(0) as usize;
+    Ok(Value::Str(s.chars().skip(skip).take(take).collect()))
+}
+
+fn trim_chars(args: &[Value]) -> Result<Vec<char>> {
+    match args.get(1) {
+        Some(_) => Ok(str_arg(args, 1)?.chars().collect()),
+        None => Ok(vec![' ']),
+    }
+}
+
+fn pad(args: &[Value], left: bool) -> Result<Value> {
+    let s = str_arg(args, 0)?;
+    let len = int_arg(args, 1)?.max(0) as usize;
+    let fill = match args.get(2) {
+        Some(_) => str_arg(args, 2)?,
+        None => " ",
+    };
+    let current = s.chars().count();
+    if current >= len || fill.is_empty() {
+        return Ok(Value::Str(s.chars().take(len).collect()));
+    }
+    let padding: String = fill.chars().cycle().take(len - current).collect();
+    Ok(Value::Str(if left {
+        padding + s
+    } else {
+        s.to_string() + &padding
+    }))
+}
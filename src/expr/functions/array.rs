@@ -0,0 +1,61 @@
+use super::{Arg, Function, Returns};
+use crate::error::{Error, Result};
+use crate::value::Value;
+
+pub static FUNCTIONS: &[Function] = &[
+    // ARRAY_EXTRACT(tags, n) is `tags[n]`.
+    Function {
+        name: "ARRAY_EXTRACT",
+        args: &[Arg::Array, Arg::Int],
+        required: 2,
+        variadic: false,
+        returns: Returns::Item,
+        call: extract,
+    },
+    Function {
+        name: "ARRAY_LENGTH",
+        args: &[Arg::Array, Arg::Int],
+        required: 1,
+        variadic: false,
+        returns: Returns::Int,
+        call: length,
+    },
+];
+
+fn array_arg(args: &[Value], idx: usize) -> Result<&[Value]> {
+    match args.get(idx) {
+        Some(Value::Array(items)) => Ok(items),
+        Some(v) => Err(Error::Type(format!("{v} is not an array"))),
+        None => Err(Error::Binding(format!("Missing argument {}", idx + 1))),
+    }
+}
+
+/// The item at a position counted from 1, or NULL past either end.
+fn extract(args: &[Value]) -> Result<Value> {
+    let items = array_arg(args, 0)?;
+    let item = args[1]
+        .as_i128()
+        .and_then(|n| usize::try_from(n - 1).ok())
+        .and_then(|idx| items.get(idx));
+    Ok(item.cloned().unwrap_or(Value::Null))
+}
+
+/// The number of items along a dimension, the first by default, or NULL
+/// for an empty array or a dimension it doesn't have.
+fn length(args: &[Value]) -> Result<Value> {
+    let mut items = array_arg(args, 0)?;
+    let dimension = args.get(1).map_or(Some(1), Value::as_i128);
+    let Some(dimension) = dimension.filter(|d| *d >= 1) else {
+        return Ok(Value::Null);
+    };
+    for _ in 1..dimension {
+        match items.first() {
+            Some(Value::Array(inner)) => items = inner,
+            _ => return Ok(Value::Null),
+        }
+    }
+    Ok(match items.len() {
+        0 => Value::Null,
+        len => Value::Int(len as i32),
+    })
+}
@@ -0,0 +1,137 @@
+use super::{str_arg, Arg, Function, Returns};
+use crate::error::{Error, Result};
+use crate::value::datetime::{self, Zone};
+use crate::value::Value;
+
+pub static FUNCTIONS: &[Function] = &[
+    Function {
+        name: "NOW",
+        args: &[],
+        required: 0,
+        variadic: false,
+        returns: Returns::TimestampTz,
+        call: |_| Ok(Value::TimestampTz(datetime::now())),
+    },
+    Function {
+        name: "CURRENT_TIMESTAMP",
+        args: &[],
+        required: 0,
+        variadic: false,
+        returns: Returns::TimestampTz,
+        call: |_| Ok(Value::TimestampTz(datetime::now())),
+    },
+    Function {
+        name: "CURRENT_DATE",
+        args: &[],
+        required: 0,
+        variadic: false,
+        returns: Returns::Date,
+        call: |_| Ok(Value::Date(datetime::days_from_micros(datetime::now()))),
+    },
+    Function {
+        name: "DATE_TRUNC",
+        args: &[Arg::Str, Arg::Timestamp],
+        required: 2,
+        variadic: false,
+        returns: Returns::Timestamp,
+        call: date_trunc,
+    },
+    Function {
+        name: "DATE_PART",
+        args: &[Arg::Str, Arg::Temporal],
+        required: 2,
+        variadic: false,
+        returns: Returns::Int,
+        call: date_part,
+    },
+    Function {
+        name: "DATEDIFF",
+        args: &[Arg::Timestamp, Arg::Timestamp],
+        required: 2,
+        variadic: false,
+        returns: Returns::Int,
+        call: |args| {
+            let days = |v: &Value| micros_arg("DATEDIFF", v).map(datetime::days_from_micros);
+            Ok(Value::Int(days(&args[0])? - days(&args[1])?))
+        },
+    },
+    Function {
+        name: "TIMEZONE",
+        args: &[Arg::Str, Arg::Timestamp],
+        required: 2,
+        variadic: false,
+        returns: Returns::SwapZone,
+        call: timezone,
+    },
+];
+
+fn micros_arg(name: &str, value: &Value) -> Result<i64> {
+    value
+        .as_micros()
+        .ok_or_else(|| Error::Type(format!("{value} is not a valid argument for {name}")))
+}
+
+fn out_of_range(name: &str, value: &Value) -> Error {
+    Error::OutOfRange(format!("{name} of {value} is out of range"))
+}
+
+/// DATE_TRUNC(unit, timestamp), where a date is truncated as the
+/// timestamp at its midnight.
+fn date_trunc(args: &[Value]) -> Result<Value> {
+    let unit = str_arg(args, 0)?;
+    let micros = micros_arg("DATE_TRUNC", &args[1])?;
+    let truncated = datetime::truncate(unit, micros)
+        .ok_or_else(|| Error::Type(format!("Unknown unit {unit} for DATE_TRUNC")))?;
+    Ok(match args[1] {
+        Value::TimestampTz(_) => Value::TimestampTz(truncated),
+        _ => Value::Timestamp(truncated),
+    })
+}
+
+fn date_part(args: &[Value]) -> Result<Value> {
+    let field = str_arg(args, 0)?;
+    if !datetime::is_field(field) {
+        return Err(Error::Type(format!("Unknown field {field} for DATE_PART")));
+    }
+    let part = match &args[1] {
+        Value::Interval(interval) => datetime::interval_part(field, interval),
+        Value::Time(t) if is_time_field(field) => datetime::timestamp_part(field, *t),
+        Value::Time(_) => None,
+        value => datetime::timestamp_part(field, micros_arg("DATE_PART", value)?),
+    };
+    let part =
+        part.ok_or_else(|| Error::Type(format!("{field} cannot be extracted from {}", args[1])))?;
+    i32::try_from(part)
+        .map(Value::Int)
+        .map_err(|_| out_of_range("DATE_PART", &args[1]))
+}
+
+fn is_time_field(field: &str) -> bool {
+    matches!(
+        field.to_lowercase().as_ref(),
+        "hour"
+            | "minute"
+            | "second"
+            | "millisecond"
+            | "milliseconds"
+            | "microsecond"
+            | "microseconds"
+            | "epoch"
+    )
+}
+
+/// TIMEZONE(zone, timestamp), written as `timestamp AT TIME ZONE zone`. A
+/// timestamp with a time zone becomes the wall clock time in `zone`, and
+/// one without is taken to be the wall clock time in `zone`.
+fn timezone(args: &[Value]) -> Result<Value> {
+    let name = str_arg(args, 0)?;
+    let zone = Zone::parse(name).ok_or_else(|| Error::Type(format!("Unknown time zone {name}")))?;
+    let value = &args[1];
+    let converted = match value {
+        Value::TimestampTz(t) => zone.to_local(*t).map(Value::Timestamp),
+        value => zone
+            .to_utc(micros_arg("TIMEZONE", value)?)
+            .map(Value::TimestampTz),
+    };
+    converted.ok_or_else(|| out_of_range("TIMEZONE", value))
+}
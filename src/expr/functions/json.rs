@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use super::{Arg, Function, Returns};
+use crate::error::{Error, Result};
+use crate::value::json::PathStep;
+use crate::value::{Json, Value};
+
+pub static FUNCTIONS: &[Function] = &[
+    // JSON_EXTRACT(doc, path) is the value at `path`; with more paths, it
+    // is an array of the values found.
+    Function {
+        name: "JSON_EXTRACT",
+        args: &[Arg::Json, Arg::JsonPath],
+        required: 2,
+        variadic: true,
+        returns: Returns::Json,
+        call: extract,
+    },
+    // JSON_EXTRACT_PATH(doc, step, ...) is `doc -> step -> ...`.
+    Function {
+        name: "JSON_EXTRACT_PATH",
+        args: &[Arg::Json, Arg::Any],
+        required: 2,
+        variadic: true,
+        returns: Returns::Json,
+        call: extract_path,
+    },
+    Function {
+        name: "JSON_UNQUOTE",
+        args: &[Arg::Json],
+        required: 1,
+        variadic: false,
+        returns: Returns::Str,
+        call: |args| Ok(json_arg(args, 0)?.to_text()),
+    },
+    Function {
+        name: "JSON_SET",
+        args: &[Arg::Json, Arg::JsonPath, Arg::JsonValue],
+        required: 3,
+        variadic: true,
+        returns: Returns::Json,
+        call: set,
+    },
+    Function {
+        name: "JSON_ARRAY_LENGTH",
+        args: &[Arg::Json, Arg::JsonPath],
+        required: 1,
+        variadic: false,
+        returns: Returns::Int,
+        call: array_length,
+    },
+    Function {
+        name: "JSON_OBJECT",
+        args: &[Arg::JsonValue],
+        required: 0,
+        variadic: true,
+        returns: Returns::Json,
+        call: object,
+    },
+    Function {
+        name: "JSON_ARRAY",
+        args: &[Arg::JsonValue],
+        required: 0,
+        variadic: true,
+        returns: Returns::Json,
+        call: |args| {
+            let items = args.iter().map(json_value).collect::<Result<_>>()?;
+            Ok(Value::Json(Json::Array(items)))
+        },
+    },
+];
+
+/// A document, which may also be given as JSON text.
+fn json_arg(args: &[Value], idx: usize) -> Result<Json> {
+    match args.get(idx) {
+        Some(Value::Json(json)) => Ok(json.clone()),
+        Some(Value::Str(s)) => {
+            Json::parse(s).ok_or_else(|| Error::Type(format!("Invalid JSON text {s}")))
+        }
+        Some(v) => Err(Error::Type(format!("{v} is not a Json"))),
+        None => Err(Error::Binding(format!("Missing argument {}", idx + 1))),
+    }
+}
+
+/// A path such as `'$.tags[0]'`.
+pub(super) fn path_arg(value: &Value) -> Result<Vec<PathStep>> {
+    match value {
+        Value::Str(s) => {
+            Json::parse_path(s).ok_or_else(|| Error::Type(format!("Invalid JSON path {s}")))
+        }
+        v => Err(Error::Type(format!("{v} is not a JSON path"))),
+    }
+}
+
+/// A step of `->`: a path, or like Postgres, a key or an array position on
+/// its own.
+fn step_arg(value: &Value) -> Result<Vec<PathStep>> {
+    match value {
+        Value::Str(s) if s.trim_start().starts_with('$') => path_arg(value),
+        Value::Str(s) => Ok(vec![PathStep::Key(s.to_string())]),
+        v => match v.as_i128().and_then(|i| i64::try_from(i).ok()) {
+            Some(i) => Ok(vec![PathStep::Index(i)]),
+            None => Err(Error::Type(format!("{v} is not a JSON path"))),
+        },
+    }
+}
+
+fn json_value(value: &Value) -> Result<Json> {
+    Json::from_value(value).ok_or_else(|| Error::Type(format!("{value} cannot be stored in JSON")))
+}
+
+fn extract(args: &[Value]) -> Result<Value> {
+    let doc = json_arg(args, 0)?;
+    let mut found = vec![];
+    for path in &args[1..] {
+        if let Some(json) = doc.get(&path_arg(path)?) {
+            found.push(json.clone());
+        }
+    }
+    Ok(match (args.len(), found.pop()) {
+        (_, None) => Value::Null,
+        (2, Some(json)) => Value::Json(json),
+        (_, Some(last)) => {
+            found.push(last);
+            Value::Json(Json::Array(found))
+        }
+    })
+}
+
+fn extract_path(args: &[Value]) -> Result<Value> {
+    let doc = json_arg(args, 0)?;
+    let mut path = vec![];
+    for step in &args[1..] {
+        path.extend(step_arg(step)?);
+    }
+    Ok(doc
+        .get(&path)
+        .map_or(Value::Null, |json| Value::Json(json.clone())))
+}
+
+fn set(args: &[Value]) -> Result<Value> {
+    if args.len().is_multiple_of(2) {
+        return Err(Error::Binding(
+            "JSON_SET takes a document and pairs of paths and values".to_string(),
+        ));
+    }
+    let mut doc = json_arg(args, 0)?;
+    for pair in args[1..].chunks(2) {
+        if pair[0].is_null() {
+            return Ok(Value::Null);
+        }
+        doc.set(&path_arg(&pair[0])?, json_value(&pair[1])?);
+    }
+    Ok(Value::Json(doc))
+}
+
+/// The number of items in an array, or NULL for anything else.
+fn array_length(args: &[Value]) -> Result<Value> {
+    let doc = json_arg(args, 0)?;
+    let path = match args.get(1) {
+        Some(path) => path_arg(path)?,
+        None => vec![],
+    };
+    Ok(match doc.get(&path) {
+        Some(Json::Array(items)) => Value::Int(items.len() as i32),
+        _ => Value::Null,
+    })
+}
+
+fn object(args: &[Value]) -> Result<Value> {
+    if !args.len().is_multiple_of(2) {
+        return Err(Error::Binding(
+            "JSON_OBJECT takes pairs of keys and values".to_string(),
+        ));
+    }
+    let mut map = BTreeMap::new();
+    for pair in args.chunks(2) {
+        match &pair[0] {
+            Value::Str(key) => map.insert(key.to_string(), json_value(&pair[1])?),
+            key => return Err(Error::Type(format!("JSON key {key} is not a Str"))),
+        };
+    }
+    Ok(Value::Json(Json::Object(map)))
+}
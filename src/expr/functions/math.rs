@@ -0,0 +1,339 @@
+use std::cmp::Ordering;
+
+use super::{int_arg, Arg, Function, Returns};
+use crate::error::{Error, Result};
+use crate::expr::numeric::promote;
+use crate::expr::{arithmetic, BinaryOp};
+use crate::table::DataType;
+use crate::value::decimal::Rounding;
+use crate::value::{Decimal, Value};
+
+pub static FUNCTIONS: &[Function] = &[
+    Function {
+        name: "ABS",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Promoted,
+        call: |args| match (&args[0], args[0].as_i128()) {
+            (Value::Decimal(d), _) if d.digits() < 0 => d
+                .checked_neg()
+                .map(Value::Decimal)
+                .ok_or_else(|| Error::OutOfRange(format!("ABS({d}) is out of range"))),
+            (Value::Decimal(_), _) => Ok(args[0].clone()),
+            (_, Some(v)) => same_type("ABS", &args[0], v.abs()),
+            _ => float_result("ABS", &args[0], float_arg(&args[0])?.abs()),
+        },
+    },
+    Function {
+        name: "ROUND",
+        args: &[Arg::Numeric, Arg::Int],
+        required: 1,
+        variadic: false,
+        returns: Returns::Rounded,
+        call: |args| round(args, "ROUND", Rounding::HalfUp),
+    },
+    // TRUNCATE(x, digits) and TRUNC(x [, digits]) round towards zero.
+    Function {
+        name: "TRUNCATE",
+        args: &[Arg::Numeric, Arg::Int],
+        required: 2,
+        variadic: false,
+        returns: Returns::Truncated,
+        call: |args| round(args, "TRUNCATE", Rounding::Down),
+    },
+    Function {
+        name: "TRUNC",
+        args: &[Arg::Numeric, Arg::Int],
+        required: 1,
+        variadic: false,
+        returns: Returns::Truncated,
+        call: |args| round(args, "TRUNC", Rounding::Down),
+    },
+    Function {
+        name: "CEIL",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Rounded,
+        call: |args| integral(args, "CEIL", Rounding::Ceiling),
+    },
+    Function {
+        name: "CEILING",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Rounded,
+        call: |args| integral(args, "CEIL", Rounding::Ceiling),
+    },
+    Function {
+        name: "FLOOR",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Rounded,
+        call: |args| integral(args, "FLOOR", Rounding::Floor),
+    },
+    Function {
+        name: "POWER",
+        args: &[Arg::Numeric, Arg::Numeric],
+        required: 2,
+        variadic: false,
+        returns: Returns::Double,
+        call: power,
+    },
+    Function {
+        name: "POW",
+        args: &[Arg::Numeric, Arg::Numeric],
+        required: 2,
+        variadic: false,
+        returns: Returns::Double,
+        call: power,
+    },
+    Function {
+        name: "SQRT",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Double,
+        call: |args| {
+            let x = float_arg(&args[0])?;
+            if x < 0.0 {
+                return Err(Error::OutOfRange(
+                    "Cannot take the square root of a negative number".to_string(),
+                ));
+            }
+            double_result("SQRT", x.sqrt())
+        },
+    },
+    Function {
+        name: "MOD",
+        args: &[Arg::Numeric, Arg::Numeric],
+        required: 2,
+        variadic: false,
+        returns: Returns::Promoted,
+        call: |args| arithmetic(&BinaryOp::Modulo, args[0].clone(), args[1].clone()),
+    },
+    Function {
+        name: "LN",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Double,
+        call: |args| double_result("LN", logarithm(float_arg(&args[0])?)?),
+    },
+    // LOG(x) is the base 10 logarithm, LOG(b, x) the logarithm to base b.
+    Function {
+        name: "LOG",
+        args: &[Arg::Numeric, Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Double,
+        call: |args| match args {
+            [base, x] => {
+                let base = logarithm(float_arg(base)?)?;
+                if base == 0.0 {
+                    return Err(Error::DivisionByZero);
+                }
+                double_result("LOG", logarithm(float_arg(x)?)? / base)
+            }
+            _ => {
+                let x = float_arg(&args[0])?;
+                logarithm(x)?;
+                double_result("LOG", x.log10())
+            }
+        },
+    },
+    Function {
+        name: "EXP",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Double,
+        call: |args| double_result("EXP", float_arg(&args[0])?.exp()),
+    },
+    Function {
+        name: "SIGN",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: false,
+        returns: Returns::Int,
+        call: |args| {
+            let sign = match args[0].as_i128() {
+                Some(v) => v.signum() as i32,
+                None => match float_arg(&args[0])?.partial_cmp(&0.0) {
+                    Some(Ordering::Greater) => 1,
+                    Some(Ordering::Less) => -1,
+                    _ => 0,
+                },
+            };
+            Ok(Value::Int(sign))
+        },
+    },
+    Function {
+        name: "GREATEST",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: true,
+        returns: Returns::Promoted,
+        call: |args| extreme(args, Ordering::Greater),
+    },
+    Function {
+        name: "LEAST",
+        args: &[Arg::Numeric],
+        required: 1,
+        variadic: true,
+        returns: Returns::Promoted,
+        call: |args| extreme(args, Ordering::Less),
+    },
+    Function {
+        name: "RANDOM",
+        args: &[],
+        required: 0,
+        variadic: false,
+        returns: Returns::Double,
+        call: |_| Ok(Value::Double(rand::random())),
+    },
+    Function {
+        name: "RAND",
+        args: &[],
+        required: 0,
+        variadic: false,
+        returns: Returns::Double,
+        call: |_| Ok(Value::Double(rand::random())),
+    },
+];
+
+fn float_arg(value: &Value) -> Result<f64> {
+    value
+        .as_f64()
+        .ok_or_else(|| Error::Type(format!("{value} is not a number")))
+}
+
+/// The integer result of a function of `arg`, in the type of `arg`.
+fn same_type(name: &str, arg: &Value, result: i128) -> Result<Value> {
+    let datatype = arg.datatype().unwrap_or(DataType::BigInt);
+    Value::from_i128(result, &datatype).ok_or_else(|| {
+        Error::OutOfRange(format!("{name}({arg}) is out of range for type {datatype}"))
+    })
+}
+
+/// Narrows a result computed in f64 back to the float type of `arg`.
+fn float_result(name: &str, arg: &Value, result: f64) -> Result<Value> {
+    let datatype = match arg {
+        Value::Float(_) => DataType::Float,
+        _ => DataType::Double,
+    };
+    Value::from_f64(result, &datatype)
+        .filter(|_| result.is_finite())
+        .ok_or_else(|| {
+            Error::OutOfRange(format!(
+                "The result of {name} is out of range for type {datatype}"
+            ))
+        })
+}
+
+fn double_result(name: &str, result: f64) -> Result<Value> {
+    float_result(name, &Value::Double(result), result)
+}
+
+fn logarithm(x: f64) -> Result<f64> {
+    if x <= 0.0 {
+        return Err(Error::OutOfRange(format!(
+            "Cannot take the logarithm of {x}"
+        )));
+    }
+    Ok(x.ln())
+}
+
+/// ROUND(x [, digits]) and its kin round `x` to `digits` after the point,
+/// or to tens, hundreds and so on for negative `digits`, in the way `mode`
+/// says. ROUND rounds halves away from zero. The result keeps the type of
+/// `x`, so a decimal keeps its scale, though it may need one more digit.
+fn round(args: &[Value], name: &str, mode: Rounding) -> Result<Value> {
+    let digits = match args.get(1) {
+        Some(_) => int_arg(args, 1)?,
+        None => 0,
+    };
+    match (&args[0], args[0].as_i128()) {
+        (Value::Decimal(d), _) => round_decimal(name, d, digits, mode),
+        (_, Some(_)) if digits >= 0 => Ok(args[0].clone()),
+        (_, Some(v)) => {
+            let rounded = Decimal::from_i128(v)
+                .and_then(|d| d.round(digits, mode))
+                .map_or(0, |d| d.digits());
+            same_type(name, &args[0], rounded)
+        }
+        _ => {
+            let x = float_arg(&args[0])?;
+            let scale = 10f64.powi(digits);
+            let scaled = x * scale;
+            let rounded = if !scaled.is_finite() {
+                x
+            } else if scale == 0.0 {
+                0.0
+            } else {
+                round_float(scaled, mode) / scale
+            };
+            float_result(name, &args[0], rounded)
+        }
+    }
+}
+
+fn round_float(x: f64, mode: Rounding) -> f64 {
+    match mode {
+        Rounding::HalfUp => x.round(),
+        Rounding::Down => x.trunc(),
+        Rounding::Ceiling => x.ceil(),
+        Rounding::Floor => x.floor(),
+    }
+}
+
+/// Rounds a decimal to `digits` after the point, which become its scale
+/// if it has as many.
+fn round_decimal(name: &str, d: &Decimal, digits: i32, mode: Rounding) -> Result<Value> {
+    d.round(digits, mode)
+        .map(Value::Decimal)
+        .ok_or_else(|| Error::OutOfRange(format!("{name}({d}) is out of range")))
+}
+
+fn integral(args: &[Value], name: &str, mode: Rounding) -> Result<Value> {
+    match (&args[0], args[0].as_i128()) {
+        (Value::Decimal(d), _) => round_decimal(name, d, 0, mode),
+        (_, Some(_)) => Ok(args[0].clone()),
+        _ => float_result(name, &args[0], round_float(float_arg(&args[0])?, mode)),
+    }
+}
+
+fn power(args: &[Value]) -> Result<Value> {
+    let (base, exponent) = (float_arg(&args[0])?, float_arg(&args[1])?);
+    if base == 0.0 && exponent < 0.0 {
+        return Err(Error::DivisionByZero);
+    }
+    if base < 0.0 && exponent.fract() != 0.0 {
+        return Err(Error::OutOfRange(
+            "Cannot raise a negative number to a fractional power".to_string(),
+        ));
+    }
+    double_result("POWER", base.powf(exponent))
+}
+
+/// GREATEST and LEAST, which compare and return their arguments in the
+/// type they all promote to.
+fn extreme(args: &[Value], wanted: Ordering) -> Result<Value> {
+    let datatype = args
+        .iter()
+        .filter_map(Value::datatype)
+        .reduce(|a, b| promote(&a, &b).unwrap_or(DataType::Double));
+    let mut best = Value::Null;
+    for value in args {
+        let value = match &datatype {
+            Some(datatype) => value.clone().coerce(datatype)?,
+            None => value.clone(),
+        };
+        if best.is_null() || value.compare(&best) == Some(wanted) {
+            best = value;
+        }
+    }
+    Ok(best)
+}
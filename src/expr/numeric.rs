@@ -0,0 +1,167 @@
+use super::BinaryOp;
+use crate::error::{Error, Result};
+use crate::table::DataType;
+use crate::value::decimal::{DIVISION_SCALE, MAX_PRECISION};
+use crate::value::Value;
+
+/// The number of bits of an integer type, and whether it is unsigned.
+fn integer_width(datatype: &DataType) -> Option<(u32, bool)> {
+    let width = match datatype {
+        DataType::SmallInt => (16, false),
+        DataType::Int => (32, false),
+        DataType::BigInt => (64, false),
+        DataType::UnsignedSmallInt => (16, true),
+        DataType::UnsignedInt => (32, true),
+        DataType::UnsignedBigInt => (64, true),
+        _ => return None,
+    };
+    Some(width)
+}
+
+/// The digits before and after the point that an integer or decimal type
+/// holds.
+fn decimal_digits(datatype: &DataType) -> Option<(u8, u8)> {
+    let digits = match datatype {
+        DataType::Decimal(precision, scale) => (precision - scale, *scale),
+        DataType::SmallInt | DataType::UnsignedSmallInt => (5, 0),
+        DataType::Int | DataType::UnsignedInt => (10, 0),
+        DataType::BigInt => (19, 0),
+        DataType::UnsignedBigInt => (20, 0),
+        _ => return None,
+    };
+    Some(digits)
+}
+
+/// The type two numbers are combined in: a float if either of them is one,
+/// a decimal wide enough for both if either is a decimal, and otherwise the
+/// wider integer type, which is unsigned if either side is.
+pub fn promote(left: &DataType, right: &DataType) -> Option<DataType> {
+    if !left.is_numeric() || !right.is_numeric() {
+        return None;
+    }
+    let datatype = match (left, right) {
+        (DataType::Double, _) | (_, DataType::Double) => DataType::Double,
+        (DataType::Float, DataType::Decimal(..)) | (DataType::Decimal(..), DataType::Float) => {
+            DataType::Double
+        }
+        (DataType::Float, _) | (_, DataType::Float) => DataType::Float,
+        (DataType::Decimal(..), _) | (_, DataType::Decimal(..)) => {
+            let (left_whole, left_scale) = decimal_digits(left)?;
+            let (right_whole, right_scale) = decimal_digits(right)?;
+            let scale = left_scale.max(right_scale);
+            let precision = (left_whole.max(right_whole) + scale).min(MAX_PRECISION);
+            DataType::Decimal(precision, scale)
+        }
+        _ => {
+            let (left_bits, left_unsigned) = integer_width(left)?;
+            let (right_bits, right_unsigned) = integer_width(right)?;
+            match (left_bits.max(right_bits), left_unsigned || right_unsigned) {
+                (16, false) => DataType::SmallInt,
+                (32, false) => DataType::Int,
+                (16, true) => DataType::UnsignedSmallInt,
+                (32, true) => DataType::UnsignedInt,
+                (_, false) => DataType::BigInt,
+                (_, true) => DataType::UnsignedBigInt,
+            }
+        }
+    };
+    Some(datatype)
+}
+
+/// The type of `left op right` for two numbers. Decimal results keep every
+/// digit after the point, except for a quotient, which has four more than
+/// its dividend.
+pub fn arithmetic_type(op: &BinaryOp, left: &DataType, right: &DataType) -> Option<DataType> {
+    let (left_scale, right_scale) = match promote(left, right)? {
+        DataType::Decimal(..) => (decimal_digits(left)?.1, decimal_digits(right)?.1),
+        datatype => return Some(datatype),
+    };
+    let scale = match op {
+        BinaryOp::Multiply => left_scale + right_scale,
+        BinaryOp::Divide => left_scale + DIVISION_SCALE,
+        _ => left_scale.max(right_scale),
+    };
+    Some(DataType::Decimal(MAX_PRECISION, scale.min(MAX_PRECISION)))
+}
+
+/// The type of `-x` for a number `x`, where an unsigned integer becomes a
+/// signed one wide enough for its negation.
+pub fn negated_type(datatype: &DataType) -> DataType {
+    match datatype {
+        DataType::UnsignedSmallInt => DataType::Int,
+        DataType::UnsignedInt | DataType::UnsignedBigInt => DataType::BigInt,
+        d => d.clone(),
+    }
+}
+
+/// Negates a number, or returns `None` if the value isn't one.
+pub fn negate(value: &Value) -> Option<Result<Value>> {
+    let datatype = negated_type(&value.datatype()?);
+    let negated = match (value, value.as_i128()) {
+        (Value::Decimal(d), _) => d.checked_neg().map(Value::Decimal),
+        (_, Some(v)) => Value::from_i128(-v, &datatype),
+        _ => Value::from_f64(-value.as_f64()?, &datatype),
+    };
+    Some(negated.ok_or_else(|| {
+        Error::OutOfRange(format!("-({value}) is out of range for type {datatype}"))
+    }))
+}
+
+/// Computes `left op right` in the type the two numbers promote to, or
+/// returns `None` if the operator doesn't apply to the values. A result
+/// that doesn't fit the type is an error rather than wrapping around.
+pub fn arithmetic(op: &BinaryOp, left: &Value, right: &Value) -> Option<Result<Value>> {
+    let datatype = promote(&left.datatype()?, &right.datatype()?)?;
+    let result = match (&datatype, left.as_i128(), right.as_i128()) {
+        (DataType::Decimal(..), _, _) => {
+            let (a, b) = (left.as_decimal()?, right.as_decimal()?);
+            let result = match op {
+                BinaryOp::Plus => a.checked_add(&b),
+                BinaryOp::Minus => a.checked_sub(&b),
+                BinaryOp::Multiply => a.checked_mul(&b),
+                BinaryOp::Divide | BinaryOp::Modulo if b.digits() == 0 => {
+                    return Some(Err(Error::DivisionByZero))
+                }
+                BinaryOp::Divide => a.checked_div(&b),
+                BinaryOp::Modulo => a.checked_rem(&b),
+                _ => return None,
+            };
+            result.map(Value::Decimal)
+        }
+        (_, Some(a), Some(b)) => {
+            let result = match op {
+                BinaryOp::Plus => a.checked_add(b),
+                BinaryOp::Minus => a.checked_sub(b),
+                BinaryOp::Multiply => a.checked_mul(b),
+                BinaryOp::Divide | BinaryOp::Modulo if b == 0 => {
+                    return Some(Err(Error::DivisionByZero))
+                }
+                BinaryOp::Divide => a.checked_div(b),
+                BinaryOp::Modulo => a.checked_rem(b),
+                _ => return None,
+            };
+            result.and_then(|r| Value::from_i128(r, &datatype))
+        }
+        _ => {
+            let (a, b) = (left.as_f64()?, right.as_f64()?);
+            let result = match op {
+                BinaryOp::Plus => a + b,
+                BinaryOp::Minus => a - b,
+                BinaryOp::Multiply => a * b,
+                BinaryOp::Divide | BinaryOp::Modulo if b == 0.0 => {
+                    return Some(Err(Error::DivisionByZero))
+                }
+                BinaryOp::Divide => a / b,
+                BinaryOp::Modulo => a % b,
+                _ => return None,
+            };
+            Value::from_f64(result, &datatype).filter(|_| result.is_finite())
+        }
+    };
+    Some(result.ok_or_else(|| {
+        Error::OutOfRange(format!(
+            "{left} {} {right} is out of range for type {datatype}",
+            op.symbol()
+        ))
+    }))
+}
@@ -0,0 +1,239 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::error::{Error, Result};
+
+/// How a pattern matching operator reads its right-hand side.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// `LIKE`, or `ILIKE` when case insensitive. `%` matches any run of
+    /// characters and `_` any single one, unless preceded by `escape`.
+    Like {
+        escape: Option<char>,
+        case_insensitive: bool,
+        negated: bool,
+    },
+    /// `REGEXP`/`RLIKE` and `~`, matching anywhere in the value.
+    Regex {
+        case_insensitive: bool,
+        negated: bool,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Char(char),
+    One,
+    Any,
+}
+
+impl Pattern {
+    pub fn like(escape: Option<char>, case_insensitive: bool, negated: bool) -> Pattern {
+        Pattern::Like {
+            escape,
+            case_insensitive,
+            negated,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Pattern::Like {
+                case_insensitive: false,
+                negated: false,
+                ..
+            } => "LIKE",
+            Pattern::Like {
+                case_insensitive: false,
+                ..
+            } => "NOT LIKE",
+            Pattern::Like { negated: false, .. } => "ILIKE",
+            Pattern::Like { .. } => "NOT ILIKE",
+            Pattern::Regex {
+                case_insensitive: false,
+                negated: false,
+            } => "~",
+            Pattern::Regex {
+                case_insensitive: true,
+                negated: false,
+            } => "~*",
+            Pattern::Regex {
+                case_insensitive: false,
+                negated: true,
+            } => "!~",
+            Pattern::Regex { .. } => "!~*",
+        }
+    }
+
+    /// Reports a malformed pattern before any row is looked at.
+    pub fn check(&self, pattern: &str) -> Result<()> {
+        match self {
+            Pattern::Like { escape, .. } => tokenize(pattern, *escape).map(|_| ()),
+            Pattern::Regex {
+                case_insensitive, ..
+            } => with_regex(pattern, *case_insensitive, |_| ()),
+        }
+    }
+
+    pub fn is_match(&self, value: &str, pattern: &str) -> Result<bool> {
+        match self {
+            Pattern::Like {
+                escape,
+                case_insensitive: false,
+                negated,
+            } => {
+                let value: Vec<char> = value.chars().collect();
+                Ok(like(&value, &tokenize(pattern, *escape)?) != *negated)
+            }
+            Pattern::Like {
+                escape, negated, ..
+            } => {
+                let value: Vec<char> = value.to_lowercase().chars().collect();
+                let tokens = tokenize(pattern, *escape)?
+                    .into_iter()
+                    .map(|t| match t {
+                        Token::Char(c) => Token::Char(c.to_lowercase().next().unwrap_or(c)),
+                        t => t,
+                    })
+                    .collect::<Vec<_>>();
+                Ok(like(&value, &tokens) != *negated)
+            }
+            Pattern::Regex {
+                case_insensitive,
+                negated,
+            } => with_regex(pattern, *case_insensitive, |re| {
+                re.is_match(value) != *negated
+            }),
+        }
+    }
+
+    /// The text every value matching a case sensitive `LIKE` starts with,
+    /// so an index can narrow the search to one range of keys.
+    pub fn prefix(&self, pattern: &str) -> Option<String> {
+        let Pattern::Like {
+            escape,
+            case_insensitive: false,
+            negated: false,
+        } = self
+        else {
+            return None;
+        };
+        let prefix: String = tokenize(pattern, *escape)
+            .ok()?
+            .iter()
+            .map_while(|t| match t {
+                Token::Char(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        (!prefix.is_empty()).then_some(prefix)
+    }
+}
+
+fn tokenize(pattern: &str, escape: Option<char>) -> Result<Vec<Token>> {
+    let escape = escape.unwrap_or('\\');
+    let mut tokens = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            c if c == escape => match chars.next() {
+                Some(escaped) => Token::Char(escaped),
+                None => {
+                    return Err(Error::Type(format!(
+                        "LIKE pattern {pattern} must not end with the escape character"
+                    )))
+                }
+            },
+            '%' => Token::Any,
+            '_' => Token::One,
+            c => Token::Char(c),
+        });
+    }
+    Ok(tokens)
+}
+
+/// Matches with backtracking to the most recent `%` only, which is enough
+/// because a later `%` can absorb anything an earlier one could.
+fn like(value: &[char], tokens: &[Token]) -> bool {
+    let (mut v, mut t) = (0, 0);
+    let mut retry: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match tokens.get(t) {
+            Some(Token::Any) => {
+                retry = Some((t + 1, v));
+                t += 1;
+            }
+            Some(Token::One) => {
+                v += 1;
+                t += 1;
+            }
+            Some(Token::Char(c)) if *c == value[v] => {
+                v += 1;
+                t += 1;
+            }
+            _ => match retry {
+                Some((after_any, start)) => {
+                    retry = Some((after_any, start + 1));
+                    t = after_any;
+                    v = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[t..].iter().all(|t| *t == Token::Any)
+}
+
+thread_local! {
+    static REGEXES: RefCell<HashMap<(String, bool), Regex>> = RefCell::new(HashMap::new());
+}
+
+/// Runs `f` with the compiled pattern. Compiled patterns are cached, since
+/// the same pattern is usually matched against every row.
+fn with_regex<T>(pattern: &str, case_insensitive: bool, f: impl FnOnce(&Regex) -> T) -> Result<T> {
+    REGEXES.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let key = (pattern.to_string(), case_insensitive);
+        if !cache.contains_key(&key) {
+            let re = RegexBuilder::new(pattern)
+                .case_insensitive(case_insensitive)
+                .build()
+                .map_err(|e| Error::Type(format!("Invalid regular expression {pattern}: {e}")))?;
+            if cache.len() >= 64 {
+                cache.clear();
+            }
+            cache.insert(key.clone(), re);
+        }
+        Ok(f(&cache[&key]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_like_wildcards_and_escapes() {
+        let like = Pattern::like(None, false, false);
+        assert!(like.is_match("banana", "b%n_").unwrap());
+        assert!(like.is_match("banana", "%an%an%").unwrap());
+        assert!(!like.is_match("banana", "%x%").unwrap());
+        assert!(like.is_match("", "%").unwrap());
+        assert!(!like.is_match("Banana", "b%").unwrap());
+        assert!(Pattern::like(None, true, false)
+            .is_match("Banana", "b%")
+            .unwrap());
+        assert!(like.is_match("50%", "50\\%").unwrap());
+        assert!(!like.is_match("500", "50\\%").unwrap());
+        assert!(Pattern::like(Some('!'), false, false)
+            .is_match("a_b", "a!_b")
+            .unwrap());
+        assert!(like.check("abc\\").is_err());
+
+        assert_eq!(like.prefix("ab%c"), Some("ab".to_string()));
+        assert_eq!(like.prefix("%ab"), None);
+        assert_eq!(Pattern::like(None, true, false).prefix("ab%"), None);
+    }
+}
@@ -0,0 +1,324 @@
+use std::fmt;
+
+use sqlparser::ast::{self, FunctionArg, FunctionArgExpr, WindowFrameBound, WindowFrameUnits};
+
+use super::aggregate::AggregateFunc;
+use super::{comparable, function_args, is_numeric, type_name, ScalarExpr};
+use crate::error::{Error, Result};
+use crate::parser::number_literal;
+use crate::parser::select::{parse_order_by, OrderBy};
+use crate::table::DataType;
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowFunc {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Lag,
+    Lead,
+    FirstValue,
+    LastValue,
+    Aggregate(AggregateFunc),
+}
+
+impl WindowFunc {
+    fn from_name(name: &str) -> Option<WindowFunc> {
+        let func = match name.to_uppercase().as_ref() {
+            "ROW_NUMBER" => WindowFunc::RowNumber,
+            "RANK" => WindowFunc::Rank,
+            "DENSE_RANK" => WindowFunc::DenseRank,
+            "LAG" => WindowFunc::Lag,
+            "LEAD" => WindowFunc::Lead,
+            "FIRST_VALUE" => WindowFunc::FirstValue,
+            "LAST_VALUE" => WindowFunc::LastValue,
+            name => WindowFunc::Aggregate(AggregateFunc::from_name(name)?),
+        };
+        Some(func)
+    }
+
+    /// The smallest and largest number of arguments the function takes.
+    fn arity(&self) -> (usize, usize) {
+        match self {
+            WindowFunc::RowNumber | WindowFunc::Rank | WindowFunc::DenseRank => (0, 0),
+            WindowFunc::Lag | WindowFunc::Lead => (1, 3),
+            WindowFunc::FirstValue | WindowFunc::LastValue => (1, 1),
+            WindowFunc::Aggregate(AggregateFunc::Count) => (0, 1),
+            WindowFunc::Aggregate(_) => (1, 1),
+        }
+    }
+}
+
+impl fmt::Display for WindowFunc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WindowFunc::RowNumber => f.write_str("ROW_NUMBER"),
+            WindowFunc::Rank => f.write_str("RANK"),
+            WindowFunc::DenseRank => f.write_str("DENSE_RANK"),
+            WindowFunc::Lag => f.write_str("LAG"),
+            WindowFunc::Lead => f.write_str("LEAD"),
+            WindowFunc::FirstValue => f.write_str("FIRST_VALUE"),
+            WindowFunc::LastValue => f.write_str("LAST_VALUE"),
+            WindowFunc::Aggregate(func) => func.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameUnits {
+    /// Offsets count rows.
+    Rows,
+    /// Offsets are distances from the current row's ORDER BY value, and the
+    /// current row includes its peers.
+    Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Value),
+    CurrentRow,
+    Following(Value),
+    UnboundedFollowing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+/// A window function call, `func(args) OVER (PARTITION BY ... ORDER BY ...
+/// frame)`. It is computed for every row from the rows of its partition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub func: WindowFunc,
+    pub args: Vec<ScalarExpr>,
+    pub partition_by: Vec<ScalarExpr>,
+    pub order_by: Vec<OrderBy>,
+    /// `None` for the default frame: the whole partition without ORDER BY,
+    /// otherwise everything up to the current row and its peers.
+    pub frame: Option<Frame>,
+}
+
+fn frame_bound(bound: &WindowFrameBound, units: &FrameUnits) -> Result<FrameBound> {
+    let offset = |expr: &ast::Expr| {
+        let value = match expr {
+            ast::Expr::Value(ast::Value::Number(n, _)) => number_literal(n)?,
+            expr => {
+                return Err(Error::Parse(format!(
+                    "Unsupported window frame offset {expr}"
+                )))
+            }
+        };
+        match (units, &value) {
+            (FrameUnits::Rows, Value::Int(v)) if *v >= 0 => Ok(value),
+            (FrameUnits::Range, v) if v.as_f64().is_some_and(|v| v >= 0.0) => Ok(value),
+            _ => Err(Error::OutOfRange(format!(
+                "Window frame offset {value} must be a non-negative {}",
+                match units {
+                    FrameUnits::Rows => "integer",
+                    FrameUnits::Range => "number",
+                }
+            ))),
+        }
+    };
+    Ok(match bound {
+        WindowFrameBound::CurrentRow => FrameBound::CurrentRow,
+        WindowFrameBound::Preceding(None) => FrameBound::UnboundedPreceding,
+        WindowFrameBound::Preceding(Some(n)) => FrameBound::Preceding(offset(n)?),
+        WindowFrameBound::Following(None) => FrameBound::UnboundedFollowing,
+        WindowFrameBound::Following(Some(n)) => FrameBound::Following(offset(n)?),
+    })
+}
+
+impl Window {
+    /// Builds the window function for a call with an OVER clause.
+    pub fn from_ast(f: &ast::Function, over: &ast::WindowSpec) -> Result<Window> {
+        let name = f.name.to_string();
+        let func = WindowFunc::from_name(&name)
+            .ok_or_else(|| Error::Parse(format!("{name} is not a window function")))?;
+        if f.distinct {
+            return Err(Error::Parse(format!(
+                "DISTINCT is not supported in window function {func}"
+            )));
+        }
+        let args = match (&func, f.args.as_slice()) {
+            (
+                WindowFunc::Aggregate(AggregateFunc::Count),
+                [FunctionArg::Unnamed(FunctionArgExpr::Wildcard)],
+            ) => vec![],
+            _ => function_args(f)?,
+        };
+        let (min, max) = func.arity();
+        if args.len() < min || args.len() > max {
+            return Err(Error::Binding(format!(
+                "Window function {func} takes {} arguments but got {}",
+                match min == max {
+                    true => min.to_string(),
+                    false => format!("{min} to {max}"),
+                },
+                args.len()
+            )));
+        }
+
+        let frame = match &over.window_frame {
+            Some(frame) => {
+                let units = match frame.units {
+                    WindowFrameUnits::Rows => FrameUnits::Rows,
+                    WindowFrameUnits::Range => FrameUnits::Range,
+                    WindowFrameUnits::Groups => {
+                        return Err(Error::Parse("GROUPS frames are not supported".to_string()))
+                    }
+                };
+                let start = frame_bound(&frame.start_bound, &units)?;
+                let end = match &frame.end_bound {
+                    Some(bound) => frame_bound(bound, &units)?,
+                    None => FrameBound::CurrentRow,
+                };
+                if start == FrameBound::UnboundedFollowing {
+                    return Err(Error::Parse(
+                        "A window frame cannot start at UNBOUNDED FOLLOWING".to_string(),
+                    ));
+                }
+                if end == FrameBound::UnboundedPreceding {
+                    return Err(Error::Parse(
+                        "A window frame cannot end at UNBOUNDED PRECEDING".to_string(),
+                    ));
+                }
+                // Offsets may still give an empty frame, like 1 PRECEDING to
+                // 2 PRECEDING, but the ends can't be named in the wrong order.
+                match (&start, &end) {
+                    (FrameBound::CurrentRow, FrameBound::Preceding(_)) => {
+                        return Err(Error::Parse(
+                            "A window frame starting from the current row cannot have preceding rows"
+                                .to_string(),
+                        ))
+                    }
+                    (
+                        FrameBound::Following(_),
+                        FrameBound::Preceding(_) | FrameBound::CurrentRow,
+                    ) => {
+                        return Err(Error::Parse(
+                            "A window frame starting from following rows cannot have preceding rows"
+                                .to_string(),
+                        ))
+                    }
+                    _ => {}
+                }
+                Some(Frame { units, start, end })
+            }
+            None => None,
+        };
+
+        Ok(Window {
+            func,
+            args,
+            partition_by: over
+                .partition_by
+                .iter()
+                .map(ScalarExpr::try_from)
+                .collect::<Result<Vec<_>>>()?,
+            order_by: parse_order_by(&over.order_by)?,
+            frame,
+        })
+    }
+
+    pub fn children(&self) -> Vec<&ScalarExpr> {
+        self.args
+            .iter()
+            .chain(&self.partition_by)
+            .chain(self.order_by.iter().map(|o| &o.expr))
+            .collect()
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut ScalarExpr> {
+        self.args
+            .iter_mut()
+            .chain(&mut self.partition_by)
+            .chain(self.order_by.iter_mut().map(|o| &mut o.expr))
+            .collect()
+    }
+
+    /// Whether the frame is measured in distances from the ORDER BY value.
+    pub fn has_range_offset(&self) -> bool {
+        match &self.frame {
+            Some(Frame {
+                units: FrameUnits::Range,
+                start,
+                end,
+            }) => [start, end]
+                .iter()
+                .any(|b| matches!(b, FrameBound::Preceding(_) | FrameBound::Following(_))),
+            _ => false,
+        }
+    }
+
+    pub fn datatype<F>(&self, column_type: &F) -> Result<Option<DataType>>
+    where
+        F: Fn(&str) -> Result<DataType>,
+    {
+        if self.children().iter().any(|c| c.contains_window()) {
+            return Err(Error::Parse(format!(
+                "Window function {} cannot contain another window function",
+                self.func
+            )));
+        }
+        for e in &self.partition_by {
+            e.datatype(column_type)?;
+        }
+        for o in &self.order_by {
+            o.expr.datatype(column_type)?;
+        }
+        if self.has_range_offset() {
+            let numeric = match self.order_by.as_slice() {
+                [o] => o.expr.datatype(column_type)?.is_none_or(|d| is_numeric(&d)),
+                _ => false,
+            };
+            if !numeric {
+                return Err(Error::Type(
+                    "RANGE with an offset needs exactly one numeric ORDER BY expression"
+                        .to_string(),
+                ));
+            }
+        }
+
+        let types = self
+            .args
+            .iter()
+            .map(|a| a.datatype(column_type))
+            .collect::<Result<Vec<_>>>()?;
+        let datatype = match &self.func {
+            WindowFunc::RowNumber | WindowFunc::Rank | WindowFunc::DenseRank => Some(DataType::Int),
+            WindowFunc::FirstValue | WindowFunc::LastValue => types[0].clone(),
+            WindowFunc::Lag | WindowFunc::Lead => {
+                if let Some(Some(offset)) = types.get(1) {
+                    if !offset.is_integer() {
+                        return Err(Error::Type(format!(
+                            "The offset of {} must be an Int, not {offset}",
+                            self.func
+                        )));
+                    }
+                }
+                match types.get(2) {
+                    Some(default) if !comparable(&types[0], default) => {
+                        return Err(Error::Type(format!(
+                            "The default of {} must be {}, not {}",
+                            self.func,
+                            type_name(&types[0]),
+                            type_name(default)
+                        )))
+                    }
+                    Some(default) => types[0].clone().or_else(|| default.clone()),
+                    None => types[0].clone(),
+                }
+            }
+            WindowFunc::Aggregate(func) => match types.first() {
+                Some(arg) => func.datatype(arg.clone())?,
+                None => Some(DataType::Int),
+            },
+        };
+        Ok(datatype)
+    }
+}
@@ -0,0 +1,34 @@
+//! An in-memory SQL database.
+//!
+//! ```
+//! use sql::{Database, QueryResult, Value};
+//!
+//! let mut db = Database::new();
+//! db.execute("CREATE TABLE users (id int PRIMARY KEY, name string)").unwrap();
+//! db.execute("INSERT INTO users (id, name) VALUES (1, 'tahmid')").unwrap();
+//!
+//! match db.execute("SELECT name FROM users WHERE id = 1").unwrap() {
+//!     QueryResult::Rows(rs) => {
+//!         assert_eq!(rs.column_names(), vec!["name"]);
+//!         assert_eq!(rs.rows, vec![vec![Value::Str("tahmid".to_string())]]);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+pub mod database;
+pub mod error;
+pub mod expr;
+pub mod parser;
+pub mod prepared;
+pub mod protocol;
+pub mod result;
+pub mod table;
+pub mod value;
+
+pub use database::Database;
+pub use error::Error;
+pub use prepared::{BoundStatement, PreparedStatement};
+pub use result::{QueryResult, ResultColumn, ResultSet};
+pub use table::DataType;
+pub use value::Value;
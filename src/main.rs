@@ -1,31 +1,48 @@
 use std::env;
 use std::fs::File;
 use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::thread;
 
-use database::Database;
+use sql::protocol;
+use sql::Database;
 
 use sqlparser::dialect::MySqlDialect;
 use sqlparser::tokenizer::Tokenizer;
 
 mod command;
-mod database;
-mod parser;
-mod table;
 
 use command::{get_command_type, handle_meta_command, process_command, CommandType};
 
 use rustyline::error::ReadlineError;
 use rustyline::{Editor, Result};
 
+fn take_address_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let pos = args.iter().position(|a| a == name)?;
+    args.remove(pos);
+    if pos < args.len() {
+        Some(args.remove(pos))
+    } else {
+        eprintln!("{name} expects an address to listen on, e.g. 127.0.0.1:5432");
+        std::process::exit(1);
+    }
+}
+
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<String> = env::args().skip(1).collect();
     let mut db = Database::new();
 
+    let postgres_addr = take_address_option(&mut args, "--postgres");
+    let mysql_addr = take_address_option(&mut args, "--mysql");
+
     for arg in args {
         match File::open(arg) {
             Ok(mut file) => {
                 let mut query = String::new();
-                file.read_to_string(&mut query).unwrap();
+                if let Err(e) = file.read_to_string(&mut query) {
+                    eprintln!("{}", e);
+                    continue;
+                }
 
                 let dialect = MySqlDialect {};
                 let mut tokenizer = Tokenizer::new(&dialect, &query);
@@ -35,7 +52,7 @@ fn main() -> Result<()> {
                     }
                     Err(e) => eprintln!("err {:?}", e),
                 }
-                process_command(query, &mut db);
+                process_command(query, &mut db, &[]);
 
                 println!("query processed");
             }
@@ -45,6 +62,22 @@ fn main() -> Result<()> {
         }
     }
 
+    if postgres_addr.is_some() || mysql_addr.is_some() {
+        let db = Arc::new(Mutex::new(db));
+        let postgres = postgres_addr.map(|addr| {
+            let db = Arc::clone(&db);
+            thread::spawn(move || protocol::postgres::serve(&addr, db))
+        });
+        if let Some(addr) = mysql_addr {
+            protocol::mysql::serve(&addr, db)?;
+        }
+        if let Some(postgres) = postgres {
+            postgres.join().expect("PostgreSQL server panicked")?;
+        }
+        return Ok(());
+    }
+
+    let mut params = vec![];
     let mut rl = Editor::<()>::new()?;
     if rl.load_history("history.txt").is_err() {
         println!("No previous history.");
@@ -55,11 +88,11 @@ fn main() -> Result<()> {
             Ok(command) => {
                 rl.add_history_entry(command.as_str());
                 match get_command_type(&command.trim().to_owned()) {
-                    CommandType::DbCommand(_) => {
-                        process_command(command.trim().to_string(), &mut db);
+                    CommandType::DbCommand(cmd) => {
+                        process_command(cmd.into_query(), &mut db, &params);
                     }
                     CommandType::MetaCommand(cmd) => {
-                        handle_meta_command(cmd, &mut db);
+                        handle_meta_command(cmd, &mut db, &mut params);
                     }
                 }
             }
@@ -1,9 +1,228 @@
-use sqlparser::ast::Statement;
+use sqlparser::ast::{self, UnaryOperator};
+use sqlparser::dialect::{Dialect, MySqlDialect};
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Tokenizer};
+
+use crate::error::{Error, Result};
+use crate::table::DataType;
+use crate::value::{from_hex, Decimal, Value};
 
 pub mod create;
 pub mod insert;
+pub mod query;
 pub mod select;
+pub mod types;
+
+use types::{parse_type_statement, AlterTypeQuery, CreateTypeQuery};
+
+/// A statement as sqlparser reads it, or one defining a type, which
+/// sqlparser has no syntax for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Sql(Box<ast::Statement>),
+    CreateType(CreateTypeQuery),
+    AlterType(AlterTypeQuery),
+}
+
+/// A value written in the query, or a `$n` placeholder that is filled in
+/// when a prepared statement is bound.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Literal(Value),
+    Param(usize),
+}
+
+/// The highest parameter number a statement can use, as in Postgres, so a
+/// placeholder like `$99999999999` can't size a huge parameter list.
+pub const MAX_PARAMS: usize = 65535;
+
+impl Operand {
+    pub fn from_placeholder(placeholder: &str) -> Result<Operand> {
+        placeholder
+            .strip_prefix('$')
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|n| (1..=MAX_PARAMS).contains(n))
+            .map(|n| Operand::Param(n - 1))
+            .ok_or_else(|| Error::Parse(format!("Invalid parameter placeholder {placeholder}")))
+    }
+
+    pub fn as_literal(&self) -> Result<&Value> {
+        match self {
+            Operand::Literal(val) => Ok(val),
+            Operand::Param(idx) => Err(Error::Binding(format!(
+                "No value bound for parameter ${}",
+                idx + 1
+            ))),
+        }
+    }
+}
+
+/// Numeric literals are Ints when they fit, then BigInts, then unsigned
+/// BigInts. Ones with a point are exact decimals, like `1.05`, and the rest,
+/// like `1e300`, are Doubles.
+pub fn number_literal(n: &str) -> Result<Value> {
+    if let Ok(v) = n.parse::<i32>() {
+        return Ok(Value::Int(v));
+    }
+    if let Ok(v) = n.parse::<i64>() {
+        return Ok(Value::BigInt(v));
+    }
+    if let Ok(v) = n.parse::<u64>() {
+        return Ok(Value::UnsignedBigInt(v));
+    }
+    if !n.contains(['e', 'E']) {
+        if let Some(v) = Decimal::parse(n) {
+            return Ok(Value::Decimal(v));
+        }
+    }
+    n.parse::<f64>()
+        .map(Value::Double)
+        .map_err(|_| Error::Parse(format!("Invalid number {n}")))
+}
+
+/// The bytes of a hex literal such as `X'0aff'` or `0x0aff`, which must
+/// have two digits for every byte.
+pub fn hex_literal(hex: &str) -> Result<Value> {
+    from_hex(hex)
+        .map(Value::Binary)
+        .ok_or_else(|| Error::Parse(format!("Invalid hex literal X'{hex}'")))
+}
+
+/// The value of a typed literal such as `DATE '2024-01-31'`, or of an
+/// interval written as `INTERVAL '1 day'` or `INTERVAL 1 DAY`. Returns
+/// `None` for any other expression.
+pub fn typed_literal(expr: &ast::Expr) -> Result<Option<Value>> {
+    let (text, datatype) = match expr {
+        ast::Expr::TypedString { data_type, value } => {
+            match DataType::new(create::type_name(data_type)) {
+                DataType::Invalid => {
+                    return Err(Error::Parse(format!("Unsupported type {data_type}")))
+                }
+                datatype => (value.to_string(), datatype),
+            }
+        }
+        ast::Expr::Interval {
+            value,
+            leading_field,
+            last_field: None,
+            ..
+        } => {
+            let text = match &**value {
+                ast::Expr::Value(ast::Value::SingleQuotedString(s) | ast::Value::Number(s, _)) => {
+                    s.to_string()
+                }
+                ast::Expr::UnaryOp {
+                    op: UnaryOperator::Minus,
+                    expr,
+                } if matches!(**expr, ast::Expr::Value(ast::Value::Number(..))) => {
+                    format!("-{expr}")
+                }
+                _ => return Err(Error::Parse(format!("Unsupported interval {expr}"))),
+            };
+            match leading_field {
+                Some(field) => (format!("{text} {field}"), DataType::Interval),
+                None => (text, DataType::Interval),
+            }
+        }
+        ast::Expr::Interval { .. } => {
+            return Err(Error::Parse(format!("Unsupported interval {expr}")))
+        }
+        _ => return Ok(None),
+    };
+    Value::Str(text).coerce(&datatype).map(Some)
+}
+
+pub fn parse_sql(sql: &str) -> Result<Vec<Statement>> {
+    let dialect = MySqlDialect {};
+    parse_with_dialect(&dialect, sql)
+}
+
+/// Parses `sql`, numbering every `?` placeholder in order so that all
+/// parameters reach the planner as `$n`.
+pub fn parse_with_dialect(dialect: &dyn Dialect, sql: &str) -> Result<Vec<Statement>> {
+    let mut next_positional = 0;
+    let tokens = Tokenizer::new(dialect, sql)
+        .tokenize()?
+        .into_iter()
+        .map(|token| match token {
+            Token::Placeholder(p) if p == "?" => {
+                next_positional += 1;
+                Token::Placeholder(format!("${next_positional}"))
+            }
+            // MySQL allows `$` in identifiers, so `$1` only arrives as a word there.
+            Token::Word(w)
+                if w.quote_style.is_none()
+                    && w.value.len() > 1
+                    && w.value.starts_with('$')
+                    && w.value[1..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                Token::Placeholder(w.value)
+            }
+            token => token,
+        })
+        .collect();
+
+    Ok(parse_tokens(dialect, regexp_operators(tokens))?)
+}
+
+/// sqlparser has no MySQL `[NOT] REGEXP`/`RLIKE`, so they are rewritten to
+/// the Postgres `~` and `!~` operators, which mean the same thing.
+fn regexp_operators(tokens: Vec<Token>) -> Vec<Token> {
+    let mut rewritten: Vec<Token> = vec![];
+    for token in tokens {
+        match token {
+            Token::Word(w)
+                if w.quote_style.is_none()
+                    && matches!(w.value.to_uppercase().as_ref(), "REGEXP" | "RLIKE") =>
+            {
+                let previous = rewritten
+                    .iter()
+                    .rposition(|t| !matches!(t, Token::Whitespace(_)));
+                match previous {
+                    Some(idx) if matches!(&rewritten[idx], Token::Word(w) if w.keyword == Keyword::NOT) =>
+                    {
+                        rewritten.truncate(idx);
+                        rewritten.push(Token::ExclamationMarkTilde);
+                    }
+                    _ => rewritten.push(Token::Tilde),
+                }
+            }
+            token => rewritten.push(token),
+        }
+    }
+    rewritten
+}
+
+/// A lone expression, such as a column's DEFAULT.
+pub fn parse_expr(sql: &str) -> Result<ast::Expr> {
+    let dialect = MySqlDialect {};
+    let tokens = Tokenizer::new(&dialect, sql).tokenize()?;
+    Ok(Parser::new(tokens, &dialect).parse_expr()?)
+}
 
-pub trait QueryType<T> {
-    fn query(statement: &Statement) -> Result<T, String>;
+fn parse_tokens(
+    dialect: &dyn Dialect,
+    tokens: Vec<Token>,
+) -> std::result::Result<Vec<Statement>, ParserError> {
+    let mut parser = Parser::new(tokens, dialect);
+    let mut statements = vec![];
+    let mut expecting_statement_delimiter = false;
+    loop {
+        while parser.consume_token(&Token::SemiColon) {
+            expecting_statement_delimiter = false;
+        }
+        if parser.peek_token() == Token::EOF {
+            break;
+        }
+        if expecting_statement_delimiter {
+            return parser.expected("end of statement", parser.peek_token());
+        }
+        match parse_type_statement(&mut parser)? {
+            Some(statement) => statements.push(statement),
+            None => statements.push(Statement::Sql(Box::new(parser.parse_statement()?))),
+        }
+        expecting_statement_delimiter = true;
+    }
+    Ok(statements)
 }
@@ -1,4 +1,7 @@
-use sqlparser::ast::{ColumnOption, DataType, Statement};
+use sqlparser::ast::{ColumnOption, DataType, ExactNumberInfo, Statement, TimezoneInfo};
+
+use crate::error::{Error, Result};
+use crate::expr::ScalarExpr;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct ParsedColumn {
@@ -6,6 +9,8 @@ pub struct ParsedColumn {
     pub datatype: String,
     pub is_pk: bool,
     pub is_nullable: bool,
+    /// The SQL of the DEFAULT expression, evaluated for each inserted row.
+    pub default: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
@@ -15,7 +20,7 @@ pub struct CreateQuery {
 }
 
 impl CreateQuery {
-    pub fn new(statement: &Statement) -> Result<CreateQuery, String> {
+    pub fn new(statement: &Statement) -> Result<CreateQuery> {
         match statement {
             Statement::CreateTable { name, columns, .. } => {
                 let table_name = name;
@@ -23,32 +28,33 @@ impl CreateQuery {
 
                 for col in columns {
                     let name = col.name.to_string();
-                    let datatype = match &col.data_type {
-                        DataType::SmallInt(_) | DataType::Int(_) | DataType::BigInt(_) => "int",
-                        DataType::Boolean => "bool",
-                        DataType::Text | DataType::Varchar(_) | DataType::String => "string",
-                        DataType::Float(_)
-                        | DataType::Double
-                        | DataType::DoublePrecision
-                        | DataType::Decimal(_) => "float",
-                        _ => {
-                            println!("could not match type");
-                            "invalid"
-                        }
-                    };
+                    let datatype = type_name(&col.data_type);
 
                     let mut is_pk: bool = false;
+                    let mut is_nullable: bool = true;
+                    let mut default = None;
                     for column_option in &col.options {
-                        if let ColumnOption::Unique { is_primary } = column_option.option {
-                            is_pk = is_primary;
+                        match &column_option.option {
+                            ColumnOption::Unique { is_primary } => is_pk = *is_primary,
+                            ColumnOption::NotNull => is_nullable = false,
+                            ColumnOption::Default(expr) => {
+                                ScalarExpr::try_from(expr)?.resolve(&|column: &str| {
+                                    Err(Error::Binding(format!(
+                                        "DEFAULT of column {name} cannot refer to column {column}"
+                                    )))
+                                })?;
+                                default = Some(expr.to_string());
+                            }
+                            _ => {}
                         }
                     }
 
                     parsed_columns.push(ParsedColumn {
                         name,
-                        datatype: datatype.to_string(),
+                        datatype,
                         is_pk,
-                        is_nullable: false,
+                        is_nullable: is_nullable && !is_pk,
+                        default,
                     });
                 }
 
@@ -57,7 +63,63 @@ impl CreateQuery {
                     columns: parsed_columns,
                 })
             }
-            _ => Err("Error parsing query".to_string()),
+            _ => Err(Error::Parse("Not a create table query".to_string())),
         }
     }
 }
+
+/// The name of the column type a SQL type is stored as, as understood by
+/// `DataType::new`, the name of a user-defined type, or `invalid` if it
+/// isn't supported.
+pub fn type_name(data_type: &DataType) -> String {
+    let name = match data_type {
+        // Like MySQL, DECIMAL is DECIMAL(10, 0) and DECIMAL(p) is DECIMAL(p, 0).
+        DataType::Decimal(info) | DataType::Numeric(info) | DataType::Dec(info) => {
+            let (precision, scale) = match info {
+                ExactNumberInfo::None => (10, 0),
+                ExactNumberInfo::Precision(p) => (*p, 0),
+                ExactNumberInfo::PrecisionAndScale(p, s) => (*p, *s),
+            };
+            return format!("decimal({precision},{scale})");
+        }
+        DataType::Array(Some(item)) => return format!("{}[]", type_name(item)),
+        DataType::SmallInt(_) => "smallint",
+        DataType::Int(_) | DataType::Integer(_) => "int",
+        DataType::BigInt(_) => "bigint",
+        DataType::UnsignedSmallInt(_) => "smallint unsigned",
+        DataType::UnsignedInt(_) | DataType::UnsignedInteger(_) => "int unsigned",
+        DataType::UnsignedBigInt(_) => "bigint unsigned",
+        DataType::Boolean => "bool",
+        DataType::Custom(name, _) if name.to_string().to_lowercase() == "bool" => "bool",
+        DataType::Text | DataType::Varchar(_) | DataType::Char(_) | DataType::String => "string",
+        // FLOAT(p) is single precision up to 24 bits of mantissa.
+        DataType::Float(Some(p)) if *p > 24 => "double",
+        DataType::Float(_) | DataType::Real => "float",
+        DataType::Double | DataType::DoublePrecision => "double",
+        DataType::Date => "date",
+        DataType::Time(..) => "time",
+        DataType::Timestamp(_, TimezoneInfo::WithTimeZone | TimezoneInfo::Tz) => "timestamptz",
+        DataType::Timestamp(..) | DataType::Datetime(_) => "timestamp",
+        DataType::Interval => "interval",
+        DataType::Uuid => "uuid",
+        // BINARY alone holds one byte, as in MySQL.
+        DataType::Binary(size) => return format!("binary({})", size.unwrap_or(1)),
+        DataType::Varbinary(Some(size)) => return format!("varbinary({size})"),
+        DataType::Varbinary(None) | DataType::Blob(_) | DataType::Bytea => "binary",
+        DataType::Custom(name, _)
+            if ["tinyblob", "mediumblob", "longblob"]
+                .contains(&name.to_string().to_lowercase().as_ref()) =>
+        {
+            "binary"
+        }
+        DataType::Custom(name, _)
+            if ["json", "jsonb"].contains(&name.to_string().to_lowercase().as_ref()) =>
+        {
+            "json"
+        }
+        // Possibly a type made with CREATE TYPE, which the database resolves.
+        DataType::Custom(name, _) => return name.to_string(),
+        _ => "invalid",
+    };
+    name.to_string()
+}
@@ -1,16 +1,28 @@
-use sqlparser::ast::{Expr, Query, SetExpr, Statement, Value, Values};
+use sqlparser::ast::{Expr, Query, SetExpr, Statement, UnaryOperator, Value, Values};
 
+use crate::error::{Error, Result};
+use crate::expr::{EmptyScope, ScalarExpr};
+use crate::parser::{hex_literal, number_literal, typed_literal, Operand};
+use crate::table::DataType;
+use crate::value;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct InsertQuery {
     pub table_name: String,
     pub columns: Vec<String>,
-    pub values: Vec<Vec<String>>,
+    pub values: Vec<Vec<Operand>>,
 }
 
 impl InsertQuery {
-    pub fn new(statement: &Statement) -> Result<InsertQuery, String> {
+    /// Parses an INSERT, with `named_type` looking up the types made with
+    /// CREATE TYPE that casts among the values name.
+    pub fn new<F>(statement: &Statement, named_type: &F) -> Result<InsertQuery>
+    where
+        F: Fn(&str) -> Option<DataType>,
+    {
         let mut tname: Option<String> = None;
         let mut columns: Vec<String> = vec![];
-        let mut all_vals: Vec<Vec<String>> = vec![];
+        let mut all_vals: Vec<Vec<Operand>> = vec![];
 
         if let Statement::Insert {
             table_name,
@@ -27,33 +39,66 @@ impl InsertQuery {
             if let SetExpr::Values(values) = body.as_ref() {
                 let Values { rows, .. } = values;
                 for row in rows {
-                    let mut value_set: Vec<String> = vec![];
+                    let mut value_set: Vec<Operand> = vec![];
                     for expr in row {
                         match expr {
                             Expr::Value(v) => match v {
                                 Value::Number(n, _) => {
-                                    value_set.push(n.to_string());
+                                    value_set.push(Operand::Literal(number_literal(n)?));
+                                }
+                                Value::Boolean(b) => {
+                                    value_set.push(Operand::Literal(value::Value::Bool(*b)));
                                 }
-                                Value::Boolean(b) => match b {
-                                    true => value_set.push("true".to_string()),
-                                    false => value_set.push("false".to_string()),
-                                },
                                 Value::SingleQuotedString(sqs) => {
-                                    value_set.push(sqs.to_string());
+                                    value_set.push(Operand::Literal(value::Value::Str(
+                                        sqs.to_string(),
+                                    )));
+                                }
+                                Value::HexStringLiteral(hex) => {
+                                    value_set.push(Operand::Literal(hex_literal(hex)?));
                                 }
                                 Value::Null => {
-                                    value_set.push("Null".to_string());
+                                    value_set.push(Operand::Literal(value::Value::Null));
+                                }
+                                Value::Placeholder(p) => {
+                                    value_set.push(Operand::from_placeholder(p)?);
+                                }
+                                v => {
+                                    return Err(Error::Parse(format!("Unsupported value {v}")))
                                 }
-                                _ => {}
                             },
+                            Expr::UnaryOp {
+                                op: UnaryOperator::Minus,
+                                expr,
+                            } if matches!(**expr, Expr::Value(Value::Number(..))) => {
+                                value_set.push(Operand::Literal(number_literal(&format!("-{expr}"))?));
+                            }
+                            // Constant arrays and casts, such as `ARRAY[]::int[]`,
+                            // are folded to the value they stand for.
+                            Expr::Array(_) | Expr::Cast { .. } => {
+                                let mut expr = ScalarExpr::try_from(expr)?;
+                                expr.resolve_types(named_type)?;
+                                value_set.push(Operand::Literal(expr.eval(&EmptyScope)?));
+                            }
                             Expr::Identifier(i) => {
-                                value_set.push(i.to_string());
+                                value_set.push(Operand::Literal(value::Value::Str(i.to_string())));
                             }
-                            _ => {}
+                            expr => match typed_literal(expr)? {
+                                Some(value) => value_set.push(Operand::Literal(value)),
+                                None => {
+                                    return Err(Error::Parse(format!(
+                                        "Unsupported expression {expr}, only literal values can be inserted"
+                                    )))
+                                }
+                            },
                         }
                     }
                     all_vals.push(value_set);
                 }
+            } else {
+                return Err(Error::Parse(
+                    "Only INSERT ... VALUES is supported".to_string(),
+                ));
             }
         }
 
@@ -63,7 +108,7 @@ impl InsertQuery {
                 columns,
                 values: all_vals,
             }),
-            None => Err(String::from("Cannot parse insert query")),
+            None => Err(Error::Parse("Cannot parse insert query".to_string())),
         }
     }
 }
@@ -0,0 +1,289 @@
+use std::fmt;
+
+use sqlparser::ast::{
+    self, FunctionArg, ObjectName, SetExpr, SetOperator, SetQuantifier, Statement, TableAlias,
+    TableFactor, TableWithJoins,
+};
+
+use crate::error::{Error, Result};
+use crate::expr::ScalarExpr;
+use crate::parser::select::{parse_limits, parse_order_by, OrderBy, SelectQuery, UnnestTable};
+use crate::parser::Operand;
+use crate::table::DataType;
+
+/// A SELECT, or a set operation combining the rows of two queries. Either
+/// can be preceded by a WITH clause, but only at the start of the statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Select(Box<SelectQuery>),
+    SetOperation(Box<SetQuery>),
+    With(Box<WithQuery>),
+    /// The items of arrays side by side, one row for each, as the query of
+    /// the CTE that `FROM UNNEST(arrays)` reads.
+    Unnest(Vec<ScalarExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetOp {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl fmt::Display for SetOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetOp::Union => f.write_str("UNION"),
+            SetOp::Intersect => f.write_str("INTERSECT"),
+            SetOp::Except => f.write_str("EXCEPT"),
+        }
+    }
+}
+
+/// `left op [ALL] right`. The ORDER BY refers to the columns of the combined
+/// result, which are named after the columns of the leftmost SELECT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetQuery {
+    pub op: SetOp,
+    pub all: bool,
+    pub left: Query,
+    pub right: Query,
+    pub order_by: Vec<OrderBy>,
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+/// `WITH [RECURSIVE] name [(columns)] AS (query), ... body`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithQuery {
+    pub recursive: bool,
+    pub ctes: Vec<Cte>,
+    pub body: Query,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cte {
+    pub name: String,
+    /// Renames the columns of `query`. Once planned, holds the name of
+    /// every column whether renamed or not, and `types` their types.
+    pub columns: Vec<String>,
+    pub types: Vec<DataType>,
+    pub query: Query,
+}
+
+impl Query {
+    pub fn new(statement: &Statement) -> Result<Query> {
+        match statement {
+            Statement::Query(bq) => match &bq.with {
+                Some(with) => {
+                    let ctes = with
+                        .cte_tables
+                        .iter()
+                        .map(|cte| {
+                            Ok(Cte {
+                                name: cte.alias.name.to_string(),
+                                columns: cte.alias.columns.iter().map(|c| c.to_string()).collect(),
+                                types: vec![],
+                                query: Query::from_query(&cte.query)?,
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    for (idx, cte) in ctes.iter().enumerate() {
+                        if ctes[..idx].iter().any(|c| c.name == cte.name) {
+                            return Err(Error::Parse(format!(
+                                "WITH query name {} is given more than once",
+                                cte.name
+                            )));
+                        }
+                    }
+                    Ok(Query::With(Box::new(WithQuery {
+                        recursive: with.recursive,
+                        ctes,
+                        body: Query::from_query(&ast::Query {
+                            with: None,
+                            ..(**bq).clone()
+                        })?,
+                    })))
+                }
+                None => Query::from_query(bq),
+            },
+            _ => Err(Error::Parse("Not a select query".to_string())),
+        }
+    }
+
+    fn from_query(bq: &ast::Query) -> Result<Query> {
+        if bq.with.is_some() {
+            return Err(Error::Parse(
+                "WITH is only supported at the start of a statement".to_string(),
+            ));
+        }
+        let order_by = parse_order_by(&bq.order_by)?;
+        let (offset, limit) = parse_limits(bq)?;
+        let mut query = Query::from_set_expr(&bq.body)?;
+        if order_by.is_empty() && offset.is_none() && limit.is_none() {
+            return Ok(query);
+        }
+        // A SELECT from UNNEST is the body of the CTE it reads.
+        let mut body = &mut query;
+        while let Query::With(with) = body {
+            body = &mut with.body;
+        }
+        let (query_order_by, query_offset, query_limit) = match body {
+            Query::Select(sq) => (&mut sq.order_by, &mut sq.offset, &mut sq.limit),
+            Query::SetOperation(set) => (&mut set.order_by, &mut set.offset, &mut set.limit),
+            Query::With(_) | Query::Unnest(_) => {
+                return Err(Error::Parse(format!("Unsupported query {bq}")))
+            }
+        };
+        if !query_order_by.is_empty() || query_offset.is_some() || query_limit.is_some() {
+            return Err(Error::Parse(format!("Unsupported query {bq}")));
+        }
+        *query_order_by = order_by;
+        *query_offset = offset;
+        *query_limit = limit;
+        match body {
+            Query::Select(sq) => sq.unqualify(),
+            Query::SetOperation(set) => {
+                for o in &mut set.order_by {
+                    o.expr.unqualify();
+                }
+            }
+            _ => {}
+        }
+        Ok(query)
+    }
+
+    fn from_set_expr(body: &SetExpr) -> Result<Query> {
+        match body {
+            SetExpr::Select(select) => {
+                let query = Query::Select(Box::new(SelectQuery::from_select(select)?));
+                match select.from.as_slice() {
+                    [TableWithJoins {
+                        relation:
+                            TableFactor::Table {
+                                name,
+                                alias,
+                                args: Some(args),
+                                ..
+                            },
+                        ..
+                    }] => Query::from_unnest(name, alias, args, query),
+                    _ => Ok(query),
+                }
+            }
+            SetExpr::Query(bq) => Query::from_query(bq),
+            SetExpr::SetOperation {
+                op,
+                set_quantifier,
+                left,
+                right,
+            } => Ok(Query::SetOperation(Box::new(SetQuery {
+                op: match op {
+                    SetOperator::Union => SetOp::Union,
+                    SetOperator::Intersect => SetOp::Intersect,
+                    SetOperator::Except => SetOp::Except,
+                },
+                all: *set_quantifier == SetQuantifier::All,
+                left: Query::from_set_expr(left)?,
+                right: Query::from_set_expr(right)?,
+                order_by: vec![],
+                offset: None,
+                limit: None,
+            }))),
+            body => Err(Error::Parse(format!("Unsupported query {body}"))),
+        }
+    }
+
+    /// `SELECT ... FROM UNNEST(arrays) [AS] name [(columns)]` as `body`
+    /// reading a CTE named `name`.
+    fn from_unnest(
+        name: &ObjectName,
+        alias: &Option<TableAlias>,
+        args: &[FunctionArg],
+        body: Query,
+    ) -> Result<Query> {
+        let unnest = UnnestTable::new(name, alias, args)?;
+        Ok(Query::With(Box::new(WithQuery {
+            recursive: false,
+            ctes: vec![Cte {
+                name: unnest.alias,
+                columns: unnest.columns,
+                types: vec![],
+                query: Query::Unnest(unnest.args),
+            }],
+            body,
+        })))
+    }
+
+    /// Every SELECT in the query, from left to right.
+    pub fn selects(&self) -> Vec<&SelectQuery> {
+        match self {
+            Query::Select(sq) => vec![sq],
+            Query::SetOperation(set) => {
+                let mut selects = set.left.selects();
+                selects.extend(set.right.selects());
+                selects
+            }
+            Query::With(with) => {
+                let mut selects: Vec<&SelectQuery> = with
+                    .ctes
+                    .iter()
+                    .flat_map(|cte| cte.query.selects())
+                    .collect();
+                selects.extend(with.body.selects());
+                selects
+            }
+            Query::Unnest(_) => vec![],
+        }
+    }
+
+    /// The arrays of every UNNEST in the query.
+    pub fn unnest_args(&self) -> Vec<&ScalarExpr> {
+        match self {
+            Query::Select(_) => vec![],
+            Query::SetOperation(set) => {
+                let mut args = set.left.unnest_args();
+                args.extend(set.right.unnest_args());
+                args
+            }
+            Query::With(with) => {
+                let mut args: Vec<&ScalarExpr> = with
+                    .ctes
+                    .iter()
+                    .flat_map(|cte| cte.query.unnest_args())
+                    .collect();
+                args.extend(with.body.unnest_args());
+                args
+            }
+            Query::Unnest(args) => args.iter().collect(),
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Query::Select(sq) => sq.operands_mut(),
+            Query::SetOperation(set) => {
+                let SetQuery {
+                    left,
+                    right,
+                    order_by,
+                    ..
+                } = &mut **set;
+                let mut operands = left.operands_mut();
+                operands.extend(right.operands_mut());
+                operands.extend(order_by.iter_mut().flat_map(|o| o.expr.operands_mut()));
+                operands
+            }
+            Query::With(with) => {
+                let WithQuery { ctes, body, .. } = &mut **with;
+                let mut operands: Vec<&mut Operand> = ctes
+                    .iter_mut()
+                    .flat_map(|cte| cte.query.operands_mut())
+                    .collect();
+                operands.extend(body.operands_mut());
+                operands
+            }
+            Query::Unnest(args) => args.iter_mut().flat_map(|a| a.operands_mut()).collect(),
+        }
+    }
+}
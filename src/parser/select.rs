@@ -1,9 +1,18 @@
+use std::cmp::Ordering;
+
 use sqlparser::ast::{
-    BinaryOperator, Expr, Ident, Offset,
-    SelectItem::{ExprWithAlias, QualifiedWildcard, UnnamedExpr, Wildcard},
-    SetExpr, Statement, TableFactor, Value,
+    BinaryOperator, Expr, FunctionArg, FunctionArgExpr, Join, JoinConstraint, JoinOperator,
+    ObjectName, OrderByExpr, Query, Select,
+    SelectItem::{ExprWithAlias, UnnamedExpr, Wildcard},
+    TableAlias, TableFactor, TableWithJoins, UnaryOperator, Value,
 };
 
+use crate::error::{Error, Result};
+use crate::expr::pattern::Pattern;
+use crate::expr::{BinaryOp, ScalarExpr};
+use crate::parser::{number_literal, typed_literal, Operand};
+use crate::value;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Binary {
     NotEq,
@@ -14,196 +23,711 @@ pub enum Binary {
     GtEq,
 }
 
-#[derive(Debug)]
+impl Binary {
+    /// Whether a value that compares to the search term as `ord` matches.
+    pub fn matches(&self, ord: Ordering) -> bool {
+        match self {
+            Binary::Eq => ord == Ordering::Equal,
+            Binary::NotEq => ord != Ordering::Equal,
+            Binary::Lt => ord == Ordering::Less,
+            Binary::LtEq => ord != Ordering::Greater,
+            Binary::Gt => ord == Ordering::Greater,
+            Binary::GtEq => ord != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Operator {
     Binary(Binary),
+    Match(Pattern),
+    Between { negated: bool },
+    In { negated: bool },
 }
 
-impl From<BinaryOperator> for Operator {
-    fn from(b: BinaryOperator) -> Self {
+impl TryFrom<&BinaryOperator> for Operator {
+    type Error = Error;
+
+    fn try_from(b: &BinaryOperator) -> Result<Self> {
         match b {
-            BinaryOperator::Eq => Operator::Binary(Binary::Eq),
-            BinaryOperator::NotEq => Operator::Binary(Binary::NotEq),
-            BinaryOperator::Gt => Operator::Binary(Binary::Gt),
-            BinaryOperator::GtEq => Operator::Binary(Binary::GtEq),
-            BinaryOperator::Lt => Operator::Binary(Binary::Lt),
-            BinaryOperator::LtEq => Operator::Binary(Binary::LtEq),
-            _ => panic!("Converting from unsupported BinaryOperator"),
+            BinaryOperator::Eq => Ok(Operator::Binary(Binary::Eq)),
+            BinaryOperator::NotEq => Ok(Operator::Binary(Binary::NotEq)),
+            BinaryOperator::Gt => Ok(Operator::Binary(Binary::Gt)),
+            BinaryOperator::GtEq => Ok(Operator::Binary(Binary::GtEq)),
+            BinaryOperator::Lt => Ok(Operator::Binary(Binary::Lt)),
+            BinaryOperator::LtEq => Ok(Operator::Binary(Binary::LtEq)),
+            _ => Err(Error::Parse(format!("Unsupported operator {b}"))),
         }
     }
 }
 
-#[derive(Debug)]
+/// `left op right` where `left` is a column. `right` holds one value for
+/// comparisons and patterns, the bounds for BETWEEN and the list for IN.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Expression {
     pub left: String,
-    pub right: String,
+    pub right: Vec<Operand>,
     pub op: Operator,
 }
 
-#[derive(Debug)]
+impl Expression {
+    pub fn terms(&self) -> Result<Vec<&value::Value>> {
+        self.right.iter().map(|o| o.as_literal()).collect()
+    }
+
+    /// Whether `value`, taken from the `left` column, satisfies the comparison.
+    /// A NULL on either side never satisfies it, negated or not.
+    pub fn matches(&self, value: &value::Value) -> Result<bool> {
+        let terms = self.terms()?;
+        let ords = terms.iter().map(|t| value.compare(t)).collect::<Vec<_>>();
+        match (&self.op, value, terms.as_slice()) {
+            (Operator::Binary(op), _, _) => Ok(ords[0].is_some_and(|ord| op.matches(ord))),
+            (Operator::Match(pattern), value::Value::Str(v), [value::Value::Str(p)]) => {
+                pattern.is_match(v, p)
+            }
+            (Operator::Match(_), _, _) => Ok(false),
+            (Operator::Between { negated }, _, _) => match (ords[0], ords[1]) {
+                (Some(low), Some(high)) => {
+                    Ok((low != Ordering::Less && high != Ordering::Greater) != *negated)
+                }
+                _ => Ok(false),
+            },
+            (Operator::In { negated: false }, _, _) => Ok(ords.contains(&Some(Ordering::Equal))),
+            (Operator::In { negated: true }, _, _) => Ok(ords
+                .iter()
+                .all(|ord| ord.is_some_and(|ord| ord != Ordering::Equal))),
+        }
+    }
+}
+
+/// One entry of the SELECT list, named after its alias, its column or the
+/// expression text. `*` is kept as a `*` column until the planner expands it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectItem {
+    pub expr: ScalarExpr,
+    pub name: String,
+}
+
+impl SelectItem {
+    pub fn column(name: String) -> SelectItem {
+        SelectItem {
+            expr: ScalarExpr::Column(name.to_string()),
+            name,
+        }
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.expr == ScalarExpr::Column("*".to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub expr: ScalarExpr,
+    pub asc: bool,
+    pub nulls_first: bool,
+}
+
+/// A table of an inner join, and the name that qualifies its columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinedTable {
+    pub table: String,
+    pub alias: String,
+}
+
+/// `UNNEST(arrays) [AS] alias [(columns)]`: the items of the arrays side
+/// by side, in a column for each.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnnestTable {
+    pub args: Vec<ScalarExpr>,
+    pub alias: String,
+    pub columns: Vec<String>,
+}
+
+impl UnnestTable {
+    pub fn new(
+        name: &ObjectName,
+        alias: &Option<TableAlias>,
+        args: &[FunctionArg],
+    ) -> Result<UnnestTable> {
+        if !name.to_string().eq_ignore_ascii_case("unnest") {
+            return Err(Error::Parse(format!("Unsupported table function {name}")));
+        }
+        let args = args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => ScalarExpr::try_from(e),
+                arg => Err(Error::Parse(format!("Unsupported function argument {arg}"))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if args.is_empty() {
+            return Err(Error::Binding(
+                "UNNEST takes at least 1 argument".to_string(),
+            ));
+        }
+        let alias_name = alias
+            .as_ref()
+            .map_or(name.to_string(), |a| a.name.value.to_string());
+        let columns = match alias {
+            Some(alias) if !alias.columns.is_empty() => {
+                alias.columns.iter().map(|c| c.value.to_string()).collect()
+            }
+            _ if args.len() == 1 => vec![alias_name.to_string()],
+            _ => {
+                return Err(Error::Parse(
+                    "UNNEST of several arrays needs a name for each column".to_string(),
+                ))
+            }
+        };
+        if columns.len() != args.len() {
+            return Err(Error::Binding(format!(
+                "UNNEST of {} arrays cannot have {} columns",
+                args.len(),
+                columns.len()
+            )));
+        }
+        Ok(UnnestTable {
+            args,
+            alias: alias_name,
+            columns,
+        })
+    }
+}
+
+/// A parsed SELECT. The WHERE clause is split at its top-level ANDs:
+/// `column op value` comparisons go to `where_expressions`, where an index
+/// can answer them, and everything else is ANDed together into `filter`.
+/// An inner join reads every pair of rows of its tables, with the columns
+/// named like `alias.column`, and its ON condition is part of the WHERE.
+/// An UNNEST after a table, or in the select list, is joined to each row
+/// of the table, expanding the arrays of that row.
+#[derive(Debug, Clone, PartialEq)]
 pub struct SelectQuery {
     pub from: String,
-    pub projection: Vec<String>,
+    pub joined: Vec<JoinedTable>,
+    pub unnest: Option<UnnestTable>,
+    pub distinct: bool,
+    pub projection: Vec<SelectItem>,
     pub where_expressions: Vec<Expression>,
+    pub filter: Option<ScalarExpr>,
+    pub group_by: Vec<ScalarExpr>,
+    pub having: Option<ScalarExpr>,
+    pub order_by: Vec<OrderBy>,
     pub offset: Option<u64>,
     pub limit: Option<u64>,
 }
 
-impl SelectQuery {
-    fn add_ops(
-        op: &BinaryOperator,
-        where_expressions: &mut Vec<Expression>,
-        col_name: &Ident,
-        n: &String,
-    ) {
-        match op {
-            BinaryOperator::Eq
-            | BinaryOperator::NotEq
-            | BinaryOperator::Gt
-            | BinaryOperator::Lt
-            | BinaryOperator::LtEq
-            | BinaryOperator::GtEq => {
-                where_expressions.push(Expression {
-                    left: col_name.to_string(),
-                    right: n.to_string(),
-                    op: Operator::from(op.clone()),
-                });
-            }
-            _ => {
-                panic!("cannot parse select query");
-            }
-        };
+fn conjuncts<'a>(expr: &'a Expr, out: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            conjuncts(left, out);
+            conjuncts(right, out);
+        }
+        Expr::Nested(e) => conjuncts(e, out),
+        e => out.push(e),
     }
+}
 
-    pub fn new(statement: &Statement) -> Result<SelectQuery, String> {
+/// Recognises `column op value`, the form an index lookup can answer.
+fn simple_comparison(expr: &Expr) -> Result<Option<Expression>> {
+    if let Some(expression) = simple_like(expr)? {
+        return Ok(Some(expression));
+    }
+    if let Some(expression) = simple_range(expr)? {
+        return Ok(Some(expression));
+    }
+    let Expr::BinaryOp { left, op, right } = expr else {
+        return Ok(None);
+    };
+    let (Expr::Identifier(col_name), Ok(op)) = (&**left, Operator::try_from(op)) else {
+        return Ok(None);
+    };
+    let Some(right) = simple_operand(right)? else {
+        return Ok(None);
+    };
+    Ok(Some(Expression {
+        left: col_name.value.to_string(),
+        right: vec![right],
+        op,
+    }))
+}
+
+/// A value the planner can look up in an index: a literal or a parameter.
+fn simple_operand(expr: &Expr) -> Result<Option<Operand>> {
+    let operand = match expr {
+        Expr::Value(Value::Number(n, _)) => Operand::Literal(number_literal(n)?),
+        Expr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => match &**expr {
+            Expr::Value(Value::Number(n, _)) => Operand::Literal(number_literal(&format!("-{n}"))?),
+            _ => return Ok(None),
+        },
+        Expr::Value(Value::Boolean(b)) => Operand::Literal(value::Value::Bool(*b)),
+        Expr::Value(Value::SingleQuotedString(s))
+        | Expr::Value(Value::DoubleQuotedString(s))
+        | Expr::Value(Value::NationalStringLiteral(s)) => {
+            Operand::Literal(value::Value::Str(s.to_string()))
+        }
+        Expr::Value(Value::Placeholder(p)) => Operand::from_placeholder(p)?,
+        expr => match typed_literal(expr)? {
+            Some(value) => Operand::Literal(value),
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(operand))
+}
+
+/// Recognises `column [NOT] BETWEEN a AND b` and `column [NOT] IN (a, b, ...)`
+/// over literal values, which an index answers with a range or point lookups.
+fn simple_range(expr: &Expr) -> Result<Option<Expression>> {
+    let (left, op, operands) = match expr {
+        Expr::Between {
+            expr,
+            negated,
+            low,
+            high,
+        } => (
+            expr,
+            Operator::Between { negated: *negated },
+            vec![&**low, &**high],
+        ),
+        Expr::InList {
+            expr,
+            list,
+            negated,
+        } => (
+            expr,
+            Operator::In { negated: *negated },
+            list.iter().collect(),
+        ),
+        _ => return Ok(None),
+    };
+    let Expr::Identifier(col_name) = &**left else {
+        return Ok(None);
+    };
+    let mut right = vec![];
+    for operand in operands {
+        match simple_operand(operand)? {
+            Some(operand) => right.push(operand),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(Expression {
+        left: col_name.value.to_string(),
+        right,
+        op,
+    }))
+}
+
+/// Recognises `column [NOT] LIKE 'pattern'`, which an index can answer
+/// when the pattern starts with fixed text.
+fn simple_like(expr: &Expr) -> Result<Option<Expression>> {
+    let (negated, left, pattern, escape_char, case_insensitive) = match expr {
+        Expr::Like {
+            negated,
+            expr,
+            pattern,
+            escape_char,
+        } => (negated, expr, pattern, escape_char, false),
+        Expr::ILike {
+            negated,
+            expr,
+            pattern,
+            escape_char,
+        } => (negated, expr, pattern, escape_char, true),
+        _ => return Ok(None),
+    };
+    let Expr::Identifier(col_name) = &**left else {
+        return Ok(None);
+    };
+    let right = match &**pattern {
+        Expr::Value(Value::SingleQuotedString(s))
+        | Expr::Value(Value::DoubleQuotedString(s))
+        | Expr::Value(Value::NationalStringLiteral(s)) => {
+            Operand::Literal(value::Value::Str(s.to_string()))
+        }
+        Expr::Value(Value::Placeholder(p)) => Operand::from_placeholder(p)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(Expression {
+        left: col_name.value.to_string(),
+        right: vec![right],
+        op: Operator::Match(Pattern::like(*escape_char, case_insensitive, *negated)),
+    }))
+}
+
+/// A plain table of a join, named by its alias if it has one.
+fn joined_table(relation: &TableFactor) -> Option<JoinedTable> {
+    match relation {
+        TableFactor::Table {
+            name,
+            alias,
+            args: None,
+            ..
+        } => Some(JoinedTable {
+            table: name.to_string(),
+            alias: alias
+                .as_ref()
+                .map_or_else(|| name.to_string(), |a| a.name.value.to_string()),
+        }),
+        _ => None,
+    }
+}
+
+/// A table joined to an UNNEST, which only a lateral join of the arrays
+/// of the table's rows can be.
+fn lateral_unnest(
+    relation: &TableFactor,
+    function: &TableFactor,
+) -> Result<(JoinedTable, UnnestTable)> {
+    let (
+        Some(table),
+        TableFactor::Table {
+            name,
+            alias,
+            args: Some(args),
+            ..
+        },
+    ) = (joined_table(relation), function)
+    else {
+        return Err(Error::Parse(
+            "Joins and derived tables are not supported".to_string(),
+        ));
+    };
+    let unnest = UnnestTable::new(name, alias, args)?;
+    if unnest.alias == table.alias {
+        return Err(Error::Binding(format!(
+            "Table name {} is specified more than once",
+            table.alias
+        )));
+    }
+    Ok((table, unnest))
+}
+
+/// The column part of a `table.column` reference.
+pub fn unqualified(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(_, column)| column)
+}
+
+fn parse_count(expr: &Expr, clause: &str) -> Result<u64> {
+    match expr {
+        Expr::Value(Value::Number(n, _)) => n
+            .parse::<u64>()
+            .map_err(|_| Error::Parse(format!("Invalid {clause} {n}"))),
+        _ => Err(Error::Parse(format!("Unsupported {clause} {expr}"))),
+    }
+}
+
+/// The OFFSET and LIMIT of a query.
+pub fn parse_limits(bq: &Query) -> Result<(Option<u64>, Option<u64>)> {
+    let offset = match &bq.offset {
+        Some(bq_offset) => Some(parse_count(&bq_offset.value, "OFFSET")?),
+        None => None,
+    };
+    let limit = match &bq.limit {
+        Some(bq_limit) => Some(parse_count(bq_limit, "LIMIT")?),
+        None => None,
+    };
+    Ok((offset, limit))
+}
+
+pub fn parse_order_by(order_by: &[OrderByExpr]) -> Result<Vec<OrderBy>> {
+    order_by
+        .iter()
+        .map(|o| {
+            let asc = o.asc.unwrap_or(true);
+            Ok(OrderBy {
+                expr: ScalarExpr::try_from(&o.expr)?,
+                asc,
+                nulls_first: o.nulls_first.unwrap_or(!asc),
+            })
+        })
+        .collect()
+}
+
+impl SelectQuery {
+    pub fn from_select(select: &Select) -> Result<SelectQuery> {
         let mut table_name: Option<String> = None;
-        let mut projection: Vec<String> = vec![];
+        let mut projection: Vec<SelectItem> = vec![];
         let mut where_expressions: Vec<Expression> = vec![];
-        let mut offset: Option<u64> = None;
-        let mut limit: Option<u64> = None;
-
-        match statement {
-            Statement::Query(bq) => {
-                if let Some(bq_offset) = &bq.offset {
-                    let value = &bq_offset.value;
-                    dbg!(value);
-                    if let Expr::Value(Value::Number(n, _)) = value {
-                        offset = Some(n.parse::<u64>().unwrap());
+        let mut filters: Vec<ScalarExpr> = vec![];
+
+        // Each UNNEST in the select list reads a column of one UNNEST of
+        // them all, so their items line up as in `UNNEST(a, b)`.
+        let mut select_unnest = vec![];
+        for p in &(select).projection {
+            let unnest_arg = match p {
+                UnnamedExpr(Expr::Function(f))
+                | ExprWithAlias {
+                    expr: Expr::Function(f),
+                    ..
+                } if f.name.to_string().eq_ignore_ascii_case("unnest") => match f.args.as_slice() {
+                    [FunctionArg::Unnamed(FunctionArgExpr::Expr(arg))] => Some(arg),
+                    _ => {
+                        return Err(Error::Parse(
+                            "UNNEST in the select list takes 1 argument".to_string(),
+                        ))
                     }
+                },
+                _ => None,
+            };
+            if let Some(arg) = unnest_arg {
+                select_unnest.push(ScalarExpr::try_from(arg)?);
+                let column = select_unnest.len().to_string();
+                projection.push(SelectItem {
+                    expr: ScalarExpr::Column(format!("unnest.{column}")),
+                    name: match p {
+                        ExprWithAlias { alias, .. } => alias.value.to_string(),
+                        _ => "unnest".to_string(),
+                    },
+                });
+                continue;
+            }
+            match p {
+                UnnamedExpr(expr) => {
+                    let scalar = ScalarExpr::try_from(expr)?;
+                    let name = match &scalar {
+                        ScalarExpr::Column(name) => unqualified(name).to_string(),
+                        _ => expr.to_string(),
+                    };
+                    projection.push(SelectItem { expr: scalar, name });
                 }
-                if let Some(Expr::Value(Value::Number(n, _))) = &bq.limit {
-                    limit = Some(n.parse::<u64>().unwrap());
-                }
-                match &*(bq).body {
-                    SetExpr::Select(select) => {
-                        for p in &(select).projection {
-                            match p {
-                                UnnamedExpr(exp) => match exp {
-                                    Expr::Identifier(i) => {
-                                        projection.push(i.to_string());
-                                    }
-                                    _ => {
-                                        println!(
-                                            "Failing to parse expression in the where clause.\
-                                         It's probably not an identifier or a value.\
-                                         Cannot parse nested expressions :( ."
-                                        );
-                                    }
-                                },
-                                QualifiedWildcard(obj_name, _) => {
-                                    println!("Found qualified wildcard in the expression. Wildcard name is  {}", obj_name);
-                                }
-                                Wildcard(_) => {
-                                    projection.push("*".to_string());
-                                }
-                                ExprWithAlias { expr, .. } => match expr {
-                                    Expr::Identifier(i) => {
-                                        projection.push(i.to_string());
-                                    }
-                                    _ => {
-                                        println!("Detected expression with alias. Cannot parse expression with alias.");
-                                    }
-                                },
-                            }
-                        }
-
-                        for f in &select.from {
-                            match &f.relation {
-                                TableFactor::Table { name, alias, .. } => {
-                                    table_name = Some(name.to_string());
-                                    match alias {
-                                        Some(alias) => println!("alias = {}", alias),
-                                        None => println!("No table alias"),
-                                    }
-                                }
-                                _ => println!("Nested join or derived tables"),
-                            }
-                        }
-
-                        match &select.selection {
-                            Some(where_expression) => {
-                                println!("where expression in select.rs = {:?}", where_expression);
-                                if let Expr::BinaryOp { left, op, right } = where_expression {
-                                    if let Expr::Identifier(col_name) = &(**left) {
-                                        if let Expr::Value(v) = &(**right) {
-                                            if let Value::Number(n, _) = v {
-                                                Self::add_ops(
-                                                    op,
-                                                    &mut where_expressions,
-                                                    col_name,
-                                                    n,
-                                                );
-                                            }
-                                            if let Value::NationalStringLiteral(n) = v {
-                                                Self::add_ops(
-                                                    op,
-                                                    &mut where_expressions,
-                                                    col_name,
-                                                    n,
-                                                );
-                                            }
-                                            if let Value::SingleQuotedString(n) = v {
-                                                Self::add_ops(
-                                                    op,
-                                                    &mut where_expressions,
-                                                    col_name,
-                                                    n,
-                                                );
-                                            }
-                                        }
-
-                                        if let Expr::Identifier(v) = &(**right) {
-                                            let n = &v.to_string();
-                                            Self::add_ops(op, &mut where_expressions, col_name, n);
-                                        }
-                                    };
-                                }
-                            }
-                            None => {}
-                        }
+                ExprWithAlias { expr, alias } => projection.push(SelectItem {
+                    expr: ScalarExpr::try_from(expr)?,
+                    name: alias.value.to_string(),
+                }),
+                Wildcard(_) => projection.push(SelectItem::column("*".to_string())),
+                p => return Err(Error::Parse(format!("Unsupported projection {p}"))),
+            }
+        }
+
+        let mut joined = vec![];
+        let mut unnest = None;
+        let mut on = None;
+        match select.from.as_slice() {
+            _ if !select_unnest.is_empty() => {
+                let table = match select.from.as_slice() {
+                    [TableWithJoins { relation, joins }] if joins.is_empty() => {
+                        joined_table(relation)
                     }
-                    _ => unimplemented!(),
+                    _ => None,
+                };
+                let Some(table) = table else {
+                    return Err(Error::Parse(
+                        "UNNEST in the select list needs a single table to read".to_string(),
+                    ));
+                };
+                table_name = Some(format!("{} JOIN unnest", table.alias));
+                joined = vec![table];
+                unnest = Some(UnnestTable {
+                    columns: (1..=select_unnest.len()).map(|i| i.to_string()).collect(),
+                    args: select_unnest,
+                    alias: "unnest".to_string(),
+                });
+            }
+            // A table function such as UNNEST is read by its alias.
+            [TableWithJoins {
+                relation:
+                    TableFactor::Table {
+                        alias: Some(alias),
+                        args: Some(_),
+                        ..
+                    },
+                joins,
+            }] if joins.is_empty() => table_name = Some(alias.name.value.to_string()),
+            [TableWithJoins {
+                relation: TableFactor::Table { name, .. },
+                joins,
+            }] if joins.is_empty() => table_name = Some(name.to_string()),
+            // A table and an UNNEST of the arrays of its rows.
+            [TableWithJoins { relation, joins }, TableWithJoins {
+                relation: function,
+                joins: function_joins,
+            }] if joins.is_empty() && function_joins.is_empty() => {
+                let (table, function) = lateral_unnest(relation, function)?;
+                table_name = Some(format!("{} JOIN {}", table.alias, function.alias));
+                joined = vec![table];
+                unnest = Some(function);
+            }
+            [TableWithJoins { relation, joins }]
+                if matches!(
+                    joins.as_slice(),
+                    [Join {
+                        join_operator: JoinOperator::CrossJoin,
+                        ..
+                    }]
+                ) =>
+            {
+                let (table, function) = lateral_unnest(relation, &joins[0].relation)?;
+                table_name = Some(format!("{} JOIN {}", table.alias, function.alias));
+                joined = vec![table];
+                unnest = Some(function);
+            }
+            [TableWithJoins { relation, joins }] if joins.len() == 1 => {
+                let (JoinOperator::Inner(JoinConstraint::On(condition)), Some(left), Some(right)) = (
+                    &joins[0].join_operator,
+                    joined_table(relation),
+                    joined_table(&joins[0].relation),
+                ) else {
+                    return Err(Error::Parse(
+                        "Only an INNER JOIN of two tables ON a condition is supported".to_string(),
+                    ));
+                };
+                if left.alias == right.alias {
+                    return Err(Error::Binding(format!(
+                        "Table name {} is specified more than once",
+                        left.alias
+                    )));
                 }
+                table_name = Some(format!("{} JOIN {}", left.alias, right.alias));
+                joined = vec![left, right];
+                on = Some(condition);
+            }
+            [] => {}
+            _ => {
+                return Err(Error::Parse(
+                    "Joins and derived tables are not supported".to_string(),
+                ))
+            }
+        }
+
+        let mut conditions = vec![];
+        for condition in on.into_iter().chain(&select.selection) {
+            conjuncts(condition, &mut conditions);
+        }
+        for condition in conditions {
+            match simple_comparison(condition)? {
+                Some(expression) => where_expressions.push(expression),
+                None => filters.push(ScalarExpr::try_from(condition)?),
             }
-            _ => unimplemented!(),
         }
+        let filter = filters
+            .into_iter()
+            .reduce(|l, r| ScalarExpr::Binary(Box::new(l), BinaryOp::And, Box::new(r)));
 
+        let group_by = select
+            .group_by
+            .iter()
+            .map(ScalarExpr::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        let having = select
+            .having
+            .as_ref()
+            .map(ScalarExpr::try_from)
+            .transpose()?;
         match table_name {
             Some(name) => Ok(SelectQuery {
                 from: name,
+                joined,
+                unnest,
+                distinct: select.distinct,
                 projection,
                 where_expressions,
-                offset,
-                limit,
+                filter,
+                group_by,
+                having,
+                order_by: vec![],
+                offset: None,
+                limit: None,
             }),
-            None => Err(
+            None => Err(Error::Parse(
                 "Error while trying to parse select statement. Cannot extract table name"
                     .to_string(),
-            ),
+            )),
         }
+        .map(|mut sq| {
+            sq.unqualify();
+            sq
+        })
+    }
+
+    /// Drops the table names from `t.column` references of a query that
+    /// reads a single table, where they can only name that table.
+    pub fn unqualify(&mut self) {
+        if !self.joined.is_empty() {
+            return;
+        }
+        let SelectQuery {
+            projection,
+            filter,
+            group_by,
+            having,
+            order_by,
+            ..
+        } = self;
+        for expr in projection
+            .iter_mut()
+            .map(|i| &mut i.expr)
+            .chain(filter.iter_mut())
+            .chain(group_by.iter_mut())
+            .chain(having.iter_mut())
+            .chain(order_by.iter_mut().map(|o| &mut o.expr))
+        {
+            expr.unqualify();
+        }
+    }
+
+    /// Whether the query computes one row per group rather than per row.
+    pub fn is_aggregate(&self) -> bool {
+        !self.group_by.is_empty()
+            || self.having.is_some()
+            || self.projection.iter().any(|i| i.expr.contains_aggregate())
+            || self.order_by.iter().any(|o| o.expr.contains_aggregate())
+    }
+
+    /// Every expression in the query other than the WHERE comparisons.
+    pub fn exprs(&self) -> impl Iterator<Item = &ScalarExpr> {
+        self.projection
+            .iter()
+            .map(|i| &i.expr)
+            .chain(self.filter.iter())
+            .chain(self.group_by.iter())
+            .chain(self.having.iter())
+            .chain(self.order_by.iter().map(|o| &o.expr))
+    }
+
+    pub fn exprs_mut(&mut self) -> impl Iterator<Item = &mut ScalarExpr> {
+        self.projection
+            .iter_mut()
+            .map(|i| &mut i.expr)
+            .chain(self.filter.iter_mut())
+            .chain(self.group_by.iter_mut())
+            .chain(self.having.iter_mut())
+            .chain(self.order_by.iter_mut().map(|o| &mut o.expr))
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        let SelectQuery {
+            projection,
+            where_expressions,
+            filter,
+            group_by,
+            having,
+            order_by,
+            ..
+        } = self;
+        where_expressions
+            .iter_mut()
+            .flat_map(|e| e.right.iter_mut())
+            .chain(
+                projection
+                    .iter_mut()
+                    .map(|i| &mut i.expr)
+                    .chain(filter.iter_mut())
+                    .chain(group_by.iter_mut())
+                    .chain(having.iter_mut())
+                    .chain(order_by.iter_mut().map(|o| &mut o.expr))
+                    .flat_map(|e| e.operands_mut()),
+            )
+            .collect()
     }
 
-    pub fn insert_projections(&mut self, projection: Vec<String>) -> &mut SelectQuery {
+    pub fn insert_projections(&mut self, projection: Vec<SelectItem>) -> &mut SelectQuery {
         self.projection = projection;
         self
     }
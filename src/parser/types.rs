@@ -0,0 +1,80 @@
+use sqlparser::keywords::Keyword;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::Token;
+
+use super::Statement;
+
+/// `CREATE TYPE name AS ENUM (labels)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTypeQuery {
+    pub name: String,
+    pub labels: Vec<String>,
+}
+
+/// Where `ALTER TYPE ... ADD VALUE` puts the new label among the others.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LabelPosition {
+    Last,
+    Before(String),
+    After(String),
+}
+
+/// `ALTER TYPE name ADD VALUE [IF NOT EXISTS] label [BEFORE | AFTER label]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterTypeQuery {
+    pub name: String,
+    pub label: String,
+    pub position: LabelPosition,
+    pub if_not_exists: bool,
+}
+
+/// Parses a `CREATE TYPE` or `ALTER TYPE` statement if one comes next,
+/// since sqlparser has no syntax for either.
+pub fn parse_type_statement(parser: &mut Parser) -> Result<Option<Statement>, ParserError> {
+    if parser.parse_keywords(&[Keyword::CREATE, Keyword::TYPE]) {
+        let name = parser.parse_object_name()?.to_string();
+        parser.expect_keywords(&[Keyword::AS, Keyword::ENUM])?;
+        parser.expect_token(&Token::LParen)?;
+        let mut labels = vec![];
+        if !parser.consume_token(&Token::RParen) {
+            labels = parser.parse_comma_separated(parse_label)?;
+            parser.expect_token(&Token::RParen)?;
+        }
+        return Ok(Some(Statement::CreateType(CreateTypeQuery {
+            name,
+            labels,
+        })));
+    }
+    if parser.parse_keywords(&[Keyword::ALTER, Keyword::TYPE]) {
+        let name = parser.parse_object_name()?.to_string();
+        parser.expect_keywords(&[Keyword::ADD, Keyword::VALUE])?;
+        let if_not_exists = parser.parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]);
+        let label = parse_label(parser)?;
+        // BEFORE and AFTER aren't keywords to sqlparser.
+        let position = match parser.peek_token() {
+            Token::Word(w) if w.value.eq_ignore_ascii_case("before") => {
+                parser.next_token();
+                LabelPosition::Before(parse_label(parser)?)
+            }
+            Token::Word(w) if w.value.eq_ignore_ascii_case("after") => {
+                parser.next_token();
+                LabelPosition::After(parse_label(parser)?)
+            }
+            _ => LabelPosition::Last,
+        };
+        return Ok(Some(Statement::AlterType(AlterTypeQuery {
+            name,
+            label,
+            position,
+            if_not_exists,
+        })));
+    }
+    Ok(None)
+}
+
+fn parse_label(parser: &mut Parser) -> Result<String, ParserError> {
+    match parser.next_token() {
+        Token::SingleQuotedString(label) => Ok(label),
+        unexpected => parser.expected("an enum label", unexpected),
+    }
+}
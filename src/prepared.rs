@@ -0,0 +1,310 @@
+use crate::error::{Error, Result};
+use crate::parser::create::CreateQuery;
+use crate::parser::insert::InsertQuery;
+use crate::parser::query::Query;
+use crate::parser::types::{AlterTypeQuery, CreateTypeQuery};
+use crate::parser::Operand;
+use crate::result::ResultColumn;
+use crate::table::DataType;
+use crate::value::Value;
+
+/// A statement that has been parsed and checked against the schema, ready
+/// to be executed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Plan {
+    CreateTable(CreateQuery),
+    Insert(InsertQuery),
+    Select(Query),
+    CreateType(CreateTypeQuery),
+    AlterType(AlterTypeQuery),
+}
+
+impl Plan {
+    fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Plan::CreateTable(_) | Plan::CreateType(_) | Plan::AlterType(_) => vec![],
+            Plan::Insert(iq) => iq.values.iter_mut().flatten().collect(),
+            Plan::Select(query) => query.operands_mut(),
+        }
+    }
+}
+
+/// A planned statement whose `?` / `$n` parameters have not been bound yet.
+/// Create one with `Database::prepare`, then `bind` it for every execution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreparedStatement {
+    pub(crate) plan: Plan,
+    pub(crate) param_types: Vec<Option<DataType>>,
+    pub(crate) columns: Option<Vec<ResultColumn>>,
+}
+
+impl PreparedStatement {
+    pub fn param_count(&self) -> usize {
+        self.param_types.len()
+    }
+
+    /// The type each parameter is compared with or stored as, if it could
+    /// be worked out from the statement.
+    pub fn param_types(&self) -> &[Option<DataType>] {
+        &self.param_types
+    }
+
+    /// The columns the statement returns, or `None` if it doesn't return rows.
+    pub fn columns(&self) -> Option<&[ResultColumn]> {
+        self.columns.as_deref()
+    }
+
+    pub fn bind(&self, params: &[Value]) -> Result<BoundStatement> {
+        if params.len() != self.param_count() {
+            return Err(Error::Binding(format!(
+                "Expected {} parameters but got {}",
+                self.param_count(),
+                params.len()
+            )));
+        }
+        let stored = matches!(self.plan, Plan::Insert(_));
+        let literals = params
+            .iter()
+            .zip(&self.param_types)
+            .enumerate()
+            .map(|(idx, (value, datatype))| bind_value(idx, value, datatype.as_ref(), stored))
+            .collect::<Result<Vec<Value>>>()?;
+
+        let mut plan = self.plan.clone();
+        for operand in plan.operands_mut() {
+            if let Operand::Param(idx) = operand {
+                *operand = Operand::Literal(literals[*idx].clone());
+            }
+        }
+        Ok(BoundStatement {
+            plan,
+            columns: self.columns.clone(),
+        })
+    }
+}
+
+/// A prepared statement with all of its parameters filled in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundStatement {
+    pub(crate) plan: Plan,
+    pub(crate) columns: Option<Vec<ResultColumn>>,
+}
+
+impl BoundStatement {
+    pub fn columns(&self) -> Option<&[ResultColumn]> {
+        self.columns.as_deref()
+    }
+}
+
+/// Works out how many parameters a plan takes, and the type of each one
+/// using `column_type` to look up the table column it is compared with or
+/// stored in.
+pub(crate) fn infer_param_types<F>(plan: &Plan, column_type: F) -> Vec<Option<DataType>>
+where
+    F: Fn(&str, &str) -> Option<DataType>,
+{
+    let mut params: Vec<(usize, Option<DataType>)> = vec![];
+    match plan {
+        Plan::CreateTable(_) | Plan::CreateType(_) | Plan::AlterType(_) => {}
+        Plan::Insert(iq) => {
+            for row in &iq.values {
+                for (operand, col) in row.iter().zip(&iq.columns) {
+                    if let Operand::Param(idx) = operand {
+                        params.push((*idx, column_type(&iq.table_name, col)));
+                    }
+                }
+            }
+        }
+        Plan::Select(query) => {
+            for sq in query.selects() {
+                let column_type = |col: &str| column_type(&sq.from, col);
+                let expr_column_type = |col: &str| {
+                    column_type(col)
+                        .ok_or_else(|| Error::Binding(format!("Cannot find column {col}")))
+                };
+                for expr in sq.exprs() {
+                    expr.infer_params(&expr_column_type, &mut params);
+                }
+                for e in &sq.where_expressions {
+                    for operand in &e.right {
+                        if let Operand::Param(idx) = operand {
+                            params.push((*idx, column_type(&e.left)));
+                        }
+                    }
+                }
+            }
+            let no_column =
+                |col: &str| Err(Error::Binding(format!("Cannot refer to column {col} here")));
+            for arg in query.unnest_args() {
+                arg.infer_params(&no_column, &mut params);
+            }
+        }
+    }
+
+    let count = params.iter().map(|(idx, _)| idx + 1).max().unwrap_or(0);
+    let mut types = vec![None; count];
+    for (idx, datatype) in params {
+        if types[idx].is_none() {
+            types[idx] = datatype;
+        }
+    }
+    types
+}
+
+/// Converts a parameter to the type it was inferred to have. A value that
+/// is stored, in an INSERT, must fit that type; one that is only compared
+/// keeps a number the type can't hold exactly, as a literal would.
+fn bind_value(
+    idx: usize,
+    value: &Value,
+    datatype: Option<&DataType>,
+    stored: bool,
+) -> Result<Value> {
+    let datatype = match datatype {
+        None | Some(DataType::Invalid) => return Ok(value.clone()),
+        Some(datatype) => datatype,
+    };
+    if let Some(exact) = value.inexact_for(datatype).filter(|_| !stored) {
+        return Ok(exact);
+    }
+    value.clone().coerce(datatype).map_err(|_| {
+        Error::Type(format!(
+            "Cannot bind {} to parameter ${} of type {}",
+            value,
+            idx + 1,
+            datatype
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::Database;
+    use crate::error::Error;
+    use crate::result::QueryResult;
+    use crate::table::DataType;
+    use crate::value::Value;
+
+    fn users() -> Database {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE users (id int PRIMARY KEY, name string, active bool)")
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn tests_binding_parameters() {
+        let mut db = users();
+        let insert = db
+            .prepare("INSERT INTO users (id, name, active) VALUES (?, ?, ?)")
+            .unwrap();
+        assert_eq!(
+            insert.param_types(),
+            &[
+                Some(DataType::Int),
+                Some(DataType::Str),
+                Some(DataType::Bool)
+            ]
+        );
+        for (id, name) in [(1, "a"), (2, "x' or '1' = '1")] {
+            let params = [Value::Int(id), Value::Str(name.to_string()), Value::Int(1)];
+            let bound = insert.bind(&params).unwrap();
+            assert_eq!(db.execute_bound(&bound), Ok(QueryResult::Inserted(1)));
+        }
+
+        let select = db.prepare("SELECT id FROM users WHERE name = $1").unwrap();
+        assert_eq!(select.param_count(), 1);
+        let bound = select
+            .bind(&[Value::Str("x' or '1' = '1".to_string())])
+            .unwrap();
+        let QueryResult::Rows(rs) = db.execute_bound(&bound).unwrap() else {
+            panic!("expected rows");
+        };
+        assert_eq!(rs.rows, vec![vec![Value::Int(2)]]);
+    }
+
+    #[test]
+    fn tests_binding_errors() {
+        let mut db = users();
+        let select = db.prepare("SELECT name FROM users WHERE id = ?").unwrap();
+        assert!(matches!(select.bind(&[]), Err(Error::Binding(_))));
+        assert!(matches!(
+            select.bind(&[Value::Str("one".to_string())]),
+            Err(Error::Type(_))
+        ));
+        assert!(select.bind(&[Value::Str("1".to_string())]).is_ok());
+        assert!(matches!(
+            db.execute("SELECT name FROM users WHERE id = ?"),
+            Err(Error::Binding(_))
+        ));
+        assert_eq!(
+            db.prepare("SELECT name FROM users WHERE id = $65535")
+                .unwrap()
+                .param_count(),
+            65535
+        );
+        for sql in [
+            "SELECT name FROM users WHERE id = $99999999999",
+            "SELECT name FROM users WHERE id = $65536",
+            "SELECT name FROM users WHERE id = $0",
+        ] {
+            assert!(matches!(db.execute(sql), Err(Error::Parse(_))));
+        }
+    }
+
+    #[test]
+    fn tests_binding_compared_values() {
+        let mut db = Database::new();
+        db.execute("CREATE TABLE items (id int PRIMARY KEY, cost decimal(5,2))")
+            .unwrap();
+        db.execute("INSERT INTO items VALUES (3, 3.0), (4, 4.0)")
+            .unwrap();
+        let count = |db: &mut Database, sql: &str, param: Value| {
+            let bound = db.prepare(sql).unwrap().bind(&[param]).unwrap();
+            match db.execute_bound(&bound).unwrap() {
+                QueryResult::Rows(rs) => rs.rows.len(),
+                _ => panic!("expected rows"),
+            }
+        };
+
+        // A compared value keeps what the column's type would narrow away.
+        assert_eq!(
+            count(
+                &mut db,
+                "SELECT id FROM items WHERE id < ?",
+                Value::Double(3.5)
+            ),
+            1
+        );
+        assert_eq!(
+            count(
+                &mut db,
+                "SELECT id FROM items WHERE id = ?",
+                Value::Double(3.5)
+            ),
+            0
+        );
+        assert_eq!(
+            count(
+                &mut db,
+                "SELECT id FROM items WHERE cost = ?",
+                Value::Str("3.001".to_string())
+            ),
+            0
+        );
+        assert_eq!(
+            count(
+                &mut db,
+                "SELECT id FROM items WHERE cost = ?",
+                Value::Str("3".to_string())
+            ),
+            1
+        );
+
+        let insert = db.prepare("INSERT INTO items (id) VALUES (?)").unwrap();
+        assert!(matches!(
+            insert.bind(&[Value::Double(4.5)]),
+            Err(Error::Type(_))
+        ));
+    }
+}
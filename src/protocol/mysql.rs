@@ -0,0 +1,1095 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use chrono::{Datelike, Timelike};
+use sqlparser::ast::{self, Expr, SelectItem, SetExpr};
+use sqlparser::dialect::MySqlDialect;
+
+use crate::database::Database;
+use crate::error::{Error, Result};
+use crate::parser::{parse_with_dialect, Statement};
+use crate::prepared::PreparedStatement;
+use crate::result::{QueryResult, ResultColumn, ResultSet};
+use crate::table::DataType;
+use crate::value::{datetime, Decimal, Value};
+
+const SERVER_VERSION: &str = "8.0.0-sql";
+
+const CLIENT_LONG_PASSWORD: u32 = 0x0000_0001;
+const CLIENT_FOUND_ROWS: u32 = 0x0000_0002;
+const CLIENT_LONG_FLAG: u32 = 0x0000_0004;
+const CLIENT_CONNECT_WITH_DB: u32 = 0x0000_0008;
+const CLIENT_PROTOCOL_41: u32 = 0x0000_0200;
+const CLIENT_TRANSACTIONS: u32 = 0x0000_2000;
+const CLIENT_SECURE_CONNECTION: u32 = 0x0000_8000;
+const CLIENT_MULTI_STATEMENTS: u32 = 0x0001_0000;
+const CLIENT_MULTI_RESULTS: u32 = 0x0002_0000;
+const CLIENT_PS_MULTI_RESULTS: u32 = 0x0004_0000;
+const CLIENT_PLUGIN_AUTH: u32 = 0x0008_0000;
+const CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA: u32 = 0x0020_0000;
+
+const SERVER_CAPABILITIES: u32 = CLIENT_LONG_PASSWORD
+    | CLIENT_FOUND_ROWS
+    | CLIENT_LONG_FLAG
+    | CLIENT_CONNECT_WITH_DB
+    | CLIENT_PROTOCOL_41
+    | CLIENT_TRANSACTIONS
+    | CLIENT_SECURE_CONNECTION
+    | CLIENT_MULTI_STATEMENTS
+    | CLIENT_MULTI_RESULTS
+    | CLIENT_PS_MULTI_RESULTS
+    | CLIENT_PLUGIN_AUTH
+    | CLIENT_PLUGIN_AUTH_LENENC_CLIENT_DATA;
+
+const SERVER_STATUS_AUTOCOMMIT: u16 = 0x0002;
+const SERVER_MORE_RESULTS_EXISTS: u16 = 0x0008;
+
+const COM_QUIT: u8 = 0x01;
+const COM_INIT_DB: u8 = 0x02;
+const COM_QUERY: u8 = 0x03;
+const COM_PING: u8 = 0x0e;
+const COM_STMT_PREPARE: u8 = 0x16;
+const COM_STMT_EXECUTE: u8 = 0x17;
+const COM_STMT_CLOSE: u8 = 0x19;
+const COM_STMT_RESET: u8 = 0x1a;
+
+const MYSQL_TYPE_DECIMAL: u8 = 0x00;
+const MYSQL_TYPE_TINY: u8 = 0x01;
+const MYSQL_TYPE_SHORT: u8 = 0x02;
+const MYSQL_TYPE_LONG: u8 = 0x03;
+const MYSQL_TYPE_FLOAT: u8 = 0x04;
+const MYSQL_TYPE_DOUBLE: u8 = 0x05;
+const MYSQL_TYPE_NULL: u8 = 0x06;
+const MYSQL_TYPE_TIMESTAMP: u8 = 0x07;
+const MYSQL_TYPE_LONGLONG: u8 = 0x08;
+const MYSQL_TYPE_INT24: u8 = 0x09;
+const MYSQL_TYPE_DATE: u8 = 0x0a;
+const MYSQL_TYPE_TIME: u8 = 0x0b;
+const MYSQL_TYPE_DATETIME: u8 = 0x0c;
+const MYSQL_TYPE_YEAR: u8 = 0x0d;
+const MYSQL_TYPE_JSON: u8 = 0xf5;
+const MYSQL_TYPE_NEWDECIMAL: u8 = 0xf6;
+const MYSQL_TYPE_TINY_BLOB: u8 = 0xf9;
+const MYSQL_TYPE_MEDIUM_BLOB: u8 = 0xfa;
+const MYSQL_TYPE_LONG_BLOB: u8 = 0xfb;
+const MYSQL_TYPE_BLOB: u8 = 0xfc;
+const MYSQL_TYPE_VAR_STRING: u8 = 0xfd;
+
+const NOT_NULL_FLAG: u16 = 0x0001;
+const PRI_KEY_FLAG: u16 = 0x0002;
+const BLOB_FLAG: u16 = 0x0010;
+const UNSIGNED_FLAG: u16 = 0x0020;
+const BINARY_FLAG: u16 = 0x0080;
+const ENUM_FLAG: u16 = 0x0100;
+const NUM_FLAG: u16 = 0x8000;
+
+const BINARY_CHARSET: u16 = 63;
+const UTF8_CHARSET: u16 = 33;
+
+const ER_UNKNOWN_COM_ERROR: u16 = 1047;
+const ER_PARSE_ERROR: u16 = 1064;
+const ER_EMPTY_QUERY: u16 = 1065;
+const ER_UNKNOWN_STMT_HANDLER: u16 = 1243;
+const ER_UNKNOWN_ERROR: u16 = 1105;
+const ER_BAD_FIELD_ERROR: u16 = 1054;
+const ER_DUP_ENTRY: u16 = 1062;
+const ER_TRUNCATED_WRONG_VALUE_FOR_FIELD: u16 = 1366;
+const ER_DIVISION_BY_ZERO: u16 = 1365;
+const ER_DATA_OUT_OF_RANGE: u16 = 1690;
+
+static NEXT_CONNECTION_ID: AtomicU32 = AtomicU32::new(1);
+
+pub fn serve(addr: &str, db: Arc<Mutex<Database>>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!(
+        "Listening for MySQL connections on {}",
+        listener.local_addr()?
+    );
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let db = Arc::clone(&db);
+                thread::spawn(move || {
+                    if let Err(e) = Session::new(stream, db).and_then(|mut s| s.run()) {
+                        eprintln!("MySQL connection closed with error: {e}");
+                    }
+                });
+            }
+            Err(e) => eprintln!("Could not accept connection: {e}"),
+        }
+    }
+    Ok(())
+}
+
+pub fn column_type(datatype: &DataType) -> u8 {
+    match datatype {
+        DataType::SmallInt | DataType::UnsignedSmallInt => MYSQL_TYPE_SHORT,
+        DataType::Int | DataType::UnsignedInt => MYSQL_TYPE_LONG,
+        DataType::BigInt | DataType::UnsignedBigInt => MYSQL_TYPE_LONGLONG,
+        DataType::Float => MYSQL_TYPE_FLOAT,
+        DataType::Double => MYSQL_TYPE_DOUBLE,
+        DataType::Decimal(..) => MYSQL_TYPE_NEWDECIMAL,
+        DataType::Bool => MYSQL_TYPE_TINY,
+        DataType::Date => MYSQL_TYPE_DATE,
+        DataType::Time => MYSQL_TYPE_TIME,
+        DataType::Timestamp => MYSQL_TYPE_DATETIME,
+        DataType::TimestampTz => MYSQL_TYPE_TIMESTAMP,
+        DataType::Binary => MYSQL_TYPE_BLOB,
+        DataType::Json => MYSQL_TYPE_JSON,
+        DataType::Str
+        | DataType::Interval
+        | DataType::Uuid
+        | DataType::Array(_)
+        | DataType::Enum(_)
+        | DataType::Invalid => MYSQL_TYPE_VAR_STRING,
+    }
+}
+
+fn column_length(datatype: &DataType) -> u32 {
+    match datatype {
+        DataType::SmallInt => 6,
+        DataType::UnsignedSmallInt => 5,
+        DataType::Int => 11,
+        DataType::UnsignedInt => 10,
+        DataType::BigInt | DataType::UnsignedBigInt => 20,
+        DataType::Float => 12,
+        DataType::Double => 22,
+        // Room for the sign and the point.
+        DataType::Decimal(precision, scale) => *precision as u32 + 1 + (*scale > 0) as u32,
+        DataType::Bool => 1,
+        DataType::Date => 10,
+        DataType::Time => 15,
+        DataType::Timestamp | DataType::TimestampTz => 26,
+        DataType::Binary => u16::MAX as u32,
+        DataType::Json => u32::MAX,
+        DataType::Uuid => 36,
+        DataType::Str
+        | DataType::Interval
+        | DataType::Array(_)
+        | DataType::Enum(_)
+        | DataType::Invalid => 1020,
+    }
+}
+
+fn text_value(value: &Value) -> String {
+    match value {
+        Value::Bool(b) => (*b as u8).to_string(),
+        // MySQL timestamps carry no offset; they are sent as UTC.
+        Value::TimestampTz(t) => datetime::format_timestamp(*t),
+        value => value.to_string(),
+    }
+}
+
+fn binary_value(packet: &mut Packet, value: &Value) {
+    match value {
+        Value::SmallInt(v) => packet.bytes(&v.to_le_bytes()),
+        Value::Int(v) => packet.bytes(&v.to_le_bytes()),
+        Value::BigInt(v) => packet.bytes(&v.to_le_bytes()),
+        Value::UnsignedSmallInt(v) => packet.bytes(&v.to_le_bytes()),
+        Value::UnsignedInt(v) => packet.bytes(&v.to_le_bytes()),
+        Value::UnsignedBigInt(v) => packet.bytes(&v.to_le_bytes()),
+        Value::Float(v) => packet.bytes(&v.to_le_bytes()),
+        Value::Double(v) => packet.bytes(&v.to_le_bytes()),
+        Value::Decimal(v) => packet.lenenc_str(v.to_string().as_bytes()),
+        Value::Bool(b) => packet.u8(*b as u8),
+        Value::Str(s) => packet.lenenc_str(s.as_bytes()),
+        Value::Binary(b) => packet.lenenc_str(b),
+        Value::Json(j) => packet.lenenc_str(j.to_string().as_bytes()),
+        Value::Uuid(_) | Value::Array(_) | Value::Enum(..) => {
+            packet.lenenc_str(value.to_string().as_bytes())
+        }
+        Value::Date(_) | Value::Timestamp(_) | Value::TimestampTz(_) => {
+            let Some(datetime) = value.as_micros().and_then(datetime::datetime_from_micros) else {
+                return packet.u8(0);
+            };
+            packet.u8(11);
+            packet.u16(datetime.year() as u16);
+            packet.u8(datetime.month() as u8);
+            packet.u8(datetime.day() as u8);
+            packet.u8(datetime.hour() as u8);
+            packet.u8(datetime.minute() as u8);
+            packet.u8(datetime.second() as u8);
+            packet.u32(datetime.nanosecond() / 1000);
+        }
+        Value::Time(t) => {
+            let seconds = t / datetime::MICROS_PER_SECOND;
+            packet.u8(12);
+            packet.u8(0);
+            packet.u32(0);
+            packet.u8((seconds / 3600) as u8);
+            packet.u8((seconds / 60 % 60) as u8);
+            packet.u8((seconds % 60) as u8);
+            packet.u32((t % datetime::MICROS_PER_SECOND) as u32);
+        }
+        Value::Interval(i) => packet.lenenc_str(i.to_string().as_bytes()),
+        Value::Null => {}
+    }
+}
+
+fn scramble(connection_id: u32) -> [u8; 20] {
+    let mut hasher = DefaultHasher::new();
+    connection_id.hash(&mut hasher);
+    SystemTime::now().hash(&mut hasher);
+    let mut seed = hasher.finish();
+
+    let mut scramble = [0u8; 20];
+    for b in scramble.iter_mut() {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        *b = 33 + ((seed >> 33) % 94) as u8;
+    }
+    scramble
+}
+
+/// Answers the `SELECT @@variable` queries that clients and connectors run
+/// right after connecting.
+fn system_variable(name: &str) -> Option<String> {
+    let value = match name {
+        "version_comment" => "sql",
+        "version" => SERVER_VERSION,
+        "max_allowed_packet" => "67108864",
+        "auto_increment_increment" => "1",
+        "autocommit" => "1",
+        "lower_case_table_names" => "0",
+        "wait_timeout" | "interactive_timeout" => "28800",
+        "net_write_timeout" => "60",
+        "net_buffer_length" => "16384",
+        "sql_mode" => "",
+        "time_zone" => "SYSTEM",
+        "system_time_zone" => "UTC",
+        "transaction_isolation" | "tx_isolation" => "REPEATABLE-READ",
+        "transaction_read_only" | "tx_read_only" => "0",
+        "character_set_client"
+        | "character_set_connection"
+        | "character_set_results"
+        | "character_set_server" => "utf8mb4",
+        "collation_connection" | "collation_server" => "utf8mb4_general_ci",
+        _ => return None,
+    };
+    Some(value.to_string())
+}
+
+fn system_variables_query(statement: &ast::Statement) -> Option<Outcome> {
+    let query = match statement {
+        ast::Statement::Query(query) => query,
+        _ => return None,
+    };
+    let select = match query.body.as_ref() {
+        SetExpr::Select(select) if select.from.is_empty() => select,
+        _ => return None,
+    };
+
+    let mut columns = vec![];
+    let mut row = vec![];
+    for item in &select.projection {
+        let (expr, alias) = match item {
+            SelectItem::UnnamedExpr(expr) => (expr, None),
+            SelectItem::ExprWithAlias { expr, alias } => (expr, Some(alias.value.to_string())),
+            _ => return None,
+        };
+        let name = match expr {
+            Expr::Identifier(ident) => ident.value.to_string(),
+            Expr::CompoundIdentifier(idents) => idents
+                .iter()
+                .map(|i| i.value.to_string())
+                .collect::<Vec<String>>()
+                .join("."),
+            _ => return None,
+        };
+        let variable = name.strip_prefix("@@")?;
+        let variable = variable
+            .strip_prefix("session.")
+            .or_else(|| variable.strip_prefix("global."))
+            .unwrap_or(variable);
+
+        columns.push(ResultColumn {
+            name: alias.unwrap_or(name.to_string()),
+            datatype: DataType::Str,
+            table: None,
+        });
+        row.push(system_variable(&variable.to_lowercase()).map_or(Value::Null, Value::Str));
+    }
+    Some(Outcome::Rows(ResultSet {
+        columns,
+        rows: vec![row],
+    }))
+}
+
+enum Outcome {
+    Rows(ResultSet),
+    Ok(u64),
+}
+
+impl From<QueryResult> for Outcome {
+    fn from(result: QueryResult) -> Self {
+        match result {
+            QueryResult::Rows(rs) => Outcome::Rows(rs),
+            QueryResult::Inserted(count) => Outcome::Ok(count as u64),
+            QueryResult::Created | QueryResult::CreatedType | QueryResult::AlteredType => {
+                Outcome::Ok(0)
+            }
+        }
+    }
+}
+
+struct Packet(Vec<u8>);
+
+impl Packet {
+    fn new() -> Packet {
+        Packet(vec![])
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+
+    fn u16(&mut self, v: u16) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn bytes(&mut self, v: &[u8]) {
+        self.0.extend_from_slice(v);
+    }
+
+    fn cstr(&mut self, v: &str) {
+        self.0.extend_from_slice(v.as_bytes());
+        self.0.push(0);
+    }
+
+    fn lenenc_int(&mut self, v: u64) {
+        match v {
+            0..=250 => self.u8(v as u8),
+            251..=0xffff => {
+                self.u8(0xfc);
+                self.u16(v as u16);
+            }
+            0x10000..=0xff_ffff => {
+                self.u8(0xfd);
+                self.bytes(&(v as u32).to_le_bytes()[..3]);
+            }
+            _ => {
+                self.u8(0xfe);
+                self.bytes(&v.to_le_bytes());
+            }
+        }
+    }
+
+    fn lenenc_str(&mut self, v: &[u8]) {
+        self.lenenc_int(v.len() as u64);
+        self.bytes(v);
+    }
+}
+
+struct PacketReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PacketReader<'a> {
+    fn new(buf: &'a [u8]) -> PacketReader<'a> {
+        PacketReader { buf, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.buf.len())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Packet is shorter than expected",
+                )
+            })?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn lenenc_int(&mut self) -> io::Result<u64> {
+        match self.u8()? {
+            0xfc => Ok(self.u16()? as u64),
+            0xfd => {
+                let b = self.take(3)?;
+                Ok(u32::from_le_bytes([b[0], b[1], b[2], 0]) as u64)
+            }
+            0xfe => self.u64(),
+            v => Ok(v as u64),
+        }
+    }
+
+    fn lenenc_str(&mut self) -> io::Result<&'a [u8]> {
+        let len = usize::try_from(self.lenenc_int()?).unwrap_or(usize::MAX);
+        self.take(len)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.buf[self.pos..];
+        self.pos = self.buf.len();
+        rest
+    }
+}
+
+/// Decodes a parameter sent with `COM_STMT_EXECUTE` into a typed value.
+fn decode_parameter(
+    reader: &mut PacketReader,
+    param_type: u8,
+    unsigned: bool,
+) -> io::Result<Value> {
+    let value = match param_type {
+        MYSQL_TYPE_TINY => match unsigned {
+            true => Value::UnsignedSmallInt(reader.u8()? as u16),
+            false => Value::SmallInt(reader.u8()? as i8 as i16),
+        },
+        MYSQL_TYPE_SHORT | MYSQL_TYPE_YEAR => match unsigned {
+            true => Value::UnsignedSmallInt(reader.u16()?),
+            false => Value::SmallInt(reader.u16()? as i16),
+        },
+        MYSQL_TYPE_LONG | MYSQL_TYPE_INT24 => match unsigned {
+            true => Value::UnsignedInt(reader.u32()?),
+            false => Value::Int(reader.u32()? as i32),
+        },
+        MYSQL_TYPE_LONGLONG => match unsigned {
+            true => Value::UnsignedBigInt(reader.u64()?),
+            false => Value::BigInt(reader.u64()? as i64),
+        },
+        MYSQL_TYPE_FLOAT => Value::Float(f32::from_le_bytes(reader.take(4)?.try_into().unwrap())),
+        MYSQL_TYPE_DOUBLE => Value::Double(f64::from_le_bytes(reader.take(8)?.try_into().unwrap())),
+        MYSQL_TYPE_DECIMAL | MYSQL_TYPE_NEWDECIMAL => {
+            let text = String::from_utf8_lossy(reader.lenenc_str()?).to_string();
+            Decimal::parse(&text).map_or(Value::Str(text), Value::Decimal)
+        }
+        MYSQL_TYPE_TINY_BLOB | MYSQL_TYPE_MEDIUM_BLOB | MYSQL_TYPE_LONG_BLOB | MYSQL_TYPE_BLOB => {
+            Value::Binary(reader.lenenc_str()?.to_vec())
+        }
+        MYSQL_TYPE_DATE | MYSQL_TYPE_DATETIME | MYSQL_TYPE_TIMESTAMP => {
+            let len = reader.u8()?;
+            let mut fields = PacketReader::new(reader.take(len as usize)?);
+            let (mut year, mut month, mut day) = (0, 0, 0);
+            if len >= 4 {
+                year = fields.u16()?;
+                month = fields.u8()?;
+                day = fields.u8()?;
+            }
+            let mut value = format!("{year:04}-{month:02}-{day:02}");
+            if len >= 7 {
+                let (hour, minute, second) = (fields.u8()?, fields.u8()?, fields.u8()?);
+                value.push_str(&format!(" {hour:02}:{minute:02}:{second:02}"));
+            }
+            if len >= 11 {
+                value.push_str(&format!(".{:06}", fields.u32()?));
+            }
+            Value::Str(value)
+        }
+        MYSQL_TYPE_TIME => {
+            let len = reader.u8()?;
+            let mut fields = PacketReader::new(reader.take(len as usize)?);
+            if len == 0 {
+                Value::Str("00:00:00".to_string())
+            } else {
+                let negative = fields.u8()? == 1;
+                let days = fields.u32()?;
+                let (hour, minute, second) = (fields.u8()?, fields.u8()?, fields.u8()?);
+                let mut value = format!(
+                    "{}{:02}:{minute:02}:{second:02}",
+                    if negative { "-" } else { "" },
+                    days * 24 + hour as u32
+                );
+                if len >= 12 {
+                    value.push_str(&format!(".{:06}", fields.u32()?));
+                }
+                Value::Str(value)
+            }
+        }
+        _ => match String::from_utf8(reader.lenenc_str()?.to_vec()) {
+            Ok(text) => Value::Str(text),
+            Err(e) => Value::Binary(e.into_bytes()),
+        },
+    };
+    Ok(value)
+}
+
+struct ServerStatement {
+    statement: Statement,
+    /// `None` for statements the server answers itself, like `SET` or `SHOW TABLES`.
+    prepared: Option<PreparedStatement>,
+    param_types: Vec<(u8, bool)>,
+}
+
+struct Session {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    db: Arc<Mutex<Database>>,
+    seq: u8,
+    connection_id: u32,
+    statements: HashMap<u32, ServerStatement>,
+    next_statement_id: u32,
+}
+
+impl Session {
+    fn new(stream: TcpStream, db: Arc<Mutex<Database>>) -> io::Result<Session> {
+        Ok(Session {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            db,
+            seq: 0,
+            connection_id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            statements: HashMap::new(),
+            next_statement_id: 1,
+        })
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        if !self.handshake()? {
+            return Ok(());
+        }
+
+        while let Some(payload) = self.read_packet()? {
+            let mut reader = PacketReader::new(&payload);
+            match reader.u8()? {
+                COM_QUIT => return Ok(()),
+                COM_INIT_DB | COM_PING | COM_STMT_RESET => self.ok(0, SERVER_STATUS_AUTOCOMMIT)?,
+                COM_QUERY => {
+                    let query = String::from_utf8_lossy(reader.rest()).to_string();
+                    self.query(&query)?;
+                }
+                COM_STMT_PREPARE => {
+                    let query = String::from_utf8_lossy(reader.rest()).to_string();
+                    self.prepare(&query)?;
+                }
+                COM_STMT_EXECUTE => self.execute(&mut reader)?,
+                COM_STMT_CLOSE => {
+                    let id = reader.u32()?;
+                    self.statements.remove(&id);
+                }
+                command => self.err(
+                    ER_UNKNOWN_COM_ERROR,
+                    "08S01",
+                    &format!("Unsupported command 0x{command:02x}"),
+                )?,
+            }
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn read_packet(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut payload = vec![];
+        loop {
+            let mut header = [0u8; 4];
+            if let Err(e) = self.reader.read_exact(&mut header) {
+                return match e.kind() {
+                    io::ErrorKind::UnexpectedEof if payload.is_empty() => Ok(None),
+                    _ => Err(e),
+                };
+            }
+            let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+            self.seq = header[3].wrapping_add(1);
+
+            let start = payload.len();
+            payload.resize(start + len, 0);
+            self.reader.read_exact(&mut payload[start..])?;
+            if len < 0xff_ffff {
+                return Ok(Some(payload));
+            }
+        }
+    }
+
+    fn write_packet(&mut self, packet: Packet) -> io::Result<()> {
+        let mut chunks = packet.0.chunks(0xff_ffff).peekable();
+        if chunks.peek().is_none() {
+            self.writer.write_all(&[0, 0, 0, self.seq])?;
+            self.seq = self.seq.wrapping_add(1);
+        }
+        for chunk in chunks {
+            let len = (chunk.len() as u32).to_le_bytes();
+            self.writer.write_all(&[len[0], len[1], len[2], self.seq])?;
+            self.writer.write_all(chunk)?;
+            self.seq = self.seq.wrapping_add(1);
+        }
+        if packet.0.len().is_multiple_of(0xff_ffff) && !packet.0.is_empty() {
+            self.writer.write_all(&[0, 0, 0, self.seq])?;
+            self.seq = self.seq.wrapping_add(1);
+        }
+        Ok(())
+    }
+
+    fn handshake(&mut self) -> io::Result<bool> {
+        let scramble = scramble(self.connection_id);
+
+        let mut greeting = Packet::new();
+        greeting.u8(10);
+        greeting.cstr(SERVER_VERSION);
+        greeting.u32(self.connection_id);
+        greeting.bytes(&scramble[..8]);
+        greeting.u8(0);
+        greeting.u16(SERVER_CAPABILITIES as u16);
+        greeting.u8(UTF8_CHARSET as u8);
+        greeting.u16(SERVER_STATUS_AUTOCOMMIT);
+        greeting.u16((SERVER_CAPABILITIES >> 16) as u16);
+        greeting.u8(scramble.len() as u8 + 1);
+        greeting.bytes(&[0; 10]);
+        greeting.bytes(&scramble[8..]);
+        greeting.u8(0);
+        greeting.cstr("mysql_native_password");
+
+        self.seq = 0;
+        self.write_packet(greeting)?;
+        self.writer.flush()?;
+
+        // There are no users, so whatever credentials the client sends are
+        // accepted.
+        match self.read_packet()? {
+            Some(_) => {
+                self.ok(0, SERVER_STATUS_AUTOCOMMIT)?;
+                self.writer.flush()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn ok(&mut self, affected_rows: u64, status: u16) -> io::Result<()> {
+        let mut packet = Packet::new();
+        packet.u8(0x00);
+        packet.lenenc_int(affected_rows);
+        packet.lenenc_int(0);
+        packet.u16(status);
+        packet.u16(0);
+        self.write_packet(packet)
+    }
+
+    fn eof(&mut self, status: u16) -> io::Result<()> {
+        let mut packet = Packet::new();
+        packet.u8(0xfe);
+        packet.u16(0);
+        packet.u16(status);
+        self.write_packet(packet)
+    }
+
+    fn err(&mut self, code: u16, state: &str, message: &str) -> io::Result<()> {
+        let mut packet = Packet::new();
+        packet.u8(0xff);
+        packet.u16(code);
+        packet.u8(b'#');
+        packet.bytes(state.as_bytes());
+        packet.bytes(message.as_bytes());
+        self.write_packet(packet)
+    }
+
+    fn query_error(&mut self, e: &Error) -> io::Result<()> {
+        let (code, state) = match e {
+            Error::Parse(_) => (ER_PARSE_ERROR, "42000"),
+            Error::Binding(_) => (ER_BAD_FIELD_ERROR, "42S22"),
+            Error::Type(_) => (ER_TRUNCATED_WRONG_VALUE_FOR_FIELD, "HY000"),
+            Error::DivisionByZero => (ER_DIVISION_BY_ZERO, "22012"),
+            Error::OutOfRange(_) => (ER_DATA_OUT_OF_RANGE, "22003"),
+            Error::Constraint(_) => (ER_DUP_ENTRY, "23000"),
+            Error::Io(_) => (ER_UNKNOWN_ERROR, "HY000"),
+        };
+        self.err(code, state, &e.to_string())
+    }
+
+    fn column_definition(&mut self, column: &ResultColumn) -> io::Result<()> {
+        let mut flags = match column.datatype {
+            DataType::UnsignedSmallInt | DataType::UnsignedInt | DataType::UnsignedBigInt => {
+                NUM_FLAG | BINARY_FLAG | UNSIGNED_FLAG
+            }
+            DataType::SmallInt
+            | DataType::Int
+            | DataType::BigInt
+            | DataType::Float
+            | DataType::Double
+            | DataType::Decimal(..)
+            | DataType::Bool => NUM_FLAG | BINARY_FLAG,
+            DataType::Date | DataType::Time | DataType::Timestamp | DataType::TimestampTz => {
+                BINARY_FLAG
+            }
+            DataType::Binary | DataType::Json => BLOB_FLAG | BINARY_FLAG,
+            DataType::Enum(_) => ENUM_FLAG,
+            DataType::Str
+            | DataType::Interval
+            | DataType::Uuid
+            | DataType::Array(_)
+            | DataType::Invalid => 0,
+        };
+        if let Some(table) = &column.table {
+            let db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+            let header = db
+                .get_table(table.to_string())
+                .and_then(|t| t.get_column(column.name.to_string()));
+            if let Ok(header) = header {
+                if header.is_primary_key {
+                    flags |= PRI_KEY_FLAG;
+                }
+                if !header.is_nullable {
+                    flags |= NOT_NULL_FLAG;
+                }
+            }
+        }
+
+        let table = column.table.clone().unwrap_or_default();
+        let mut packet = Packet::new();
+        packet.lenenc_str(b"def");
+        packet.lenenc_str(b"");
+        packet.lenenc_str(table.as_bytes());
+        packet.lenenc_str(table.as_bytes());
+        packet.lenenc_str(column.name.as_bytes());
+        packet.lenenc_str(column.name.as_bytes());
+        packet.lenenc_int(0x0c);
+        packet.u16(match column.datatype {
+            DataType::Str | DataType::Interval | DataType::Invalid => UTF8_CHARSET,
+            _ => BINARY_CHARSET,
+        });
+        packet.u32(column_length(&column.datatype));
+        packet.u8(column_type(&column.datatype));
+        packet.u16(flags);
+        packet.u8(match column.datatype {
+            DataType::Float | DataType::Double => 31,
+            DataType::Decimal(_, scale) => scale,
+            _ => 0,
+        });
+        packet.u16(0);
+        self.write_packet(packet)
+    }
+
+    fn parameter_definition(&mut self) -> io::Result<()> {
+        let mut packet = Packet::new();
+        packet.lenenc_str(b"def");
+        for _ in 0..4 {
+            packet.lenenc_str(b"");
+        }
+        packet.lenenc_str(b"?");
+        packet.lenenc_int(0x0c);
+        packet.u16(BINARY_CHARSET);
+        packet.u32(0);
+        packet.u8(MYSQL_TYPE_VAR_STRING);
+        packet.u16(0);
+        packet.u8(0);
+        packet.u16(0);
+        self.write_packet(packet)
+    }
+
+    /// Answers the statements that clients use to set up their session,
+    /// which never reach the database.
+    fn session_outcome(&self, statement: &Statement) -> Option<Outcome> {
+        let Statement::Sql(statement) = statement else {
+            return None;
+        };
+        match &**statement {
+            ast::Statement::SetVariable { .. }
+            | ast::Statement::SetNames { .. }
+            | ast::Statement::SetNamesDefault {}
+            | ast::Statement::SetTransaction { .. }
+            | ast::Statement::StartTransaction { .. }
+            | ast::Statement::Commit { .. }
+            | ast::Statement::Rollback { .. }
+            | ast::Statement::Use { .. } => Some(Outcome::Ok(0)),
+            ast::Statement::ShowTables { .. } => {
+                let db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+                Some(Outcome::Rows(ResultSet {
+                    columns: vec![ResultColumn {
+                        name: "Tables".to_string(),
+                        datatype: DataType::Str,
+                        table: None,
+                    }],
+                    rows: db
+                        .tables
+                        .iter()
+                        .map(|t| vec![Value::Str(t.name.to_string())])
+                        .collect(),
+                }))
+            }
+            _ => system_variables_query(statement),
+        }
+    }
+
+    fn run_statement(&self, statement: &Statement) -> Result<Outcome> {
+        if let Some(outcome) = self.session_outcome(statement) {
+            return Ok(outcome);
+        }
+        let mut db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+        db.execute_statement(statement).map(Outcome::from)
+    }
+
+    fn query(&mut self, query: &str) -> io::Result<()> {
+        let statements = match parse_with_dialect(&MySqlDialect {}, query) {
+            Ok(statements) => statements,
+            Err(e) => return self.query_error(&e),
+        };
+        if statements.is_empty() {
+            return self.err(ER_EMPTY_QUERY, "42000", "Query was empty");
+        }
+
+        for (i, statement) in statements.iter().enumerate() {
+            let mut status = SERVER_STATUS_AUTOCOMMIT;
+            if i + 1 < statements.len() {
+                status |= SERVER_MORE_RESULTS_EXISTS;
+            }
+            match self.run_statement(statement) {
+                Ok(outcome) => self.send_outcome(outcome, status, false)?,
+                Err(e) => return self.query_error(&e),
+            }
+        }
+        Ok(())
+    }
+
+    fn send_outcome(&mut self, outcome: Outcome, status: u16, binary: bool) -> io::Result<()> {
+        let ResultSet { columns, rows } = match outcome {
+            Outcome::Ok(affected) => return self.ok(affected, status),
+            Outcome::Rows(rs) => rs,
+        };
+
+        let mut count = Packet::new();
+        count.lenenc_int(columns.len() as u64);
+        self.write_packet(count)?;
+        for column in &columns {
+            self.column_definition(column)?;
+        }
+        self.eof(status)?;
+
+        for row in rows {
+            let mut packet = Packet::new();
+            if binary {
+                packet.u8(0x00);
+                let mut null_bitmap = vec![0u8; (columns.len() + 7 + 2) / 8];
+                for (i, value) in row.iter().enumerate() {
+                    if value.is_null() {
+                        null_bitmap[(i + 2) / 8] |= 1 << ((i + 2) % 8);
+                    }
+                }
+                packet.bytes(&null_bitmap);
+                for value in &row {
+                    binary_value(&mut packet, value);
+                }
+            } else {
+                for value in &row {
+                    match value {
+                        Value::Null => packet.u8(0xfb),
+                        Value::Binary(b) => packet.lenenc_str(b),
+                        value => packet.lenenc_str(text_value(value).as_bytes()),
+                    }
+                }
+            }
+            self.write_packet(packet)?;
+        }
+        self.eof(status)
+    }
+
+    fn prepare(&mut self, query: &str) -> io::Result<()> {
+        let mut statements = match parse_with_dialect(&MySqlDialect {}, query) {
+            Ok(statements) => statements,
+            Err(e) => return self.query_error(&e),
+        };
+        let statement = match statements.pop() {
+            Some(_) if !statements.is_empty() => {
+                return self.err(
+                    ER_UNKNOWN_ERROR,
+                    "HY000",
+                    "Cannot prepare multiple statements at once",
+                )
+            }
+            Some(statement) => statement,
+            None => return self.err(ER_EMPTY_QUERY, "42000", "Query was empty"),
+        };
+
+        let (prepared, columns) = match self.session_outcome(&statement) {
+            Some(Outcome::Rows(rs)) => (None, rs.columns),
+            Some(Outcome::Ok(_)) => (None, vec![]),
+            None => {
+                let prepared = {
+                    let mut db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+                    db.prepare_statement(&statement)
+                };
+                match prepared {
+                    Ok(prepared) => {
+                        let columns = prepared.columns().unwrap_or_default().to_vec();
+                        (Some(prepared), columns)
+                    }
+                    Err(e) => return self.query_error(&e),
+                }
+            }
+        };
+        let num_params = prepared.as_ref().map_or(0, |p| p.param_count());
+
+        let id = self.next_statement_id;
+        self.next_statement_id += 1;
+        self.statements.insert(
+            id,
+            ServerStatement {
+                statement,
+                prepared,
+                param_types: vec![],
+            },
+        );
+
+        let mut packet = Packet::new();
+        packet.u8(0x00);
+        packet.u32(id);
+        packet.u16(columns.len() as u16);
+        packet.u16(num_params as u16);
+        packet.u8(0);
+        packet.u16(0);
+        self.write_packet(packet)?;
+
+        if num_params > 0 {
+            for _ in 0..num_params {
+                self.parameter_definition()?;
+            }
+            self.eof(SERVER_STATUS_AUTOCOMMIT)?;
+        }
+        if !columns.is_empty() {
+            for column in &columns {
+                self.column_definition(column)?;
+            }
+            self.eof(SERVER_STATUS_AUTOCOMMIT)?;
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, reader: &mut PacketReader) -> io::Result<()> {
+        let id = reader.u32()?;
+        let _flags = reader.u8()?;
+        let _iterations = reader.u32()?;
+
+        let statement = match self.statements.get_mut(&id) {
+            Some(statement) => statement,
+            None => {
+                return self.err(
+                    ER_UNKNOWN_STMT_HANDLER,
+                    "HY000",
+                    &format!(
+                        "Unknown prepared statement handler ({id}) given to mysqld_stmt_execute"
+                    ),
+                )
+            }
+        };
+
+        let num_params = statement.prepared.as_ref().map_or(0, |p| p.param_count());
+        let mut params = vec![];
+        if num_params > 0 {
+            let null_bitmap = reader.take(num_params.div_ceil(8))?.to_vec();
+            if reader.u8()? == 1 {
+                statement.param_types = (0..num_params)
+                    .map(|_| Ok((reader.u8()?, reader.u8()? & 0x80 != 0)))
+                    .collect::<io::Result<Vec<(u8, bool)>>>()?;
+            }
+            for i in 0..num_params {
+                if null_bitmap[i / 8] & (1 << (i % 8)) != 0 {
+                    params.push(Value::Null);
+                    continue;
+                }
+                let (param_type, unsigned) = statement
+                    .param_types
+                    .get(i)
+                    .copied()
+                    .unwrap_or((MYSQL_TYPE_VAR_STRING, false));
+                match param_type {
+                    MYSQL_TYPE_NULL => params.push(Value::Null),
+                    _ => params.push(decode_parameter(reader, param_type, unsigned)?),
+                }
+            }
+        }
+
+        let outcome = match &self.statements[&id] {
+            ServerStatement {
+                prepared: Some(prepared),
+                ..
+            } => prepared.bind(&params).and_then(|bound| {
+                let mut db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+                db.execute_bound(&bound).map(Outcome::from)
+            }),
+            ServerStatement { statement, .. } => {
+                Ok(self.session_outcome(statement).unwrap_or(Outcome::Ok(0)))
+            }
+        };
+        match outcome {
+            Ok(outcome) => self.send_outcome(outcome, SERVER_STATUS_AUTOCOMMIT, true),
+            Err(e) => self.query_error(&e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tests_length_encoded_integers_round_trip() {
+        for v in [
+            0u64,
+            250,
+            251,
+            0xffff,
+            0x10000,
+            0xff_ffff,
+            0x100_0000,
+            u64::MAX,
+        ] {
+            let mut packet = Packet::new();
+            packet.lenenc_int(v);
+            let mut reader = PacketReader::new(&packet.0);
+            assert_eq!(reader.lenenc_int().unwrap(), v);
+            assert!(reader.rest().is_empty());
+        }
+    }
+
+    #[test]
+    fn tests_decoding_binary_parameters() {
+        let datetime = [7u8, 0xe6, 0x07, 12, 28, 13, 5, 9];
+        let mut reader = PacketReader::new(&datetime);
+        assert_eq!(
+            decode_parameter(&mut reader, MYSQL_TYPE_DATETIME, false).unwrap(),
+            Value::Str("2022-12-28 13:05:09".to_string())
+        );
+
+        let negative = (-5i32).to_le_bytes();
+        let mut reader = PacketReader::new(&negative);
+        assert_eq!(
+            decode_parameter(&mut reader, MYSQL_TYPE_LONG, false).unwrap(),
+            Value::Int(-5)
+        );
+
+        // A string claiming more bytes than the packet holds, up to the
+        // largest length a 0xfe prefix can give, is malformed.
+        let mut truncated = vec![5u8];
+        truncated.extend(b"abc");
+        let mut oversized = vec![0xfeu8];
+        oversized.extend(u64::MAX.to_le_bytes());
+        oversized.extend(b"abc");
+        for packet in [truncated, oversized] {
+            let mut reader = PacketReader::new(&packet);
+            let error = decode_parameter(&mut reader, MYSQL_TYPE_VAR_STRING, false).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,1296 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use sqlparser::ast;
+use sqlparser::dialect::PostgreSqlDialect;
+
+use crate::database::Database;
+use crate::error::{Error, Result};
+use crate::parser::{parse_with_dialect, Statement};
+use crate::prepared::{BoundStatement, PreparedStatement};
+use crate::result::{QueryResult, ResultColumn};
+use crate::table::DataType;
+use crate::value::datetime::MICROS_PER_DAY;
+use crate::value::decimal::Rounding;
+use crate::value::{array, from_hex, uuid, Decimal, Interval, Value};
+
+const PROTOCOL_VERSION: i32 = 196608;
+const SSL_REQUEST: i32 = 80877103;
+const GSSENC_REQUEST: i32 = 80877104;
+const CANCEL_REQUEST: i32 = 80877102;
+
+const TEXT_OID: i32 = 25;
+const NUMERIC_OID: i32 = 1700;
+const BYTEA_OID: i32 = 17;
+const JSON_OID: i32 = 114;
+const JSONB_OID: i32 = 3802;
+const UUID_OID: i32 = 2950;
+const TEXT_ARRAY_OID: i32 = 1009;
+/// The OID of each type with the OID of an array of it.
+const ARRAY_OIDS: &[(i32, i32)] = &[
+    (16, 1000),
+    (BYTEA_OID, 1001),
+    (20, 1016),
+    (21, 1005),
+    (23, 1007),
+    (TEXT_OID, TEXT_ARRAY_OID),
+    (JSON_OID, 199),
+    (700, 1021),
+    (701, 1022),
+    (1082, 1182),
+    (1083, 1183),
+    (1114, 1115),
+    (1184, 1185),
+    (1186, 1187),
+    (NUMERIC_OID, 1231),
+    (UUID_OID, 2951),
+];
+const NUMERIC_NEGATIVE: u16 = 0x4000;
+/// Binary dates and timestamps count from 2000-01-01, which is this many
+/// days after 1970-01-01.
+const POSTGRES_EPOCH_DAYS: i32 = 10957;
+
+const PROTOCOL_VIOLATION: &str = "08P01";
+const FEATURE_NOT_SUPPORTED: &str = "0A000";
+
+/// The longest message a client may send, as in Postgres, so a bad length
+/// can't make the server allocate gigabytes.
+const MAX_MESSAGE_LEN: i32 = 1 << 30;
+
+static NEXT_BACKEND_ID: AtomicI32 = AtomicI32::new(1);
+
+pub fn serve(addr: &str, db: Arc<Mutex<Database>>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!(
+        "Listening for PostgreSQL connections on {}",
+        listener.local_addr()?
+    );
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let db = Arc::clone(&db);
+                thread::spawn(move || {
+                    if let Err(e) = Session::new(stream, db).and_then(|mut s| s.run()) {
+                        eprintln!("PostgreSQL connection closed with error: {e}");
+                    }
+                });
+            }
+            Err(e) => eprintln!("Could not accept connection: {e}"),
+        }
+    }
+    Ok(())
+}
+
+pub fn type_oid(datatype: &DataType) -> i32 {
+    match datatype {
+        DataType::SmallInt => 21,
+        DataType::Int | DataType::UnsignedSmallInt => 23,
+        DataType::BigInt | DataType::UnsignedInt => 20,
+        DataType::UnsignedBigInt | DataType::Decimal(..) => NUMERIC_OID,
+        DataType::Float => 700,
+        DataType::Double => 701,
+        DataType::Str | DataType::Enum(_) => TEXT_OID,
+        DataType::Bool => 16,
+        DataType::Date => 1082,
+        DataType::Time => 1083,
+        DataType::Timestamp => 1114,
+        DataType::TimestampTz => 1184,
+        DataType::Interval => 1186,
+        DataType::Binary => BYTEA_OID,
+        DataType::Json => JSON_OID,
+        DataType::Uuid => UUID_OID,
+        // Nested arrays have the type of a flat array of their items.
+        DataType::Array(item) => match &**item {
+            DataType::Array(_) => type_oid(item),
+            item => ARRAY_OIDS
+                .iter()
+                .find(|(oid, _)| *oid == type_oid(item))
+                .map_or(TEXT_ARRAY_OID, |(_, array)| *array),
+        },
+        DataType::Invalid => 705,
+    }
+}
+
+fn type_size(datatype: &DataType) -> i16 {
+    match datatype {
+        DataType::SmallInt => 2,
+        DataType::Int | DataType::UnsignedSmallInt | DataType::Float | DataType::Date => 4,
+        DataType::BigInt | DataType::UnsignedInt | DataType::Double => 8,
+        DataType::Time | DataType::Timestamp | DataType::TimestampTz => 8,
+        DataType::Interval | DataType::Uuid => 16,
+        DataType::Bool => 1,
+        DataType::Str
+        | DataType::Enum(_)
+        | DataType::UnsignedBigInt
+        | DataType::Decimal(..)
+        | DataType::Binary
+        | DataType::Json
+        | DataType::Array(_)
+        | DataType::Invalid => -1,
+    }
+}
+
+fn encode_value(value: &Value, binary: bool) -> Option<Vec<u8>> {
+    let encoded = match (value, binary) {
+        (Value::Null, _) => return None,
+        (Value::Bool(v), false) => match v {
+            true => b"t".to_vec(),
+            false => b"f".to_vec(),
+        },
+        (Value::Bool(v), true) => vec![*v as u8],
+        (Value::SmallInt(v), true) => v.to_be_bytes().to_vec(),
+        (Value::Int(v), true) => v.to_be_bytes().to_vec(),
+        (Value::BigInt(v), true) => v.to_be_bytes().to_vec(),
+        // Unsigned integers are sent as the next wider signed type.
+        (Value::UnsignedSmallInt(v), true) => (*v as i32).to_be_bytes().to_vec(),
+        (Value::UnsignedInt(v), true) => (*v as i64).to_be_bytes().to_vec(),
+        (Value::UnsignedBigInt(v), true) => numeric_bytes(&v.to_string()),
+        (Value::Decimal(v), true) => numeric_bytes(&v.to_string()),
+        (Value::Float(v), true) => v.to_be_bytes().to_vec(),
+        (Value::Double(v), true) => v.to_be_bytes().to_vec(),
+        (Value::Date(v), true) => (v - POSTGRES_EPOCH_DAYS).to_be_bytes().to_vec(),
+        (Value::Time(v), true) => v.to_be_bytes().to_vec(),
+        (Value::Timestamp(v) | Value::TimestampTz(v), true) => (v - POSTGRES_EPOCH_DAYS as i64
+            * MICROS_PER_DAY)
+            .to_be_bytes()
+            .to_vec(),
+        (Value::Interval(v), true) => [
+            &v.micros.to_be_bytes()[..],
+            &v.days.to_be_bytes(),
+            &v.months.to_be_bytes(),
+        ]
+        .concat(),
+        (Value::Binary(v), true) => v.clone(),
+        (Value::Uuid(v), true) => v.to_be_bytes().to_vec(),
+        (Value::Array(items), true) => array_bytes(items),
+        (v, _) => v.to_string().into_bytes(),
+    };
+    Some(encoded)
+}
+
+/// The binary form of a `numeric` written as `text`: its base 10000 digits,
+/// most significant first, after a header of the digit count, the weight of
+/// the first digit, the sign and the display scale.
+fn numeric_bytes(text: &str) -> Vec<u8> {
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => (NUMERIC_NEGATIVE, text),
+        None => (0, text),
+    };
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    // Both sides of the point are padded to whole base 10000 digits.
+    let whole = format!("{}{whole}", "0".repeat((4 - whole.len() % 4) % 4));
+    let padded = format!("{fraction}{}", "0".repeat((4 - fraction.len() % 4) % 4));
+    let mut digits: VecDeque<i16> = whole
+        .as_bytes()
+        .chunks(4)
+        .chain(padded.as_bytes().chunks(4))
+        .map(|c| c.iter().fold(0, |n, b| n * 10 + (b - b'0') as i16))
+        .collect();
+    let mut weight = (whole.len() / 4) as i16 - 1;
+    // Leading and trailing zero digits are implied by the weight.
+    while digits.front() == Some(&0) {
+        digits.pop_front();
+        weight -= 1;
+    }
+    while digits.back() == Some(&0) {
+        digits.pop_back();
+    }
+    if digits.is_empty() {
+        weight = 0;
+    }
+    let mut bytes = vec![];
+    bytes.extend((digits.len() as i16).to_be_bytes());
+    bytes.extend(weight.to_be_bytes());
+    bytes.extend(sign.to_be_bytes());
+    bytes.extend((fraction.len() as u16).to_be_bytes());
+    for digit in digits {
+        bytes.extend(digit.to_be_bytes());
+    }
+    bytes
+}
+
+/// The binary form of an array: the number of dimensions, whether any
+/// item is NULL and the OID of the items, then the length and lower bound
+/// of each dimension, and every item after its length, or -1 for a NULL.
+fn array_bytes(items: &[Value]) -> Vec<u8> {
+    let mut dims = vec![];
+    let mut level = items;
+    while !level.is_empty() {
+        dims.push(level.len() as i32);
+        match &level[0] {
+            Value::Array(inner) => level = inner,
+            _ => break,
+        }
+    }
+    let mut leaves = vec![];
+    array::flatten(items, &mut leaves);
+    let oid = leaves
+        .iter()
+        .find_map(|v| v.datatype())
+        .map_or(TEXT_OID, |d| type_oid(&d));
+
+    let mut bytes = vec![];
+    bytes.extend((dims.len() as i32).to_be_bytes());
+    bytes.extend((leaves.iter().any(|v| v.is_null()) as i32).to_be_bytes());
+    bytes.extend(oid.to_be_bytes());
+    for len in dims {
+        bytes.extend(len.to_be_bytes());
+        bytes.extend(1i32.to_be_bytes());
+    }
+    for leaf in leaves {
+        match encode_value(leaf, true) {
+            Some(item) => {
+                bytes.extend((item.len() as i32).to_be_bytes());
+                bytes.extend(item);
+            }
+            None => bytes.extend((-1i32).to_be_bytes()),
+        }
+    }
+    bytes
+}
+
+/// Reads the binary form of an array written by `array_bytes`.
+fn array_from_bytes(bytes: &[u8]) -> Result<Value> {
+    let invalid = || Error::Type("Invalid parameter value for an array".to_string());
+    let int = |at: usize| {
+        bytes
+            .get(at..at + 4)
+            .map(|b| i32::from_be_bytes(b.try_into().unwrap()))
+            .ok_or_else(invalid)
+    };
+    let dims = (0..int(0)?.max(0) as usize)
+        .map(|d| Ok(int(12 + d * 8)?.max(0) as usize))
+        .collect::<Result<Vec<_>>>()?;
+    let (oid, mut at) = (int(8)?, 12 + dims.len() * 8);
+    if dims.is_empty() || dims.contains(&0) {
+        return Ok(Value::Array(vec![]));
+    }
+    // Every item takes at least the 4 bytes of its length, so a count that
+    // doesn't fit in what is left of the message is rejected up front.
+    let count = dims
+        .iter()
+        .try_fold(1usize, |count, len| count.checked_mul(*len))
+        .filter(|count| *count <= bytes.len().saturating_sub(at) / 4)
+        .ok_or_else(invalid)?;
+    let mut items = vec![];
+    for _ in 0..count {
+        let len = int(at)?;
+        at += 4;
+        if len < 0 {
+            items.push(Value::Null);
+            continue;
+        }
+        let item = bytes.get(at..at + len as usize).ok_or_else(invalid)?;
+        items.push(decode_parameter(item, oid, true)?);
+        at += len as usize;
+    }
+    // The innermost dimension varies fastest.
+    for len in dims[1..].iter().rev() {
+        items = items
+            .chunks(*len)
+            .map(|chunk| Value::Array(chunk.to_vec()))
+            .collect();
+    }
+    Ok(Value::Array(items))
+}
+
+/// Reads the binary form of a `numeric` written by `numeric_bytes`.
+fn numeric_from_bytes(bytes: &[u8]) -> Option<Decimal> {
+    let field = |i: usize| Some(u16::from_be_bytes(bytes.get(i..i + 2)?.try_into().ok()?));
+    let (count, weight, sign, scale) = (field(0)?, field(2)? as i16, field(4)?, field(6)?);
+    let mut text = String::new();
+    for i in 0..count as usize {
+        text.push_str(&format!("{:04}", field(8 + i * 2)?));
+    }
+    // The last digit read is worth 10000^(weight - count + 1).
+    let exponent = (weight as i32 - count as i32 + 1) * 4;
+    let sign = if sign == NUMERIC_NEGATIVE { "-" } else { "" };
+    let decimal = Decimal::parse(&format!("{sign}0{text}e{exponent}"))?;
+    decimal.rescale(scale.try_into().ok()?, Rounding::HalfUp)
+}
+
+fn decode_parameter(value: &[u8], oid: i32, binary: bool) -> Result<Value> {
+    let is_array = ARRAY_OIDS.iter().any(|(_, array)| *array == oid);
+    if binary && is_array {
+        return array_from_bytes(value);
+    }
+    if binary {
+        let parsed = match (oid, value.len()) {
+            (16, 1) => Some(Value::Bool(value[0] != 0)),
+            (21, 2) => Some(Value::SmallInt(i16::from_be_bytes([value[0], value[1]]))),
+            (23, 4) => Some(Value::Int(i32::from_be_bytes(value.try_into().unwrap()))),
+            (20, 8) => Some(Value::BigInt(i64::from_be_bytes(value.try_into().unwrap()))),
+            (700, 4) => Some(Value::Float(f32::from_be_bytes(value.try_into().unwrap()))),
+            (1082, 4) => Some(Value::Date(
+                i32::from_be_bytes(value.try_into().unwrap()) + POSTGRES_EPOCH_DAYS,
+            )),
+            (1083, 8) => Some(Value::Time(i64::from_be_bytes(value.try_into().unwrap()))),
+            (1114 | 1184, 8) => {
+                let v = i64::from_be_bytes(value.try_into().unwrap())
+                    + POSTGRES_EPOCH_DAYS as i64 * MICROS_PER_DAY;
+                Some(match oid {
+                    1114 => Value::Timestamp(v),
+                    _ => Value::TimestampTz(v),
+                })
+            }
+            (1186, 16) => Some(Value::Interval(Interval::new(
+                i32::from_be_bytes(value[12..].try_into().unwrap()),
+                i32::from_be_bytes(value[8..12].try_into().unwrap()),
+                i64::from_be_bytes(value[..8].try_into().unwrap()),
+            ))),
+            (701, 8) => Some(Value::Double(f64::from_be_bytes(value.try_into().unwrap()))),
+            (NUMERIC_OID, _) => numeric_from_bytes(value).map(Value::Decimal),
+            (BYTEA_OID, _) => Some(Value::Binary(value.to_vec())),
+            (UUID_OID, 16) => Some(Value::Uuid(u128::from_be_bytes(value.try_into().unwrap()))),
+            // Binary jsonb is its text after a version byte of 1.
+            (JSONB_OID, _) if value.first() == Some(&1) => {
+                return decode_parameter(&value[1..], JSON_OID, false)
+            }
+            _ => None,
+        };
+        if let Some(parsed) = parsed {
+            return Ok(parsed);
+        }
+    }
+    if oid == BYTEA_OID {
+        return match value.strip_prefix(b"\\x") {
+            Some(hex) => std::str::from_utf8(hex)
+                .ok()
+                .and_then(from_hex)
+                .map(Value::Binary)
+                .ok_or_else(|| Error::Type("Invalid parameter value for type bytea".to_string())),
+            None => Ok(Value::Binary(value.to_vec())),
+        };
+    }
+
+    let text = String::from_utf8(value.to_vec())
+        .map_err(|_| Error::Type("Parameter value is not valid UTF-8".to_string()))?;
+    let invalid = || Error::Type(format!("Invalid parameter value {text} for type {oid}"));
+    match oid {
+        16 => match text.as_ref() {
+            "t" | "true" | "1" | "on" | "yes" => Ok(Value::Bool(true)),
+            "f" | "false" | "0" | "off" | "no" => Ok(Value::Bool(false)),
+            _ => Err(invalid()),
+        },
+        21 => text
+            .trim()
+            .parse()
+            .map(Value::SmallInt)
+            .map_err(|_| invalid()),
+        23 => text.trim().parse().map(Value::Int).map_err(|_| invalid()),
+        20 => text
+            .trim()
+            .parse()
+            .map(Value::BigInt)
+            .map_err(|_| invalid()),
+        700 => text.trim().parse().map(Value::Float).map_err(|_| invalid()),
+        701 => text
+            .trim()
+            .parse()
+            .map(Value::Double)
+            .map_err(|_| invalid()),
+        NUMERIC_OID => Decimal::parse(&text)
+            .map(Value::Decimal)
+            .ok_or_else(invalid),
+        UUID_OID => uuid::parse(&text).map(Value::Uuid).ok_or_else(invalid),
+        // The items are read as text, and converted once the type they are
+        // bound as is known.
+        _ if is_array => array::parse(&text).map(Value::Array).ok_or_else(invalid),
+        _ => Ok(Value::Str(text)),
+    }
+}
+
+fn command_tag(result: &QueryResult) -> String {
+    match result {
+        QueryResult::Rows(rs) => format!("SELECT {}", rs.rows.len()),
+        QueryResult::Inserted(count) => format!("INSERT 0 {count}"),
+        QueryResult::Created => "CREATE TABLE".to_string(),
+        QueryResult::CreatedType => "CREATE TYPE".to_string(),
+        QueryResult::AlteredType => "ALTER TYPE".to_string(),
+    }
+}
+
+/// Statements clients send as part of their normal session setup that have
+/// no meaning here. They are acknowledged so that drivers keep working.
+fn noop_tag(statement: &Statement) -> Option<&'static str> {
+    let Statement::Sql(statement) = statement else {
+        return None;
+    };
+    match &**statement {
+        ast::Statement::StartTransaction { .. } => Some("BEGIN"),
+        ast::Statement::Commit { .. } => Some("COMMIT"),
+        ast::Statement::Rollback { .. } => Some("ROLLBACK"),
+        ast::Statement::SetVariable { .. }
+        | ast::Statement::SetTimeZone { .. }
+        | ast::Statement::SetNames { .. }
+        | ast::Statement::SetNamesDefault {}
+        | ast::Statement::SetTransaction { .. } => Some("SET"),
+        _ => None,
+    }
+}
+
+struct MessageBody(Vec<u8>);
+
+impl MessageBody {
+    fn new() -> MessageBody {
+        MessageBody(vec![])
+    }
+
+    fn byte(mut self, v: u8) -> Self {
+        self.0.push(v);
+        self
+    }
+
+    fn int16(mut self, v: i16) -> Self {
+        self.0.extend_from_slice(&v.to_be_bytes());
+        self
+    }
+
+    fn int32(mut self, v: i32) -> Self {
+        self.0.extend_from_slice(&v.to_be_bytes());
+        self
+    }
+
+    fn cstr(mut self, v: &str) -> Self {
+        self.0.extend_from_slice(v.as_bytes());
+        self.0.push(0);
+        self
+    }
+
+    fn bytes(mut self, v: &[u8]) -> Self {
+        self.0.extend_from_slice(v);
+        self
+    }
+}
+
+struct MessageReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MessageReader<'a> {
+    fn new(buf: &'a [u8]) -> MessageReader<'a> {
+        MessageReader { buf, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|end| *end <= self.buf.len())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Message is shorter than expected",
+                )
+            })?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn int16(&mut self) -> io::Result<i16> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn int32(&mut self) -> io::Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn cstr(&mut self) -> io::Result<String> {
+        let rest = &self.buf[self.pos..];
+        match rest.iter().position(|b| *b == 0) {
+            Some(end) => {
+                let s = String::from_utf8_lossy(&rest[..end]).to_string();
+                self.pos += end + 1;
+                Ok(s)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Unterminated string in message",
+            )),
+        }
+    }
+}
+
+/// What a Parse message turned into. Session statements like `BEGIN` are
+/// acknowledged without ever reaching the database.
+enum Prepared {
+    Plan(PreparedStatement),
+    Noop(&'static str),
+    Empty,
+}
+
+struct ParsedStatement {
+    prepared: Prepared,
+    param_types: Vec<i32>,
+}
+
+enum Bound {
+    Plan(BoundStatement),
+    Noop(&'static str),
+    Empty,
+}
+
+struct Portal {
+    bound: Bound,
+    result_formats: Vec<i16>,
+    pending: Option<PendingRows>,
+}
+
+struct PendingRows {
+    rows: VecDeque<Vec<Value>>,
+    sent: usize,
+}
+
+struct Session {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    db: Arc<Mutex<Database>>,
+    statements: HashMap<String, ParsedStatement>,
+    portals: HashMap<String, Portal>,
+    ignore_until_sync: bool,
+}
+
+impl Session {
+    fn new(stream: TcpStream, db: Arc<Mutex<Database>>) -> io::Result<Session> {
+        Ok(Session {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            db,
+            statements: HashMap::new(),
+            portals: HashMap::new(),
+            ignore_until_sync: false,
+        })
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        if !self.startup()? {
+            return Ok(());
+        }
+
+        loop {
+            let mut tag = [0u8; 1];
+            if self.reader.read_exact(&mut tag).is_err() {
+                return Ok(());
+            }
+            let body = self.read_body()?;
+            let mut msg = MessageReader::new(&body);
+
+            if self.ignore_until_sync && !matches!(tag[0], b'S' | b'X') {
+                continue;
+            }
+
+            match tag[0] {
+                b'Q' => {
+                    let query = msg.cstr()?;
+                    self.simple_query(&query)?;
+                }
+                b'P' => {
+                    let name = msg.cstr()?;
+                    let query = msg.cstr()?;
+                    let count = msg.int16()?;
+                    let mut param_types = vec![];
+                    for _ in 0..count {
+                        param_types.push(msg.int32()?);
+                    }
+                    if let Err(e) = self.parse(name, &query, param_types)? {
+                        self.extended_error(&e)?;
+                    }
+                }
+                b'B' => {
+                    if let Err(e) = self.bind(&mut msg)? {
+                        self.extended_error(&e)?;
+                    }
+                }
+                b'D' => {
+                    let kind = msg.byte()?;
+                    let name = msg.cstr()?;
+                    if let Err(e) = self.describe(kind, &name)? {
+                        self.extended_error(&e)?;
+                    }
+                }
+                b'E' => {
+                    let portal = msg.cstr()?;
+                    let max_rows = msg.int32()?;
+                    if let Err(e) = self.execute(&portal, max_rows)? {
+                        self.extended_error(&e)?;
+                    }
+                }
+                b'C' => {
+                    let kind = msg.byte()?;
+                    let name = msg.cstr()?;
+                    match kind {
+                        b'S' => self.statements.remove(&name).map(|_| ()),
+                        _ => self.portals.remove(&name).map(|_| ()),
+                    };
+                    self.send(b'3', MessageBody::new())?;
+                }
+                b'S' => {
+                    self.ignore_until_sync = false;
+                    self.portals.clear();
+                    self.ready_for_query()?;
+                }
+                b'H' => self.writer.flush()?,
+                b'X' => return Ok(()),
+                other => {
+                    self.error_response(
+                        PROTOCOL_VIOLATION,
+                        &format!("Unsupported protocol message '{}'", other as char),
+                    )?;
+                    self.ready_for_query()?;
+                }
+            }
+        }
+    }
+
+    fn read_body(&mut self) -> io::Result<Vec<u8>> {
+        let mut len = [0u8; 4];
+        self.reader.read_exact(&mut len)?;
+        let len = i32::from_be_bytes(len);
+        if !(4..=MAX_MESSAGE_LEN).contains(&len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid message length",
+            ));
+        }
+        let mut body = vec![0u8; len as usize - 4];
+        self.reader.read_exact(&mut body)?;
+        Ok(body)
+    }
+
+    fn startup(&mut self) -> io::Result<bool> {
+        loop {
+            let body = self.read_body()?;
+            let mut msg = MessageReader::new(&body);
+            match msg.int32()? {
+                SSL_REQUEST | GSSENC_REQUEST => {
+                    self.writer.write_all(b"N")?;
+                    self.writer.flush()?;
+                }
+                CANCEL_REQUEST => return Ok(false),
+                PROTOCOL_VERSION => break,
+                version => {
+                    self.error_response(
+                        FEATURE_NOT_SUPPORTED,
+                        &format!(
+                            "Unsupported frontend protocol {}.{}",
+                            version >> 16,
+                            version & 0xffff
+                        ),
+                    )?;
+                    self.writer.flush()?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        self.send(b'R', MessageBody::new().int32(0))?;
+        for (name, value) in [
+            ("server_version", "14.0"),
+            ("server_encoding", "UTF8"),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("TimeZone", "UTC"),
+            ("integer_datetimes", "on"),
+            ("standard_conforming_strings", "on"),
+        ] {
+            self.send(b'S', MessageBody::new().cstr(name).cstr(value))?;
+        }
+        let backend_id = NEXT_BACKEND_ID.fetch_add(1, Ordering::Relaxed);
+        self.send(b'K', MessageBody::new().int32(backend_id).int32(0))?;
+        self.ready_for_query()?;
+        Ok(true)
+    }
+
+    fn send(&mut self, tag: u8, body: MessageBody) -> io::Result<()> {
+        self.writer.write_all(&[tag])?;
+        self.writer
+            .write_all(&(body.0.len() as i32 + 4).to_be_bytes())?;
+        self.writer.write_all(&body.0)
+    }
+
+    fn ready_for_query(&mut self) -> io::Result<()> {
+        self.send(b'Z', MessageBody::new().byte(b'I'))?;
+        self.writer.flush()
+    }
+
+    fn error_response(&mut self, code: &str, message: &str) -> io::Result<()> {
+        let body = MessageBody::new()
+            .byte(b'S')
+            .cstr("ERROR")
+            .byte(b'V')
+            .cstr("ERROR")
+            .byte(b'C')
+            .cstr(code)
+            .byte(b'M')
+            .cstr(message)
+            .byte(0);
+        self.send(b'E', body)
+    }
+
+    fn query_error(&mut self, e: &Error) -> io::Result<()> {
+        self.error_response(e.sqlstate(), &e.to_string())
+    }
+
+    fn extended_error(&mut self, e: &Error) -> io::Result<()> {
+        self.ignore_until_sync = true;
+        self.query_error(e)
+    }
+
+    fn row_description(&mut self, columns: &[ResultColumn], formats: &[i16]) -> io::Result<()> {
+        let mut body = MessageBody::new().int16(columns.len() as i16);
+        for (i, col) in columns.iter().enumerate() {
+            body = body
+                .cstr(&col.name)
+                .int32(0)
+                .int16(0)
+                .int32(type_oid(&col.datatype))
+                .int16(type_size(&col.datatype))
+                .int32(-1)
+                .int16(Self::format_for(formats, i));
+        }
+        self.send(b'T', body)
+    }
+
+    fn data_row(&mut self, row: &[Value], formats: &[i16]) -> io::Result<()> {
+        let mut body = MessageBody::new().int16(row.len() as i16);
+        for (i, value) in row.iter().enumerate() {
+            match encode_value(value, Self::format_for(formats, i) == 1) {
+                Some(encoded) => body = body.int32(encoded.len() as i32).bytes(&encoded),
+                None => body = body.int32(-1),
+            }
+        }
+        self.send(b'D', body)
+    }
+
+    fn format_for(formats: &[i16], idx: usize) -> i16 {
+        match formats.len() {
+            0 => 0,
+            1 => formats[0],
+            _ => formats.get(idx).copied().unwrap_or(0),
+        }
+    }
+
+    fn simple_query(&mut self, query: &str) -> io::Result<()> {
+        let statements = match parse_with_dialect(&PostgreSqlDialect {}, query) {
+            Ok(statements) => statements,
+            Err(e) => {
+                self.query_error(&e)?;
+                return self.ready_for_query();
+            }
+        };
+
+        if statements.is_empty() {
+            self.send(b'I', MessageBody::new())?;
+        }
+
+        for statement in &statements {
+            if let Some(tag) = noop_tag(statement) {
+                self.send(b'C', MessageBody::new().cstr(tag))?;
+                continue;
+            }
+
+            let result = {
+                let mut db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+                db.execute_statement(statement)
+            };
+            match result {
+                Ok(result) => {
+                    if let QueryResult::Rows(rs) = &result {
+                        self.row_description(&rs.columns, &[])?;
+                        for row in &rs.rows {
+                            self.data_row(row, &[])?;
+                        }
+                    }
+                    self.send(b'C', MessageBody::new().cstr(&command_tag(&result)))?;
+                }
+                Err(e) => {
+                    self.query_error(&e)?;
+                    break;
+                }
+            }
+        }
+        self.ready_for_query()
+    }
+
+    fn parse(
+        &mut self,
+        name: String,
+        query: &str,
+        param_types: Vec<i32>,
+    ) -> io::Result<Result<()>> {
+        let mut statements = match parse_with_dialect(&PostgreSqlDialect {}, query) {
+            Ok(statements) => statements,
+            Err(e) => return Ok(Err(e)),
+        };
+        if statements.len() > 1 {
+            return Ok(Err(Error::Parse(
+                "Cannot insert multiple commands into a prepared statement".to_string(),
+            )));
+        }
+
+        let prepared = match statements.pop() {
+            None => Prepared::Empty,
+            Some(statement) => match noop_tag(&statement) {
+                Some(tag) => Prepared::Noop(tag),
+                None => {
+                    let mut db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+                    match db.prepare_statement(&statement) {
+                        Ok(prepared) => Prepared::Plan(prepared),
+                        Err(e) => return Ok(Err(e)),
+                    }
+                }
+            },
+        };
+
+        let inferred = match &prepared {
+            Prepared::Plan(prepared) => prepared.param_types(),
+            _ => &[],
+        };
+        let param_types = (0..inferred.len().max(param_types.len()))
+            .map(|i| match (param_types.get(i), inferred.get(i)) {
+                (Some(oid), _) if *oid != 0 => *oid,
+                (_, Some(Some(datatype))) => type_oid(datatype),
+                _ => TEXT_OID,
+            })
+            .collect();
+
+        self.statements.insert(
+            name,
+            ParsedStatement {
+                prepared,
+                param_types,
+            },
+        );
+        self.send(b'1', MessageBody::new())?;
+        Ok(Ok(()))
+    }
+
+    fn bind(&mut self, msg: &mut MessageReader) -> io::Result<Result<()>> {
+        let portal = msg.cstr()?;
+        let statement = msg.cstr()?;
+
+        let mut param_formats = vec![];
+        for _ in 0..msg.int16()? {
+            param_formats.push(msg.int16()?);
+        }
+        let mut raw_params = vec![];
+        for _ in 0..msg.int16()? {
+            let len = msg.int32()?;
+            match len {
+                -1 => raw_params.push(None),
+                len if len < -1 => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Invalid parameter length",
+                    ))
+                }
+                len => raw_params.push(Some(msg.take(len as usize)?.to_vec())),
+            }
+        }
+        let mut result_formats = vec![];
+        for _ in 0..msg.int16()? {
+            result_formats.push(msg.int16()?);
+        }
+
+        let parsed = match self.statements.get(&statement) {
+            Some(parsed) => parsed,
+            None => {
+                return Ok(Err(Error::Binding(format!(
+                    "Prepared statement \"{statement}\" does not exist"
+                ))))
+            }
+        };
+
+        let mut params = vec![];
+        for (i, raw) in raw_params.iter().enumerate() {
+            match raw {
+                Some(raw) => {
+                    let oid = parsed.param_types.get(i).copied().unwrap_or(0);
+                    let binary = Self::format_for(&param_formats, i) == 1;
+                    match decode_parameter(raw, oid, binary) {
+                        Ok(p) => params.push(p),
+                        Err(e) => return Ok(Err(e)),
+                    }
+                }
+                None => params.push(Value::Null),
+            }
+        }
+
+        let bound = match &parsed.prepared {
+            Prepared::Plan(prepared) => match prepared.bind(&params) {
+                Ok(bound) => Bound::Plan(bound),
+                Err(e) => return Ok(Err(e)),
+            },
+            Prepared::Noop(tag) => Bound::Noop(tag),
+            Prepared::Empty => Bound::Empty,
+        };
+
+        self.portals.insert(
+            portal,
+            Portal {
+                bound,
+                result_formats,
+                pending: None,
+            },
+        );
+        self.send(b'2', MessageBody::new())?;
+        Ok(Ok(()))
+    }
+
+    fn describe(&mut self, kind: u8, name: &str) -> io::Result<Result<()>> {
+        let (columns, formats) = if kind == b'S' {
+            let parsed = match self.statements.get(name) {
+                Some(parsed) => parsed,
+                None => {
+                    return Ok(Err(Error::Binding(format!(
+                        "Prepared statement \"{name}\" does not exist"
+                    ))))
+                }
+            };
+            let mut body = MessageBody::new().int16(parsed.param_types.len() as i16);
+            for oid in &parsed.param_types {
+                body = body.int32(*oid);
+            }
+            let columns = match &parsed.prepared {
+                Prepared::Plan(prepared) => prepared.columns().map(|c| c.to_vec()),
+                _ => None,
+            };
+            self.send(b't', body)?;
+            (columns, vec![])
+        } else {
+            match self.portals.get(name) {
+                Some(Portal {
+                    bound: Bound::Plan(bound),
+                    result_formats,
+                    ..
+                }) => (bound.columns().map(|c| c.to_vec()), result_formats.clone()),
+                Some(_) => (None, vec![]),
+                None => {
+                    return Ok(Err(Error::Binding(format!(
+                        "Portal \"{name}\" does not exist"
+                    ))))
+                }
+            }
+        };
+
+        match columns {
+            Some(columns) => self.row_description(&columns, &formats)?,
+            None => self.send(b'n', MessageBody::new())?,
+        }
+        Ok(Ok(()))
+    }
+
+    fn execute(&mut self, name: &str, max_rows: i32) -> io::Result<Result<()>> {
+        let mut portal = match self.portals.remove(name) {
+            Some(portal) => portal,
+            None => {
+                return Ok(Err(Error::Binding(format!(
+                    "Portal \"{name}\" does not exist"
+                ))))
+            }
+        };
+
+        if portal.pending.is_none() {
+            let bound = match &portal.bound {
+                Bound::Plan(bound) => bound,
+                Bound::Noop(tag) => {
+                    self.send(b'C', MessageBody::new().cstr(tag))?;
+                    return Ok(Ok(()));
+                }
+                Bound::Empty => {
+                    self.send(b'I', MessageBody::new())?;
+                    return Ok(Ok(()));
+                }
+            };
+
+            let result = {
+                let mut db = self.db.lock().unwrap_or_else(|e| e.into_inner());
+                db.execute_bound(bound)
+            };
+            match result {
+                Ok(QueryResult::Rows(rs)) => {
+                    portal.pending = Some(PendingRows {
+                        rows: rs.rows.into(),
+                        sent: 0,
+                    });
+                }
+                Ok(result) => {
+                    self.send(b'C', MessageBody::new().cstr(&command_tag(&result)))?;
+                    return Ok(Ok(()));
+                }
+                Err(e) => return Ok(Err(e)),
+            }
+        }
+
+        let mut pending = portal.pending.take().unwrap();
+        let limit = if max_rows > 0 {
+            max_rows as usize
+        } else {
+            usize::MAX
+        };
+        let mut batch = 0;
+        while batch < limit {
+            match pending.rows.pop_front() {
+                Some(row) => {
+                    self.data_row(&row, &portal.result_formats)?;
+                    pending.sent += 1;
+                    batch += 1;
+                }
+                None => break,
+            }
+        }
+
+        if pending.rows.is_empty() {
+            self.send(
+                b'C',
+                MessageBody::new().cstr(&format!("SELECT {}", pending.sent)),
+            )?;
+        } else {
+            self.send(b's', MessageBody::new())?;
+            portal.pending = Some(pending);
+            self.portals.insert(name.to_string(), portal);
+        }
+        Ok(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A session on one end of a loopback connection, and the other end.
+    fn session() -> (TcpStream, thread::JoinHandle<io::Result<()>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let db = Arc::new(Mutex::new(Database::new()));
+        let handle = thread::spawn(move || Session::new(stream, db)?.run());
+        (client, handle)
+    }
+
+    fn send(client: &mut TcpStream, tag: Option<u8>, body: MessageBody) {
+        if let Some(tag) = tag {
+            client.write_all(&[tag]).unwrap();
+        }
+        client
+            .write_all(&(body.0.len() as i32 + 4).to_be_bytes())
+            .unwrap();
+        client.write_all(&body.0).unwrap();
+    }
+
+    /// The tags and bodies of the messages sent up to ReadyForQuery.
+    fn until_ready(client: &mut TcpStream) -> Vec<(u8, Vec<u8>)> {
+        let mut messages = vec![];
+        loop {
+            let mut header = [0u8; 5];
+            client.read_exact(&mut header).unwrap();
+            let len = i32::from_be_bytes(header[1..].try_into().unwrap());
+            let mut body = vec![0u8; len as usize - 4];
+            client.read_exact(&mut body).unwrap();
+            messages.push((header[0], body));
+            if header[0] == b'Z' {
+                return messages;
+            }
+        }
+    }
+
+    fn tags(messages: &[(u8, Vec<u8>)]) -> String {
+        messages.iter().map(|(tag, _)| *tag as char).collect()
+    }
+
+    fn startup(client: &mut TcpStream) {
+        let body = MessageBody::new()
+            .int32(PROTOCOL_VERSION)
+            .cstr("user")
+            .cstr("test")
+            .byte(0);
+        send(client, None, body);
+        assert_eq!(tags(&until_ready(client)), "RSSSSSSSKZ");
+    }
+
+    /// The text of every column of a DataRow.
+    fn columns(body: &[u8]) -> Vec<Option<String>> {
+        let mut msg = MessageReader::new(body);
+        (0..msg.int16().unwrap())
+            .map(|_| match msg.int32().unwrap() {
+                -1 => None,
+                len => Some(String::from_utf8(msg.take(len as usize).unwrap().to_vec()).unwrap()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tests_startup_and_simple_query() {
+        let (mut client, handle) = session();
+        startup(&mut client);
+
+        let query = "CREATE TABLE t (id INT PRIMARY KEY, name TEXT); \
+                     INSERT INTO t VALUES (1, 'a'), (2, NULL);";
+        send(&mut client, Some(b'Q'), MessageBody::new().cstr(query));
+        assert_eq!(tags(&until_ready(&mut client)), "CCZ");
+
+        let query = "SELECT id, name FROM t ORDER BY id;";
+        send(&mut client, Some(b'Q'), MessageBody::new().cstr(query));
+        let messages = until_ready(&mut client);
+        assert_eq!(tags(&messages), "TDDCZ");
+        assert_eq!(columns(&messages[2].1), [Some("2".to_string()), None]);
+        assert_eq!(messages[3].1, b"SELECT 2\0");
+
+        send(
+            &mut client,
+            Some(b'Q'),
+            MessageBody::new().cstr("SELECT * FROM nope;"),
+        );
+        assert_eq!(tags(&until_ready(&mut client)), "EZ");
+
+        send(&mut client, Some(b'X'), MessageBody::new());
+        assert!(handle.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn tests_extended_query() {
+        let (mut client, handle) = session();
+        startup(&mut client);
+        send(
+            &mut client,
+            Some(b'Q'),
+            MessageBody::new().cstr("CREATE TABLE t (id INT PRIMARY KEY, name TEXT);"),
+        );
+        until_ready(&mut client);
+
+        let insert = MessageBody::new()
+            .cstr("ins")
+            .cstr("INSERT INTO t VALUES ($1, $2)")
+            .int16(0);
+        send(&mut client, Some(b'P'), insert);
+        let bind = MessageBody::new()
+            .cstr("")
+            .cstr("ins")
+            .int16(1)
+            .int16(1)
+            .int16(2)
+            .int32(4)
+            .int32(7)
+            .int32(-1)
+            .int16(0);
+        send(&mut client, Some(b'B'), bind);
+        send(
+            &mut client,
+            Some(b'E'),
+            MessageBody::new().cstr("").int32(0),
+        );
+        send(&mut client, Some(b'S'), MessageBody::new());
+        let messages = until_ready(&mut client);
+        assert_eq!(tags(&messages), "12CZ");
+        assert_eq!(messages[2].1, b"INSERT 0 1\0");
+
+        let select = MessageBody::new()
+            .cstr("")
+            .cstr("SELECT id, name FROM t WHERE id = $1")
+            .int16(0);
+        send(&mut client, Some(b'P'), select);
+        let bind = MessageBody::new()
+            .cstr("")
+            .cstr("")
+            .int16(0)
+            .int16(1)
+            .int32(1)
+            .bytes(b"7")
+            .int16(0);
+        send(&mut client, Some(b'B'), bind);
+        send(
+            &mut client,
+            Some(b'D'),
+            MessageBody::new().byte(b'P').cstr(""),
+        );
+        send(
+            &mut client,
+            Some(b'E'),
+            MessageBody::new().cstr("").int32(0),
+        );
+        send(&mut client, Some(b'S'), MessageBody::new());
+        let messages = until_ready(&mut client);
+        assert_eq!(tags(&messages), "12TDCZ");
+        assert_eq!(columns(&messages[3].1), [Some("7".to_string()), None]);
+
+        send(&mut client, Some(b'X'), MessageBody::new());
+        assert!(handle.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn tests_malformed_messages_close_the_session() {
+        // A parameter length below -1 would otherwise be read as a huge
+        // unsigned length.
+        let (mut client, handle) = session();
+        startup(&mut client);
+        let bind = MessageBody::new()
+            .cstr("")
+            .cstr("")
+            .int16(0)
+            .int16(1)
+            .int32(-2)
+            .int16(0);
+        send(&mut client, Some(b'B'), bind);
+        let error = handle.join().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // A message longer than any client needs is refused before its
+        // body is allocated.
+        let (mut client, handle) = session();
+        startup(&mut client);
+        client.write_all(b"Q").unwrap();
+        client.write_all(&i32::MAX.to_be_bytes()).unwrap();
+        let error = handle.join().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut msg = MessageReader::new(&[1, 2, 3]);
+        assert!(msg.take(2).is_ok());
+        assert!(msg.take(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn tests_decoding_binary_arrays() {
+        let header = |dims: &[i32]| {
+            let mut bytes = vec![];
+            bytes.extend((dims.len() as i32).to_be_bytes());
+            bytes.extend(0i32.to_be_bytes());
+            bytes.extend(23i32.to_be_bytes());
+            for len in dims {
+                bytes.extend(len.to_be_bytes());
+                bytes.extend(1i32.to_be_bytes());
+            }
+            bytes
+        };
+        let mut bytes = header(&[2]);
+        for v in [7i32, 8] {
+            bytes.extend(4i32.to_be_bytes());
+            bytes.extend(v.to_be_bytes());
+        }
+        assert_eq!(
+            array_from_bytes(&bytes),
+            Ok(Value::Array(vec![Value::Int(7), Value::Int(8)]))
+        );
+
+        // Dimensions whose product overflows, or claims more items than
+        // the message holds, are rejected rather than read.
+        assert!(matches!(
+            array_from_bytes(&header(&[i32::MAX, i32::MAX, i32::MAX])),
+            Err(Error::Type(_))
+        ));
+        assert!(matches!(
+            array_from_bytes(&header(&[1000])),
+            Err(Error::Type(_))
+        ));
+    }
+}
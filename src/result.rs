@@ -0,0 +1,32 @@
+use crate::table::DataType;
+use crate::value::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultColumn {
+    pub name: String,
+    pub datatype: DataType,
+    /// The table the column was read from, if it maps directly onto one.
+    pub table: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<ResultColumn>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ResultSet {
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.to_string()).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryResult {
+    Rows(ResultSet),
+    /// Number of rows inserted.
+    Inserted(usize),
+    Created,
+    CreatedType,
+    AlteredType,
+}
@@ -1,38 +1,151 @@
-use prettytable::{row, Cell, Row, Table as PTable};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::ops::Bound::{self, Excluded, Included, Unbounded};
-use std::result::Result;
+use std::sync::Arc;
 
-use crate::parser::{
-    create::CreateQuery,
-    select::{Binary, Expression, Operator, SelectQuery},
-};
+use crate::error::{Error, Result};
+use crate::expr::{EmptyScope, ScalarExpr};
+use crate::parser::select::{unqualified, Expression};
+use crate::parser::{create::CreateQuery, parse_expr};
+use crate::value::{array, decimal, Decimal, Interval, Json, Value};
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+mod select;
+mod window;
+
+pub(crate) use select::compare_sort_keys;
+pub use select::{GroupScope, RowScope};
+pub use window::WindowScope;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub enum DataType {
     Int,
     Str,
     Float,
     Bool,
+    Date,
+    Time,
+    Timestamp,
+    TimestampTz,
+    Interval,
+    SmallInt,
+    BigInt,
+    UnsignedSmallInt,
+    UnsignedInt,
+    UnsignedBigInt,
+    Double,
+    /// An exact number with up to `precision` digits, `scale` of them after
+    /// the point.
+    Decimal(u8, u8),
+    Binary,
+    Json,
+    Uuid,
+    /// An array of items of the inner type, named like `int[]`.
+    Array(Box<DataType>),
+    /// A type made with `CREATE TYPE ... AS ENUM`, named like
+    /// `enum status {new,paid}`.
+    Enum(Arc<EnumType>),
     Invalid,
 }
 
+/// The labels of an ENUM type in the order they were declared. Values are
+/// stored as their position among the labels, so they sort in that order.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct EnumType {
+    pub name: String,
+    pub labels: Vec<String>,
+}
+
+impl EnumType {
+    /// The code `label` is stored as.
+    pub fn code(&self, label: &str) -> Option<u32> {
+        self.labels
+            .iter()
+            .position(|l| l == label)
+            .map(|code| code as u32)
+    }
+}
+
 impl DataType {
     pub fn new(cmd: String) -> DataType {
+        if let Some(item) = cmd.strip_suffix("[]") {
+            return match DataType::new(item.to_string()) {
+                DataType::Invalid => DataType::Invalid,
+                item => DataType::Array(Box::new(item)),
+            };
+        }
+        if let Some(datatype) = enum_type(&cmd) {
+            return datatype;
+        }
         match cmd.to_lowercase().as_ref() {
+            "smallint" => DataType::SmallInt,
             "int" => DataType::Int,
+            "bigint" => DataType::BigInt,
+            "smallint unsigned" => DataType::UnsignedSmallInt,
+            "int unsigned" => DataType::UnsignedInt,
+            "bigint unsigned" => DataType::UnsignedBigInt,
             "string" => DataType::Str,
             "float" => DataType::Float,
-            "double" => DataType::Float,
+            "double" => DataType::Double,
             "bool" => DataType::Bool,
-            _ => {
-                println!("Invalid data type given {}", cmd);
-                DataType::Invalid
-            }
+            "date" => DataType::Date,
+            "time" => DataType::Time,
+            "timestamp" | "datetime" => DataType::Timestamp,
+            "timestamptz" => DataType::TimestampTz,
+            "interval" => DataType::Interval,
+            "binary" => DataType::Binary,
+            name if binary_length(name).is_some() => DataType::Binary,
+            "json" => DataType::Json,
+            "uuid" => DataType::Uuid,
+            name => decimal_type(name).unwrap_or(DataType::Invalid),
+        }
+    }
+
+    /// This type with the ENUM type named like `updated` replaced by it,
+    /// or `None` if it doesn't use that type.
+    pub fn with_enum(&self, updated: &Arc<EnumType>) -> Option<DataType> {
+        match self {
+            DataType::Enum(t) if t.name == updated.name => Some(DataType::Enum(updated.clone())),
+            DataType::Array(item) => item
+                .with_enum(updated)
+                .map(|item| DataType::Array(Box::new(item))),
+            _ => None,
         }
     }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            DataType::SmallInt
+                | DataType::Int
+                | DataType::BigInt
+                | DataType::UnsignedSmallInt
+                | DataType::UnsignedInt
+                | DataType::UnsignedBigInt
+        )
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer()
+            || matches!(
+                self,
+                DataType::Float | DataType::Double | DataType::Decimal(..)
+            )
+    }
+
+    /// The smallest and largest value of an integer type.
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        let range = match self {
+            DataType::SmallInt => (i16::MIN as i128, i16::MAX as i128),
+            DataType::Int => (i32::MIN as i128, i32::MAX as i128),
+            DataType::BigInt => (i64::MIN as i128, i64::MAX as i128),
+            DataType::UnsignedSmallInt => (0, u16::MAX as i128),
+            DataType::UnsignedInt => (0, u32::MAX as i128),
+            DataType::UnsignedBigInt => (0, u64::MAX as i128),
+            _ => return None,
+        };
+        Some(range)
+    }
 }
 
 impl fmt::Display for DataType {
@@ -42,11 +155,99 @@ impl fmt::Display for DataType {
             DataType::Str => f.write_str("Str"),
             DataType::Float => f.write_str("Float"),
             DataType::Bool => f.write_str("Boolean"),
+            DataType::Date => f.write_str("Date"),
+            DataType::Time => f.write_str("Time"),
+            DataType::Timestamp => f.write_str("Timestamp"),
+            DataType::TimestampTz => f.write_str("TimestampTz"),
+            DataType::Interval => f.write_str("Interval"),
+            DataType::SmallInt => f.write_str("SmallInt"),
+            DataType::BigInt => f.write_str("BigInt"),
+            DataType::UnsignedSmallInt => f.write_str("UnsignedSmallInt"),
+            DataType::UnsignedInt => f.write_str("UnsignedInt"),
+            DataType::UnsignedBigInt => f.write_str("UnsignedBigInt"),
+            DataType::Double => f.write_str("Double"),
+            DataType::Decimal(precision, scale) => write!(f, "Decimal({precision},{scale})"),
+            DataType::Binary => f.write_str("Binary"),
+            DataType::Json => f.write_str("Json"),
+            DataType::Uuid => f.write_str("Uuid"),
+            DataType::Array(ref item) => write!(f, "{item}[]"),
+            DataType::Enum(ref t) => f.write_str(&t.name),
             DataType::Invalid => f.write_str("Invalid"),
         }
     }
 }
 
+/// The size of a BINARY(n) or VARBINARY(n) column.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub enum BinaryLength {
+    /// Shorter values are padded with zero bytes to `n`.
+    Fixed(u64),
+    /// Values can have up to `n` bytes.
+    Varying(u64),
+}
+
+impl fmt::Display for BinaryLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryLength::Fixed(n) => write!(f, "BINARY({n})"),
+            BinaryLength::Varying(n) => write!(f, "VARBINARY({n})"),
+        }
+    }
+}
+
+/// Parses `binary(n)` and `varbinary(n)`.
+fn binary_length(name: &str) -> Option<BinaryLength> {
+    let (kind, rest) = name.split_once('(')?;
+    let n = rest.strip_suffix(')')?.trim().parse().ok()?;
+    match kind {
+        "binary" => Some(BinaryLength::Fixed(n)),
+        "varbinary" => Some(BinaryLength::Varying(n)),
+        _ => None,
+    }
+}
+
+/// Parses `decimal(p,s)`, where `p` is from 1 to 38 and `s` at most `p`.
+fn decimal_type(name: &str) -> Option<DataType> {
+    let (precision, scale) = name
+        .strip_prefix("decimal(")?
+        .strip_suffix(')')?
+        .split_once(',')?;
+    let precision: u8 = precision.trim().parse().ok()?;
+    let scale: u8 = scale.trim().parse().ok()?;
+    (1..=decimal::MAX_PRECISION)
+        .contains(&precision)
+        .then_some(DataType::Decimal(precision, scale))
+        .filter(|_| scale <= precision)
+}
+
+/// Parses `enum name {labels}`, with the labels in the text form of an
+/// array.
+fn enum_type(name: &str) -> Option<DataType> {
+    let rest = name.strip_prefix("enum ")?;
+    let (name, labels) = rest.split_at(rest.find('{')?);
+    let labels = array::parse(labels)?
+        .into_iter()
+        .map(|label| match label {
+            Value::Str(label) => Some(label),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some(DataType::Enum(Arc::new(EnumType {
+        name: name.trim_end().to_string(),
+        labels,
+    })))
+}
+
+fn duplicate_key(name: &str, val: &Value) -> Error {
+    Error::Constraint(format!(
+        "unique constraint violation for column {name}. Value {val} already exists for column {name}"
+    ))
+}
+
+fn mismatch(val: &Value, datatype: &DataType) -> Error {
+    Error::Type(format!("{val} is not a valid {datatype}"))
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct ColumnHeader {
     pub name: String,
@@ -54,17 +255,22 @@ pub struct ColumnHeader {
     pub is_indexed: bool,
     pub index: ColumnIndex,
     pub is_primary_key: bool,
+    pub is_nullable: bool,
+    pub default: Option<String>,
+    pub binary_length: Option<BinaryLength>,
 }
 
 impl ColumnHeader {
-    pub fn new(name: String, datatype: String, is_primary_key: bool) -> ColumnHeader {
+    pub fn new(
+        name: String,
+        datatype: String,
+        is_primary_key: bool,
+        is_nullable: bool,
+        default: Option<String>,
+    ) -> ColumnHeader {
+        let binary_length = binary_length(&datatype.to_lowercase());
         let dt = DataType::new(datatype);
-        let index = match dt {
-            DataType::Int => ColumnIndex::Int(BTreeMap::new()),
-            DataType::Str => ColumnIndex::Str(BTreeMap::new()),
-            DataType::Bool => ColumnIndex::Bool(BTreeMap::new()),
-            DataType::Float | DataType::Invalid => ColumnIndex::None,
-        };
+        let index = ColumnIndex::new(&dt);
 
         ColumnHeader {
             name,
@@ -72,260 +278,625 @@ impl ColumnHeader {
             is_indexed: is_primary_key,
             index,
             is_primary_key,
+            is_nullable: is_nullable && !is_primary_key,
+            default,
+            binary_length,
         }
     }
 
     pub fn get_mut_index(&mut self) -> &mut ColumnIndex {
         &mut self.index
     }
+
+    /// The value of a column an insert leaves out: its DEFAULT, evaluated
+    /// afresh so `gen_random_uuid()` differs from row to row, or NULL.
+    pub fn default_value(&self) -> Result<Value> {
+        match &self.default {
+            Some(sql) => {
+                let expr = ScalarExpr::try_from(&parse_expr(sql)?)?;
+                self.storable(expr.eval(&EmptyScope)?)
+            }
+            None => Ok(Value::Null),
+        }
+    }
+
+    /// Converts `val` to this column's type, or fails with a type error, or
+    /// a range error for a number that is too large for the column.
+    pub fn coerce(&self, val: Value) -> Result<Value> {
+        if self.datatype == DataType::Invalid {
+            return Err(Error::Type(format!(
+                "Column {} has an unsupported type",
+                self.name
+            )));
+        }
+        let shown = val.to_string();
+        val.coerce(&self.datatype).map_err(|e| match e {
+            Error::OutOfRange(_) => Error::OutOfRange(format!(
+                "{} is out of range for column {} of type {}",
+                shown, self.name, self.datatype
+            )),
+            _ => Error::Type(format!(
+                "{} is not a valid value for column {} of type {}",
+                shown, self.name, self.datatype
+            )),
+        })
+    }
+
+    /// Converts `val` to a value this column can store: `coerce`d, and for
+    /// a BINARY(n) column padded with zero bytes to n bytes. A value longer
+    /// than a BINARY(n) or VARBINARY(n) column allows is out of range.
+    pub fn storable(&self, val: Value) -> Result<Value> {
+        match (self.coerce(val)?, self.binary_length) {
+            (Value::Binary(bytes), Some(length)) => {
+                let (BinaryLength::Fixed(n) | BinaryLength::Varying(n)) = length;
+                if bytes.len() as u64 > n {
+                    return Err(Error::OutOfRange(format!(
+                        "{} is too long for column {} of type {}",
+                        Value::Binary(bytes),
+                        self.name,
+                        length
+                    )));
+                }
+                let mut bytes = bytes;
+                if let BinaryLength::Fixed(n) = length {
+                    bytes.resize(n as usize, 0);
+                }
+                Ok(Value::Binary(bytes))
+            }
+            (val, _) => Ok(val),
+        }
+    }
 }
 
+/// The values of one column, where `None` is a NULL.
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub enum ColumnData {
-    Int(Vec<i32>),
-    Str(Vec<String>),
-    Float(Vec<f32>),
-    Bool(Vec<bool>),
+    Int(Vec<Option<i32>>),
+    Str(Vec<Option<String>>),
+    Float(Vec<Option<f32>>),
+    Bool(Vec<Option<bool>>),
+    Date(Vec<Option<i32>>),
+    Time(Vec<Option<i64>>),
+    Timestamp(Vec<Option<i64>>),
+    TimestampTz(Vec<Option<i64>>),
+    Interval(Vec<Option<Interval>>),
+    SmallInt(Vec<Option<i16>>),
+    BigInt(Vec<Option<i64>>),
+    UnsignedSmallInt(Vec<Option<u16>>),
+    UnsignedInt(Vec<Option<u32>>),
+    UnsignedBigInt(Vec<Option<u64>>),
+    Double(Vec<Option<f64>>),
+    Decimal(Vec<Option<Decimal>>),
+    Binary(Vec<Option<Vec<u8>>>),
+    Json(Vec<Option<Json>>),
+    Uuid(Vec<Option<u128>>),
+    /// The items of every array laid end to end in `values`, with those of
+    /// row `i` at `offsets[i]..offsets[i + 1]`. A NULL array has no items
+    /// and is marked in `nulls`.
+    Array {
+        offsets: Vec<usize>,
+        nulls: Vec<bool>,
+        values: Box<ColumnData>,
+    },
+    /// The position of each value among the labels of its type.
+    Enum {
+        datatype: Arc<EnumType>,
+        codes: Vec<Option<u32>>,
+    },
     None,
 }
 
 impl ColumnData {
-    fn get_serialized_col_data(&self) -> Vec<String> {
-        match self {
-            ColumnData::Int(cd) => cd.iter().map(|v| v.to_string()).collect(),
-            ColumnData::Float(cd) => cd.iter().map(|v| v.to_string()).collect(),
-            ColumnData::Str(cd) => cd.iter().map(|v| v.to_string()).collect(),
-            ColumnData::Bool(cd) => cd.iter().map(|v| v.to_string()).collect(),
-            ColumnData::None => panic!("Found None in columns"),
+    pub fn new(datatype: &DataType) -> ColumnData {
+        match datatype {
+            DataType::Int => ColumnData::Int(vec![]),
+            DataType::Float => ColumnData::Float(vec![]),
+            DataType::Str => ColumnData::Str(vec![]),
+            DataType::Bool => ColumnData::Bool(vec![]),
+            DataType::Date => ColumnData::Date(vec![]),
+            DataType::Time => ColumnData::Time(vec![]),
+            DataType::Timestamp => ColumnData::Timestamp(vec![]),
+            DataType::TimestampTz => ColumnData::TimestampTz(vec![]),
+            DataType::Interval => ColumnData::Interval(vec![]),
+            DataType::SmallInt => ColumnData::SmallInt(vec![]),
+            DataType::BigInt => ColumnData::BigInt(vec![]),
+            DataType::UnsignedSmallInt => ColumnData::UnsignedSmallInt(vec![]),
+            DataType::UnsignedInt => ColumnData::UnsignedInt(vec![]),
+            DataType::UnsignedBigInt => ColumnData::UnsignedBigInt(vec![]),
+            DataType::Double => ColumnData::Double(vec![]),
+            DataType::Decimal(..) => ColumnData::Decimal(vec![]),
+            DataType::Binary => ColumnData::Binary(vec![]),
+            DataType::Json => ColumnData::Json(vec![]),
+            DataType::Uuid => ColumnData::Uuid(vec![]),
+            DataType::Array(item) => ColumnData::Array {
+                offsets: vec![0],
+                nulls: vec![],
+                values: Box::new(ColumnData::new(item)),
+            },
+            DataType::Enum(datatype) => ColumnData::Enum {
+                datatype: datatype.clone(),
+                codes: vec![],
+            },
+            DataType::Invalid => ColumnData::None,
         }
     }
 
-    fn get_serialized_col_data_by_index(&self, indices: &[usize]) -> Vec<String> {
-        let mut selected_data = vec![];
-        match self {
-            ColumnData::Int(cd) => {
-                indices
-                    .iter()
-                    .for_each(|i| selected_data.push((cd[*i]).to_string()));
-            }
-            ColumnData::Float(cd) => {
-                indices
-                    .iter()
-                    .for_each(|i| selected_data.push((cd[*i]).to_string()));
-            }
-            ColumnData::Str(cd) => {
-                indices
-                    .iter()
-                    .for_each(|i| selected_data.push((cd[*i]).to_string()));
+    fn get_value(&self, idx: usize) -> Value {
+        let value = match self {
+            ColumnData::Int(cd) => cd.get(idx).copied().flatten().map(Value::Int),
+            ColumnData::Float(cd) => cd.get(idx).copied().flatten().map(Value::Float),
+            ColumnData::Str(cd) => cd.get(idx).cloned().flatten().map(Value::Str),
+            ColumnData::Bool(cd) => cd.get(idx).copied().flatten().map(Value::Bool),
+            ColumnData::Date(cd) => cd.get(idx).copied().flatten().map(Value::Date),
+            ColumnData::Time(cd) => cd.get(idx).copied().flatten().map(Value::Time),
+            ColumnData::Timestamp(cd) => cd.get(idx).copied().flatten().map(Value::Timestamp),
+            ColumnData::TimestampTz(cd) => cd.get(idx).copied().flatten().map(Value::TimestampTz),
+            ColumnData::Interval(cd) => cd.get(idx).copied().flatten().map(Value::Interval),
+            ColumnData::SmallInt(cd) => cd.get(idx).copied().flatten().map(Value::SmallInt),
+            ColumnData::BigInt(cd) => cd.get(idx).copied().flatten().map(Value::BigInt),
+            ColumnData::UnsignedSmallInt(cd) => {
+                cd.get(idx).copied().flatten().map(Value::UnsignedSmallInt)
             }
-            ColumnData::Bool(cd) => {
-                indices
-                    .iter()
-                    .for_each(|i| selected_data.push((cd[*i]).to_string()));
+            ColumnData::UnsignedInt(cd) => cd.get(idx).copied().flatten().map(Value::UnsignedInt),
+            ColumnData::UnsignedBigInt(cd) => {
+                cd.get(idx).copied().flatten().map(Value::UnsignedBigInt)
             }
-            ColumnData::None => panic!("Found None in columns"),
-        }
-        selected_data
+            ColumnData::Double(cd) => cd.get(idx).copied().flatten().map(Value::Double),
+            ColumnData::Decimal(cd) => cd.get(idx).copied().flatten().map(Value::Decimal),
+            ColumnData::Binary(cd) => cd.get(idx).cloned().flatten().map(Value::Binary),
+            ColumnData::Json(cd) => cd.get(idx).cloned().flatten().map(Value::Json),
+            ColumnData::Uuid(cd) => cd.get(idx).copied().flatten().map(Value::Uuid),
+            ColumnData::Array {
+                offsets,
+                nulls,
+                values,
+            } => match nulls.get(idx) {
+                Some(false) => Some(Value::Array(
+                    (offsets[idx]..offsets[idx + 1])
+                        .map(|i| values.get_value(i))
+                        .collect(),
+                )),
+                _ => None,
+            },
+            ColumnData::Enum { datatype, codes } => codes
+                .get(idx)
+                .copied()
+                .flatten()
+                .map(|code| Value::Enum(code, datatype.clone())),
+            ColumnData::None => None,
+        };
+        value.unwrap_or(Value::Null)
     }
 
-    fn work<A, B, C, D>(
-        &self,
-        search_term: &str,
-        scanned_vals: &mut Vec<usize>,
-        func1: A,
-        func2: B,
-        func3: C,
-        func4: D,
-    ) -> Vec<usize>
-    where
-        A: Fn(i32, i32) -> bool,
-        B: Fn(f32, f32) -> bool,
-        C: Fn(&String, &String) -> bool,
-        D: Fn(bool, bool) -> bool,
-    {
-        match self {
-            ColumnData::Int(cd) => {
-                let search_term = search_term.parse::<i32>().unwrap();
+    pub fn get_values(&self) -> Vec<Value> {
+        (0..self.count()).map(|i| self.get_value(i)).collect()
+    }
 
-                for (idx, i) in cd.iter().enumerate() {
-                    if func1(*i, search_term) {
-                        scanned_vals.push(idx);
+    fn push(&mut self, value: Value) -> Result<usize> {
+        match (&mut *self, value) {
+            (ColumnData::Int(cd), Value::Int(v)) => cd.push(Some(v)),
+            (ColumnData::Int(cd), Value::Null) => cd.push(None),
+            (ColumnData::Float(cd), Value::Float(v)) => cd.push(Some(v)),
+            (ColumnData::Float(cd), Value::Null) => cd.push(None),
+            (ColumnData::Str(cd), Value::Str(v)) => cd.push(Some(v)),
+            (ColumnData::Str(cd), Value::Null) => cd.push(None),
+            (ColumnData::Bool(cd), Value::Bool(v)) => cd.push(Some(v)),
+            (ColumnData::Bool(cd), Value::Null) => cd.push(None),
+            (ColumnData::Date(cd), Value::Date(v)) => cd.push(Some(v)),
+            (ColumnData::Date(cd), Value::Null) => cd.push(None),
+            (ColumnData::Time(cd), Value::Time(v)) => cd.push(Some(v)),
+            (ColumnData::Time(cd), Value::Null) => cd.push(None),
+            (ColumnData::Timestamp(cd), Value::Timestamp(v)) => cd.push(Some(v)),
+            (ColumnData::Timestamp(cd), Value::Null) => cd.push(None),
+            (ColumnData::TimestampTz(cd), Value::TimestampTz(v)) => cd.push(Some(v)),
+            (ColumnData::TimestampTz(cd), Value::Null) => cd.push(None),
+            (ColumnData::Interval(cd), Value::Interval(v)) => cd.push(Some(v)),
+            (ColumnData::Interval(cd), Value::Null) => cd.push(None),
+            (ColumnData::SmallInt(cd), Value::SmallInt(v)) => cd.push(Some(v)),
+            (ColumnData::SmallInt(cd), Value::Null) => cd.push(None),
+            (ColumnData::BigInt(cd), Value::BigInt(v)) => cd.push(Some(v)),
+            (ColumnData::BigInt(cd), Value::Null) => cd.push(None),
+            (ColumnData::UnsignedSmallInt(cd), Value::UnsignedSmallInt(v)) => cd.push(Some(v)),
+            (ColumnData::UnsignedSmallInt(cd), Value::Null) => cd.push(None),
+            (ColumnData::UnsignedInt(cd), Value::UnsignedInt(v)) => cd.push(Some(v)),
+            (ColumnData::UnsignedInt(cd), Value::Null) => cd.push(None),
+            (ColumnData::UnsignedBigInt(cd), Value::UnsignedBigInt(v)) => cd.push(Some(v)),
+            (ColumnData::UnsignedBigInt(cd), Value::Null) => cd.push(None),
+            (ColumnData::Double(cd), Value::Double(v)) => cd.push(Some(v)),
+            (ColumnData::Double(cd), Value::Null) => cd.push(None),
+            (ColumnData::Decimal(cd), Value::Decimal(v)) => cd.push(Some(v)),
+            (ColumnData::Decimal(cd), Value::Null) => cd.push(None),
+            (ColumnData::Binary(cd), Value::Binary(v)) => cd.push(Some(v)),
+            (ColumnData::Binary(cd), Value::Null) => cd.push(None),
+            (ColumnData::Json(cd), Value::Json(v)) => cd.push(Some(v)),
+            (ColumnData::Json(cd), Value::Null) => cd.push(None),
+            (ColumnData::Uuid(cd), Value::Uuid(v)) => cd.push(Some(v)),
+            (ColumnData::Uuid(cd), Value::Null) => cd.push(None),
+            (
+                ColumnData::Array {
+                    offsets,
+                    nulls,
+                    values,
+                },
+                value,
+            ) if matches!(value, Value::Array(_) | Value::Null) => {
+                let is_null = value.is_null();
+                if let Value::Array(items) = value {
+                    for item in items {
+                        values.push(item)?;
                     }
                 }
-                scanned_vals.to_vec()
+                offsets.push(values.count());
+                nulls.push(is_null);
             }
-            ColumnData::Float(cd) => {
-                let search_term = search_term.parse::<f32>().unwrap();
-
-                for (idx, i) in cd.iter().enumerate() {
-                    if func2(*i, search_term) {
-                        scanned_vals.push(idx);
-                    }
-                }
-                scanned_vals.to_vec()
+            (ColumnData::Enum { datatype, codes }, Value::Enum(code, t))
+                if t.name == datatype.name =>
+            {
+                codes.push(Some(code))
             }
-            ColumnData::Str(cd) => {
-                let search_term = search_term.parse::<String>().unwrap();
+            (ColumnData::Enum { codes, .. }, Value::Null) => codes.push(None),
+            (_, value) => return Err(Error::Type(format!("Cannot store {value} in this column"))),
+        }
+        Ok(self.count() - 1)
+    }
 
-                for (idx, i) in cd.iter().enumerate() {
-                    if func3(i, &search_term) {
-                        scanned_vals.push(idx);
-                    }
-                }
-                scanned_vals.to_vec()
-            }
-            ColumnData::Bool(cd) => {
-                let search_term = search_term.parse::<bool>().unwrap();
-                for (idx, i) in cd.iter().enumerate() {
-                    if func4(*i, search_term) {
-                        scanned_vals.push(idx);
-                    }
-                }
-                scanned_vals.to_vec()
+    fn get_serialized_col_data_by_scanning(&self, expr: &Expression) -> Result<Vec<usize>> {
+        let mut rows = vec![];
+        for idx in 0..self.count() {
+            if expr.matches(&self.get_value(idx))? {
+                rows.push(idx);
             }
-            ColumnData::None => panic!("Found None in columns"),
-        }
-    }
-
-    fn get_serialized_col_data_by_scanning(&self, expr: &Expression) -> Vec<usize> {
-        let search_term = (expr.right).to_string();
-        let mut scanned_vals = vec![];
-        match &expr.op {
-            Operator::Binary(binary_op) => match binary_op {
-                Binary::NotEq => Self::work(
-                    self,
-                    &search_term,
-                    &mut scanned_vals,
-                    |a, b| a != b,
-                    |a, b| a != b,
-                    |a, b| a != b,
-                    |a, b| a != b,
-                ),
-                Binary::Eq => self.work(
-                    &search_term,
-                    &mut scanned_vals,
-                    |a, b| a == b,
-                    |a, b| a == b,
-                    |a, b| a == b,
-                    |a, b| a == b,
-                ),
-                Binary::Gt => self.work(
-                    &search_term,
-                    &mut scanned_vals,
-                    |a, b| a > b,
-                    |a, b| a > b,
-                    |a, b| a > b,
-                    |a, b| a & !b,
-                ),
-                Binary::Lt => self.work(
-                    &search_term,
-                    &mut scanned_vals,
-                    |a, b| a < b,
-                    |a, b| a < b,
-                    |a, b| a < b,
-                    |a, b| !a & b,
-                ),
-                Binary::LtEq => self.work(
-                    &search_term,
-                    &mut scanned_vals,
-                    |a, b| a <= b,
-                    |a, b| a <= b,
-                    |a, b| a <= b,
-                    |a, b| a <= b,
-                ),
-                Binary::GtEq => self.work(
-                    &search_term,
-                    &mut scanned_vals,
-                    |a, b| a >= b,
-                    |a, b| a >= b,
-                    |a, b| a >= b,
-                    |a, b| a >= b,
-                ),
-            },
         }
+        Ok(rows)
     }
 
-    fn count(&self) -> usize {
+    pub fn count(&self) -> usize {
         match self {
             ColumnData::Int(cd) => cd.len(),
             ColumnData::Float(cd) => cd.len(),
             ColumnData::Str(cd) => cd.len(),
             ColumnData::Bool(cd) => cd.len(),
-            ColumnData::None => panic!("Found None in columns"),
+            ColumnData::Date(cd) => cd.len(),
+            ColumnData::Time(cd) => cd.len(),
+            ColumnData::Timestamp(cd) => cd.len(),
+            ColumnData::TimestampTz(cd) => cd.len(),
+            ColumnData::Interval(cd) => cd.len(),
+            ColumnData::SmallInt(cd) => cd.len(),
+            ColumnData::BigInt(cd) => cd.len(),
+            ColumnData::UnsignedSmallInt(cd) => cd.len(),
+            ColumnData::UnsignedInt(cd) => cd.len(),
+            ColumnData::UnsignedBigInt(cd) => cd.len(),
+            ColumnData::Double(cd) => cd.len(),
+            ColumnData::Decimal(cd) => cd.len(),
+            ColumnData::Binary(cd) => cd.len(),
+            ColumnData::Json(cd) => cd.len(),
+            ColumnData::Uuid(cd) => cd.len(),
+            ColumnData::Array { nulls, .. } => nulls.len(),
+            ColumnData::Enum { codes, .. } => codes.len(),
+            ColumnData::None => 0,
         }
     }
 }
 
+/// Maps the non-NULL values of an indexed column to their row.
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub enum ColumnIndex {
     Int(BTreeMap<i32, usize>),
     Str(BTreeMap<String, usize>),
     Bool(BTreeMap<bool, usize>),
+    Date(BTreeMap<i32, usize>),
+    Time(BTreeMap<i64, usize>),
+    /// Microseconds since the epoch, for timestamps with or without a time zone.
+    Timestamp(BTreeMap<i64, usize>),
+    SmallInt(BTreeMap<i16, usize>),
+    BigInt(BTreeMap<i64, usize>),
+    UnsignedSmallInt(BTreeMap<u16, usize>),
+    UnsignedInt(BTreeMap<u32, usize>),
+    UnsignedBigInt(BTreeMap<u64, usize>),
+    Decimal(BTreeMap<Decimal, usize>),
+    Binary(BTreeMap<Vec<u8>, usize>),
+    Uuid(BTreeMap<u128, usize>),
+    /// Keys from `float_key`, for floats of either width.
+    Float(BTreeMap<i64, usize>),
+    Double(BTreeMap<i64, usize>),
+    /// Lengths in microseconds, so `1 mon` and `30 days` are one key.
+    Interval(BTreeMap<i128, usize>),
+    /// Codes, which follow the order of the labels.
+    Enum(Arc<EnumType>, BTreeMap<u32, usize>),
     None,
 }
 
+/// The key of an integer index for `v`, which may be an integer of any width.
+fn integer_key<T: TryFrom<i128>>(v: &Value) -> Option<T> {
+    v.as_i128()?.try_into().ok()
+}
+
+/// The key of a decimal index for `v`, which may also be an integer or a
+/// float, taken as the shortest decimal that reads back as it.
+fn decimal_key(v: &Value) -> Option<Decimal> {
+    match v {
+        Value::Float(f) => Decimal::from_float(f),
+        Value::Double(f) => Decimal::from_float(f),
+        v => v.as_decimal(),
+    }
+}
+
+/// The key of a float index for `v`: its bits, arranged to sort like
+/// `f64::total_cmp`. -0 and 0 make one key, as do all NaNs, which sort
+/// above infinity.
+fn float_key(v: &Value) -> Option<i64> {
+    let f = match v {
+        Value::Float(f) => f64::from(*f),
+        Value::Double(f) => *f,
+        _ => return None,
+    };
+    let f = if f.is_nan() { f64::NAN } else { f + 0.0 };
+    let bits = f.to_bits() as i64;
+    Some(bits ^ (((bits >> 63) as u64) >> 1) as i64)
+}
+
+fn str_key(v: &Value) -> Option<String> {
+    match v {
+        Value::Str(k) => Some(k.to_string()),
+        _ => None,
+    }
+}
+
+fn binary_key(v: &Value) -> Option<Vec<u8>> {
+    match v {
+        Value::Binary(k) => Some(k.clone()),
+        _ => None,
+    }
+}
+
+fn uuid_key(v: &Value) -> Option<u128> {
+    match v {
+        Value::Uuid(k) => Some(*k),
+        _ => None,
+    }
+}
+
+fn bool_key(v: &Value) -> Option<bool> {
+    match v {
+        Value::Bool(k) => Some(*k),
+        _ => None,
+    }
+}
+
+fn date_key(v: &Value) -> Option<i32> {
+    match v {
+        Value::Date(k) => Some(*k),
+        _ => None,
+    }
+}
+
+fn time_key(v: &Value) -> Option<i64> {
+    match v {
+        Value::Time(k) => Some(*k),
+        _ => None,
+    }
+}
+
+fn timestamp_key(v: &Value) -> Option<i64> {
+    match v {
+        Value::Timestamp(k) | Value::TimestampTz(k) => Some(*k),
+        _ => None,
+    }
+}
+
+fn interval_key(v: &Value) -> Option<i128> {
+    match v {
+        Value::Interval(k) => Some(k.sort_key()),
+        _ => None,
+    }
+}
+
+fn enum_key(v: &Value) -> Option<u32> {
+    match v {
+        Value::Enum(k, _) => Some(*k),
+        _ => None,
+    }
+}
+
 impl ColumnIndex {
-    fn get_idx_data(&self, val: &String) -> Result<Option<&usize>, String> {
+    /// An empty index for a column of type `datatype`.
+    fn new(datatype: &DataType) -> ColumnIndex {
+        match datatype {
+            DataType::Int => ColumnIndex::Int(BTreeMap::new()),
+            DataType::Str => ColumnIndex::Str(BTreeMap::new()),
+            DataType::Bool => ColumnIndex::Bool(BTreeMap::new()),
+            DataType::Date => ColumnIndex::Date(BTreeMap::new()),
+            DataType::Time => ColumnIndex::Time(BTreeMap::new()),
+            DataType::Timestamp | DataType::TimestampTz => ColumnIndex::Timestamp(BTreeMap::new()),
+            DataType::SmallInt => ColumnIndex::SmallInt(BTreeMap::new()),
+            DataType::BigInt => ColumnIndex::BigInt(BTreeMap::new()),
+            DataType::UnsignedSmallInt => ColumnIndex::UnsignedSmallInt(BTreeMap::new()),
+            DataType::UnsignedInt => ColumnIndex::UnsignedInt(BTreeMap::new()),
+            DataType::UnsignedBigInt => ColumnIndex::UnsignedBigInt(BTreeMap::new()),
+            DataType::Decimal(..) => ColumnIndex::Decimal(BTreeMap::new()),
+            DataType::Binary => ColumnIndex::Binary(BTreeMap::new()),
+            DataType::Uuid => ColumnIndex::Uuid(BTreeMap::new()),
+            DataType::Float => ColumnIndex::Float(BTreeMap::new()),
+            DataType::Double => ColumnIndex::Double(BTreeMap::new()),
+            DataType::Interval => ColumnIndex::Interval(BTreeMap::new()),
+            DataType::Enum(t) => ColumnIndex::Enum(t.clone(), BTreeMap::new()),
+            DataType::Json | DataType::Array(_) | DataType::Invalid => ColumnIndex::None,
+        }
+    }
+
+    fn datatype(&self) -> DataType {
         match self {
-            ColumnIndex::Int(index) => match val.parse::<i32>() {
-                Ok(val) => Ok(index.get(&val)),
-                Err(e) => Err(e.to_string()),
-            },
-            ColumnIndex::Bool(index) => match val.parse::<bool>() {
-                Ok(val) => Ok(index.get(&val)),
-                Err(e) => Err(e.to_string()),
-            },
-            ColumnIndex::Str(index) => Ok(index.get(val)),
-            ColumnIndex::None => Ok(None),
+            ColumnIndex::Int(_) => DataType::Int,
+            ColumnIndex::Str(_) => DataType::Str,
+            ColumnIndex::Bool(_) => DataType::Bool,
+            ColumnIndex::Date(_) => DataType::Date,
+            ColumnIndex::Time(_) => DataType::Time,
+            ColumnIndex::Timestamp(_) => DataType::Timestamp,
+            ColumnIndex::SmallInt(_) => DataType::SmallInt,
+            ColumnIndex::BigInt(_) => DataType::BigInt,
+            ColumnIndex::UnsignedSmallInt(_) => DataType::UnsignedSmallInt,
+            ColumnIndex::UnsignedInt(_) => DataType::UnsignedInt,
+            ColumnIndex::UnsignedBigInt(_) => DataType::UnsignedBigInt,
+            ColumnIndex::Decimal(_) => DataType::Decimal(decimal::MAX_PRECISION, 0),
+            ColumnIndex::Binary(_) => DataType::Binary,
+            ColumnIndex::Uuid(_) => DataType::Uuid,
+            ColumnIndex::Float(_) => DataType::Float,
+            ColumnIndex::Double(_) => DataType::Double,
+            ColumnIndex::Interval(_) => DataType::Interval,
+            ColumnIndex::Enum(t, _) => DataType::Enum(t.clone()),
+            ColumnIndex::None => DataType::Invalid,
         }
     }
 
-    fn get_indexes_from_op<T: Clone>(val: T, op: Binary) -> (Bound<T>, Bound<T>) {
-        match op {
-            Binary::Eq => (Included(val.clone()), Included(val)),
-            Binary::NotEq => (Excluded(val.clone()), Excluded(val)),
-            Binary::Gt => (Excluded(val), Unbounded),
-            Binary::GtEq => (Included(val), Unbounded),
-            Binary::Lt => (Unbounded, Excluded(val)),
-            Binary::LtEq => (Unbounded, Included(val)),
+    fn get_idx_data(&self, val: &Value) -> Result<Option<&usize>> {
+        fn get<T: Ord>(index: &BTreeMap<T, usize>, key: Option<T>) -> Option<Option<&usize>> {
+            Some(index.get(&key?))
+        }
+        if val.is_null() {
+            return Ok(None);
         }
+        let found = match self {
+            ColumnIndex::Int(index) => get(index, integer_key(val)),
+            ColumnIndex::Str(index) => get(index, str_key(val)),
+            ColumnIndex::Bool(index) => get(index, bool_key(val)),
+            ColumnIndex::Date(index) => get(index, date_key(val)),
+            ColumnIndex::Time(index) => get(index, time_key(val)),
+            ColumnIndex::Timestamp(index) => get(index, timestamp_key(val)),
+            ColumnIndex::SmallInt(index) => get(index, integer_key(val)),
+            ColumnIndex::BigInt(index) => get(index, integer_key(val)),
+            ColumnIndex::UnsignedSmallInt(index) => get(index, integer_key(val)),
+            ColumnIndex::UnsignedInt(index) => get(index, integer_key(val)),
+            ColumnIndex::UnsignedBigInt(index) => get(index, integer_key(val)),
+            ColumnIndex::Decimal(index) => get(index, decimal_key(val)),
+            ColumnIndex::Binary(index) => get(index, binary_key(val)),
+            ColumnIndex::Uuid(index) => get(index, uuid_key(val)),
+            ColumnIndex::Float(index) | ColumnIndex::Double(index) => get(index, float_key(val)),
+            ColumnIndex::Interval(index) => get(index, interval_key(val)),
+            ColumnIndex::Enum(_, index) => get(index, enum_key(val)),
+            ColumnIndex::None => return Ok(None),
+        };
+        found.ok_or_else(|| mismatch(val, &self.datatype()))
     }
 
-    fn get_idx_data_by_range(&self, val: &String, op: Binary) -> Result<Vec<usize>, String> {
-        let mut indexes: Vec<usize> = vec![];
+    /// Records that the row `idx` holds `val`.
+    fn insert(&mut self, val: &Value, idx: usize) {
+        fn insert<T: Ord>(index: &mut BTreeMap<T, usize>, key: Option<T>, idx: usize) {
+            if let Some(key) = key {
+                index.insert(key, idx);
+            }
+        }
         match self {
-            ColumnIndex::Int(index) => match val.parse::<i32>() {
-                Ok(val) => {
-                    for (_, idx) in index.range(Self::get_indexes_from_op::<i32>(val, op)) {
-                        indexes.push(*idx);
-                    }
-                    Ok(indexes)
-                }
-                Err(e) => Err(e.to_string()),
-            },
+            ColumnIndex::Int(index) => insert(index, integer_key(val), idx),
+            ColumnIndex::Str(index) => insert(index, str_key(val), idx),
+            ColumnIndex::Bool(index) => insert(index, bool_key(val), idx),
+            ColumnIndex::Date(index) => insert(index, date_key(val), idx),
+            ColumnIndex::Time(index) => insert(index, time_key(val), idx),
+            ColumnIndex::Timestamp(index) => insert(index, timestamp_key(val), idx),
+            ColumnIndex::SmallInt(index) => insert(index, integer_key(val), idx),
+            ColumnIndex::BigInt(index) => insert(index, integer_key(val), idx),
+            ColumnIndex::UnsignedSmallInt(index) => insert(index, integer_key(val), idx),
+            ColumnIndex::UnsignedInt(index) => insert(index, integer_key(val), idx),
+            ColumnIndex::UnsignedBigInt(index) => insert(index, integer_key(val), idx),
+            ColumnIndex::Decimal(index) => insert(index, decimal_key(val), idx),
+            ColumnIndex::Binary(index) => insert(index, binary_key(val), idx),
+            ColumnIndex::Uuid(index) => insert(index, uuid_key(val), idx),
+            ColumnIndex::Float(index) | ColumnIndex::Double(index) => {
+                insert(index, float_key(val), idx)
+            }
+            ColumnIndex::Interval(index) => insert(index, interval_key(val), idx),
+            ColumnIndex::Enum(_, index) => insert(index, enum_key(val), idx),
+            ColumnIndex::None => {}
+        }
+    }
 
-            ColumnIndex::Bool(index) => match val.parse::<bool>() {
-                Ok(val) => {
-                    for (_, idx) in index.range(Self::get_indexes_from_op::<bool>(val, op)) {
-                        indexes.push(*idx);
-                    }
-                    Ok(indexes)
-                }
-                Err(e) => Err(e.to_string()),
-            },
-            ColumnIndex::Str(index) => {
-                for (_, idx) in
-                    index.range(Self::get_indexes_from_op::<String>(val.to_string(), op))
-                {
-                    indexes.push(*idx);
-                }
-                Ok(indexes)
+    fn range_lookup<T: Ord>(
+        index: &BTreeMap<T, usize>,
+        low: Bound<T>,
+        high: Bound<T>,
+    ) -> Vec<usize> {
+        // BTreeMap::range panics on reversed ranges and on (Excluded(x), Excluded(x)).
+        let empty = match (&low, &high) {
+            (Included(l) | Excluded(l), Included(h) | Excluded(h)) => {
+                l > h || (l == h && matches!((&low, &high), (Excluded(_), Excluded(_))))
             }
-            ColumnIndex::None => Ok(indexes),
+            _ => false,
+        };
+        if empty {
+            return vec![];
+        }
+        index.range((low, high)).map(|(_, idx)| *idx).collect()
+    }
+
+    /// The rows whose key starts with `prefix`, in key order.
+    fn get_idx_data_by_prefix(&self, prefix: &str) -> Vec<usize> {
+        match self {
+            ColumnIndex::Str(index) => index
+                .range::<str, _>((Included(prefix), Unbounded))
+                .take_while(|(k, _)| k.starts_with(prefix))
+                .map(|(_, idx)| *idx)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn range_by_key<T: Ord>(
+        &self,
+        index: &BTreeMap<T, usize>,
+        low: Bound<&Value>,
+        high: Bound<&Value>,
+        key: fn(&Value) -> Option<T>,
+    ) -> Result<Vec<usize>> {
+        let key = |v: &Value| key(v).ok_or_else(|| mismatch(v, &self.datatype()));
+        Ok(Self::range_lookup(
+            index,
+            map_bound(low, key)?,
+            map_bound(high, key)?,
+        ))
+    }
+
+    /// The rows whose key lies between `low` and `high`, in key order.
+    fn get_idx_data_by_range(&self, low: Bound<&Value>, high: Bound<&Value>) -> Result<Vec<usize>> {
+        if [low, high]
+            .iter()
+            .any(|b| matches!(b, Included(Value::Null) | Excluded(Value::Null)))
+        {
+            return Ok(vec![]);
+        }
+        match self {
+            ColumnIndex::Int(index) => self.range_by_key(index, low, high, integer_key),
+            ColumnIndex::Bool(index) => self.range_by_key(index, low, high, bool_key),
+            ColumnIndex::Str(index) => self.range_by_key(index, low, high, str_key),
+            ColumnIndex::Date(index) => self.range_by_key(index, low, high, date_key),
+            ColumnIndex::Time(index) => self.range_by_key(index, low, high, time_key),
+            ColumnIndex::Timestamp(index) => self.range_by_key(index, low, high, timestamp_key),
+            ColumnIndex::SmallInt(index) => self.range_by_key(index, low, high, integer_key),
+            ColumnIndex::BigInt(index) => self.range_by_key(index, low, high, integer_key),
+            ColumnIndex::UnsignedSmallInt(index) => {
+                self.range_by_key(index, low, high, integer_key)
+            }
+            ColumnIndex::UnsignedInt(index) => self.range_by_key(index, low, high, integer_key),
+            ColumnIndex::UnsignedBigInt(index) => self.range_by_key(index, low, high, integer_key),
+            ColumnIndex::Decimal(index) => self.range_by_key(index, low, high, decimal_key),
+            ColumnIndex::Binary(index) => self.range_by_key(index, low, high, binary_key),
+            ColumnIndex::Uuid(index) => self.range_by_key(index, low, high, uuid_key),
+            ColumnIndex::Float(index) | ColumnIndex::Double(index) => {
+                self.range_by_key(index, low, high, float_key)
+            }
+            ColumnIndex::Interval(index) => self.range_by_key(index, low, high, interval_key),
+            ColumnIndex::Enum(_, index) => self.range_by_key(index, low, high, enum_key),
+            ColumnIndex::None => Ok(vec![]),
         }
     }
 }
 
+fn map_bound<T>(bound: Bound<&Value>, key: impl Fn(&Value) -> Result<T>) -> Result<Bound<T>> {
+    match bound {
+        Included(v) => Ok(Included(key(v)?)),
+        Excluded(v) => Ok(Excluded(key(v)?)),
+        Unbounded => Ok(Unbounded),
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct Table {
     pub columns: Vec<ColumnHeader>,
@@ -345,15 +916,14 @@ impl Table {
                 c.name.to_string(),
                 c.datatype.to_string(),
                 c.is_pk,
+                c.is_nullable,
+                c.default.clone(),
             ));
 
-            match DataType::new(c.datatype.to_string()) {
-                DataType::Int => table_data.insert(c.name.to_string(), ColumnData::Int(vec![])),
-                DataType::Float => table_data.insert(c.name.to_string(), ColumnData::Float(vec![])),
-                DataType::Str => table_data.insert(c.name.to_string(), ColumnData::Str(vec![])),
-                DataType::Bool => table_data.insert(c.name.to_string(), ColumnData::Bool(vec![])),
-                DataType::Invalid => table_data.insert(c.name.to_string(), ColumnData::None),
-            };
+            table_data.insert(
+                c.name.to_string(),
+                ColumnData::new(&DataType::new(c.datatype.to_string())),
+            );
         }
 
         Table {
@@ -363,321 +933,174 @@ impl Table {
         }
     }
 
-    pub fn get_column(&self, col_name: String) -> &ColumnHeader {
-        self.columns
+    /// Finds a column by name. `t.id` also finds `id`, and in the table of a
+    /// join, where columns are named like `t.id`, `id` finds the one column
+    /// of that name.
+    pub fn get_column(&self, col_name: String) -> Result<&ColumnHeader> {
+        if let Some(column) = self.columns.iter().find(|c| c.name == col_name) {
+            return Ok(column);
+        }
+        let suffix = format!(".{col_name}");
+        let unqualified = unqualified(&col_name);
+        let found: Vec<&ColumnHeader> = self
+            .columns
             .iter()
-            .filter(|c| c.name == col_name)
-            .collect::<Vec<&ColumnHeader>>()
-            .first()
-            .unwrap()
+            .filter(|c| c.name.ends_with(&suffix) || c.name == unqualified)
+            .collect();
+        match found.as_slice() {
+            [column] => Ok(column),
+            [] => Err(Error::Binding(format!(
+                "Cannot find column {} in table {}",
+                col_name, self.name
+            ))),
+            _ => Err(Error::Binding(format!(
+                "Column reference {col_name} is ambiguous"
+            ))),
+        }
     }
 
-    pub fn does_violate_unique_constraint(
-        &self,
-        cols: &[String],
-        values: &[String],
-    ) -> Result<(), String> {
-        for c in &self.columns {
-            if c.is_primary_key {
-                let col_idx = &c.index;
-                for (idx, name) in cols.iter().enumerate() {
-                    if *name == c.name {
-                        let val = &values[idx];
-
-                        match col_idx {
-                            ColumnIndex::Int(index) => {
-                                if let true = index.contains_key(
-                                    &val.parse::<i32>().expect("Couldn't parse value to an int."),
-                                ) {
-                                    return Err(format!(
-                                        "Error: unique constraint violation for column {}.
-                            Value {} already exists for column {}",
-                                        *name, val, *name
-                                    ));
-                                } else {
-                                    return Ok(());
-                                }
-                            }
-                            ColumnIndex::Bool(index) => {
-                                if let true = index.contains_key(
-                                    &val.parse::<bool>()
-                                        .expect("couldn't parse value to be a boolean"),
-                                ) {
-                                    return Err(format!(
-                                        "Error: unique constraint violation for column {}.
-                            Value {} already exists for column {}",
-                                        *name, val, *name
-                                    ));
-                                } else {
-                                    return Ok(());
-                                }
-                            }
-                            ColumnIndex::Str(index) => {
-                                if let true = index.contains_key(val) {
-                                    return Err(format!(
-                                        "Error: unique constraint violation for column {}.
-                            Value {} already exists for column {}",
-                                        *name, val, *name
-                                    ));
-                                } else {
-                                    return Ok(());
-                                }
-                            }
-                            ColumnIndex::None => {
-                                return Err(format!("Error: cannot find index for column {name}"));
-                            }
-                        }
-                    }
+    /// Moves the columns of the ENUM type named like `updated` over to it,
+    /// recoding their values and index keys by label.
+    pub fn update_enum(&mut self, updated: &Arc<EnumType>) -> Result<()> {
+        for column in &mut self.columns {
+            let Some(datatype) = column.datatype.with_enum(updated) else {
+                continue;
+            };
+            if let Some(data) = self.rows.get_mut(&column.name) {
+                let mut recoded = ColumnData::new(&datatype);
+                let mut index = ColumnIndex::Enum(updated.clone(), BTreeMap::new());
+                for value in data.get_values() {
+                    let value = value.coerce(&datatype)?;
+                    index.insert(&value, recoded.push(value.clone())?);
+                }
+                *data = recoded;
+                if let ColumnIndex::Enum(..) = column.index {
+                    column.index = index;
                 }
             }
+            column.datatype = datatype;
         }
         Ok(())
     }
 
-    pub fn insert_row(&mut self, cols: &[String], values: &[Vec<String>]) {
-        (0..cols.len()).for_each(|i| {
-            let key = &cols[i];
-            let table_col_data = self.rows.get_mut(&key.to_string()).unwrap();
+    pub fn column_type(&self, col_name: &str) -> Result<DataType> {
+        Ok(self.get_column(col_name.to_string())?.datatype.clone())
+    }
 
-            let mut column_headers = self
-                .columns
-                .iter_mut()
-                .filter(|c| c.name == *key)
-                .collect::<Vec<&mut ColumnHeader>>();
-
-            let col_index = column_headers
-                .first_mut()
-                .expect("Couldn't find column to insert row")
-                .get_mut_index();
-
-            values.iter().for_each(|value| {
-                let val = &value[i];
-                match table_col_data {
-                    ColumnData::Int(c_vec) => {
-                        let val = val.parse::<i32>().unwrap();
-                        c_vec.push(val);
-                        if let ColumnIndex::Int(index) = col_index {
-                            index.insert(val, table_col_data.count() - 1);
-                        }
-                    }
-                    ColumnData::Bool(c_vec) => {
-                        let val = val.parse::<bool>().unwrap();
-                        c_vec.push(val);
-                        if let ColumnIndex::Bool(index) = col_index {
-                            index.insert(val, table_col_data.count() - 1);
+    fn column_data(&self, col_name: &str) -> Result<&ColumnData> {
+        let column = self.get_column(col_name.to_string())?;
+        self.rows.get(&column.name).ok_or_else(|| {
+            Error::Binding(format!(
+                "Cannot find column {} in table {}",
+                col_name, self.name
+            ))
+        })
+    }
+
+    pub fn does_violate_unique_constraint(&self, cols: &[String], values: &[Value]) -> Result<()> {
+        for c in &self.columns {
+            if c.is_primary_key {
+                for (name, val) in cols.iter().zip(values) {
+                    if *name == c.name {
+                        if let ColumnIndex::None = c.index {
+                            return Err(Error::Constraint(format!(
+                                "cannot find index for column {name}"
+                            )));
                         }
-                    }
-                    ColumnData::Str(c_vec) => {
-                        let val = val.to_string();
-                        c_vec.push(val.clone());
-                        if let ColumnIndex::Str(index) = col_index {
-                            index.insert(val, table_col_data.count() - 1);
+                        if c.index.get_idx_data(&c.storable(val.clone())?)?.is_some() {
+                            return Err(duplicate_key(name, val));
                         }
+                        return Ok(());
                     }
-                    ColumnData::Float(c_vec) => {
-                        c_vec.push(val.parse::<f32>().unwrap());
-                    }
-                    ColumnData::None => panic!("None data Found"),
                 }
-            });
-        });
-    }
-
-    fn select_data(&self, columns_to_fetch: &[String], indexes: &[usize]) -> Vec<Vec<String>> {
-        let mut data = vec![];
-        for col in columns_to_fetch {
-            let row = self.rows.get(col).unwrap();
-            data.push(row.get_serialized_col_data_by_index(indexes));
+            }
         }
-        data
+        Ok(())
     }
 
-    fn execute_select_query_without_index(&self, sq: &SelectQuery) -> Vec<Vec<String>> {
-        let expr = sq.where_expressions.first();
-        match expr {
-            Some(where_expr) => {
-                let col = self.get_column(where_expr.left.to_string());
-                let row = self.rows.get(&col.name).unwrap();
-
-                let indices = row.get_serialized_col_data_by_scanning(where_expr);
-
-                sq.projection
-                    .iter()
-                    .map(|col_name| {
-                        self.rows
-                            .get(&col_name.to_string())
-                            .expect("The searched column doesn't exist")
-                    })
-                    .map(|col_data| col_data.get_serialized_col_data_by_index(&indices))
-                    .collect::<Vec<Vec<String>>>()
-            }
-
-            None => {
-                panic!("Invalid where expression given");
+    /// Checks rows inserted together against the primary key: each row's
+    /// key must be new to the table and to the rows before it.
+    pub fn check_unique_rows(&self, cols: &[String], rows: &[Vec<Value>]) -> Result<()> {
+        let Some(pk) = self.columns.iter().find(|c| c.is_primary_key) else {
+            return Ok(());
+        };
+        let Some(pos) = cols.iter().position(|c| *c == pk.name) else {
+            return Ok(());
+        };
+        let mut batch = ColumnIndex::new(&pk.datatype);
+        for (idx, row) in rows.iter().enumerate() {
+            self.does_violate_unique_constraint(cols, row)?;
+            if let Some(val) = row.get(pos) {
+                let key = pk.storable(val.clone())?;
+                if batch.get_idx_data(&key)?.is_some() {
+                    return Err(duplicate_key(&pk.name, val));
+                }
+                batch.insert(&key, idx);
             }
         }
+        Ok(())
     }
 
-    fn execute_select(
-        &self,
-        sq: &SelectQuery,
-        where_expr: &Expression,
-        op: &Binary,
-        col: &ColumnHeader,
-    ) -> Vec<Vec<String>> {
-        match &col
-            .index
-            .get_idx_data_by_range(&where_expr.right, op.clone())
-        {
-            Ok(indexes) => self.select_data(&sq.projection, indexes),
-            Err(e) => {
-                panic!("Error while trying to retrieve value from index: {}", e);
-            }
+    /// Inserts rows given as values for `cols`. Columns that aren't listed
+    /// are set to NULL.
+    pub fn insert_row(&mut self, cols: &[String], values: &[Vec<Value>]) -> Result<()> {
+        for col in cols {
+            self.get_column(col.to_string())?;
         }
-    }
 
-    pub fn execute_select_query(&self, sq: &SelectQuery) {
-        let mut data: Vec<Vec<String>> = vec![];
-
-        let expr = sq.where_expressions.first();
-        match expr {
-            Some(where_expr) => {
-                let col = self.get_column(where_expr.left.to_string());
-
-                if col.is_indexed {
-                    println!("Executing select expression with index");
-                    match &where_expr.op {
-                        Operator::Binary(bop) => match bop {
-                            Binary::Eq => match &col.index.get_idx_data(&where_expr.right) {
-                                Ok(v) => {
-                                    let matched_indexes = match v {
-                                        Some(idx) => vec![**idx],
-                                        None => vec![],
-                                    };
-                                    data = self.select_data(&sq.projection, &matched_indexes);
-                                }
-                                Err(e) => {
-                                    println!(
-                                        "Error while trying to retrieve value from index: {e}"
-                                    );
-                                }
-                            },
-                            Binary::Gt
-                            | Binary::Lt
-                            | Binary::LtEq
-                            | Binary::GtEq
-                            | Binary::NotEq => {
-                                data = self.execute_select(sq, where_expr, bop, col);
-                            }
-                        },
-                    }
-                } else {
-                    println!("Executing select expression without index");
-                    data = self.execute_select_query_without_index(sq);
-                }
+        // Check every value before touching the table, so a bad row leaves it unchanged.
+        let mut parsed_rows = vec![];
+        for value in values {
+            if value.len() != cols.len() {
+                return Err(Error::Binding(format!(
+                    "Expected {} values but got {}",
+                    cols.len(),
+                    value.len()
+                )));
             }
-            None => {
-                println!("In none block");
-                for col in &sq.projection {
-                    let row = self.rows.get(col).unwrap();
-                    let column = row.get_serialized_col_data();
-                    data.push(column);
+            let mut parsed = vec![];
+            for column in &self.columns {
+                let val = match cols.iter().position(|c| *c == column.name) {
+                    Some(pos) => column.storable(value[pos].clone())?,
+                    None => column.default_value()?,
+                };
+                if val.is_null() && !column.is_nullable {
+                    return Err(Error::Constraint(format!(
+                        "Column {} cannot be NULL",
+                        column.name
+                    )));
                 }
+                // A value the column's storage would refuse must fail here,
+                // before earlier columns of the row have been pushed.
+                ColumnData::new(&column.datatype).push(val.clone())?;
+                parsed.push(val);
             }
+            parsed_rows.push(parsed);
         }
 
-        let rotated_data = Self::rotate_2d_vec(&data);
-        Self::pretty_print(&rotated_data, &sq.projection);
-    }
-
-    pub fn print_table(&self) {
-        let mut table = PTable::new();
-        table.add_row(row!["Column Name", "Data Type"]);
-
-        for col in &self.columns {
-            table.add_row(row![col.name, col.datatype]);
-        }
-
-        table.printstd();
-    }
-
-    pub fn print_table_data(&self) {
-        let mut p_table = PTable::new();
-
-        let cnames = self
-            .columns
-            .iter()
-            .map(|col| col.name.to_string())
-            .collect::<Vec<String>>();
-
-        let header_row = Row::new(
-            cnames
-                .iter()
-                .map(|col| Cell::new(col))
-                .collect::<Vec<Cell>>(),
-        );
-
-        let first_col_data = self.rows.get(&self.columns.first().unwrap().name).unwrap();
-        let num_rows = first_col_data.count();
-        let mut print_table_rows: Vec<Row> = vec![Row::new(vec![]); num_rows];
-
-        for col_name in &cnames {
-            let col_val = self
-                .rows
-                .get(col_name)
-                .expect("Can't find any rows with the given column");
-            let columns: Vec<String> = col_val.get_serialized_col_data();
-
-            for i in 0..num_rows {
-                print_table_rows[i].add_cell(Cell::new(&columns[i]));
+        for row in parsed_rows {
+            for (column_header, val) in self.columns.iter_mut().zip(row) {
+                let table_col_data = self.rows.get_mut(&column_header.name).ok_or_else(|| {
+                    Error::Binding(format!(
+                        "Cannot find column {} in table {}",
+                        column_header.name, self.name
+                    ))
+                })?;
+
+                let row_idx = table_col_data.push(val.clone())?;
+                column_header.get_mut_index().insert(&val, row_idx);
             }
         }
+        Ok(())
+    }
 
-        p_table.add_row(header_row);
-        for row in print_table_rows {
-            p_table.add_row(row);
-        }
-
-        p_table.printstd();
+    pub fn row_count(&self) -> usize {
+        self.rows.values().map(|c| c.count()).max().unwrap_or(0)
     }
 
     pub fn column_exists(&self, column: String) -> bool {
         self.columns.iter().any(|col| col.name == column)
     }
-
-    fn rotate_2d_vec(data: &[Vec<String>]) -> Vec<Vec<&String>> {
-        match data.first() {
-            None => vec![vec![]],
-            _ => {
-                let number_of_rows = data.first().unwrap().len();
-                let number_of_cols = data.len();
-                let mut ret_data: Vec<Vec<&String>> = vec![vec![]; number_of_rows];
-
-                (0..number_of_rows).for_each(|row_idx| {
-                    (0..number_of_cols).for_each(|col_idx| {
-                        ret_data[row_idx].push(&data[col_idx][row_idx]);
-                    });
-                });
-
-                ret_data
-            }
-        }
-    }
-
-    fn pretty_print(data: &[Vec<&String>], header: &[String]) {
-        let mut p_table = PTable::new();
-
-        p_table.add_row(Row::new(
-            header.iter().map(|h| Cell::new(h)).collect::<Vec<Cell>>(),
-        ));
-
-        for row in data {
-            p_table.add_row(Row::new(
-                row.iter().map(|c| Cell::new(c)).collect::<Vec<Cell>>(),
-            ));
-        }
-        p_table.printstd();
-    }
 }
 
 #[cfg(test)]
@@ -721,6 +1144,17 @@ mod tests {
             assert_eq!(table.name, "users");
             assert_eq!(column_names, expected_column_names);
             assert_eq!(column_types, expected_column_types);
+
+            // The column that can't store anything fails the row before any
+            // column has been written.
+            let mut table = table;
+            let cols = vec!["id".to_string(), "name".to_string()];
+            let row = vec![Value::Int(1), Value::Str("a".to_string())];
+            assert!(matches!(
+                table.insert_row(&cols, &[row]),
+                Err(Error::Type(_))
+            ));
+            assert!(table.rows.values().all(|c| c.count() == 0));
         }
     }
 
@@ -734,9 +1168,9 @@ mod tests {
             let cq = CreateQuery::new(statement).unwrap();
             let mut table = Table::new(cq);
             let cols = vec!["id".to_string(), "name".to_string()];
-            let val = vec!["1".to_string(), "tahmid".to_string()];
+            let val = vec![Value::Int(1), Value::Str("tahmid".to_string())];
             table.does_violate_unique_constraint(&cols, &val).unwrap();
-            table.insert_row(&cols, &[val.clone()]);
+            table.insert_row(&cols, std::slice::from_ref(&val)).unwrap();
             assert!(table.does_violate_unique_constraint(&cols, &val).is_err());
         }
     }
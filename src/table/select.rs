@@ -0,0 +1,295 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound::{Excluded, Included, Unbounded};
+
+use crate::error::Result;
+use crate::expr::aggregate::Aggregate;
+use crate::expr::Scope;
+use crate::parser::select::{Binary, Expression, Operator, OrderBy, SelectQuery};
+use crate::parser::Operand;
+use crate::value::{Key, Value};
+
+use super::{Table, WindowScope};
+
+/// Evaluates expressions against a single row of a table.
+pub struct RowScope<'a> {
+    pub table: &'a Table,
+    pub idx: usize,
+}
+
+impl Scope for RowScope<'_> {
+    fn column(&self, name: &str) -> Result<Value> {
+        Ok(self.table.column_data(name)?.get_value(self.idx))
+    }
+}
+
+/// Evaluates expressions once for a group of rows. Columns are read from
+/// the first row of the group, which is only allowed for grouped columns.
+pub struct GroupScope<'a> {
+    pub table: &'a Table,
+    pub rows: &'a [usize],
+}
+
+impl Scope for GroupScope<'_> {
+    fn column(&self, name: &str) -> Result<Value> {
+        match self.rows.first() {
+            Some(idx) => Ok(self.table.column_data(name)?.get_value(*idx)),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn aggregate(&self, aggregate: &Aggregate) -> Result<Value> {
+        let mut values = self
+            .rows
+            .iter()
+            .map(|idx| match &aggregate.arg {
+                Some(arg) => arg.eval(&RowScope {
+                    table: self.table,
+                    idx: *idx,
+                }),
+                None => Ok(Value::Bool(true)),
+            })
+            .collect::<Result<Vec<Value>>>()?;
+        if aggregate.distinct {
+            let mut seen = HashSet::new();
+            values.retain(|v| seen.insert(Key(vec![v.clone()])));
+        }
+        aggregate.func.accumulate(values)
+    }
+}
+
+pub(crate) fn compare_sort_keys(a: &[Value], b: &[Value], order_by: &[OrderBy]) -> Ordering {
+    for ((a, b), o) in a.iter().zip(b).zip(order_by) {
+        let ord = match (a.is_null(), b.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if o.nulls_first => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if o.nulls_first => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            _ if o.asc => a.compare(b).unwrap_or(Ordering::Equal),
+            _ => a.compare(b).unwrap_or(Ordering::Equal).reverse(),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+impl Table {
+    /// Whether the index of the compared column can find the matching rows.
+    fn uses_index(&self, where_expr: &Expression) -> bool {
+        // The index holds values of the column's type, so a literal that
+        // isn't one, like 1.5 for an int column, is compared row by row.
+        let indexed = self.get_column(where_expr.left.to_string()).is_ok_and(|c| {
+            c.is_indexed
+                && where_expr.right.iter().all(|operand| match operand {
+                    Operand::Literal(v) => v.clone().coerce(&c.datatype).as_ref() == Ok(v),
+                    Operand::Param(_) => true,
+                })
+        });
+        match (&where_expr.op, where_expr.right.as_slice()) {
+            (Operator::Binary(Binary::NotEq), _) => false,
+            (Operator::Binary(_), _) => indexed,
+            (Operator::Match(pattern), [Operand::Literal(Value::Str(p))]) => {
+                indexed && pattern.prefix(p).is_some()
+            }
+            (Operator::Match(_), _) => false,
+            (Operator::Between { negated } | Operator::In { negated }, _) => indexed && !negated,
+        }
+    }
+
+    fn rows_matching(&self, where_expr: &Expression) -> Result<Vec<usize>> {
+        let col = self.get_column(where_expr.left.to_string())?;
+        let data = self.column_data(&col.name)?;
+        if !self.uses_index(where_expr) {
+            return data.get_serialized_col_data_by_scanning(where_expr);
+        }
+
+        let terms = where_expr.terms()?;
+        match (&where_expr.op, terms.as_slice()) {
+            (Operator::Match(pattern), [Value::Str(p)]) => {
+                let prefix = pattern.prefix(p).unwrap_or_default();
+                let mut rows = vec![];
+                for idx in col.index.get_idx_data_by_prefix(&prefix) {
+                    if where_expr.matches(&data.get_value(idx))? {
+                        rows.push(idx);
+                    }
+                }
+                Ok(rows)
+            }
+            (Operator::Between { .. }, [low, high]) => col
+                .index
+                .get_idx_data_by_range(Included(*low), Included(*high)),
+            (Operator::In { .. }, terms) => {
+                let mut rows = vec![];
+                for term in terms {
+                    rows.extend(col.index.get_idx_data(term)?);
+                }
+                rows.sort_unstable();
+                rows.dedup();
+                Ok(rows)
+            }
+            (Operator::Binary(Binary::Eq), [term]) => {
+                Ok(col.index.get_idx_data(term)?.into_iter().copied().collect())
+            }
+            (Operator::Binary(bop), [term]) => {
+                let bounds = match bop {
+                    Binary::Gt => (Excluded(*term), Unbounded),
+                    Binary::GtEq => (Included(*term), Unbounded),
+                    Binary::Lt => (Unbounded, Excluded(*term)),
+                    _ => (Unbounded, Included(*term)),
+                };
+                col.index.get_idx_data_by_range(bounds.0, bounds.1)
+            }
+            _ => data.get_serialized_col_data_by_scanning(where_expr),
+        }
+    }
+
+    /// Finds the rows that satisfy the WHERE clause. One comparison on an
+    /// indexed column, if there is one, picks the candidate rows; the rest
+    /// of the clause is checked row by row.
+    fn matching_rows(&self, sq: &SelectQuery) -> Result<Vec<usize>> {
+        let indexed = sq.where_expressions.iter().position(|e| self.uses_index(e));
+        let chosen = match (indexed, sq.where_expressions.is_empty()) {
+            (Some(i), _) => Some(i),
+            (None, false) => Some(0),
+            (None, true) => None,
+        };
+        let mut rows = match chosen {
+            Some(i) => self.rows_matching(&sq.where_expressions[i])?,
+            None => (0..self.row_count()).collect(),
+        };
+
+        for (i, e) in sq.where_expressions.iter().enumerate() {
+            if Some(i) == chosen {
+                continue;
+            }
+            let data = self.column_data(&e.left)?;
+            let mut kept = vec![];
+            for idx in rows {
+                if e.matches(&data.get_value(idx))? {
+                    kept.push(idx);
+                }
+            }
+            rows = kept;
+        }
+
+        if let Some(filter) = &sq.filter {
+            let mut kept = vec![];
+            for idx in rows {
+                if filter.eval(&RowScope { table: self, idx })? == Value::Bool(true) {
+                    kept.push(idx);
+                }
+            }
+            rows = kept;
+        }
+        Ok(rows)
+    }
+
+    /// Splits rows into groups with equal GROUP BY values, in order of first
+    /// appearance. Without GROUP BY all rows form a single group.
+    fn group_rows(&self, sq: &SelectQuery, rows: Vec<usize>) -> Result<Vec<Vec<usize>>> {
+        if sq.group_by.is_empty() {
+            return Ok(vec![rows]);
+        }
+        let mut positions: HashMap<Key, usize> = HashMap::new();
+        let mut groups: Vec<Vec<usize>> = vec![];
+        for idx in rows {
+            let scope = RowScope { table: self, idx };
+            let key = Key(sq
+                .group_by
+                .iter()
+                .map(|e| e.eval(&scope))
+                .collect::<Result<Vec<Value>>>()?);
+            match positions.get(&key) {
+                Some(group) => groups[*group].push(idx),
+                None => {
+                    positions.insert(key, groups.len());
+                    groups.push(vec![idx]);
+                }
+            }
+        }
+        Ok(groups)
+    }
+
+    /// The ORDER BY keys and the SELECT list values for one output row.
+    fn output_row(sq: &SelectQuery, scope: &dyn Scope) -> Result<(Vec<Value>, Vec<Value>)> {
+        let keys = sq
+            .order_by
+            .iter()
+            .map(|o| o.expr.eval(scope))
+            .collect::<Result<Vec<Value>>>()?;
+        let row = sq
+            .projection
+            .iter()
+            .map(|item| item.expr.eval(scope))
+            .collect::<Result<Vec<Value>>>()?;
+        Ok((keys, row))
+    }
+
+    /// Evaluates the SELECT list for every matching row or group.
+    pub fn execute_select_query(&self, sq: &SelectQuery) -> Result<Vec<Vec<Value>>> {
+        let offset = sq.offset.unwrap_or(0) as usize;
+        let limit = sq.limit.map_or(usize::MAX, |l| l as usize);
+        let rows = self.matching_rows(sq)?;
+        let mut windows = vec![];
+        for expr in sq.projection.iter().map(|i| &i.expr) {
+            expr.windows(&mut windows);
+        }
+        for o in &sq.order_by {
+            o.expr.windows(&mut windows);
+        }
+
+        let mut output = vec![];
+        if !windows.is_empty() {
+            let windows = windows
+                .into_iter()
+                .map(|w| Ok((w, self.window_values(w, &rows)?)))
+                .collect::<Result<Vec<_>>>()?;
+            for (position, idx) in rows.into_iter().enumerate() {
+                let scope = WindowScope {
+                    row: RowScope { table: self, idx },
+                    position,
+                    windows: &windows,
+                };
+                output.push(Self::output_row(sq, &scope)?);
+            }
+        } else if sq.is_aggregate() {
+            for group in self.group_rows(sq, rows)? {
+                let scope = GroupScope {
+                    table: self,
+                    rows: &group,
+                };
+                if let Some(having) = &sq.having {
+                    if having.eval(&scope)? != Value::Bool(true) {
+                        continue;
+                    }
+                }
+                output.push(Self::output_row(sq, &scope)?);
+            }
+        } else if sq.order_by.is_empty() && !sq.distinct {
+            // Without sorting, skipped rows don't need to be evaluated at all.
+            for idx in rows.into_iter().skip(offset).take(limit) {
+                output.push(Self::output_row(sq, &RowScope { table: self, idx })?);
+            }
+            return Ok(output.into_iter().map(|(_, row)| row).collect());
+        } else {
+            for idx in rows {
+                output.push(Self::output_row(sq, &RowScope { table: self, idx })?);
+            }
+        }
+
+        if sq.distinct {
+            let mut seen = HashSet::new();
+            output.retain(|(_, row)| seen.insert(Key(row.clone())));
+        }
+        output.sort_by(|(a, _), (b, _)| compare_sort_keys(a, b, &sq.order_by));
+        Ok(output
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(_, row)| row)
+            .collect())
+    }
+}
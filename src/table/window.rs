@@ -0,0 +1,239 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
+use crate::expr::window::{Frame, FrameBound, FrameUnits, Window, WindowFunc};
+use crate::expr::{ScalarExpr, Scope};
+use crate::value::{Key, Value};
+
+use super::select::{compare_sort_keys, RowScope};
+use super::Table;
+
+/// Evaluates expressions against one row, reading window function results
+/// that were computed for all rows beforehand.
+pub struct WindowScope<'a> {
+    pub row: RowScope<'a>,
+    /// The position of the row among the rows the windows were computed for.
+    pub position: usize,
+    pub windows: &'a [(&'a Window, Vec<Value>)],
+}
+
+impl Scope for WindowScope<'_> {
+    fn column(&self, name: &str) -> Result<Value> {
+        self.row.column(name)
+    }
+
+    fn window(&self, window: &Window) -> Result<Value> {
+        match self.windows.iter().find(|(w, _)| *w == window) {
+            Some((_, values)) => Ok(values[self.position].clone()),
+            None => Err(Error::Parse(format!(
+                "Window function {} was not computed",
+                window.func
+            ))),
+        }
+    }
+}
+
+fn eval_all<'a>(
+    exprs: impl IntoIterator<Item = &'a ScalarExpr>,
+    scope: &dyn Scope,
+) -> Result<Vec<Value>> {
+    exprs.into_iter().map(|e| e.eval(scope)).collect()
+}
+
+/// One partition, with its rows sorted by the window's ORDER BY.
+struct Partition<'a> {
+    window: &'a Window,
+    keys: Vec<&'a [Value]>,
+    /// For every row, the first and one past the last row of its peers:
+    /// the rows with equal ORDER BY values.
+    peers: Vec<(usize, usize)>,
+}
+
+impl Partition<'_> {
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn frame(&self, i: usize) -> (usize, usize) {
+        let n = self.len();
+        let Some(Frame { units, start, end }) = &self.window.frame else {
+            return match self.window.order_by.is_empty() {
+                true => (0, n),
+                false => (0, self.peers[i].1),
+            };
+        };
+        let (start, end) = match units {
+            FrameUnits::Rows => {
+                let offset = |v: &Value| match v {
+                    Value::Int(v) => *v as usize,
+                    _ => 0,
+                };
+                let start = match start {
+                    FrameBound::UnboundedPreceding => 0,
+                    FrameBound::Preceding(k) => i.saturating_sub(offset(k)),
+                    FrameBound::CurrentRow => i,
+                    FrameBound::Following(k) => i.saturating_add(offset(k)),
+                    FrameBound::UnboundedFollowing => n,
+                };
+                let end = match end {
+                    FrameBound::UnboundedPreceding => 0,
+                    FrameBound::Preceding(k) => (i + 1).saturating_sub(offset(k)),
+                    FrameBound::CurrentRow => i + 1,
+                    FrameBound::Following(k) => i.saturating_add(offset(k)).saturating_add(1),
+                    FrameBound::UnboundedFollowing => n,
+                };
+                (start, end)
+            }
+            FrameUnits::Range => (
+                self.range_bound(i, start, true),
+                self.range_bound(i, end, false),
+            ),
+        };
+        (start.min(n), end.min(n))
+    }
+
+    /// Where a RANGE frame starts (or ends, one past the last row). Offsets
+    /// are distances from the current row's value in ORDER BY direction, and
+    /// a NULL value only has its peers in range.
+    fn range_bound(&self, i: usize, bound: &FrameBound, is_start: bool) -> usize {
+        let peers = self.peers[i];
+        let offset = match bound {
+            FrameBound::UnboundedPreceding => return 0,
+            FrameBound::UnboundedFollowing => return self.len(),
+            FrameBound::CurrentRow if is_start => return peers.0,
+            FrameBound::CurrentRow => return peers.1,
+            FrameBound::Preceding(k) => -k.as_f64().unwrap_or(0.0),
+            FrameBound::Following(k) => k.as_f64().unwrap_or(0.0),
+        };
+        let Some(current) = self.keys[i][0].as_f64() else {
+            return if is_start { peers.0 } else { peers.1 };
+        };
+        let direction = match self.window.order_by[0].asc {
+            true => 1.0,
+            false => -1.0,
+        };
+        // NULLs sort together at one end, and the distance grows along the rest.
+        let first = self.keys.iter().position(|k| !k[0].is_null()).unwrap_or(0);
+        let last = self
+            .keys
+            .iter()
+            .rposition(|k| !k[0].is_null())
+            .map_or(0, |l| l + 1);
+        let distance = |k: &&[Value]| (k[0].as_f64().unwrap_or(0.0) - current) * direction;
+        let keys = &self.keys[first..last];
+        first
+            + match is_start {
+                true => keys.partition_point(|k| distance(k) < offset),
+                false => keys.partition_point(|k| distance(k) <= offset),
+            }
+    }
+}
+
+impl Table {
+    /// Computes a window function for `rows`, returning the value for each
+    /// row in the same order. Rows are split into partitions and each
+    /// partition is sorted as a permutation of row ids; the column data
+    /// itself is never reordered.
+    pub(crate) fn window_values(&self, window: &Window, rows: &[usize]) -> Result<Vec<Value>> {
+        let mut sort_keys = Vec::with_capacity(rows.len());
+        let mut args = Vec::with_capacity(rows.len());
+        let mut positions: HashMap<Key, usize> = HashMap::new();
+        let mut partitions: Vec<Vec<usize>> = vec![];
+        for (position, idx) in rows.iter().enumerate() {
+            let scope = RowScope {
+                table: self,
+                idx: *idx,
+            };
+            sort_keys.push(eval_all(window.order_by.iter().map(|o| &o.expr), &scope)?);
+            args.push(eval_all(&window.args, &scope)?);
+            let key = Key(eval_all(&window.partition_by, &scope)?);
+            match positions.get(&key) {
+                Some(partition) => partitions[*partition].push(position),
+                None => {
+                    positions.insert(key, partitions.len());
+                    partitions.push(vec![position]);
+                }
+            }
+        }
+
+        let mut values = vec![Value::Null; rows.len()];
+        for mut permutation in partitions {
+            permutation.sort_by(|a, b| {
+                compare_sort_keys(&sort_keys[*a], &sort_keys[*b], &window.order_by)
+            });
+            let keys: Vec<&[Value]> = permutation.iter().map(|p| &*sort_keys[*p]).collect();
+            let mut peers = vec![(0, 0); keys.len()];
+            let mut start = 0;
+            for end in 1..=keys.len() {
+                let same = end < keys.len()
+                    && compare_sort_keys(keys[start], keys[end], &window.order_by)
+                        == Ordering::Equal;
+                if !same {
+                    peers[start..end].fill((start, end));
+                    start = end;
+                }
+            }
+            let partition = Partition {
+                window,
+                keys,
+                peers,
+            };
+            let arg = |i: usize, n: usize| args[permutation[i]].get(n).cloned();
+
+            let mut dense_rank = 0;
+            for i in 0..partition.len() {
+                if partition.peers[i].0 == i {
+                    dense_rank += 1;
+                }
+                let value = match &window.func {
+                    WindowFunc::RowNumber => Value::Int(i as i32 + 1),
+                    WindowFunc::Rank => Value::Int(partition.peers[i].0 as i32 + 1),
+                    WindowFunc::DenseRank => Value::Int(dense_rank),
+                    WindowFunc::Lag | WindowFunc::Lead => {
+                        let offset = match arg(i, 1) {
+                            None => 1,
+                            Some(offset) if offset.as_i128().is_some_and(|o| o >= 0) => {
+                                offset.as_i128().map_or(0, |o| o as usize)
+                            }
+                            Some(Value::Null) => {
+                                values[permutation[i]] = Value::Null;
+                                continue;
+                            }
+                            Some(offset) => {
+                                return Err(Error::OutOfRange(format!(
+                                    "The offset of {} must be a non-negative Int, not {offset}",
+                                    window.func
+                                )))
+                            }
+                        };
+                        let target = match window.func {
+                            WindowFunc::Lag => i.checked_sub(offset),
+                            _ => i.checked_add(offset).filter(|t| *t < partition.len()),
+                        };
+                        match target {
+                            Some(t) => arg(t, 0).unwrap_or(Value::Null),
+                            None => arg(i, 2).unwrap_or(Value::Null),
+                        }
+                    }
+                    WindowFunc::FirstValue | WindowFunc::LastValue => match partition.frame(i) {
+                        (start, end) if start >= end => Value::Null,
+                        (start, _) if window.func == WindowFunc::FirstValue => {
+                            arg(start, 0).unwrap_or(Value::Null)
+                        }
+                        (_, end) => arg(end - 1, 0).unwrap_or(Value::Null),
+                    },
+                    WindowFunc::Aggregate(func) => {
+                        let (start, end) = partition.frame(i);
+                        let frame = (start..end.max(start))
+                            .map(|j| arg(j, 0).unwrap_or(Value::Bool(true)))
+                            .collect();
+                        func.accumulate(frame)?
+                    }
+                };
+                values[permutation[i]] = value;
+            }
+        }
+        Ok(values)
+    }
+}
@@ -0,0 +1,523 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::expr::promote;
+use crate::table::{DataType, EnumType};
+
+pub mod array;
+pub mod datetime;
+pub mod decimal;
+pub mod json;
+pub mod uuid;
+
+pub use datetime::Interval;
+pub use decimal::Decimal;
+pub use json::Json;
+
+/// A single typed value, as written in a query, stored in a table or
+/// produced by a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    Str(String),
+    Bool(bool),
+    /// Days since 1970-01-01.
+    Date(i32),
+    /// Microseconds since midnight.
+    Time(i64),
+    /// Microseconds since 1970-01-01 00:00:00, in no particular time zone.
+    Timestamp(i64),
+    /// Microseconds since 1970-01-01 00:00:00 UTC.
+    TimestampTz(i64),
+    Interval(Interval),
+    SmallInt(i16),
+    BigInt(i64),
+    UnsignedSmallInt(u16),
+    UnsignedInt(u32),
+    UnsignedBigInt(u64),
+    Double(f64),
+    Decimal(Decimal),
+    Binary(Vec<u8>),
+    Json(Json),
+    Uuid(u128),
+    /// Items of one type, or NULLs.
+    Array(Vec<Value>),
+    /// The position of a label among those of its ENUM type.
+    Enum(u32, Arc<EnumType>),
+    Null,
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn datatype(&self) -> Option<DataType> {
+        match self {
+            Value::Int(_) => Some(DataType::Int),
+            Value::Float(_) => Some(DataType::Float),
+            Value::Str(_) => Some(DataType::Str),
+            Value::Bool(_) => Some(DataType::Bool),
+            Value::Date(_) => Some(DataType::Date),
+            Value::Time(_) => Some(DataType::Time),
+            Value::Timestamp(_) => Some(DataType::Timestamp),
+            Value::TimestampTz(_) => Some(DataType::TimestampTz),
+            Value::Interval(_) => Some(DataType::Interval),
+            Value::SmallInt(_) => Some(DataType::SmallInt),
+            Value::BigInt(_) => Some(DataType::BigInt),
+            Value::UnsignedSmallInt(_) => Some(DataType::UnsignedSmallInt),
+            Value::UnsignedInt(_) => Some(DataType::UnsignedInt),
+            Value::UnsignedBigInt(_) => Some(DataType::UnsignedBigInt),
+            Value::Double(_) => Some(DataType::Double),
+            Value::Decimal(d) => Some(DataType::Decimal(decimal::MAX_PRECISION, d.scale())),
+            Value::Binary(_) => Some(DataType::Binary),
+            Value::Json(_) => Some(DataType::Json),
+            Value::Uuid(_) => Some(DataType::Uuid),
+            // The type of an array with no items but NULLs isn't known.
+            Value::Array(items) => Some(DataType::Array(Box::new(
+                items.iter().find_map(Value::datatype)?,
+            ))),
+            Value::Enum(_, datatype) => Some(DataType::Enum(datatype.clone())),
+            Value::Null => None,
+        }
+    }
+
+    /// An array of `items` converted to one type: numbers to the widest of
+    /// their types, and anything else to the type of the first item.
+    pub fn array(items: Vec<Value>) -> Result<Value> {
+        let mut datatype: Option<DataType> = None;
+        for item in &items {
+            datatype = match (datatype, item.datatype()) {
+                (None, d) | (d, None) => d,
+                (Some(a), Some(b)) if a.is_numeric() && b.is_numeric() => {
+                    promote(&a, &b).or(Some(a))
+                }
+                (a, _) => a,
+            };
+        }
+        match datatype {
+            Some(datatype) => items
+                .into_iter()
+                .map(|item| item.coerce(&datatype))
+                .collect::<Result<_>>()
+                .map(Value::Array),
+            None => Ok(Value::Array(items)),
+        }
+    }
+
+    /// An integer of any type, widened so that every one of them fits.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Value::SmallInt(v) => Some(*v as i128),
+            Value::Int(v) => Some(*v as i128),
+            Value::BigInt(v) => Some(*v as i128),
+            Value::UnsignedSmallInt(v) => Some(*v as i128),
+            Value::UnsignedInt(v) => Some(*v as i128),
+            Value::UnsignedBigInt(v) => Some(*v as i128),
+            _ => None,
+        }
+    }
+
+    /// A number of any type as an integer, if it has no fraction.
+    pub fn as_whole_number(&self) -> Option<i128> {
+        match self {
+            Value::Decimal(v) => v.to_i128(),
+            Value::Float(_) | Value::Double(_) => {
+                let f = self.as_f64()?;
+                (f.is_finite() && f.fract() == 0.0).then_some(f as i128)
+            }
+            v => v.as_i128(),
+        }
+    }
+
+    /// A number of any type as a double.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(v) => Some(*v as f64),
+            Value::Double(v) => Some(*v),
+            Value::Decimal(v) => Some(v.to_f64()),
+            v => v.as_i128().map(|v| v as f64),
+        }
+    }
+
+    /// An integer or decimal as an exact decimal.
+    pub fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            Value::Decimal(v) => Some(*v),
+            v => Decimal::from_i128(v.as_i128()?),
+        }
+    }
+
+    /// `v` as a value of the integer type `datatype`, or `None` if it is out
+    /// of the type's range.
+    pub fn from_i128(v: i128, datatype: &DataType) -> Option<Value> {
+        let value = match datatype {
+            DataType::SmallInt => Value::SmallInt(v.try_into().ok()?),
+            DataType::Int => Value::Int(v.try_into().ok()?),
+            DataType::BigInt => Value::BigInt(v.try_into().ok()?),
+            DataType::UnsignedSmallInt => Value::UnsignedSmallInt(v.try_into().ok()?),
+            DataType::UnsignedInt => Value::UnsignedInt(v.try_into().ok()?),
+            DataType::UnsignedBigInt => Value::UnsignedBigInt(v.try_into().ok()?),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// `v` as a value of the float type `datatype`, or `None` if it is too
+    /// large for it.
+    pub fn from_f64(v: f64, datatype: &DataType) -> Option<Value> {
+        let value = match datatype {
+            DataType::Float => Value::Float(v as f32),
+            DataType::Double => Value::Double(v),
+            _ => return None,
+        };
+        match value.as_f64() {
+            Some(f) if f.is_finite() || !v.is_finite() => Some(value),
+            _ => None,
+        }
+    }
+
+    /// A date or timestamp as microseconds since the epoch, with dates at
+    /// midnight and timestamps without a time zone taken to be in UTC.
+    pub fn as_micros(&self) -> Option<i64> {
+        match self {
+            Value::Date(d) => Some(datetime::micros_from_days(*d)),
+            Value::Timestamp(t) | Value::TimestampTz(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    /// Converts the value so it can be stored in, or compared with, a column
+    /// of type `datatype`. Text is parsed, so `'42'` fits an int column, but
+    /// `'abc'` is a type error. Numbers only convert without losing their
+    /// fraction, so `3.0` fits an int column and `3.5` is a type error;
+    /// `CAST` rounds instead.
+    pub fn coerce(self, datatype: &DataType) -> Result<Value> {
+        if datatype.is_numeric() {
+            return self.coerce_number(datatype);
+        }
+        let coerced = match (datatype, self) {
+            (_, Value::Null) => Some(Value::Null),
+            (DataType::Bool, Value::Bool(b)) => Some(Value::Bool(b)),
+            (DataType::Bool, v) if v.as_i128() == Some(0) => Some(Value::Bool(false)),
+            (DataType::Bool, v) if v.as_i128() == Some(1) => Some(Value::Bool(true)),
+            (DataType::Bool, Value::Str(s)) => match s.trim().to_lowercase().as_ref() {
+                "t" | "true" | "1" => Some(Value::Bool(true)),
+                "f" | "false" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            (DataType::Date, Value::Date(d)) => Some(Value::Date(d)),
+            (DataType::Date, Value::Timestamp(t) | Value::TimestampTz(t)) => {
+                Some(Value::Date(datetime::days_from_micros(t)))
+            }
+            (DataType::Date, Value::Str(s)) => datetime::parse_date(&s).map(Value::Date),
+            (DataType::Time, Value::Time(t)) => Some(Value::Time(t)),
+            (DataType::Time, Value::Str(s)) => datetime::parse_time(&s).map(Value::Time),
+            (
+                DataType::Timestamp,
+                v @ (Value::Date(_) | Value::Timestamp(_) | Value::TimestampTz(_)),
+            ) => v.as_micros().map(Value::Timestamp),
+            (DataType::Timestamp, Value::Str(s)) => {
+                datetime::parse_timestamp(&s).map(Value::Timestamp)
+            }
+            (
+                DataType::TimestampTz,
+                v @ (Value::Date(_) | Value::Timestamp(_) | Value::TimestampTz(_)),
+            ) => v.as_micros().map(Value::TimestampTz),
+            (DataType::TimestampTz, Value::Str(s)) => {
+                datetime::parse_timestamptz(&s).map(Value::TimestampTz)
+            }
+            (DataType::Interval, Value::Interval(i)) => Some(Value::Interval(i)),
+            (DataType::Interval, Value::Str(s)) => Interval::parse(&s).map(Value::Interval),
+            (DataType::Binary, Value::Binary(b)) => Some(Value::Binary(b)),
+            (DataType::Binary, Value::Str(s)) => match s.strip_prefix("\\x") {
+                Some(hex) => from_hex(hex).map(Value::Binary),
+                None => Some(Value::Binary(s.into_bytes())),
+            },
+            (DataType::Uuid, Value::Uuid(u)) => Some(Value::Uuid(u)),
+            (DataType::Uuid, Value::Str(s)) => uuid::parse(&s).map(Value::Uuid),
+            (DataType::Uuid, Value::Binary(b)) if b.len() == 16 => b
+                .try_into()
+                .ok()
+                .map(|b| Value::Uuid(u128::from_be_bytes(b))),
+            (DataType::Array(item), Value::Array(items)) => Some(Value::Array(
+                items
+                    .into_iter()
+                    .map(|v| v.coerce(item))
+                    .collect::<Result<_>>()?,
+            )),
+            (DataType::Array(item), Value::Str(s)) => match array::parse(&s) {
+                Some(items) => Some(Value::Array(
+                    items
+                        .into_iter()
+                        .map(|v| v.coerce(item))
+                        .collect::<Result<_>>()?,
+                )),
+                None => None,
+            },
+            (DataType::Enum(t), Value::Str(label)) => {
+                t.code(&label).map(|code| Value::Enum(code, t.clone()))
+            }
+            // The same type from before labels were added to it.
+            (DataType::Enum(t), Value::Enum(code, from)) if from.name == t.name => from
+                .labels
+                .get(code as usize)
+                .and_then(|label| t.code(label))
+                .map(|code| Value::Enum(code, t.clone())),
+            (DataType::Json, Value::Str(s)) => Json::parse(&s).map(Value::Json),
+            (DataType::Json, v) => match Json::from_value(&v) {
+                Some(json) => Some(Value::Json(json)),
+                None => return Err(type_mismatch(&v, datatype)),
+            },
+            (DataType::Str, Value::Str(s)) => Some(Value::Str(s)),
+            (DataType::Str, v) => Some(Value::Str(v.to_string())),
+            (_, v) => return Err(type_mismatch(&v, datatype)),
+        };
+        // Only text is parsed above, so a failed parse is reported against it.
+        coerced.ok_or_else(|| Error::Type(format!("Invalid input for type {datatype}")))
+    }
+
+    /// The number this value stands for, if coercing it to `datatype` would
+    /// change it, like 1.5 for an int or 3.001 for a DECIMAL(5,2). A value
+    /// compared with such a column is kept as this number, since `compare`
+    /// widens, rather than narrowed to one it isn't equal to. Text is read
+    /// as a number, and floating-point types, being approximate, narrow.
+    pub fn inexact_for(&self, datatype: &DataType) -> Option<Value> {
+        if !datatype.is_integer() && !matches!(datatype, DataType::Decimal(..)) {
+            return None;
+        }
+        let exact = match self {
+            Value::Str(s) => Decimal::parse(s.trim())
+                .map(Value::Decimal)
+                .or_else(|| s.trim().parse().ok().map(Value::Double))?,
+            v if v.as_f64().is_some() => v.clone(),
+            _ => return None,
+        };
+        match exact.clone().coerce(datatype) {
+            Ok(coerced) if coerced.compare(&exact) == Some(Ordering::Equal) => None,
+            _ => Some(exact),
+        }
+    }
+
+    /// Converts the value to a numeric type, failing with a range error if
+    /// it is too large for the type. A DECIMAL's scale is the precision it
+    /// keeps, so extra fraction digits round half up to it, as they do in
+    /// Postgres and MySQL, and `1.005` is stored in a DECIMAL(10,2) as
+    /// `1.01`. An integer type keeps no fraction at all, so a number with
+    /// one, like `1.5`, is rejected rather than silently becoming another
+    /// number; CAST rounds it explicitly.
+    fn coerce_number(self, datatype: &DataType) -> Result<Value> {
+        let text = match &self {
+            Value::Null => return Ok(Value::Null),
+            Value::Str(s) => Some(s.trim()),
+            _ => None,
+        };
+        let coerced = match (datatype, text) {
+            (DataType::Decimal(precision, scale), _) => {
+                let decimal = match (&self, text) {
+                    (_, Some(s)) => Decimal::parse(s),
+                    (Value::Float(f), None) => Decimal::from_float(f),
+                    (Value::Double(f), None) => Decimal::from_float(f),
+                    (v, None) => v.as_decimal(),
+                };
+                // A number with too many digits is out of range, not invalid.
+                let is_number = decimal.is_some()
+                    || self.as_f64().is_some()
+                    || text.is_some_and(|s| s.parse::<f64>().is_ok());
+                is_number.then(|| {
+                    decimal
+                        .and_then(|d| d.rescale(*scale, decimal::Rounding::HalfUp))
+                        .filter(|d| d.fits(*precision))
+                        .map(Value::Decimal)
+                })
+            }
+            (d, Some(s)) if d.is_integer() => s.parse().ok().map(|n| Value::from_i128(n, d)),
+            (d, None) if d.is_integer() => self.as_whole_number().map(|n| Value::from_i128(n, d)),
+            (d, Some(s)) => s.parse().ok().map(|n| Value::from_f64(n, d)),
+            (d, None) => self.as_f64().map(|n| Value::from_f64(n, d)),
+        };
+        match coerced {
+            Some(Some(value)) => Ok(value),
+            Some(None) => Err(Error::OutOfRange(format!(
+                "{self} is out of range for type {datatype}"
+            ))),
+            None if text.is_some() => {
+                Err(Error::Type(format!("Invalid input for type {datatype}")))
+            }
+            None if self.as_f64().is_some() => Err(Error::Type(format!(
+                "{self} would lose its fraction as type {datatype}"
+            ))),
+            None => Err(type_mismatch(&self, datatype)),
+        }
+    }
+
+    /// Compares two values, treating integers, decimals and floats of every
+    /// width as numbers and dates as the midnight that starts them. JSON is
+    /// compared with other values as their JSON form, and arrays item by
+    /// item, with NULL items last. Returns `None` when either side is NULL
+    /// or the types can't be compared.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        if let (Some(a), Some(b)) = (self.as_i128(), other.as_i128()) {
+            return Some(a.cmp(&b));
+        }
+        if let (Some(a), Some(b)) = (self.as_decimal(), other.as_decimal()) {
+            return Some(a.cmp(&b));
+        }
+        if let (Some(a), Some(b)) = (self.as_f64(), other.as_f64()) {
+            return a.partial_cmp(&b);
+        }
+        match (self, other) {
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::Binary(a), Value::Binary(b)) => Some(a.cmp(b)),
+            (Value::Uuid(a), Value::Uuid(b)) => Some(a.cmp(b)),
+            (Value::Enum(a, ta), Value::Enum(b, tb)) if ta.name == tb.name => Some(a.cmp(b)),
+            (Value::Enum(a, t), Value::Str(b)) => Some(a.cmp(&t.code(b)?)),
+            (Value::Str(a), Value::Enum(b, t)) => Some(t.code(a)?.cmp(b)),
+            (Value::Array(a), Value::Array(b)) => {
+                for pair in a.iter().zip(b) {
+                    let ord = match pair {
+                        (Value::Null, Value::Null) => Ordering::Equal,
+                        (Value::Null, _) => Ordering::Greater,
+                        (_, Value::Null) => Ordering::Less,
+                        (x, y) => x.compare(y)?,
+                    };
+                    if ord != Ordering::Equal {
+                        return Some(ord);
+                    }
+                }
+                Some(a.len().cmp(&b.len()))
+            }
+            (Value::Json(a), b) if !b.is_null() => Some(a.cmp(&Json::from_value(b)?)),
+            (a, Value::Json(b)) if !a.is_null() => Some(Json::from_value(a)?.cmp(b)),
+            (Value::Time(a), Value::Time(b)) => Some(a.cmp(b)),
+            (Value::Interval(a), Value::Interval(b)) => Some(a.sort_key().cmp(&b.sort_key())),
+            (a, b) => Some(a.as_micros()?.cmp(&b.as_micros()?)),
+        }
+    }
+}
+
+/// The bytes as lowercase hex digits, two per byte.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Decodes hex digits of either case, or `None` if there is an odd number
+/// of them or anything else.
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn type_mismatch(value: &Value, datatype: &DataType) -> Error {
+    Error::Type(format!("{value} is not a valid {datatype}"))
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{v}"),
+            Value::Float(v) => write!(f, "{v}"),
+            Value::Str(v) => f.write_str(v),
+            Value::Bool(v) => write!(f, "{v}"),
+            Value::Date(v) => f.write_str(&datetime::format_date(*v)),
+            Value::Time(v) => f.write_str(&datetime::format_time(*v)),
+            Value::Timestamp(v) => f.write_str(&datetime::format_timestamp(*v)),
+            Value::TimestampTz(v) => f.write_str(&datetime::format_timestamptz(*v)),
+            Value::Interval(v) => v.fmt(f),
+            Value::SmallInt(v) => write!(f, "{v}"),
+            Value::BigInt(v) => write!(f, "{v}"),
+            Value::UnsignedSmallInt(v) => write!(f, "{v}"),
+            Value::UnsignedInt(v) => write!(f, "{v}"),
+            Value::UnsignedBigInt(v) => write!(f, "{v}"),
+            Value::Double(v) => write!(f, "{v}"),
+            Value::Decimal(v) => v.fmt(f),
+            Value::Binary(v) => write!(f, "\\x{}", to_hex(v)),
+            Value::Json(v) => v.fmt(f),
+            Value::Uuid(v) => f.write_str(&uuid::format(*v)),
+            Value::Array(v) => f.write_str(&array::format(v)),
+            Value::Enum(code, t) => f.write_str(&t.labels[*code as usize]),
+            Value::Null => f.write_str("NULL"),
+        }
+    }
+}
+
+/// A row of values usable as a hash map key, e.g. to group rows. Floats are
+/// compared by their bits, and NULLs are equal to each other.
+#[derive(Debug, Clone)]
+pub struct Key(pub Vec<Value>);
+
+fn float_bits(f: f64) -> u64 {
+    // 0.0 and -0.0 are the same key.
+    if f == 0.0 {
+        0
+    } else {
+        f.to_bits()
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().zip(&other.0).all(|pair| match pair {
+                (Value::Float(a), Value::Float(b)) => {
+                    float_bits(*a as f64) == float_bits(*b as f64)
+                }
+                (Value::Double(a), Value::Double(b)) => float_bits(*a) == float_bits(*b),
+                (a, b) => a == b,
+            })
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for value in &self.0 {
+            hash_value(value, state);
+        }
+    }
+}
+
+fn hash_value<H: Hasher>(value: &Value, state: &mut H) {
+    std::mem::discriminant(value).hash(state);
+    match value {
+        Value::Int(v) => v.hash(state),
+        Value::Float(v) => float_bits(*v as f64).hash(state),
+        Value::Double(v) => float_bits(*v).hash(state),
+        Value::SmallInt(v) => v.hash(state),
+        Value::BigInt(v) => v.hash(state),
+        Value::UnsignedSmallInt(v) => v.hash(state),
+        Value::UnsignedInt(v) => v.hash(state),
+        Value::UnsignedBigInt(v) => v.hash(state),
+        Value::Str(v) => v.hash(state),
+        Value::Bool(v) => v.hash(state),
+        Value::Date(v) => v.hash(state),
+        Value::Time(v) | Value::Timestamp(v) | Value::TimestampTz(v) => v.hash(state),
+        Value::Interval(v) => v.hash(state),
+        Value::Decimal(v) => v.hash(state),
+        Value::Binary(v) => v.hash(state),
+        Value::Json(v) => v.hash(state),
+        Value::Uuid(v) => v.hash(state),
+        Value::Enum(code, t) => {
+            code.hash(state);
+            t.name.hash(state);
+        }
+        Value::Array(items) => {
+            items.len().hash(state);
+            for item in items {
+                hash_value(item, state);
+            }
+        }
+        Value::Null => {}
+    }
+}
@@ -0,0 +1,98 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use super::Value;
+
+/// Parses the text form of an array, such as `{1,2,NULL}` or
+/// `{{"a b",c},{d,e}}`, into its items as text, or `None` if it is
+/// malformed.
+pub fn parse(text: &str) -> Option<Vec<Value>> {
+    let mut chars = text.trim().chars().peekable();
+    let items = parse_items(&mut chars)?;
+    chars.next().is_none().then_some(items)
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+fn parse_items(chars: &mut Peekable<Chars>) -> Option<Vec<Value>> {
+    if chars.next()? != '{' {
+        return None;
+    }
+    let mut items = vec![];
+    skip_whitespace(chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Some(items);
+    }
+    loop {
+        skip_whitespace(chars);
+        let item = match chars.peek()? {
+            '{' => Value::Array(parse_items(chars)?),
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => text.push(chars.next()?),
+                        c => text.push(c),
+                    }
+                }
+                Value::Str(text)
+            }
+            _ => {
+                let mut text = String::new();
+                while let Some(c) = chars.next_if(|c| *c != ',' && *c != '}') {
+                    text.push(c);
+                }
+                match text.trim() {
+                    "" => return None,
+                    t if t.eq_ignore_ascii_case("null") => Value::Null,
+                    t => Value::Str(t.to_string()),
+                }
+            }
+        };
+        items.push(item);
+        skip_whitespace(chars);
+        match chars.next()? {
+            ',' => {}
+            '}' => return Some(items),
+            _ => return None,
+        }
+    }
+}
+
+/// The items of a multi-dimensional array, in order.
+pub fn flatten<'a>(items: &'a [Value], leaves: &mut Vec<&'a Value>) {
+    for item in items {
+        match item {
+            Value::Array(inner) => flatten(inner, leaves),
+            item => leaves.push(item),
+        }
+    }
+}
+
+/// The text form of an array, with the items quoted that would otherwise
+/// read back differently.
+pub fn format(items: &[Value]) -> String {
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| match item {
+            Value::Null => "NULL".to_string(),
+            Value::Array(items) => format(items),
+            item => quote(&item.to_string()),
+        })
+        .collect();
+    format!("{{{}}}", items.join(","))
+}
+
+fn quote(text: &str) -> String {
+    let plain = !text.is_empty()
+        && !text.eq_ignore_ascii_case("null")
+        && !text.contains(|c: char| c.is_whitespace() || "{}\",\\".contains(c));
+    if plain {
+        return text.to_string();
+    }
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,479 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    Timelike,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_DAY: i64 = 86_400 * MICROS_PER_SECOND;
+
+/// A span of time. Months and days are kept apart from the rest, because
+/// their length depends on the date they are added to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    pub fn new(months: i32, days: i32, micros: i64) -> Interval {
+        Interval {
+            months,
+            days,
+            micros,
+        }
+    }
+
+    /// Orders intervals by their length, counting a month as 30 days.
+    pub fn sort_key(&self) -> i128 {
+        (self.months as i128 * 30 + self.days as i128) * MICROS_PER_DAY as i128
+            + self.micros as i128
+    }
+
+    pub fn checked_add(&self, other: &Interval) -> Option<Interval> {
+        Some(Interval {
+            months: self.months.checked_add(other.months)?,
+            days: self.days.checked_add(other.days)?,
+            micros: self.micros.checked_add(other.micros)?,
+        })
+    }
+
+    pub fn checked_neg(&self) -> Option<Interval> {
+        Some(Interval {
+            months: self.months.checked_neg()?,
+            days: self.days.checked_neg()?,
+            micros: self.micros.checked_neg()?,
+        })
+    }
+
+    /// Scales every part, carrying fractions of months into days and
+    /// fractions of days into the time, as `INTERVAL '1 month' / 2` is 15 days.
+    pub fn checked_mul(&self, factor: f64) -> Option<Interval> {
+        let months = self.months as f64 * factor;
+        let days = self.days as f64 * factor + months.fract() * 30.0;
+        let micros = self.micros as f64 * factor + days.fract() * MICROS_PER_DAY as f64;
+        let fits = |v: f64, max: f64| v.is_finite() && v.abs() < max;
+        if !fits(months, i32::MAX as f64) || !fits(days, i32::MAX as f64) {
+            return None;
+        }
+        if !fits(micros, i64::MAX as f64) {
+            return None;
+        }
+        Some(Interval::new(
+            months.trunc() as i32,
+            days.trunc() as i32,
+            micros.round() as i64,
+        ))
+    }
+
+    /// Parses intervals such as `1 day`, `2 hours 30 minutes`, `1 year 2 mons
+    /// 3 days 04:05:06` or `-1 week ago`.
+    pub fn parse(s: &str) -> Option<Interval> {
+        let mut interval = Interval::new(0, 0, 0);
+        let mut tokens = s.split_whitespace().peekable();
+        let mut any = false;
+        while let Some(token) = tokens.next() {
+            let part = if token.contains(':') {
+                Interval::new(0, 0, parse_clock(token)?)
+            } else if token.eq_ignore_ascii_case("ago") && tokens.peek().is_none() && any {
+                interval = interval.checked_neg()?;
+                break;
+            } else {
+                // The unit may follow the number directly, as in `10s`.
+                let split = token
+                    .find(|c: char| c.is_ascii_alphabetic())
+                    .unwrap_or(token.len());
+                let (amount, unit) = match token.split_at(split) {
+                    (amount, "") => (amount, tokens.next()?),
+                    split => split,
+                };
+                unit_interval(amount.parse().ok()?, unit)?
+            };
+            interval = interval.checked_add(&part)?;
+            any = true;
+        }
+        any.then_some(interval)
+    }
+}
+
+/// `[-]H:M[:S[.f]]` as microseconds.
+fn parse_clock(s: &str) -> Option<i64> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let mut parts = s.split(':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = match parts.next() {
+        Some(seconds) => seconds.parse().ok()?,
+        None => 0.0,
+    };
+    if parts.next().is_some() || !(0..60).contains(&minutes) || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    let micros = hours
+        .checked_mul(3600 * MICROS_PER_SECOND)?
+        .checked_add(minutes * 60 * MICROS_PER_SECOND)?
+        .checked_add((seconds * MICROS_PER_SECOND as f64).round() as i64)?;
+    Some(if negative { -micros } else { micros })
+}
+
+fn unit_interval(amount: f64, unit: &str) -> Option<Interval> {
+    let months = |n: f64| Interval::new(1, 0, 0).checked_mul(amount * n);
+    let days = |n: f64| Interval::new(0, 1, 0).checked_mul(amount * n);
+    let micros = |n: i64| {
+        let micros = amount * n as f64;
+        (micros.abs() < i64::MAX as f64).then(|| Interval::new(0, 0, micros.round() as i64))
+    };
+    match unit.to_lowercase().as_ref() {
+        "microsecond" | "microseconds" | "us" | "usec" | "usecs" => micros(1),
+        "millisecond" | "milliseconds" | "ms" | "msec" | "msecs" => micros(1000),
+        "second" | "seconds" | "s" | "sec" | "secs" => micros(MICROS_PER_SECOND),
+        "minute" | "minutes" | "m" | "min" | "mins" => micros(60 * MICROS_PER_SECOND),
+        "hour" | "hours" | "h" | "hr" | "hrs" => micros(3600 * MICROS_PER_SECOND),
+        "day" | "days" | "d" => days(1.0),
+        "week" | "weeks" | "w" => days(7.0),
+        "month" | "months" | "mon" | "mons" => months(1.0),
+        "quarter" | "quarters" => months(3.0),
+        "year" | "years" | "y" | "yr" | "yrs" => months(12.0),
+        "decade" | "decades" => months(120.0),
+        "century" | "centuries" => months(1200.0),
+        "millennium" | "millennia" | "millenniums" => months(12000.0),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = vec![];
+        let plural = |n: i64, unit: &str| match n {
+            1 => format!("1 {unit}"),
+            n => format!("{n} {unit}s"),
+        };
+        let (years, months) = (self.months / 12, self.months % 12);
+        if years != 0 {
+            parts.push(plural(years as i64, "year"));
+        }
+        if months != 0 {
+            parts.push(plural(months as i64, "mon"));
+        }
+        if self.days != 0 {
+            parts.push(plural(self.days as i64, "day"));
+        }
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 { "-" } else { "" };
+            let micros = self.micros.unsigned_abs();
+            let seconds = micros / MICROS_PER_SECOND as u64;
+            let mut clock = format!(
+                "{sign}{:02}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            );
+            let fraction = micros % MICROS_PER_SECOND as u64;
+            if fraction != 0 {
+                clock += format!(".{fraction:06}").trim_end_matches('0');
+            }
+            parts.push(clock);
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+}
+
+pub fn date_from_days(days: i32) -> Option<NaiveDate> {
+    epoch().checked_add_signed(chrono::Duration::days(days as i64))
+}
+
+pub fn days_from_date(date: NaiveDate) -> i32 {
+    (date - epoch()).num_days() as i32
+}
+
+pub fn datetime_from_micros(micros: i64) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp_micros(micros).map(|dt| dt.naive_utc())
+}
+
+pub fn micros_from_datetime(datetime: NaiveDateTime) -> i64 {
+    datetime.and_utc().timestamp_micros()
+}
+
+/// The start of the day `micros` falls on, in days since 1970-01-01.
+pub fn days_from_micros(micros: i64) -> i32 {
+    micros.div_euclid(MICROS_PER_DAY) as i32
+}
+
+pub fn micros_from_days(days: i32) -> i64 {
+    days as i64 * MICROS_PER_DAY
+}
+
+pub fn parse_date(s: &str) -> Option<i32> {
+    match NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+        Ok(date) => Some(days_from_date(date)),
+        Err(_) => parse_timestamp(s).map(days_from_micros),
+    }
+}
+
+pub fn parse_time(s: &str) -> Option<i64> {
+    let time = ["%H:%M:%S%.f", "%H:%M"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(s.trim(), format).ok())?;
+    Some(
+        time.num_seconds_from_midnight() as i64 * MICROS_PER_SECOND
+            + (time.nanosecond() / 1000) as i64,
+    )
+}
+
+/// Parses a timestamp without a time zone. A date alone is its midnight.
+pub fn parse_timestamp(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let datetime = [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .ok()
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+    })?;
+    Some(micros_from_datetime(datetime))
+}
+
+/// Parses a timestamp with a time zone into microseconds since the epoch
+/// in UTC. The zone is an offset such as `+02:00` or a name such as
+/// `Europe/Paris`, and a timestamp without one is taken to be in UTC.
+pub fn parse_timestamptz(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let with_offset = ["%Y-%m-%d %H:%M:%S%.f%#z", "%Y-%m-%dT%H:%M:%S%.f%#z"]
+        .iter()
+        .find_map(|format| DateTime::parse_from_str(s, format).ok());
+    if let Some(datetime) = with_offset {
+        return Some(datetime.timestamp_micros());
+    }
+    if let Some((local, zone)) = s.rsplit_once(' ') {
+        if let Some(zone) = Zone::parse(zone) {
+            return zone.to_utc(parse_timestamp(local)?);
+        }
+    }
+    parse_timestamp(s)
+}
+
+pub fn format_date(days: i32) -> String {
+    match date_from_days(days) {
+        Some(date) => date.format("%Y-%m-%d").to_string(),
+        None => format!("{days} days"),
+    }
+}
+
+pub fn format_time(micros: i64) -> String {
+    let seconds = micros.div_euclid(MICROS_PER_SECOND) as u32;
+    let nanos = micros.rem_euclid(MICROS_PER_SECOND) as u32 * 1000;
+    match NaiveTime::from_num_seconds_from_midnight_opt(seconds, nanos) {
+        Some(time) => time.format("%H:%M:%S%.f").to_string(),
+        None => format!("{micros} microseconds"),
+    }
+}
+
+pub fn format_timestamp(micros: i64) -> String {
+    match datetime_from_micros(micros) {
+        Some(datetime) => datetime.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+        None => format!("{micros} microseconds"),
+    }
+}
+
+/// Timestamps with a time zone are shown in UTC.
+pub fn format_timestamptz(micros: i64) -> String {
+    format_timestamp(micros) + "+00"
+}
+
+/// Adds an interval to a timestamp. Months are added first and keep the
+/// day of the month where they can, so January 31st plus a month is the
+/// last day of February.
+pub fn add_interval(micros: i64, interval: &Interval) -> Option<i64> {
+    let datetime = datetime_from_micros(micros)?;
+    let datetime = match interval.months {
+        m if m >= 0 => datetime.checked_add_months(Months::new(m as u32))?,
+        m => datetime.checked_sub_months(Months::new(m.unsigned_abs()))?,
+    };
+    let datetime = match interval.days {
+        d if d >= 0 => datetime.checked_add_days(Days::new(d as u64))?,
+        d => datetime.checked_sub_days(Days::new(d.unsigned_abs() as u64))?,
+    };
+    let micros = micros_from_datetime(datetime).checked_add(interval.micros)?;
+    datetime_from_micros(micros).map(|_| micros)
+}
+
+/// The difference of two timestamps, in days and the time left over.
+pub fn between(end: i64, start: i64) -> Option<Interval> {
+    let micros = end.checked_sub(start)?;
+    Some(Interval::new(
+        0,
+        (micros / MICROS_PER_DAY) as i32,
+        micros % MICROS_PER_DAY,
+    ))
+}
+
+/// A time zone as written in `AT TIME ZONE` or a timestamp literal.
+pub enum Zone {
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl Zone {
+    /// Parses a zone name such as `UTC` or `America/New_York`, or an offset
+    /// from UTC such as `+05:30` or `-08`.
+    pub fn parse(s: &str) -> Option<Zone> {
+        if let Ok(tz) = Tz::from_str(s) {
+            return Some(Zone::Named(tz));
+        }
+        let (sign, rest) = match s.as_bytes().first()? {
+            b'+' => (1, &s[1..]),
+            b'-' => (-1, &s[1..]),
+            _ => return None,
+        };
+        let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+        let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+        if !(0..60).contains(&minutes) {
+            return None;
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).map(Zone::Fixed)
+    }
+
+    /// Converts local wall clock time in this zone to UTC. A time skipped by
+    /// a daylight saving change is moved forward by the change.
+    pub fn to_utc(&self, local: i64) -> Option<i64> {
+        let naive = datetime_from_micros(local)?;
+        let utc = match self {
+            Zone::Named(tz) => match tz.from_local_datetime(&naive).earliest() {
+                Some(datetime) => datetime.naive_utc(),
+                None => {
+                    let shifted = naive.checked_add_signed(chrono::Duration::hours(1))?;
+                    tz.from_local_datetime(&shifted).earliest()?.naive_utc()
+                }
+            },
+            Zone::Fixed(offset) => offset.from_local_datetime(&naive).single()?.naive_utc(),
+        };
+        Some(micros_from_datetime(utc))
+    }
+
+    /// Converts UTC to local wall clock time in this zone.
+    pub fn to_local(&self, utc: i64) -> Option<i64> {
+        let naive = datetime_from_micros(utc)?;
+        let local = match self {
+            Zone::Named(tz) => tz.from_utc_datetime(&naive).naive_local(),
+            Zone::Fixed(offset) => offset.from_utc_datetime(&naive).naive_local(),
+        };
+        Some(micros_from_datetime(local))
+    }
+}
+
+/// Truncates a timestamp to the start of `unit`, e.g. `month` or `hour`.
+pub fn truncate(unit: &str, micros: i64) -> Option<i64> {
+    let datetime = datetime_from_micros(micros)?;
+    let date = datetime.date();
+    let start_of_year = |year: i32| NaiveDate::from_ymd_opt(year, 1, 1);
+    let start = match unit.to_lowercase().as_ref() {
+        "microsecond" | "microseconds" => return Some(micros),
+        "millisecond" | "milliseconds" => return Some(micros - micros.rem_euclid(1000)),
+        "second" => return Some(micros - micros.rem_euclid(MICROS_PER_SECOND)),
+        "minute" => return Some(micros - micros.rem_euclid(60 * MICROS_PER_SECOND)),
+        "hour" => return Some(micros - micros.rem_euclid(3600 * MICROS_PER_SECOND)),
+        "day" => date,
+        "week" => date.checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))?,
+        "month" => date.with_day(1)?,
+        "quarter" => NaiveDate::from_ymd_opt(date.year(), (date.month0() / 3) * 3 + 1, 1)?,
+        "year" => start_of_year(date.year())?,
+        "decade" => start_of_year(date.year().div_euclid(10) * 10)?,
+        "century" => start_of_year((date.year() - 1).div_euclid(100) * 100 + 1)?,
+        "millennium" => start_of_year((date.year() - 1).div_euclid(1000) * 1000 + 1)?,
+        _ => return None,
+    };
+    Some(micros_from_datetime(start.and_hms_opt(0, 0, 0)?))
+}
+
+/// Whether `field` can be extracted with `date_part`.
+pub fn is_field(field: &str) -> bool {
+    matches!(
+        field.to_lowercase().as_ref(),
+        "year"
+            | "quarter"
+            | "month"
+            | "week"
+            | "day"
+            | "dow"
+            | "isodow"
+            | "doy"
+            | "hour"
+            | "minute"
+            | "second"
+            | "millisecond"
+            | "milliseconds"
+            | "microsecond"
+            | "microseconds"
+            | "epoch"
+    )
+}
+
+/// A field of a timestamp, e.g. its `year` or `dow` (day of the week,
+/// counting from Sunday as 0). The `epoch` is in seconds.
+pub fn timestamp_part(field: &str, micros: i64) -> Option<i64> {
+    let datetime = datetime_from_micros(micros)?;
+    let in_minute = micros.rem_euclid(60 * MICROS_PER_SECOND);
+    let part = match field.to_lowercase().as_ref() {
+        "year" => datetime.year() as i64,
+        "quarter" => (datetime.month0() / 3 + 1) as i64,
+        "month" => datetime.month() as i64,
+        "week" => datetime.iso_week().week() as i64,
+        "day" => datetime.day() as i64,
+        "dow" => datetime.weekday().num_days_from_sunday() as i64,
+        "isodow" => datetime.weekday().number_from_monday() as i64,
+        "doy" => datetime.ordinal() as i64,
+        "hour" => datetime.hour() as i64,
+        "minute" => datetime.minute() as i64,
+        "second" => datetime.second() as i64,
+        "millisecond" | "milliseconds" => in_minute / 1000,
+        "microsecond" | "microseconds" => in_minute,
+        "epoch" => micros.div_euclid(MICROS_PER_SECOND),
+        _ => return None,
+    };
+    Some(part)
+}
+
+/// A field of an interval. Fields that don't apply to intervals, such as
+/// `dow`, are `None`.
+pub fn interval_part(field: &str, interval: &Interval) -> Option<i64> {
+    let micros = interval.micros;
+    let in_minute = micros % (60 * MICROS_PER_SECOND);
+    let part = match field.to_lowercase().as_ref() {
+        "year" => (interval.months / 12) as i64,
+        "quarter" => (interval.months % 12 / 3 + 1) as i64,
+        "month" => (interval.months % 12) as i64,
+        "day" => interval.days as i64,
+        "hour" => micros / (3600 * MICROS_PER_SECOND),
+        "minute" => micros / (60 * MICROS_PER_SECOND) % 60,
+        "second" => in_minute / MICROS_PER_SECOND,
+        "millisecond" | "milliseconds" => in_minute / 1000,
+        "microsecond" | "microseconds" => in_minute,
+        "epoch" => {
+            let days = interval.months as i64 * 30 + interval.days as i64;
+            days * 86_400 + micros / MICROS_PER_SECOND
+        }
+        _ => return None,
+    };
+    Some(part)
+}
+
+pub fn now() -> i64 {
+    chrono::Utc::now().timestamp_micros()
+}
@@ -0,0 +1,285 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+/// The most digits a decimal can have, which is as many as fit in an `i128`.
+pub const MAX_PRECISION: u8 = 38;
+
+/// How many more digits after the point a quotient has than its dividend.
+pub const DIVISION_SCALE: u8 = 4;
+
+/// How to drop the digits that don't fit a scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// To the nearest, with halves away from zero: 2.5 is 3 and -2.5 is -3.
+    HalfUp,
+    /// Towards zero.
+    Down,
+    /// Towards positive infinity.
+    Ceiling,
+    /// Towards negative infinity.
+    Floor,
+}
+
+/// An exact decimal number, `digits / 10^scale`. Equal numbers are equal
+/// whatever their scale, so 1.5 and 1.50 are the same key.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Decimal {
+    digits: i128,
+    scale: u8,
+}
+
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+/// `n / d` for a positive `d`, rounded the way `mode` says.
+fn div_round(n: i128, d: i128, mode: Rounding) -> i128 {
+    let (q, r) = (n / d, n % d);
+    let adjust = match mode {
+        _ if r == 0 => 0,
+        Rounding::HalfUp if r.abs() >= d - r.abs() => n.signum(),
+        Rounding::Ceiling if r > 0 => 1,
+        Rounding::Floor if r < 0 => -1,
+        _ => 0,
+    };
+    q + adjust
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        digits: 0,
+        scale: 0,
+    };
+
+    /// `digits / 10^scale`, or `None` if it has more than 38 digits.
+    pub fn new(digits: i128, scale: u8) -> Option<Decimal> {
+        let decimal = Decimal { digits, scale };
+        (scale <= MAX_PRECISION && decimal.fits(MAX_PRECISION)).then_some(decimal)
+    }
+
+    pub fn from_i128(v: i128) -> Option<Decimal> {
+        Decimal::new(v, 0)
+    }
+
+    pub fn digits(&self) -> i128 {
+        self.digits
+    }
+
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Parses a number such as `-12.50`, `.5` or `1.2e3`.
+    pub fn parse(s: &str) -> Option<Decimal> {
+        let s = s.trim();
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(pos) => (&s[..pos], s[pos + 1..].parse::<i32>().ok()?),
+            None => (s, 0),
+        };
+        let (negative, mantissa) = match mantissa.as_bytes().first()? {
+            b'-' => (true, &mantissa[1..]),
+            b'+' => (false, &mantissa[1..]),
+            _ => (false, mantissa),
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return None;
+        }
+        let mut digits: i128 = 0;
+        for b in whole.bytes().chain(fraction.bytes()) {
+            if !b.is_ascii_digit() {
+                return None;
+            }
+            digits = digits.checked_mul(10)?.checked_add((b - b'0') as i128)?;
+        }
+        if negative {
+            digits = -digits;
+        }
+        // Digits past the 38th after the point are rounded off.
+        let scale = fraction.len() as i64 - exponent as i64;
+        match scale {
+            s if s < 0 => Decimal::new(digits.checked_mul(pow10((-s) as u32)?)?, 0),
+            s if s > MAX_PRECISION as i64 => {
+                let dropped = pow10((s - MAX_PRECISION as i64) as u32).unwrap_or(i128::MAX);
+                Decimal::new(div_round(digits, dropped, Rounding::HalfUp), MAX_PRECISION)
+            }
+            s => Decimal::new(digits, s as u8),
+        }
+    }
+
+    /// The shortest decimal that reads back as the same float.
+    pub fn from_float(f: impl fmt::Display) -> Option<Decimal> {
+        Decimal::parse(&f.to_string())
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// The value if it is a whole number.
+    pub fn to_i128(&self) -> Option<i128> {
+        let unit = pow10(self.scale as u32)?;
+        (self.digits % unit == 0).then(|| self.digits / unit)
+    }
+
+    /// Whether the number has at most `precision` digits.
+    pub fn fits(&self, precision: u8) -> bool {
+        pow10(precision as u32).is_none_or(|limit| self.digits.abs() < limit)
+    }
+
+    /// The number with exactly `scale` digits after the point, rounding off
+    /// the ones that don't fit. `None` if it grows past 38 digits.
+    pub fn rescale(&self, scale: u8, mode: Rounding) -> Option<Decimal> {
+        let digits = match scale.cmp(&self.scale) {
+            Ordering::Equal => self.digits,
+            Ordering::Greater => self
+                .digits
+                .checked_mul(pow10((scale - self.scale) as u32)?)?,
+            Ordering::Less => div_round(self.digits, pow10((self.scale - scale) as u32)?, mode),
+        };
+        Decimal::new(digits, scale)
+    }
+
+    /// Rounds to `places` digits after the point, or to tens, hundreds and
+    /// so on for a negative `places`. The scale never grows.
+    pub fn round(&self, places: i32, mode: Rounding) -> Option<Decimal> {
+        if places >= 0 {
+            return self.rescale(places.min(self.scale as i32) as u8, mode);
+        }
+        let places = places.unsigned_abs();
+        let rounded = match pow10(self.scale as u32 + places) {
+            Some(unit) => div_round(self.digits, unit, mode),
+            // Every digit is rounded off.
+            None => match mode {
+                Rounding::Ceiling => (self.digits > 0) as i128,
+                Rounding::Floor => -((self.digits < 0) as i128),
+                _ => 0,
+            },
+        };
+        Decimal::new(rounded.checked_mul(pow10(places)?)?, 0)
+    }
+
+    pub fn checked_neg(&self) -> Option<Decimal> {
+        Decimal::new(self.digits.checked_neg()?, self.scale)
+    }
+
+    /// Both numbers with the larger of their scales.
+    fn aligned(&self, other: &Decimal) -> Option<(i128, i128, u8)> {
+        let scale = self.scale.max(other.scale);
+        let a = self.rescale(scale, Rounding::Down)?;
+        let b = other.rescale(scale, Rounding::Down)?;
+        Some((a.digits, b.digits, scale))
+    }
+
+    pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other)?;
+        Decimal::new(a.checked_add(b)?, scale)
+    }
+
+    pub fn checked_sub(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other)?;
+        Decimal::new(a.checked_sub(b)?, scale)
+    }
+
+    /// The exact product, with the digits after the point of both sides up
+    /// to 38 of them.
+    pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+        let digits = self.digits.checked_mul(other.digits)?;
+        let scale = self.scale as u32 + other.scale as u32;
+        match scale.checked_sub(MAX_PRECISION as u32) {
+            Some(extra) if extra > 0 => Decimal::new(
+                div_round(digits, pow10(extra)?, Rounding::HalfUp),
+                MAX_PRECISION,
+            ),
+            _ => Decimal::new(digits, scale as u8),
+        }
+    }
+
+    /// The quotient with four more digits after the point than `self`,
+    /// rounded half up, or `None` for a zero divisor or too many digits.
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        if other.digits == 0 {
+            return None;
+        }
+        let scale = (self.scale + DIVISION_SCALE).min(MAX_PRECISION);
+        // digits / 10^scale = (a / 10^sa) / (b / 10^sb)
+        let exp = scale as u32 + other.scale as u32 - self.scale as u32;
+        let numerator = self.digits.checked_mul(pow10(exp)?)?;
+        let (numerator, divisor) = match other.digits < 0 {
+            true => (numerator.checked_neg()?, other.digits.checked_neg()?),
+            false => (numerator, other.digits),
+        };
+        Decimal::new(div_round(numerator, divisor, Rounding::HalfUp), scale)
+    }
+
+    /// The remainder of truncating division, with the sign of `self`.
+    pub fn checked_rem(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, scale) = self.aligned(other)?;
+        Decimal::new(a.checked_rem(b)?, scale)
+    }
+
+    /// The same number without trailing zeros after the point.
+    fn normalized(&self) -> Decimal {
+        let mut decimal = *self;
+        while decimal.scale > 0 && decimal.digits % 10 == 0 {
+            decimal.digits /= 10;
+            decimal.scale -= 1;
+        }
+        decimal
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        // Whole parts first, so neither side is scaled past what an i128 holds.
+        let split = |d: &Decimal| {
+            let unit = pow10(d.scale as u32).unwrap_or(1);
+            (d.digits / unit, d.digits % unit)
+        };
+        let ((a_whole, a_fraction), (b_whole, b_fraction)) = (split(self), split(other));
+        let scale = self.scale.max(other.scale);
+        let widen = |fraction: i128, from: u8| fraction * pow10((scale - from) as u32).unwrap_or(1);
+        a_whole
+            .cmp(&b_whole)
+            .then_with(|| widen(a_fraction, self.scale).cmp(&widen(b_fraction, other.scale)))
+    }
+}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let decimal = self.normalized();
+        decimal.digits.hash(state);
+        decimal.scale.hash(state);
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.digits.unsigned_abs().to_string();
+        let sign = if self.digits < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return write!(f, "{sign}{digits}");
+        }
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{sign}{whole}.{fraction}")
+    }
+}
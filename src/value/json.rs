@@ -0,0 +1,419 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use super::Value;
+
+/// A parsed JSON document. Objects keep one value per key, the last one
+/// written, in key order.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+/// One step of a path into a document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathStep {
+    Key(String),
+    /// An array position, counted from the end when negative.
+    Index(i64),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut parser = Parser { text, pos: 0 };
+        let json = parser.value()?;
+        parser.skip_whitespace();
+        (parser.pos == parser.text.len()).then_some(json)
+    }
+
+    /// The JSON form of a SQL value, with NULL as JSON null. `None` for
+    /// values that JSON has no form for, like dates.
+    pub fn from_value(value: &Value) -> Option<Json> {
+        let json = match value {
+            Value::Null => Json::Null,
+            Value::Bool(b) => Json::Bool(*b),
+            Value::Str(s) => Json::Str(s.to_string()),
+            Value::Enum(..) => Json::Str(value.to_string()),
+            Value::Json(j) => j.clone(),
+            Value::Array(items) => {
+                Json::Array(items.iter().map(Json::from_value).collect::<Option<_>>()?)
+            }
+            Value::Float(_) | Value::Double(_) | Value::Decimal(_) => {
+                Json::Float(Some(value.as_f64()?).filter(|f| f.is_finite())?)
+            }
+            v => match i64::try_from(v.as_i128()?) {
+                Ok(i) => Json::Int(i),
+                Err(_) => Json::Float(v.as_f64()?),
+            },
+        };
+        Some(json)
+    }
+
+    /// The document as a SQL value, with strings unquoted and JSON null as
+    /// SQL NULL, like `->>`.
+    pub fn to_text(&self) -> Value {
+        match self {
+            Json::Null => Value::Null,
+            Json::Str(s) => Value::Str(s.to_string()),
+            json => Value::Str(json.to_string()),
+        }
+    }
+
+    /// Reads a path such as `$.items[0]."unit price"`.
+    pub fn parse_path(path: &str) -> Option<Vec<PathStep>> {
+        let mut rest = path.trim().strip_prefix('$')?;
+        let mut steps = vec![];
+        while let Some(c) = rest.chars().next() {
+            rest = &rest[c.len_utf8()..];
+            match c {
+                '.' if rest.starts_with('"') => {
+                    let end = rest[1..].find('"')? + 1;
+                    steps.push(PathStep::Key(rest[1..end].to_string()));
+                    rest = &rest[end + 1..];
+                }
+                '.' => {
+                    let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                    if end == 0 {
+                        return None;
+                    }
+                    steps.push(PathStep::Key(rest[..end].to_string()));
+                    rest = &rest[end..];
+                }
+                '[' => {
+                    let end = rest.find(']')?;
+                    steps.push(PathStep::Index(rest[..end].trim().parse().ok()?));
+                    rest = &rest[end + 1..];
+                }
+                _ => return None,
+            }
+        }
+        Some(steps)
+    }
+
+    pub fn get(&self, path: &[PathStep]) -> Option<&Json> {
+        path.iter().try_fold(self, |json, step| match (json, step) {
+            (Json::Object(map), PathStep::Key(key)) => map.get(key),
+            (Json::Array(items), PathStep::Index(i)) => items.get(array_position(items, *i)?),
+            _ => None,
+        })
+    }
+
+    /// Replaces the value at `path`, or adds it if its parent exists: a
+    /// missing key is inserted, and a position past the end of an array is
+    /// appended. Paths whose parent is missing are left alone.
+    pub fn set(&mut self, path: &[PathStep], value: Json) {
+        let Some((last, parent)) = path.split_last() else {
+            *self = value;
+            return;
+        };
+        let mut json = self;
+        for step in parent {
+            let child = match (json, step) {
+                (Json::Object(map), PathStep::Key(key)) => map.get_mut(key),
+                (Json::Array(items), PathStep::Index(i)) => match array_position(items, *i) {
+                    Some(i) => items.get_mut(i),
+                    None => None,
+                },
+                _ => None,
+            };
+            match child {
+                Some(child) => json = child,
+                None => return,
+            }
+        }
+        match (json, last) {
+            (Json::Object(map), PathStep::Key(key)) => {
+                map.insert(key.to_string(), value);
+            }
+            (Json::Array(items), PathStep::Index(i)) => match array_position(items, *i) {
+                Some(i) if i < items.len() => items[i] = value,
+                Some(_) => items.push(value),
+                None => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// Where values sort among other types, as in MySQL: null, numbers,
+    /// strings, objects, arrays, then booleans.
+    fn rank(&self) -> u8 {
+        match self {
+            Json::Null => 0,
+            Json::Int(_) | Json::Float(_) => 1,
+            Json::Str(_) => 2,
+            Json::Object(_) => 3,
+            Json::Array(_) => 4,
+            Json::Bool(_) => 5,
+        }
+    }
+}
+
+/// The position `i` stands for in `items`, which may be past the end for a
+/// non-negative `i`.
+fn array_position(items: &[Json], i: i64) -> Option<usize> {
+    match i < 0 {
+        true => items.len().checked_sub(i.unsigned_abs() as usize),
+        false => Some(i as usize),
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn byte(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.byte().is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.byte()
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.text[self.pos..].starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        match self.peek()? {
+            b'{' => self.object(),
+            b'[' => self.array(),
+            b'"' => self.string().map(Json::Str),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ if self.eat("null") => Some(Json::Null),
+            _ if self.eat("true") => Some(Json::Bool(true)),
+            _ if self.eat("false") => Some(Json::Bool(false)),
+            _ => None,
+        }
+    }
+
+    fn object(&mut self) -> Option<Json> {
+        self.pos += 1;
+        let mut map = BTreeMap::new();
+        if self.eat("}") {
+            return Some(Json::Object(map));
+        }
+        loop {
+            if self.peek()? != b'"' {
+                return None;
+            }
+            let key = self.string()?;
+            if !self.eat(":") {
+                return None;
+            }
+            map.insert(key, self.value()?);
+            if self.eat("}") {
+                return Some(Json::Object(map));
+            }
+            if !self.eat(",") {
+                return None;
+            }
+        }
+    }
+
+    fn array(&mut self) -> Option<Json> {
+        self.pos += 1;
+        let mut items = vec![];
+        if self.eat("]") {
+            return Some(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            if self.eat("]") {
+                return Some(Json::Array(items));
+            }
+            if !self.eat(",") {
+                return None;
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let c = self.text[self.pos..].chars().next()?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Some(s),
+                '\\' => {
+                    let escape = self.byte()?;
+                    self.pos += 1;
+                    s.push(match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return None,
+                    });
+                }
+                c if c.is_control() => return None,
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\uXXXX` escape, which may be the first half of a
+    /// surrogate pair.
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high);
+        }
+        if !self.text[self.pos..].starts_with("\\u") {
+            return None;
+        }
+        self.pos += 2;
+        let low = self.hex4()?;
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + low.checked_sub(0xdc00)?)
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let hex = self.text.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        u32::from_str_radix(hex, 16).ok()
+    }
+
+    fn number(&mut self) -> Option<Json> {
+        let start = self.pos;
+        while self
+            .byte()
+            .is_some_and(|b| matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+        {
+            self.pos += 1;
+        }
+        let text = &self.text[start..self.pos];
+        let digits = text.strip_prefix('-').unwrap_or(text);
+        if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        match text.parse() {
+            Ok(i) => Some(Json::Int(i)),
+            Err(_) => text.parse().ok().map(Json::Float),
+        }
+    }
+}
+
+impl PartialEq for Json {
+    fn eq(&self, other: &Json) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Json {}
+
+impl PartialOrd for Json {
+    fn partial_cmp(&self, other: &Json) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Json {
+    fn cmp(&self, other: &Json) -> Ordering {
+        match (self, other) {
+            (Json::Int(a), Json::Int(b)) => a.cmp(b),
+            (Json::Int(a), Json::Float(b)) => (*a as f64).total_cmp(b),
+            (Json::Float(a), Json::Int(b)) => a.total_cmp(&(*b as f64)),
+            (Json::Float(a), Json::Float(b)) => a.total_cmp(b),
+            (Json::Bool(a), Json::Bool(b)) => a.cmp(b),
+            (Json::Str(a), Json::Str(b)) => a.cmp(b),
+            (Json::Array(a), Json::Array(b)) => a.cmp(b),
+            (Json::Object(a), Json::Object(b)) => a.cmp(b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+}
+
+impl Hash for Json {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Json::Null => {}
+            Json::Bool(b) => b.hash(state),
+            // Whole floats hash like the equal integer.
+            Json::Int(i) => (*i as f64).to_bits().hash(state),
+            Json::Float(f) => f.to_bits().hash(state),
+            Json::Str(s) => s.hash(state),
+            Json::Array(items) => items.hash(state),
+            Json::Object(map) => map.hash(state),
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Int(i) => write!(f, "{i}"),
+            Json::Float(v) => write!(f, "{v}"),
+            Json::Str(s) => write_string(f, s),
+            Json::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    item.fmt(f)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(map) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_string(f, key)?;
+                    f.write_str(": ")?;
+                    value.fmt(f)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
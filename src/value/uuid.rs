@@ -0,0 +1,37 @@
+/// Parses a UUID written as 32 hex digits, with or without the hyphens of
+/// `a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11` and optionally in braces.
+pub fn parse(s: &str) -> Option<u128> {
+    let s = s.trim();
+    let s = match s.strip_prefix('{') {
+        Some(braced) => braced.strip_suffix('}')?,
+        None => s,
+    };
+    let hyphens: Vec<usize> = s.match_indices('-').map(|(i, _)| i).collect();
+    if !hyphens.is_empty() && hyphens != [8, 13, 18, 23] {
+        return None;
+    }
+    let hex: String = s.chars().filter(|c| *c != '-').collect();
+    if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u128::from_str_radix(&hex, 16).ok()
+}
+
+pub fn format(uuid: u128) -> String {
+    let hex = format!("{uuid:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// A random, version 4 UUID.
+pub fn random() -> u128 {
+    let bits = rand::random::<u128>();
+    // Version 4 in the 13th hex digit, and the variant bits `10` after it.
+    (bits & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62)
+}